@@ -0,0 +1,324 @@
+use crate::collation::Collation;
+use crate::error::VirtualTableError;
+use crate::expression::Expression;
+use crate::money::Money;
+use crate::predicate::{compare_values, Predicate};
+use crate::{DataType, Table, TableValue};
+use std::cmp::Ordering;
+
+/// Aggregates over the values of an expression. Just like in SQL, NULL values are skipped and
+/// aggregates over no values are NULL, except for the counts which are 0.
+#[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Aggregate {
+    // Number of rows, like COUNT(*)
+    Count,
+    // Number of values that aren't NULL
+    CountValues(Expression),
+    Sum(Expression),
+    Min(Expression),
+    Max(Expression),
+}
+
+/// Whether `Table::percentile` and `Table::standard_deviation` look at every row. Approximations
+/// of tables with more than 10,000 rows only look at an evenly spread sample of at most 10,000 rows.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Precision {
+    Exact,
+    Approximate,
+}
+
+const APPROXIMATION_SAMPLE_SIZE: usize = 10_000;
+
+impl Table {
+    /// Computes the aggregate over all rows, or only over the rows for which the filter is true.
+    pub fn aggregate(
+        &self,
+        aggregate: &Aggregate,
+        filter: Option<&Predicate>,
+    ) -> Result<TableValue, VirtualTableError> {
+        let indexes = match filter {
+            Some(predicate) => self.matching_indexes(predicate)?,
+            None => (0..self.row_ids.len()).collect(),
+        };
+
+        let expression = match aggregate {
+            Aggregate::Count => return Result::Ok(TableValue::Integer(indexes.len() as i64)),
+            Aggregate::CountValues(expression)
+            | Aggregate::Sum(expression)
+            | Aggregate::Min(expression)
+            | Aggregate::Max(expression) => expression,
+        };
+
+        let mut values = Vec::new();
+        for index in indexes {
+            match expression.evaluate(self, index)? {
+                TableValue::Null => {}
+                value => values.push(value),
+            }
+        }
+
+        match aggregate {
+            Aggregate::CountValues(_) => Result::Ok(TableValue::Integer(values.len() as i64)),
+            Aggregate::Sum(_) => sum(expression, values),
+            Aggregate::Min(_) => extreme(values, self.collation_for(expression), Ordering::Less),
+            Aggregate::Max(_) => extreme(values, self.collation_for(expression), Ordering::Greater),
+            Aggregate::Count => unreachable!("Counting rows returns early."),
+        }
+    }
+
+    /// Smallest value of the column, NULL if it only has NULL values.
+    pub fn column_min(&self, column_identifier: &str) -> Result<TableValue, VirtualTableError> {
+        let column = self.column_expression(column_identifier)?;
+        self.aggregate(&Aggregate::Min(column), None)
+    }
+
+    /// Largest value of the column, NULL if it only has NULL values.
+    pub fn column_max(&self, column_identifier: &str) -> Result<TableValue, VirtualTableError> {
+        let column = self.column_expression(column_identifier)?;
+        self.aggregate(&Aggregate::Max(column), None)
+    }
+
+    /// Sum of an integer or money column, NULL if it only has NULL values.
+    pub fn column_sum(&self, column_identifier: &str) -> Result<TableValue, VirtualTableError> {
+        let column = self.column_expression(column_identifier)?;
+        let data_type = column.data_type(self)?;
+        if !data_type.is_integer() && data_type != DataType::Money {
+            return Result::Err(VirtualTableError::InvalidDataType(
+                String::from(column_identifier),
+                DataType::Integer,
+                data_type,
+            ));
+        }
+
+        self.aggregate(&Aggregate::Sum(column), None)
+    }
+
+    /// Arithmetic mean of an integer column, None if it only has NULL values.
+    pub fn column_mean(&self, column_identifier: &str) -> Result<Option<f64>, VirtualTableError> {
+        let column = self.column_expression(column_identifier)?;
+        let data_type = column.data_type(self)?;
+        if !data_type.is_integer() {
+            return Result::Err(VirtualTableError::InvalidDataType(
+                String::from(column_identifier),
+                DataType::Integer,
+                data_type,
+            ));
+        }
+
+        let values = self.columns[column_identifier]
+            .values
+            .iter()
+            .filter_map(TableValue::as_i128)
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            return Result::Ok(None);
+        }
+
+        // Summing up as floats can't overflow, it only loses precision for huge values
+        let sum = values.iter().map(|value| *value as f64).sum::<f64>();
+        Result::Ok(Some(sum / values.len() as f64))
+    }
+
+    /// Percentile of an integer expression with linear interpolation between the two closest
+    /// values, like PERCENTILE_CONT in SQL. The percentile is given between 0 and 100, the result
+    /// is None if there are no values.
+    pub fn percentile(
+        &self,
+        expression: &Expression,
+        percentile: f64,
+        filter: Option<&Predicate>,
+        precision: Precision,
+    ) -> Result<Option<f64>, VirtualTableError> {
+        if !(0.0..=100.0).contains(&percentile) {
+            return Result::Err(VirtualTableError::ArithmeticError(format!(
+                "The percentile {} isn't between 0 and 100.",
+                percentile
+            )));
+        }
+
+        let mut values = self.integer_values(expression, filter, precision)?;
+        if values.is_empty() {
+            return Result::Ok(None);
+        }
+        values.sort_unstable();
+
+        let rank = percentile / 100.0 * (values.len() - 1) as f64;
+        let lower = values[rank.floor() as usize] as f64;
+        let upper = values[rank.ceil() as usize] as f64;
+        Result::Ok(Some(lower + (upper - lower) * rank.fract()))
+    }
+
+    /// The 50th percentile, see `Table::percentile`.
+    pub fn median(
+        &self,
+        expression: &Expression,
+        filter: Option<&Predicate>,
+        precision: Precision,
+    ) -> Result<Option<f64>, VirtualTableError> {
+        self.percentile(expression, 50.0, filter, precision)
+    }
+
+    /// Sample standard deviation of an integer expression like STDDEV in SQL, None if there are
+    /// less than two values.
+    pub fn standard_deviation(
+        &self,
+        expression: &Expression,
+        filter: Option<&Predicate>,
+        precision: Precision,
+    ) -> Result<Option<f64>, VirtualTableError> {
+        let values = self.integer_values(expression, filter, precision)?;
+        if values.len() < 2 {
+            return Result::Ok(None);
+        }
+
+        // Welford's algorithm, it doesn't lose precision on large values like summing up squares
+        let count = values.len();
+        let (mut mean, mut squared_distances) = (0.0, 0.0);
+        for (seen, value) in values.into_iter().enumerate() {
+            let value = value as f64;
+            let delta = value - mean;
+            mean += delta / (seen + 1) as f64;
+            squared_distances += delta * (value - mean);
+        }
+        Result::Ok(Some((squared_distances / (count - 1) as f64).sqrt()))
+    }
+
+    /// Median of an integer column, None if it only has NULL values.
+    pub fn column_median(&self, column_identifier: &str) -> Result<Option<f64>, VirtualTableError> {
+        let column = self.column_expression(column_identifier)?;
+        self.median(&column, None, Precision::Exact)
+    }
+
+    /// Percentile between 0 and 100 of an integer column, None if it only has NULL values.
+    pub fn column_percentile(
+        &self,
+        column_identifier: &str,
+        percentile: f64,
+    ) -> Result<Option<f64>, VirtualTableError> {
+        let column = self.column_expression(column_identifier)?;
+        self.percentile(&column, percentile, None, Precision::Exact)
+    }
+
+    /// Sample standard deviation of an integer column, None if it has less than two values.
+    pub fn column_stddev(&self, column_identifier: &str) -> Result<Option<f64>, VirtualTableError> {
+        let column = self.column_expression(column_identifier)?;
+        self.standard_deviation(&column, None, Precision::Exact)
+    }
+
+    // Values of an integer expression without NULLs, approximations only look at an evenly spread
+    // sample of the rows
+    fn integer_values(
+        &self,
+        expression: &Expression,
+        filter: Option<&Predicate>,
+        precision: Precision,
+    ) -> Result<Vec<i128>, VirtualTableError> {
+        let data_type = expression.data_type(self)?;
+        if !data_type.is_integer() {
+            return Result::Err(VirtualTableError::InvalidDataType(
+                expression.to_string(),
+                DataType::Integer,
+                data_type,
+            ));
+        }
+
+        let mut indexes = match filter {
+            Some(predicate) => self.matching_indexes(predicate)?,
+            None => (0..self.row_ids.len()).collect(),
+        };
+        if precision == Precision::Approximate && indexes.len() > APPROXIMATION_SAMPLE_SIZE {
+            let step = indexes.len().div_ceil(APPROXIMATION_SAMPLE_SIZE);
+            indexes = indexes.into_iter().step_by(step).collect();
+        }
+
+        let mut values = Vec::with_capacity(indexes.len());
+        for index in indexes {
+            if let Some(value) = expression.evaluate(self, index)?.as_i128() {
+                values.push(value);
+            }
+        }
+
+        Result::Ok(values)
+    }
+
+    fn column_expression(&self, column_identifier: &str) -> Result<Expression, VirtualTableError> {
+        if !self.columns.contains_key(column_identifier) {
+            return Result::Err(VirtualTableError::UnknownColumn(String::from(
+                column_identifier,
+            )));
+        }
+
+        Result::Ok(Expression::column(column_identifier))
+    }
+}
+
+fn sum(expression: &Expression, values: Vec<TableValue>) -> Result<TableValue, VirtualTableError> {
+    if values.is_empty() {
+        return Result::Ok(TableValue::Null);
+    }
+
+    if let TableValue::Money(first) = values[0] {
+        return sum_money(expression, first, &values[1..]);
+    }
+
+    // The sum has the integer type of the values and is only checked against it at the end
+    let data_type = values[0].data_type().unwrap_or(DataType::Integer);
+    let overflow =
+        || VirtualTableError::ArithmeticError(format!("The sum of {} overflowed.", expression));
+    let sum = values
+        .into_iter()
+        .try_fold(0i128, |sum, value| match value.as_i128() {
+            Some(integer) if value.data_type() == Some(data_type) => {
+                sum.checked_add(integer).ok_or_else(overflow)
+            }
+            _ => Result::Err(VirtualTableError::InvalidDataType(
+                expression.to_string(),
+                DataType::Integer,
+                value.data_type().unwrap_or(DataType::Integer),
+            )),
+        })?;
+
+    TableValue::from_i128(sum, data_type).ok_or_else(overflow)
+}
+
+// Amounts are only summed up if they all have the same currency
+fn sum_money(
+    expression: &Expression,
+    first: Money,
+    values: &[TableValue],
+) -> Result<TableValue, VirtualTableError> {
+    values
+        .iter()
+        .try_fold(first, |sum, value| match value {
+            TableValue::Money(money) if money.currency() != sum.currency() => Result::Err(
+                VirtualTableError::CurrencyMismatch(sum.currency(), money.currency()),
+            ),
+            TableValue::Money(money) => sum.checked_add(*money).ok_or_else(|| {
+                VirtualTableError::ArithmeticError(format!("The sum of {} overflowed.", expression))
+            }),
+            other => Result::Err(VirtualTableError::InvalidDataType(
+                expression.to_string(),
+                DataType::Money,
+                other.data_type().unwrap_or(DataType::Money),
+            )),
+        })
+        .map(TableValue::Money)
+}
+
+// Finds the value that is ordered before (Less) or after (Greater) all others
+fn extreme(
+    values: Vec<TableValue>,
+    collation: Collation,
+    wanted: Ordering,
+) -> Result<TableValue, VirtualTableError> {
+    let mut result = TableValue::Null;
+    for value in values {
+        if result == TableValue::Null || compare_values(&value, &result, collation)? == Some(wanted)
+        {
+            result = value;
+        }
+    }
+
+    Result::Ok(result)
+}
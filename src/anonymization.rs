@@ -0,0 +1,180 @@
+use crate::error::VirtualTableError;
+use crate::events::TableEvent;
+use crate::fixtures::Generator;
+use crate::{Cell, DataType, Row, Table, TableValue};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// How `Table::anonymize` replaces the values of a column. NULL values are always kept.
+#[derive(Debug, Clone)]
+pub enum Anonymization {
+    // Replaces String values with a hash salted anew on every call, so equal values still have
+    // equal hashes, but the hashes can't be matched against precomputed ones
+    Hash,
+    // Replaces every value with a made up one, see `Generator::default_for`
+    Fake(Generator),
+    // Moves the values randomly between the rows, so the distribution of the column stays intact
+    Shuffle,
+}
+
+impl Table {
+    /// Replaces the values of the column for good, e.g. before the table is shared for debugging.
+    /// The original values are replaced in the event log and the undo history as well, so they
+    /// can't be brought back by replaying or undoing. Rows that were updated before an error stay
+    /// anonymized.
+    pub fn anonymize(
+        &mut self,
+        column_identifier: &str,
+        anonymization: Anonymization,
+    ) -> Result<(), Vec<VirtualTableError>> {
+        let column = self.columns.get(column_identifier).ok_or_else(|| {
+            vec![VirtualTableError::UnknownColumn(String::from(
+                column_identifier,
+            ))]
+        })?;
+        let data_type = column.data_type;
+        if matches!(anonymization, Anonymization::Hash) && data_type != DataType::String {
+            return Result::Err(vec![VirtualTableError::InvalidDataType(
+                String::from(column_identifier),
+                DataType::String,
+                data_type,
+            )]);
+        }
+        if let Anonymization::Fake(generator) = &anonymization {
+            generator
+                .validate(column_identifier)
+                .map_err(|error| vec![error])?;
+        }
+
+        let mut rng = StdRng::from_entropy();
+        let salt = rng.gen::<u64>();
+        let values = (0..self.row_ids.len())
+            .filter_map(|index| Some((self.key_at(index)?, column.value_at(index)?.clone())))
+            .filter(|(_, value)| *value != TableValue::Null)
+            .collect::<Vec<_>>();
+        let cells = match &anonymization {
+            Anonymization::Shuffle => {
+                let mut cells = values
+                    .iter()
+                    .map(|(_, value)| Cell {
+                        data_type,
+                        inner: value.clone(),
+                    })
+                    .collect::<Vec<_>>();
+                cells.shuffle(&mut rng);
+                cells
+            }
+            anonymization => values
+                .iter()
+                .map(|(_, value)| anonymization.replace(value, data_type, salt, &[], &mut rng))
+                .collect(),
+        };
+
+        // Nothing may record the original values while they are overwritten
+        let (history, events) = (self.history.take(), self.events.take());
+        let result = values
+            .into_iter()
+            .zip(cells)
+            .try_for_each(|((key, _), cell)| {
+                let mut row = Row::create(self, key);
+                row.set_cell(String::from(column_identifier), cell);
+                self.apply_update(row)
+            });
+        self.history = history;
+        self.events = events;
+
+        let pool = (0..self.row_ids.len())
+            .filter_map(|index| self.columns.get(column_identifier)?.value_at(index))
+            .filter(|value| **value != TableValue::Null)
+            .cloned()
+            .collect::<Vec<_>>();
+        self.forget_original_values(column_identifier, |value| {
+            anonymization.replace(value, data_type, salt, &pool, &mut rng)
+        });
+
+        result
+    }
+
+    /// Like `Table::anonymize`, but anonymizes a copy of the rows and leaves this table as it is.
+    /// The copy has neither an event log nor an undo history.
+    pub fn anonymized_copy(
+        &self,
+        column_identifier: &str,
+        anonymization: Anonymization,
+    ) -> Result<Table, Vec<VirtualTableError>> {
+        let mut table = self.copy_rows(&self.identifier, (0..self.row_ids.len()).collect());
+        table.anonymize(column_identifier, anonymization)?;
+
+        Result::Ok(table)
+    }
+
+    // Replaces the values of the column in the event log and the undo history. Rows get the value
+    // they hold now, rows that were deleted since get a replacement of their own.
+    fn forget_original_values<F>(&mut self, column_identifier: &str, mut replace: F)
+    where
+        F: FnMut(&TableValue) -> Cell,
+    {
+        let (mut history, mut events) = (self.history.take(), self.events.take());
+        let column = self
+            .columns
+            .get(column_identifier)
+            .expect("Only existing columns are anonymized.");
+        let mut replacements = HashMap::new();
+        let mut forget = |row: &mut Row| {
+            if let Some(Some(cell)) = row.cells.get_mut(column_identifier) {
+                if cell.inner == TableValue::Null {
+                    return;
+                }
+
+                let current = self
+                    .keys
+                    .get(&row.primary_key)
+                    .and_then(|index| column.cell_at(*index));
+                *cell = replacements
+                    .entry(row.primary_key)
+                    .or_insert_with(|| current.unwrap_or_else(|| replace(&cell.inner)))
+                    .clone();
+            }
+        };
+
+        events.iter_mut().flatten().for_each(|event| match event {
+            TableEvent::RowCreated(row) | TableEvent::RowUpdated(row) => forget(row),
+            TableEvent::RowDeleted(_) => {}
+        });
+        if let Some(history) = history.as_mut() {
+            history.for_each_row(&mut forget);
+        }
+
+        self.history = history;
+        self.events = events;
+    }
+}
+
+impl Anonymization {
+    // Replacement for a single value, shuffled values are picked from the pool
+    fn replace(
+        &self,
+        value: &TableValue,
+        data_type: DataType,
+        salt: u64,
+        pool: &[TableValue],
+        rng: &mut StdRng,
+    ) -> Cell {
+        let inner = match self {
+            Anonymization::Hash => {
+                let mut hasher = DefaultHasher::new();
+                salt.hash(&mut hasher);
+                value.hash(&mut hasher);
+                TableValue::String(format!("{:016x}", hasher.finish()))
+            }
+            Anonymization::Fake(generator) => return generator.generate_cell(data_type, rng),
+            Anonymization::Shuffle => pool.choose(rng).cloned().unwrap_or(TableValue::Null),
+        };
+
+        Cell { data_type, inner }
+    }
+}
@@ -0,0 +1,337 @@
+use crate::duration::parse_duration;
+use crate::error::VirtualTableError;
+use crate::geo::Point;
+use crate::instrumentation::OperationSpan;
+use crate::money::Money;
+use crate::timestamp::Timestamp;
+use crate::{Cell, DataType, Row, Table, TableValue};
+use arrow::array::{Array, ArrayRef, AsArray};
+use arrow::compute::{cast_with_options, CastOptions};
+use arrow::datatypes::{
+    DataType as ArrowType, Decimal128Type, DurationNanosecondType, Int64Type, TimeUnit,
+    TimestampMicrosecondType, UInt64Type,
+};
+use arrow::record_batch::RecordBatch;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Decides which column every field of a record batch ends up in. By default a field is stored in
+/// the column with the same name, and fields without a column are rejected.
+#[derive(Debug, Default, Clone)]
+pub struct ColumnMapping {
+    renames: HashMap<String, String>,
+    ignored_fields: HashSet<String>,
+}
+
+impl ColumnMapping {
+    pub fn create() -> Self {
+        ColumnMapping::default()
+    }
+
+    pub fn rename(mut self, field_name: &str, column_identifier: &str) -> Self {
+        self.renames
+            .insert(String::from(field_name), String::from(column_identifier));
+        self
+    }
+
+    pub fn ignore(mut self, field_name: &str) -> Self {
+        self.ignored_fields.insert(String::from(field_name));
+        self
+    }
+
+    fn column_for<'a>(&'a self, field_name: &'a str) -> Option<&'a str> {
+        if self.ignored_fields.contains(field_name) {
+            return None;
+        }
+
+        Some(
+            self.renames
+                .get(field_name)
+                .map_or(field_name, |identifier| identifier.as_str()),
+        )
+    }
+}
+
+/// A row of a record batch that couldn't be appended, see `Table::append_record_batch_quarantined`.
+#[derive(Debug, Eq, PartialEq)]
+pub struct QuarantinedRow {
+    // Position of the row in the record batch
+    pub position: usize,
+    pub row: Row,
+    pub errors: Vec<VirtualTableError>,
+}
+
+#[derive(Debug, Eq, PartialEq, Default)]
+pub struct ImportReport {
+    pub appended: usize,
+    pub quarantined: Vec<QuarantinedRow>,
+}
+
+impl ImportReport {
+    pub fn is_complete(&self) -> bool {
+        self.quarantined.is_empty()
+    }
+}
+
+impl Display for ImportReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(&format!(
+            "Appended {} rows, quarantined {} rows.",
+            self.appended,
+            self.quarantined.len()
+        ))?;
+        for quarantined in &self.quarantined {
+            let errors = quarantined
+                .errors
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<_>>();
+            f.write_str(&format!(
+                "\nRow {}: {}",
+                quarantined.position,
+                errors.join(" ")
+            ))?;
+        }
+
+        Result::Ok(())
+    }
+}
+
+impl Table {
+    /// Appends the rows of an Arrow record batch. Arrays are cast to the data type of their
+    /// column first, so e.g. Utf8 digits can feed an Integer column. A field mapped to "ID"
+    /// provides the primary keys, otherwise keys come from `Table::generate_key`.
+    ///
+    /// Every row is validated like in `Table::create_row`. Since there are no transactions, rows
+    /// appended before an invalid row are kept.
+    pub fn append_record_batch(
+        &mut self,
+        batch: &RecordBatch,
+        mapping: &ColumnMapping,
+    ) -> Result<usize, Vec<VirtualTableError>> {
+        let span = OperationSpan::start("append_record_batch", self);
+        let mut appended = 0;
+        let result = self
+            .read_record_batch(batch, mapping)
+            .map_err(|error| vec![error])
+            .and_then(|columns| {
+                self.in_batch(|table| {
+                    (0..batch.num_rows()).try_for_each(|index| {
+                        let row = table.row_from_batch(&columns, index);
+                        table.create_row(row)?;
+                        appended += 1;
+
+                        Result::Ok(())
+                    })
+                })
+            });
+        span.finish(&result, appended);
+
+        result.map(|_| appended)
+    }
+
+    /// Like `Table::append_record_batch`, but rows that fail validation are set aside with their
+    /// errors instead of stopping the import. Fields that can't be read at all still fail the
+    /// whole batch, since no row could be built from them.
+    pub fn append_record_batch_quarantined(
+        &mut self,
+        batch: &RecordBatch,
+        mapping: &ColumnMapping,
+    ) -> Result<ImportReport, VirtualTableError> {
+        let span = OperationSpan::start("append_record_batch", self);
+        let result = self.read_record_batch(batch, mapping).map(|columns| {
+            self.in_batch(|table| {
+                let mut report = ImportReport::default();
+                for position in 0..batch.num_rows() {
+                    let row = table.row_from_batch(&columns, position);
+                    match table.create_row(row.clone()) {
+                        Result::Ok(()) => report.appended += 1,
+                        Result::Err(errors) => report.quarantined.push(QuarantinedRow {
+                            position,
+                            row,
+                            errors,
+                        }),
+                    }
+                }
+
+                report
+            })
+        });
+        span.finish(&result, result.as_ref().map_or(0, |report| report.appended));
+
+        result
+    }
+
+    // Reads every mapped field into the values of its column
+    fn read_record_batch(
+        &self,
+        batch: &RecordBatch,
+        mapping: &ColumnMapping,
+    ) -> Result<Vec<(String, DataType, Vec<TableValue>)>, VirtualTableError> {
+        let schema = batch.schema();
+        schema
+            .fields()
+            .iter()
+            .zip(batch.columns())
+            .filter_map(|(field, array)| {
+                mapping
+                    .column_for(field.name())
+                    .map(|identifier| (String::from(identifier), array))
+            })
+            .map(|(identifier, array)| {
+                let data_type = self
+                    .columns
+                    .get(&identifier)
+                    .ok_or_else(|| VirtualTableError::UnknownColumn(identifier.clone()))?
+                    .data_type;
+                let values = read_array(array, data_type).map_err(|message| {
+                    VirtualTableError::ImportError(identifier.clone(), message)
+                })?;
+
+                Result::Ok((identifier, data_type, values))
+            })
+            .collect()
+    }
+
+    fn row_from_batch(
+        &mut self,
+        columns: &[(String, DataType, Vec<TableValue>)],
+        index: usize,
+    ) -> Row {
+        let primary_key = columns
+            .iter()
+            .find(|(identifier, _, _)| identifier == "ID")
+            .and_then(|(_, _, values)| match values[index] {
+                TableValue::Uuid(key) => Some(key),
+                _ => None,
+            })
+            .unwrap_or_else(|| self.generate_key());
+
+        let mut row = Row::create(self, primary_key);
+        columns
+            .iter()
+            .filter(|(identifier, _, _)| identifier != "ID")
+            .for_each(|(identifier, data_type, values)| {
+                row.set_cell(
+                    identifier.clone(),
+                    Cell {
+                        data_type: *data_type,
+                        inner: values[index].clone(),
+                    },
+                )
+            });
+
+        row
+    }
+}
+
+fn read_array(array: &ArrayRef, data_type: DataType) -> Result<Vec<TableValue>, String> {
+    match data_type {
+        DataType::Integer => {
+            let array = cast(array, ArrowType::Int64)?;
+            let values = array.as_primitive::<Int64Type>();
+            read_values(values, |index| {
+                Result::Ok(TableValue::Integer(values.value(index)))
+            })
+        }
+        DataType::BigInt => {
+            let array = cast(array, ArrowType::Decimal128(38, 0))?;
+            let values = array.as_primitive::<Decimal128Type>();
+            read_values(values, |index| {
+                Result::Ok(TableValue::BigInt(values.value(index)))
+            })
+        }
+        DataType::UnsignedInteger => {
+            let array = cast(array, ArrowType::UInt64)?;
+            let values = array.as_primitive::<UInt64Type>();
+            read_values(values, |index| {
+                Result::Ok(TableValue::UnsignedInteger(values.value(index)))
+            })
+        }
+        DataType::Duration if !matches!(array.data_type(), ArrowType::Utf8) => {
+            let array = cast(array, ArrowType::Duration(TimeUnit::Nanosecond))?;
+            let values = array.as_primitive::<DurationNanosecondType>();
+            read_values(values, |index| {
+                let nanos = values.value(index);
+                u64::try_from(nanos)
+                    .map(|nanos| TableValue::Duration(Duration::from_nanos(nanos)))
+                    .map_err(|_| format!("Durations can't be negative, got {}ns.", nanos))
+            })
+        }
+        DataType::Duration => parse_strings(array, |value| {
+            parse_duration(value).map(TableValue::Duration)
+        }),
+        DataType::Timestamp => {
+            // Casting to a zone keeps the instant, while casting to a naive timestamp would
+            // shift it to the local time of the zone
+            let utc = ArrowType::Timestamp(TimeUnit::Microsecond, Some(Arc::from("+00:00")));
+            let array = cast(array, utc)?;
+            let values = array.as_primitive::<TimestampMicrosecondType>();
+            read_values(values, |index| {
+                Result::Ok(TableValue::Timestamp(Timestamp::from_unix_micros(
+                    values.value(index),
+                )))
+            })
+        }
+        DataType::Money => parse_strings(array, |value| Money::parse(value).map(TableValue::Money)),
+        DataType::Point => parse_strings(array, |value| Point::parse(value).map(TableValue::Point)),
+        DataType::String => parse_strings(array, |value| Some(TableValue::from(value))),
+        DataType::Uuid if matches!(array.data_type(), ArrowType::FixedSizeBinary(16)) => {
+            let values = array.as_fixed_size_binary();
+            read_values(values, |index| {
+                Uuid::from_slice(values.value(index))
+                    .map(TableValue::Uuid)
+                    .map_err(|error| error.to_string())
+            })
+        }
+        DataType::Uuid => parse_strings(array, |value| {
+            Uuid::from_str(value.trim()).ok().map(TableValue::Uuid)
+        }),
+        DataType::Custom(type_id) => parse_strings(array, |value| {
+            type_id.parse(value).ok().map(TableValue::Custom)
+        }),
+    }
+}
+
+// Unlike the default, failing casts are errors instead of silently turning values into NULLs
+fn cast(array: &ArrayRef, data_type: ArrowType) -> Result<ArrayRef, String> {
+    let options = CastOptions {
+        safe: false,
+        ..CastOptions::default()
+    };
+
+    cast_with_options(array, &data_type, &options).map_err(|error| error.to_string())
+}
+
+fn read_values<F>(array: &dyn Array, read: F) -> Result<Vec<TableValue>, String>
+where
+    F: Fn(usize) -> Result<TableValue, String>,
+{
+    (0..array.len())
+        .map(|index| {
+            if array.is_null(index) {
+                Result::Ok(TableValue::Null)
+            } else {
+                read(index)
+            }
+        })
+        .collect()
+}
+
+// Reads the values as text and parses them the way coercion does
+fn parse_strings<F>(array: &ArrayRef, parse: F) -> Result<Vec<TableValue>, String>
+where
+    F: Fn(&str) -> Option<TableValue>,
+{
+    let array = cast(array, ArrowType::Utf8)?;
+    let values = array.as_string::<i32>();
+    read_values(values, |index| {
+        let value = values.value(index);
+        parse(value).ok_or_else(|| format!("Can't read '{}'.", value))
+    })
+}
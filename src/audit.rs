@@ -0,0 +1,98 @@
+use crate::error::VirtualTableError;
+use crate::timestamp::Timestamp;
+use crate::transformer::WriteStage;
+use crate::{Cell, Column, DataType, IntoCell, Row, Table, TableValue};
+
+pub const CREATED_AT: &str = "created_at";
+pub const UPDATED_AT: &str = "updated_at";
+pub const UPDATED_BY: &str = "updated_by";
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct AuditColumns {
+    track_updated_by: bool,
+}
+
+impl Table {
+    /// Lets the table maintain the Timestamp columns "created_at" and "updated_at" on every write,
+    /// values written by callers are replaced. With `track_updated_by`, the String column
+    /// "updated_by" holds the principal of the context passed to `Table::create_row_as` or
+    /// `Table::update_row_as`, writes without a context set it to NULL.
+    ///
+    /// Missing columns are added as nullable columns, existing rows get NULL for them. Existing
+    /// columns are used as they are, but must have the right data type.
+    pub fn enable_audit_columns(
+        &mut self,
+        track_updated_by: bool,
+    ) -> Result<(), VirtualTableError> {
+        let mut audit_columns = vec![
+            (CREATED_AT, DataType::Timestamp),
+            (UPDATED_AT, DataType::Timestamp),
+        ];
+        if track_updated_by {
+            audit_columns.push((UPDATED_BY, DataType::String));
+        }
+
+        if let Some((identifier, data_type, column)) = audit_columns
+            .iter()
+            .filter_map(|(identifier, data_type)| {
+                Some((identifier, data_type, self.columns.get(*identifier)?))
+            })
+            .find(|(_, data_type, column)| column.data_type != **data_type)
+        {
+            return Result::Err(VirtualTableError::InvalidDataType(
+                String::from(*identifier),
+                *data_type,
+                column.data_type,
+            ));
+        }
+
+        for (identifier, data_type) in audit_columns {
+            if self.columns.contains_key(identifier) {
+                continue;
+            }
+
+            let mut column = Column::create(String::from(identifier), data_type, true);
+            (0..self.keys.len()).for_each(|index| column.values.set(index, TableValue::Null));
+            self.columns.insert(String::from(identifier), column);
+        }
+        self.audit_columns = Some(AuditColumns { track_updated_by });
+
+        Result::Ok(())
+    }
+
+    /// Stops maintaining the audit columns, the columns and their values are kept.
+    pub fn disable_audit_columns(&mut self) {
+        self.audit_columns = None;
+    }
+
+    pub(crate) fn stamp_audit_columns(
+        &self,
+        stage: WriteStage,
+        mut row: Row,
+        principal: Option<&str>,
+    ) -> Row {
+        let audit_columns = match self.audit_columns {
+            Some(audit_columns) => audit_columns,
+            None => return row,
+        };
+
+        let now = Timestamp::now();
+        match stage {
+            WriteStage::BeforeInsert => row.set_cell(String::from(CREATED_AT), now.into_cell()),
+            // Updates never touch the creation time
+            WriteStage::BeforeUpdate => {
+                row.cells.insert(String::from(CREATED_AT), None);
+            }
+        }
+        row.set_cell(String::from(UPDATED_AT), now.into_cell());
+        if audit_columns.track_updated_by {
+            let updated_by = match principal {
+                Some(principal) => principal.into_cell(),
+                None => Cell::null(DataType::String),
+            };
+            row.set_cell(String::from(UPDATED_BY), updated_by);
+        }
+
+        row
+    }
+}
@@ -0,0 +1,329 @@
+use crate::collation::collation_key;
+use crate::error::VirtualTableError;
+use crate::instrumentation::OperationSpan;
+use crate::query::ColumnSpecification;
+use crate::row_id::RowId;
+use crate::{Index, Row, Table, TableValue};
+use roaring::RoaringBitmap;
+use std::collections::HashMap;
+
+/// Maps every distinct value of a column to the set of rows holding it, which is compact and
+/// fast to combine for columns with only a few distinct values like status flags.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct BitmapIndex {
+    bitmaps: HashMap<TableValue, RoaringBitmap>,
+}
+
+impl BitmapIndex {
+    pub(crate) fn insert(&mut self, value: &TableValue, row_id: RowId) {
+        self.bitmaps
+            .entry(value.clone())
+            .or_default()
+            .insert(row_id.0 as u32);
+    }
+
+    pub(crate) fn remove(&mut self, value: &TableValue, row_id: RowId) {
+        if let Some(bitmap) = self.bitmaps.get_mut(value) {
+            bitmap.remove(row_id.0 as u32);
+            if bitmap.is_empty() {
+                self.bitmaps.remove(value);
+            }
+        }
+    }
+}
+
+// A bitmap index that is built a batch of rows at a time, see `Table::start_bitmap_index_build`
+#[derive(Debug, Default)]
+pub(crate) struct PendingBitmapIndex {
+    index: BitmapIndex,
+    // Rows with lower ids were indexed by a batch, all rows written since the start of the build
+    // are indexed right away like in a finished index
+    next_row_id: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum BitmapFilter {
+    Equals(String, TableValue),
+    And(Vec<BitmapFilter>),
+    Or(Vec<BitmapFilter>),
+}
+
+impl Table {
+    pub fn create_bitmap_index(
+        &mut self,
+        column_identifier: &str,
+    ) -> Result<(), VirtualTableError> {
+        let span = OperationSpan::start("create_bitmap_index", self);
+        let result = self.build_bitmap_index(column_identifier);
+        span.finish(&result, self.row_ids.len());
+
+        result
+    }
+
+    fn build_bitmap_index(&mut self, column_identifier: &str) -> Result<(), VirtualTableError> {
+        let index = self.bitmap_index_of(column_identifier)?;
+        self.pending_bitmap_indexes.remove(column_identifier);
+        self.bitmap_indexes
+            .insert(String::from(column_identifier), index);
+
+        Result::Ok(())
+    }
+
+    /// Starts building a bitmap index without indexing all rows at once, which would block the
+    /// table on large tables. The rows are indexed by `Table::continue_bitmap_index_builds`, and
+    /// the index is only used once all of them are. Writes in between are no problem, they are
+    /// indexed right away.
+    pub fn start_bitmap_index_build(
+        &mut self,
+        column_identifier: &str,
+    ) -> Result<(), VirtualTableError> {
+        if !self.columns.contains_key(column_identifier) {
+            return Result::Err(VirtualTableError::UnknownColumn(String::from(
+                column_identifier,
+            )));
+        }
+
+        self.pending_bitmap_indexes.insert(
+            String::from(column_identifier),
+            PendingBitmapIndex::default(),
+        );
+        Result::Ok(())
+    }
+
+    /// Indexes the next batch of rows for every bitmap index that is being built. Finished indexes
+    /// replace the previous index of their column. Returns how many builds are still pending.
+    pub fn continue_bitmap_index_builds(&mut self, batch_size: usize) -> usize {
+        let columns = &self.columns;
+        let collations = &self.collations;
+        let row_slots = &self.row_slots;
+        self.pending_bitmap_indexes
+            .iter_mut()
+            .for_each(|(column_identifier, pending)| {
+                let end = (pending.next_row_id + batch_size).min(row_slots.len());
+                let collation = collations
+                    .get(column_identifier)
+                    .copied()
+                    .unwrap_or_default();
+                (pending.next_row_id..end).for_each(|row_id| {
+                    if let Some(value) = row_slots[row_id]
+                        .and_then(|index| columns.get(column_identifier)?.value_at(index))
+                    {
+                        pending
+                            .index
+                            .insert(&collation_key(collation, value), RowId(row_id));
+                    }
+                });
+                pending.next_row_id = end;
+            });
+
+        let finished = self
+            .pending_bitmap_indexes
+            .iter()
+            .filter(|(_, pending)| pending.next_row_id >= self.row_slots.len())
+            .map(|(column_identifier, _)| column_identifier.clone())
+            .collect::<Vec<_>>();
+        finished.into_iter().for_each(|column_identifier| {
+            if let Some(pending) = self.pending_bitmap_indexes.remove(&column_identifier) {
+                self.bitmap_indexes.insert(column_identifier, pending.index);
+            }
+        });
+
+        self.pending_bitmap_indexes.len()
+    }
+
+    pub fn is_building_bitmap_index(&self, column_identifier: &str) -> bool {
+        self.pending_bitmap_indexes.contains_key(column_identifier)
+    }
+
+    // Indexes the values the column holds right now
+    pub(crate) fn bitmap_index_of(
+        &self,
+        column_identifier: &str,
+    ) -> Result<BitmapIndex, VirtualTableError> {
+        let column = self
+            .columns
+            .get(column_identifier)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(String::from(column_identifier)))?;
+
+        let collation = self.collation_of(column_identifier);
+        let mut index = BitmapIndex::default();
+        column
+            .values
+            .iter()
+            .zip(&self.row_ids)
+            .for_each(|(value, row_id)| index.insert(&collation_key(collation, value), *row_id));
+
+        Result::Ok(index)
+    }
+
+    // Whether the pending build of the column holds every row its batches passed, and besides
+    // those only rows written since it started, all with the values the column holds right now
+    pub(crate) fn is_bitmap_index_build_current(&self, column_identifier: &str) -> bool {
+        let pending = match self.pending_bitmap_indexes.get(column_identifier) {
+            Some(pending) => pending,
+            None => return true,
+        };
+        let current = match self.bitmap_index_of(column_identifier) {
+            Result::Ok(index) => index,
+            Result::Err(_) => return false,
+        };
+
+        let has_no_stale_rows = pending.index.bitmaps.iter().all(|(value, bitmap)| {
+            matches!(current.bitmaps.get(value), Some(current) if bitmap.is_subset(current))
+        });
+        let has_all_batched_rows = current.bitmaps.iter().all(|(value, bitmap)| {
+            let pending_bitmap = pending.index.bitmaps.get(value);
+            bitmap
+                .iter()
+                .filter(|row_id| (*row_id as usize) < pending.next_row_id)
+                .all(|row_id| matches!(pending_bitmap, Some(pending) if pending.contains(row_id)))
+        });
+
+        has_no_stale_rows && has_all_batched_rows
+    }
+
+    pub(crate) fn rebuild_bitmap_indexes(&mut self) {
+        let column_identifiers = self.bitmap_indexes.keys().cloned().collect::<Vec<_>>();
+        column_identifiers.iter().for_each(|column_identifier| {
+            // Indexes only exist for columns of the table, so building can't fail
+            let _ = self.build_bitmap_index(column_identifier);
+        });
+        self.pending_bitmap_indexes
+            .values_mut()
+            .for_each(|pending| *pending = PendingBitmapIndex::default());
+    }
+
+    // Starts a pending build over, e.g. because the collation of its column changed
+    pub(crate) fn restart_bitmap_index_build(&mut self, column_identifier: &str) {
+        if let Some(pending) = self.pending_bitmap_indexes.get_mut(column_identifier) {
+            *pending = PendingBitmapIndex::default();
+        }
+    }
+
+    /// Drops the index of the column and cancels a build of it.
+    pub fn drop_bitmap_index(&mut self, column_identifier: &str) -> bool {
+        let is_pending = self
+            .pending_bitmap_indexes
+            .remove(column_identifier)
+            .is_some();
+        self.bitmap_indexes.remove(column_identifier).is_some() || is_pending
+    }
+
+    pub fn has_bitmap_index(&self, column_identifier: &str) -> bool {
+        self.bitmap_indexes.contains_key(column_identifier)
+    }
+
+    /// Finds all rows matching the filter, answered from bitmap indexes alone. Every column used in
+    /// the filter needs a bitmap index. Rows are returned in insertion order.
+    pub fn find_rows_by_bitmap(
+        &self,
+        filter: &BitmapFilter,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let span = OperationSpan::start("find_rows_by_bitmap", self);
+        self.statistics.record_query();
+        let result = self.collect_bitmap_rows(filter, column_specification);
+        span.finish(&result, result.as_ref().map_or(0, |rows| rows.len()));
+
+        result
+    }
+
+    fn collect_bitmap_rows(
+        &self,
+        filter: &BitmapFilter,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let bitmap = self.evaluate_bitmap_filter(filter)?;
+
+        let mut row_ids = bitmap
+            .iter()
+            .map(|row_id| RowId(row_id as usize))
+            .filter_map(|row_id| self.index_of(row_id).map(|index| (index, row_id)))
+            .collect::<Vec<_>>();
+        row_ids.sort_unstable();
+
+        row_ids
+            .into_iter()
+            .filter_map(|(_, row_id)| self.pk_for(row_id))
+            .map(|key| {
+                self.try_find_row(&key, column_specification.clone())
+                    .map(|row| row.expect("Indexed rows always exist."))
+            })
+            .collect()
+    }
+
+    fn evaluate_bitmap_filter(
+        &self,
+        filter: &BitmapFilter,
+    ) -> Result<RoaringBitmap, VirtualTableError> {
+        match filter {
+            BitmapFilter::Equals(column_identifier, value) => {
+                self.record_index_lookup("bitmap_index", self.has_bitmap_index(column_identifier));
+                let index = self
+                    .bitmap_indexes
+                    .get(column_identifier)
+                    .ok_or_else(|| VirtualTableError::MissingIndex(column_identifier.clone()))?;
+
+                let key = collation_key(self.collation_of(column_identifier), value);
+                Result::Ok(index.bitmaps.get(&key).cloned().unwrap_or_default())
+            }
+            BitmapFilter::And(filters) => {
+                let mut bitmaps = filters
+                    .iter()
+                    .map(|filter| self.evaluate_bitmap_filter(filter))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .into_iter();
+
+                // An empty conjunction matches everything
+                let first = match bitmaps.next() {
+                    Some(bitmap) => bitmap,
+                    None => {
+                        return Result::Ok(
+                            self.row_ids.iter().map(|row_id| row_id.0 as u32).collect(),
+                        )
+                    }
+                };
+
+                Result::Ok(bitmaps.fold(first, |result, bitmap| result & bitmap))
+            }
+            BitmapFilter::Or(filters) => filters
+                .iter()
+                .map(|filter| self.evaluate_bitmap_filter(filter))
+                .try_fold(RoaringBitmap::new(), |result, bitmap| {
+                    Result::Ok(result | bitmap?)
+                }),
+        }
+    }
+
+    pub(crate) fn update_bitmap_indexes<F>(&mut self, index: Index, update: F)
+    where
+        F: Fn(&mut BitmapIndex, &TableValue, RowId),
+    {
+        let row_id = match self.row_ids.get(index) {
+            Some(row_id) => *row_id,
+            None => return,
+        };
+
+        let columns = &self.columns;
+        let collations = &self.collations;
+        let pending_indexes = self
+            .pending_bitmap_indexes
+            .iter_mut()
+            .map(|(column_identifier, pending)| (column_identifier, &mut pending.index));
+        self.bitmap_indexes
+            .iter_mut()
+            .chain(pending_indexes)
+            .for_each(|(column_identifier, bitmap_index)| {
+                if let Some(value) = columns
+                    .get(column_identifier)
+                    .and_then(|column| column.value_at(index))
+                {
+                    let collation = collations
+                        .get(column_identifier)
+                        .copied()
+                        .unwrap_or_default();
+                    update(bitmap_index, &collation_key(collation, value), row_id);
+                }
+            });
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{PrimaryKey, Table};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+// Keeps the rate of false positives at about 1% as long as the expected number of keys isn't exceeded
+const FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Probabilistic set of primary keys. It never misses a key that has been inserted, but might
+/// report keys that were never inserted. Keys can't be removed, so removed rows stay in the filter.
+#[derive(Debug)]
+pub(crate) struct BloomFilter {
+    bits: Vec<u64>,
+    bit_count: u64,
+    hash_count: u32,
+}
+
+impl BloomFilter {
+    fn with_capacity(expected_keys: usize) -> Self {
+        let expected_keys = expected_keys.max(1) as f64;
+        let ln_2 = std::f64::consts::LN_2;
+        let bit_count = (-expected_keys * FALSE_POSITIVE_RATE.ln() / (ln_2 * ln_2)).ceil() as u64;
+        let hash_count = ((bit_count as f64 / expected_keys) * ln_2).round().max(1.0) as u32;
+
+        BloomFilter {
+            bits: vec![0; bit_count.div_ceil(64) as usize],
+            bit_count,
+            hash_count,
+        }
+    }
+
+    pub(crate) fn insert(&mut self, key: &PrimaryKey) {
+        for bit in self.bit_positions(key) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    // Keeps the size, so the filter can be refilled with the current keys
+    pub(crate) fn clear(&mut self) {
+        self.bits.iter_mut().for_each(|bits| *bits = 0);
+    }
+
+    pub(crate) fn might_contain(&self, key: &PrimaryKey) -> bool {
+        self.bit_positions(key)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    // Derives all positions from two hashes, see Kirsch and Mitzenmacher
+    fn bit_positions(&self, key: &PrimaryKey) -> impl Iterator<Item = u64> {
+        let first = hash_with_seed(key, 0);
+        let second = hash_with_seed(key, 1);
+        let bit_count = self.bit_count;
+
+        (0..self.hash_count as u64)
+            .map(move |i| first.wrapping_add(i.wrapping_mul(second)) % bit_count)
+    }
+}
+
+fn hash_with_seed(key: &PrimaryKey, seed: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Table {
+    /// Maintains a bloom filter over the primary keys, so lookups of keys that aren't in the table
+    /// can be answered without touching the key index. The filter is sized for the expected number
+    /// of keys, it gets less effective once the table grows beyond that.
+    pub fn enable_bloom_filter(&mut self, expected_keys: usize) {
+        let mut bloom_filter = BloomFilter::with_capacity(expected_keys);
+        self.keys.keys().for_each(|key| bloom_filter.insert(key));
+        self.bloom_filter = Some(bloom_filter);
+    }
+
+    pub fn has_bloom_filter(&self) -> bool {
+        self.bloom_filter.is_some()
+    }
+
+    pub fn contains_key(&self, key: &PrimaryKey) -> bool {
+        if let Some(bloom_filter) = &self.bloom_filter {
+            let is_rejected = !bloom_filter.might_contain(key);
+            self.record_index_lookup("bloom_filter", is_rejected);
+            if is_rejected {
+                return false;
+            }
+        }
+
+        self.keys.contains_key(key)
+    }
+}
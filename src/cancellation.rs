@@ -0,0 +1,98 @@
+use crate::error::VirtualTableError;
+use crate::instrumentation::OperationSpan;
+use crate::query::ColumnSpecification;
+use crate::{Row, Table};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Lets long-running operations be aborted from the outside. Clones share the same state, so the
+/// token can be handed to another thread and cancelled there. Operations check the token between
+/// rows and stop with `VirtualTableError::Cancelled`.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    is_cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    pub fn create() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Creates a token that cancels itself once the deadline has passed.
+    pub fn with_deadline(deadline: Instant) -> Self {
+        CancellationToken {
+            is_cancelled: Arc::new(AtomicBool::new(false)),
+            deadline: Some(deadline),
+        }
+    }
+
+    pub fn with_timeout(timeout: Duration) -> Self {
+        CancellationToken::with_deadline(Instant::now() + timeout)
+    }
+
+    pub fn cancel(&self) {
+        self.is_cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.is_cancelled.load(Ordering::Relaxed)
+            || matches!(self.deadline, Some(deadline) if Instant::now() >= deadline)
+    }
+
+    pub(crate) fn check(&self) -> Result<(), VirtualTableError> {
+        if self.is_cancelled() {
+            return Result::Err(VirtualTableError::Cancelled);
+        }
+
+        Result::Ok(())
+    }
+}
+
+impl Table {
+    /// Fetches all rows in insertion order, checking the token before every row.
+    pub fn scan(
+        &self,
+        column_specification: ColumnSpecification,
+        token: &CancellationToken,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let span = OperationSpan::start("scan", self);
+        self.statistics.record_query();
+        let result = (0..self.row_ids.len())
+            .filter_map(|index| self.key_at(index))
+            .map(|key| {
+                token.check()?;
+                self.try_find_row(&key, column_specification.clone())
+                    .map(|row| row.expect("Rows at valid indexes always exist."))
+            })
+            .collect::<Result<Vec<_>, _>>();
+        self.record_rows_scanned("scan", result.as_ref().map_or(0, |rows| rows.len()));
+        span.finish(&result, result.as_ref().map_or(0, |rows| rows.len()));
+
+        result
+    }
+
+    /// Applies all updates in order, checking the token before every row. Since there are no
+    /// transactions, updates applied before a cancellation or an error are kept.
+    pub fn update_rows(
+        &mut self,
+        rows: Vec<Row>,
+        token: &CancellationToken,
+    ) -> Result<usize, Vec<VirtualTableError>> {
+        let span = OperationSpan::start("update_rows", self);
+        let mut updated = 0;
+        let result = self.in_batch(|table| {
+            rows.into_iter().try_for_each(|row| {
+                token.check().map_err(|error| vec![error])?;
+                table.update_row(row)?;
+                updated += 1;
+
+                Result::Ok(())
+            })
+        });
+        span.finish(&result, updated);
+
+        result.map(|_| updated)
+    }
+}
@@ -0,0 +1,56 @@
+use crate::query::ColumnSpecification;
+use crate::{PrimaryKey, Row, Table, TableValue};
+
+// Parameters of the 64 bit FNV-1a hash, which unlike `DefaultHasher` is guaranteed to stay the same
+pub(crate) const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+impl Row {
+    /// Hash of the cell values, the same on every system and in every version of this crate, so
+    /// rows can be compared without transferring them. Cells are hashed in the order of their
+    /// identifiers, the primary key and cells that aren't set are left out.
+    pub fn content_hash(&self) -> u64 {
+        let mut cells = self
+            .cells()
+            .filter(|(identifier, _)| *identifier != "ID")
+            .collect::<Vec<_>>();
+        cells.sort_by_key(|(identifier, _)| *identifier);
+
+        let mut hash = FNV_OFFSET_BASIS;
+        for (identifier, value) in cells {
+            let data_type = match value {
+                TableValue::Null => String::from("NULL"),
+                value => value
+                    .data_type()
+                    .map_or_else(String::new, |data_type| data_type.to_string()),
+            };
+            // Lengths go first, so the boundaries between the parts can't be shifted
+            for part in [identifier, data_type.as_str(), String::from(value).as_str()] {
+                hash = fnv1a(hash, &(part.len() as u64).to_le_bytes());
+                hash = fnv1a(hash, part.as_bytes());
+            }
+        }
+
+        hash
+    }
+}
+
+impl Table {
+    /// The content hash of every row with its key, in insertion order, see `Row::content_hash`.
+    pub fn row_hashes(&self) -> Vec<(PrimaryKey, u64)> {
+        self.record_rows_scanned("row_hashes", self.row_ids.len());
+        (0..self.row_ids.len())
+            .filter_map(|index| self.key_at(index))
+            .filter_map(|key| {
+                let row = self.find_row(&key, ColumnSpecification::All)?;
+                Some((key, row.content_hash()))
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
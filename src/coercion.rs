@@ -0,0 +1,93 @@
+use crate::duration::{format_duration, parse_duration};
+use crate::geo::Point;
+use crate::money::Money;
+use crate::timestamp::Timestamp;
+use crate::{Cell, DataType, Row, Table, TableValue};
+use std::str::FromStr;
+use uuid::Uuid;
+
+impl Table {
+    /// Converts compatible values to the data type of their column on insert and update instead
+    /// of rejecting them, e.g. "42" for an Integer column. Values that can't be converted are
+    /// still rejected with `VirtualTableError::InvalidDataType`.
+    pub fn enable_coercion(&mut self) {
+        self.coerce_values = true;
+    }
+
+    pub fn disable_coercion(&mut self) {
+        self.coerce_values = false;
+    }
+
+    pub fn is_coercing_values(&self) -> bool {
+        self.coerce_values
+    }
+
+    // Coercing the whole row up front means that events and the history only see converted values
+    pub(crate) fn coerce_row(&self, mut row: Row) -> Row {
+        if !self.coerce_values {
+            return row;
+        }
+
+        row.cells.iter_mut().for_each(|(identifier, cell_option)| {
+            if let Some(column) = self.columns.get(identifier) {
+                *cell_option = cell_option
+                    .take()
+                    .map(|cell| coerce(cell, column.data_type));
+            }
+        });
+
+        row
+    }
+}
+
+// Returns the cell unchanged if there is no conversion to the data type
+pub(crate) fn coerce(cell: Cell, data_type: DataType) -> Cell {
+    if cell.data_type == data_type {
+        return cell;
+    }
+
+    let value = match (&cell.inner, data_type) {
+        (TableValue::Null, _) => Some(TableValue::Null),
+        (value, data_type) if data_type.is_integer() && value.as_i128().is_some() => value
+            .as_i128()
+            .and_then(|value| TableValue::from_i128(value, data_type)),
+        (value, DataType::String) if value.as_i128().is_some() => {
+            Some(TableValue::String(String::from(value)))
+        }
+        (TableValue::Duration(value), DataType::String) => {
+            Some(TableValue::String(format_duration(*value)))
+        }
+        (TableValue::String(value), DataType::Duration) => {
+            parse_duration(value).map(TableValue::Duration)
+        }
+        (TableValue::Timestamp(value), DataType::String) => {
+            Some(TableValue::String(value.to_string()))
+        }
+        (TableValue::String(value), DataType::Timestamp) => {
+            Timestamp::parse(value).map(TableValue::Timestamp)
+        }
+        (TableValue::Money(value), DataType::String) => Some(TableValue::String(value.to_string())),
+        (TableValue::String(value), DataType::Money) => Money::parse(value).map(TableValue::Money),
+        (TableValue::Point(value), DataType::String) => Some(TableValue::String(value.to_string())),
+        (TableValue::String(value), DataType::Point) => Point::parse(value).map(TableValue::Point),
+        (TableValue::Uuid(value), DataType::String) => Some(TableValue::String(value.to_string())),
+        (TableValue::Custom(value), DataType::String) => Some(TableValue::from(value.as_str())),
+        (TableValue::String(value), data_type) if data_type.is_integer() => {
+            i128::from_str(value.trim())
+                .ok()
+                .and_then(|value| TableValue::from_i128(value, data_type))
+        }
+        (TableValue::String(value), DataType::Uuid) => {
+            Uuid::from_str(value.trim()).ok().map(TableValue::Uuid)
+        }
+        (TableValue::String(value), DataType::Custom(type_id)) => {
+            type_id.parse(value).ok().map(TableValue::Custom)
+        }
+        _ => None,
+    };
+
+    match value {
+        Some(inner) => Cell { data_type, inner },
+        None => cell,
+    }
+}
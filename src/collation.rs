@@ -0,0 +1,129 @@
+use crate::error::VirtualTableError;
+use crate::expression::Expression;
+use crate::{DataType, Table, TableValue};
+use std::cmp::Ordering;
+#[cfg(feature = "unicode-collation")]
+use unicode_normalization::UnicodeNormalization;
+
+#[cfg(feature = "unicode-collation")]
+thread_local! {
+    // The collator caches data between comparisons, so we keep one around per thread
+    static COLLATOR: std::cell::RefCell<feruca::Collator> = std::cell::RefCell::new(feruca::Collator::default());
+}
+
+/// Decides how String values of a column are compared, which affects predicates, sorting and
+/// aggregates like MIN and MAX.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub enum Collation {
+    // Compares the raw bytes, which is the default
+    #[default]
+    Binary,
+    CaseInsensitive,
+    // Locale-independent ordering of the Unicode Collation Algorithm, e.g. "é" sorts before "f"
+    #[cfg(feature = "unicode-collation")]
+    Unicode,
+}
+
+impl Collation {
+    pub fn compare(&self, left: &str, right: &str) -> Ordering {
+        match self {
+            Collation::Binary => left.cmp(right),
+            Collation::CaseInsensitive => left
+                .chars()
+                .flat_map(char::to_lowercase)
+                .cmp(right.chars().flat_map(char::to_lowercase)),
+            #[cfg(feature = "unicode-collation")]
+            Collation::Unicode => {
+                COLLATOR.with(|collator| collator.borrow_mut().collate(left, right))
+            }
+        }
+    }
+}
+
+impl Collation {
+    /// Maps the value to a key that is equal for all values this collation considers equal,
+    /// which is what indexes are built on.
+    pub fn key(&self, value: &str) -> String {
+        match self {
+            Collation::Binary => String::from(value),
+            Collation::CaseInsensitive => value.chars().flat_map(char::to_lowercase).collect(),
+            // Canonically equivalent strings are the ones that compare equal
+            #[cfg(feature = "unicode-collation")]
+            Collation::Unicode => value.nfd().collect(),
+        }
+    }
+}
+
+// Non-String values are their own key
+pub(crate) fn collation_key(collation: Collation, value: &TableValue) -> TableValue {
+    match value {
+        TableValue::String(value) => TableValue::String(collation.key(value)),
+        other => other.clone(),
+    }
+}
+
+impl Table {
+    /// Sets the collation of a String column.
+    pub fn set_collation(
+        &mut self,
+        column_identifier: &str,
+        collation: Collation,
+    ) -> Result<(), VirtualTableError> {
+        let column = self
+            .columns
+            .get(column_identifier)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(String::from(column_identifier)))?;
+        if column.data_type != DataType::String {
+            return Result::Err(VirtualTableError::InvalidDataType(
+                String::from(column_identifier),
+                DataType::String,
+                column.data_type,
+            ));
+        }
+
+        let previous = self
+            .collations
+            .insert(String::from(column_identifier), collation);
+
+        // Indexes on the column are keyed by the collation, so they have to be rebuilt
+        if let Err(error) = self.rebuild_column_indexes(column_identifier) {
+            match previous {
+                Some(previous) => self
+                    .collations
+                    .insert(String::from(column_identifier), previous),
+                None => self.collations.remove(column_identifier),
+            };
+            self.rebuild_column_indexes(column_identifier)
+                .expect("The previous collation was valid.");
+
+            return Result::Err(error);
+        }
+
+        Result::Ok(())
+    }
+
+    fn rebuild_column_indexes(&mut self, column_identifier: &str) -> Result<(), VirtualTableError> {
+        if self.has_bitmap_index(column_identifier) {
+            self.create_bitmap_index(column_identifier)?;
+        }
+        self.restart_bitmap_index_build(column_identifier);
+        self.rebuild_unique_index(column_identifier)?;
+
+        Result::Ok(())
+    }
+
+    pub fn collation_of(&self, column_identifier: &str) -> Collation {
+        self.collations
+            .get(column_identifier)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // Values of plain columns are compared with the collation of the column, all others binary
+    pub(crate) fn collation_for(&self, expression: &Expression) -> Collation {
+        match expression {
+            Expression::Column(identifier) => self.collation_of(identifier),
+            _ => Collation::Binary,
+        }
+    }
+}
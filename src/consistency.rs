@@ -0,0 +1,341 @@
+use crate::constraint::Constraint;
+use crate::error::VirtualTableError;
+use crate::instrumentation::OperationSpan;
+use crate::row_id::RowId;
+use crate::{DataType, Index, PrimaryKey, Table, TableValue};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum InvariantViolation {
+    // The column holds a different number of cells than there are rows in the table
+    ColumnLengthMismatch(String, usize, usize),
+    IndexOutOfBounds(PrimaryKey, Index),
+    DuplicateIndex(Index, Vec<PrimaryKey>),
+    // The ID column does not contain the primary key that points to its index
+    PrimaryKeyMismatch(PrimaryKey, Index),
+    InvalidDataType(String, Index, DataType),
+    InvalidNullValue(String, Index),
+    ConstraintViolation(String, Index, Constraint),
+    // The row id stored for the index doesn't lead back to it
+    RowIdMismatch(Index),
+    // The slot of a row id points to an index that holds another row
+    StaleRowSlot(usize, Index),
+    // The index differs from one rebuilt from the values of its column
+    StaleBitmapIndex(String),
+    // The bitmap index being built misses rows its batches passed or holds values the column doesn't
+    StaleBitmapIndexBuild(String),
+    StaleUniqueIndex(String),
+    StaleSpatialIndex(String),
+    // The column of a unique index holds the value more than once
+    DuplicateUniqueValue(String, TableValue),
+    // The ordered keys differ from the primary keys of the table
+    StaleOrderedKeys,
+    // The bloom filter would report the key of an existing row as unknown
+    MissingBloomFilterKey(PrimaryKey),
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct InvariantReport {
+    violations: Vec<InvariantViolation>,
+}
+
+impl InvariantReport {
+    pub fn is_consistent(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    pub fn violations(&self) -> &[InvariantViolation] {
+        &self.violations
+    }
+}
+
+impl Table {
+    /// Verifies the internal bookkeeping of the table, which is mostly useful for debugging and tests.
+    /// The row ids and all secondary indexes are compared against the stored values.
+    pub fn check_invariants(&self) -> InvariantReport {
+        let mut violations = Vec::new();
+        let row_count = self.keys.len();
+
+        self.columns.iter().for_each(|(identifier, column)| {
+            if column.values.len() != row_count {
+                violations.push(InvariantViolation::ColumnLengthMismatch(
+                    identifier.clone(),
+                    row_count,
+                    column.values.len(),
+                ));
+            }
+
+            column.values.iter().enumerate().for_each(|(index, value)| {
+                match value.data_type() {
+                    Some(data_type) if data_type != column.data_type => {
+                        violations.push(InvariantViolation::InvalidDataType(
+                            identifier.clone(),
+                            index,
+                            data_type,
+                        ))
+                    }
+                    _ => {}
+                }
+
+                if !column.is_nullable && *value == TableValue::Null {
+                    violations.push(InvariantViolation::InvalidNullValue(
+                        identifier.clone(),
+                        index,
+                    ));
+                }
+
+                if let Some(constraint) = column
+                    .constraints
+                    .iter()
+                    .find(|constraint| !constraint.is_satisfied_by(value))
+                {
+                    violations.push(InvariantViolation::ConstraintViolation(
+                        identifier.clone(),
+                        index,
+                        constraint.clone(),
+                    ));
+                }
+            });
+        });
+
+        let mut keys_by_index: HashMap<Index, Vec<PrimaryKey>> = HashMap::new();
+        self.keys.iter().for_each(|(key, index)| {
+            keys_by_index.entry(*index).or_default().push(*key);
+
+            if *index >= row_count {
+                violations.push(InvariantViolation::IndexOutOfBounds(*key, *index));
+                return;
+            }
+
+            let id_column = self
+                .columns
+                .get("ID")
+                .expect("Every table has an ID column.");
+            if id_column.value_at(*index) != Some(&TableValue::Uuid(*key)) {
+                violations.push(InvariantViolation::PrimaryKeyMismatch(*key, *index));
+            }
+        });
+
+        let mut duplicates = keys_by_index
+            .into_iter()
+            .filter(|(_, keys)| keys.len() > 1)
+            .collect::<Vec<_>>();
+        duplicates.sort_by_key(|(index, _)| *index);
+        duplicates.into_iter().for_each(|(index, mut keys)| {
+            keys.sort();
+            violations.push(InvariantViolation::DuplicateIndex(index, keys));
+        });
+
+        self.check_row_ids(&mut violations);
+        self.check_secondary_indexes(&mut violations);
+
+        InvariantReport { violations }
+    }
+
+    fn check_row_ids(&self, violations: &mut Vec<InvariantViolation>) {
+        let row_count = self.keys.len().max(self.row_ids.len());
+        (0..row_count).for_each(|index| {
+            let row_id = self.row_ids.get(index).copied();
+            if row_id.and_then(|row_id| self.index_of(row_id)) != Some(index) {
+                violations.push(InvariantViolation::RowIdMismatch(index));
+            }
+        });
+
+        self.row_slots
+            .iter()
+            .enumerate()
+            .filter_map(|(row_id, slot)| slot.map(|index| (row_id, index)))
+            .filter(|(row_id, index)| self.row_ids.get(*index) != Some(&RowId(*row_id)))
+            .for_each(|(row_id, index)| {
+                violations.push(InvariantViolation::StaleRowSlot(row_id, index))
+            });
+    }
+
+    // Compares every secondary index against one rebuilt from the column values
+    fn check_secondary_indexes(&self, violations: &mut Vec<InvariantViolation>) {
+        let mut identifiers = self.bitmap_indexes.keys().collect::<Vec<_>>();
+        identifiers.sort();
+        identifiers.into_iter().for_each(|identifier| {
+            if self.bitmap_index_of(identifier).ok().as_ref() != self.bitmap_indexes.get(identifier)
+            {
+                violations.push(InvariantViolation::StaleBitmapIndex(identifier.clone()));
+            }
+        });
+
+        let mut identifiers = self.pending_bitmap_indexes.keys().collect::<Vec<_>>();
+        identifiers.sort();
+        identifiers.into_iter().for_each(|identifier| {
+            if !self.is_bitmap_index_build_current(identifier) {
+                violations.push(InvariantViolation::StaleBitmapIndexBuild(
+                    identifier.clone(),
+                ));
+            }
+        });
+
+        let mut identifiers = self.unique_indexes.keys().collect::<Vec<_>>();
+        identifiers.sort();
+        identifiers.into_iter().for_each(|identifier| {
+            match self.is_unique_index_current(identifier) {
+                Result::Ok(true) => {}
+                Result::Err(VirtualTableError::DuplicateValue(_, value)) => violations.push(
+                    InvariantViolation::DuplicateUniqueValue(identifier.clone(), value),
+                ),
+                _ => violations.push(InvariantViolation::StaleUniqueIndex(identifier.clone())),
+            }
+        });
+
+        let mut identifiers = self.spatial_indexes.keys().collect::<Vec<_>>();
+        identifiers.sort();
+        identifiers.into_iter().for_each(|identifier| {
+            if self.spatial_index_of(identifier).as_ref() != self.spatial_indexes.get(identifier) {
+                violations.push(InvariantViolation::StaleSpatialIndex(identifier.clone()));
+            }
+        });
+
+        if let Some(ordered_keys) = &self.ordered_keys {
+            if ordered_keys.len() != self.keys.len()
+                || ordered_keys.iter().any(|key| !self.keys.contains_key(key))
+            {
+                violations.push(InvariantViolation::StaleOrderedKeys);
+            }
+        }
+
+        // Removed rows stay in the bloom filter, so only missing keys are a problem
+        if let Some(bloom_filter) = &self.bloom_filter {
+            let mut keys = self
+                .keys
+                .keys()
+                .filter(|key| !bloom_filter.might_contain(key))
+                .collect::<Vec<_>>();
+            keys.sort();
+            keys.into_iter()
+                .for_each(|key| violations.push(InvariantViolation::MissingBloomFilterKey(*key)));
+        }
+    }
+
+    /// Rebuilds all secondary indexes, the ordered keys and the bloom filter from the stored
+    /// values, e.g. when `Table::check_invariants` or wrong query results hint at corruption.
+    /// Unique indexes whose column holds duplicates by now are dropped and reported as errors,
+    /// all other indexes are rebuilt regardless.
+    pub fn rebuild_indexes(&mut self) -> Result<(), Vec<VirtualTableError>> {
+        let span = OperationSpan::start("rebuild_indexes", self);
+        self.rebuild_bitmap_indexes();
+        self.rebuild_spatial_indexes();
+        let errors = self.rebuild_unique_indexes();
+
+        if self.ordered_keys.is_some() {
+            self.ordered_keys = Some(self.keys.keys().copied().collect::<BTreeSet<_>>());
+        }
+        if let Some(bloom_filter) = self.bloom_filter.as_mut() {
+            bloom_filter.clear();
+            self.keys.keys().for_each(|key| bloom_filter.insert(key));
+        }
+
+        let result = if errors.is_empty() {
+            Result::Ok(())
+        } else {
+            Result::Err(errors)
+        };
+        span.finish(&result, self.row_ids.len());
+
+        result
+    }
+}
+
+impl Display for InvariantViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            InvariantViolation::ColumnLengthMismatch(identifier, expected, actual) => {
+                f.write_str(&format!(
+                    "Column {} holds {} cells but the table has {} rows.",
+                    identifier, actual, expected
+                ))
+            }
+            InvariantViolation::IndexOutOfBounds(key, index) => f.write_str(&format!(
+                "Primary key {} points to index {} which is out of bounds.",
+                key, index
+            )),
+            InvariantViolation::DuplicateIndex(index, keys) => f.write_str(&format!(
+                "Index {} is referenced by multiple primary keys: {}",
+                index,
+                keys.iter()
+                    .map(|key| key.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            InvariantViolation::PrimaryKeyMismatch(key, index) => f.write_str(&format!(
+                "The ID column at index {} does not contain the primary key {}.",
+                index, key
+            )),
+            InvariantViolation::InvalidDataType(identifier, index, data_type) => {
+                f.write_str(&format!(
+                "Cell at index {} in column {} has data type {} which does not match the column.",
+                index, identifier, data_type
+            ))
+            }
+            InvariantViolation::InvalidNullValue(identifier, index) => f.write_str(&format!(
+                "Cell at index {} in column {} is NULL although the column is not nullable.",
+                index, identifier
+            )),
+            InvariantViolation::ConstraintViolation(identifier, index, constraint) => {
+                f.write_str(&format!(
+                    "Cell at index {} in column {} violates the constraint {}.",
+                    index, identifier, constraint
+                ))
+            }
+            InvariantViolation::RowIdMismatch(index) => f.write_str(&format!(
+                "The row id at index {} does not point back to it.",
+                index
+            )),
+            InvariantViolation::StaleRowSlot(row_id, index) => f.write_str(&format!(
+                "Row id {} points to index {} which holds another row.",
+                row_id, index
+            )),
+            InvariantViolation::StaleBitmapIndex(identifier) => f.write_str(&format!(
+                "The bitmap index of column {} does not match the column values.",
+                identifier
+            )),
+            InvariantViolation::StaleBitmapIndexBuild(identifier) => f.write_str(&format!(
+                "The bitmap index being built for column {} does not match the column values.",
+                identifier
+            )),
+            InvariantViolation::StaleUniqueIndex(identifier) => f.write_str(&format!(
+                "The unique index of column {} does not match the column values.",
+                identifier
+            )),
+            InvariantViolation::StaleSpatialIndex(identifier) => f.write_str(&format!(
+                "The spatial index of column {} does not match the column values.",
+                identifier
+            )),
+            InvariantViolation::DuplicateUniqueValue(identifier, value) => f.write_str(&format!(
+                "Column {} has a unique index but holds the value {} more than once.",
+                identifier,
+                String::from(value)
+            )),
+            InvariantViolation::StaleOrderedKeys => {
+                f.write_str("The ordered keys do not match the primary keys of the table.")
+            }
+            InvariantViolation::MissingBloomFilterKey(key) => f.write_str(&format!(
+                "The bloom filter does not contain the primary key {}.",
+                key
+            )),
+        }
+    }
+}
+
+impl Display for InvariantReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        if self.is_consistent() {
+            return f.write_str("No invariant violations found.");
+        }
+
+        let lines = self
+            .violations
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect::<Vec<_>>();
+
+        f.write_str(&lines.join("\n"))
+    }
+}
@@ -0,0 +1,85 @@
+use crate::TableValue;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Declarative bounds for the values of a column, checked on every insert and update.
+/// NULL values always satisfy constraints, use `is_nullable` to forbid them. Constraints
+/// that don't fit the data type of the column are ignored.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Constraint {
+    // Inclusive bounds for Integer values
+    Min(i64),
+    Max(i64),
+    // Inclusive bounds for the number of characters of String values
+    MinLength(usize),
+    MaxLength(usize),
+    // String values may only consist of characters of this charset
+    Charset(Charset),
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Charset {
+    Ascii,
+    Alphanumeric,
+    Numeric,
+    // Only the characters in this String are allowed
+    Characters(String),
+}
+
+impl Constraint {
+    pub fn is_satisfied_by(&self, value: &TableValue) -> bool {
+        match (self, value) {
+            (Constraint::Min(min), value) if value.as_i128().is_some() => {
+                value.as_i128() >= Some(i128::from(*min))
+            }
+            (Constraint::Max(max), value) if value.as_i128().is_some() => {
+                value.as_i128() <= Some(i128::from(*max))
+            }
+            (Constraint::MinLength(min), TableValue::String(value)) => {
+                value.chars().count() >= *min
+            }
+            (Constraint::MaxLength(max), TableValue::String(value)) => {
+                value.chars().count() <= *max
+            }
+            (Constraint::Charset(charset), TableValue::String(value)) => {
+                value.chars().all(|character| charset.contains(character))
+            }
+            _ => true,
+        }
+    }
+}
+
+impl Charset {
+    pub fn contains(&self, character: char) -> bool {
+        match self {
+            Charset::Ascii => character.is_ascii(),
+            Charset::Alphanumeric => character.is_alphanumeric(),
+            Charset::Numeric => character.is_numeric(),
+            Charset::Characters(characters) => characters.contains(character),
+        }
+    }
+}
+
+impl Display for Constraint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Constraint::Min(min) => f.write_str(&format!("MIN {}", min)),
+            Constraint::Max(max) => f.write_str(&format!("MAX {}", max)),
+            Constraint::MinLength(min) => f.write_str(&format!("MIN LENGTH {}", min)),
+            Constraint::MaxLength(max) => f.write_str(&format!("MAX LENGTH {}", max)),
+            Constraint::Charset(charset) => f.write_str(&format!("CHARSET {}", charset)),
+        }
+    }
+}
+
+impl Display for Charset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Charset::Ascii => f.write_str("ASCII"),
+            Charset::Alphanumeric => f.write_str("ALPHANUMERIC"),
+            Charset::Numeric => f.write_str("NUMERIC"),
+            Charset::Characters(characters) => {
+                f.write_str(&format!("'{}'", characters.replace('\'', "''")))
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use crate::error::VirtualTableError;
+use crate::query::ColumnSpecification;
+use crate::timestamp::Timestamp;
+use crate::{PrimaryKey, Row, Table, TableValue};
+use std::collections::HashMap;
+
+/// When and on which node a cell was last written. Later clocks win, clocks of the same time are
+/// ordered by their node.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone)]
+pub struct Clock {
+    pub timestamp: Timestamp,
+    pub node_id: u64,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct CellClocks {
+    // Writes are only stamped once a node is set, see `Table::enable_crdt`
+    node_id: Option<u64>,
+    // Never goes backwards, even if the system clock does
+    last_timestamp: Option<Timestamp>,
+    clocks: HashMap<(PrimaryKey, String), Clock>,
+    // When rows were deleted, so a merge doesn't bring back rows that were deleted on one replica
+    tombstones: HashMap<PrimaryKey, Clock>,
+}
+
+// The clocks and tombstones of some rows, put back when a merge fails
+struct SavedClocks {
+    keys: Vec<PrimaryKey>,
+    clocks: Vec<((PrimaryKey, String), Clock)>,
+    tombstones: Vec<(PrimaryKey, Clock)>,
+}
+
+impl Table {
+    /// Stamps every cell written from now on with a `Clock` of this node, so replicas of the table
+    /// can be merged with `Table::merge_crdt`. Every replica needs its own node id. Cells that
+    /// were written before are stamped as written now.
+    pub fn enable_crdt(&mut self, node_id: u64) {
+        self.cell_clocks.node_id = Some(node_id);
+        let keys = self.keys.keys().copied().collect::<Vec<_>>();
+        for key in keys {
+            let identifiers = self.column_identifiers();
+            self.stamp_cell_clocks(key, identifiers);
+        }
+    }
+
+    pub fn cell_clock(&self, key: &PrimaryKey, column_identifier: &str) -> Option<Clock> {
+        self.cell_clocks
+            .clocks
+            .get(&(*key, String::from(column_identifier)))
+            .copied()
+    }
+
+    /// Merges the rows of another replica into this one, cell by cell the last write wins. Rows
+    /// only the other replica has are created. A row deleted on either replica is deleted on
+    /// both, unless one of its cells was written after the delete. Merging in any order leads to
+    /// the same table on every replica. Cells without a clock lose against all others, ties of
+    /// cells without a clock are decided by their values. Returns the number of rows that changed.
+    /// If a row can't be merged, e.g. because the schemas differ, the table is left untouched.
+    pub fn merge_crdt(&mut self, other: &Table) -> Result<usize, Vec<VirtualTableError>> {
+        let identifiers = other.column_identifiers();
+        let saved_clocks = self.save_cell_clocks(other);
+        let result = self.in_atomic_batch(|table| {
+            let mut changed = 0;
+            for (key, other_tombstone) in &other.cell_clocks.tombstones {
+                let tombstone = match table.cell_clocks.tombstones.get(key) {
+                    Some(tombstone) if tombstone >= other_tombstone => *tombstone,
+                    _ => *other_tombstone,
+                };
+                if table.contains_key(key) && table.newest_cell_clock(key) < Some(tombstone) {
+                    table.remove_row(key);
+                    changed += 1;
+                }
+                // Set after the removal, which stamps a tombstone of its own
+                table.cell_clocks.tombstones.insert(*key, tombstone);
+            }
+
+            for index in 0..other.row_ids.len() {
+                let key = match other.key_at(index) {
+                    Some(key) => key,
+                    None => continue,
+                };
+                let other_row = match other.find_row(&key, ColumnSpecification::All) {
+                    Some(row) => row,
+                    None => continue,
+                };
+                if let Some(tombstone) = table.cell_clocks.tombstones.get(&key) {
+                    if other.newest_cell_clock(&key) < Some(*tombstone) {
+                        continue;
+                    }
+                }
+
+                let mut row = Row::create(table, key);
+                let mut clocks = Vec::new();
+                let mut has_changes = false;
+                let is_new = !table.contains_key(&key);
+                let current = table.find_row(&key, ColumnSpecification::All);
+                for identifier in &identifiers {
+                    let other_value = other_row.get_cell(identifier).unwrap_or(&TableValue::Null);
+                    let other_clock = other.cell_clock(&key, identifier);
+                    if !is_new {
+                        let value = current
+                            .as_ref()
+                            .and_then(|row| row.get_cell(identifier))
+                            .unwrap_or(&TableValue::Null);
+                        let clock = table.cell_clock(&key, identifier);
+                        if (other_clock, String::from(other_value)) <= (clock, String::from(value))
+                        {
+                            continue;
+                        }
+                    }
+
+                    if let Some(cell) = other_row.cells.get(identifier).cloned().flatten() {
+                        row.cells.insert(identifier.clone(), Some(cell));
+                        has_changes = true;
+                    }
+                    if let Some(clock) = other_clock {
+                        clocks.push((identifier.clone(), clock));
+                    }
+                }
+                if !is_new && !has_changes {
+                    continue;
+                }
+
+                if is_new {
+                    table.insert_row(row)?;
+                } else {
+                    table.apply_update(row)?;
+                }
+                for (identifier, clock) in clocks {
+                    table.cell_clocks.clocks.insert((key, identifier), clock);
+                }
+                changed += 1;
+            }
+
+            Result::Ok(changed)
+        });
+        // Reverting the rows stamps clocks and tombstones of its own
+        if result.is_err() {
+            self.restore_cell_clocks(saved_clocks);
+        }
+
+        result
+    }
+
+    pub fn is_crdt_enabled(&self) -> bool {
+        self.cell_clocks.node_id.is_some()
+    }
+
+    // Stamps the cells with a new clock of this node, if there is one
+    pub(crate) fn stamp_cell_clocks(&mut self, key: PrimaryKey, identifiers: Vec<String>) {
+        let clock = match self.next_clock() {
+            Some(clock) => clock,
+            None => return,
+        };
+
+        for identifier in identifiers {
+            self.cell_clocks.clocks.insert((key, identifier), clock);
+        }
+    }
+
+    // Replaces the clocks of a deleted row by a tombstone, which wins against all older cells
+    pub(crate) fn forget_cell_clocks(&mut self, key: &PrimaryKey) {
+        self.cell_clocks.clocks.retain(|(other, _), _| other != key);
+        if let Some(clock) = self.next_clock() {
+            self.cell_clocks.tombstones.insert(*key, clock);
+        }
+    }
+
+    // The clocks and tombstones of all rows a merge of the other table may touch
+    fn save_cell_clocks(&self, other: &Table) -> SavedClocks {
+        let keys = other
+            .cell_clocks
+            .tombstones
+            .keys()
+            .chain(other.keys.keys())
+            .copied()
+            .collect::<Vec<_>>();
+        let identifiers = self.column_identifiers();
+        let clocks = keys
+            .iter()
+            .flat_map(|key| identifiers.iter().map(move |identifier| (*key, identifier)))
+            .filter_map(|(key, identifier)| {
+                let clock = self.cell_clock(&key, identifier)?;
+                Some(((key, identifier.clone()), clock))
+            })
+            .collect();
+        let tombstones = keys
+            .iter()
+            .filter_map(|key| Some((*key, *self.cell_clocks.tombstones.get(key)?)))
+            .collect();
+
+        SavedClocks {
+            keys,
+            clocks,
+            tombstones,
+        }
+    }
+
+    fn restore_cell_clocks(&mut self, saved: SavedClocks) {
+        let identifiers = self.column_identifiers();
+        for key in &saved.keys {
+            for identifier in &identifiers {
+                self.cell_clocks.clocks.remove(&(*key, identifier.clone()));
+            }
+            self.cell_clocks.tombstones.remove(key);
+        }
+        self.cell_clocks.clocks.extend(saved.clocks);
+        self.cell_clocks.tombstones.extend(saved.tombstones);
+    }
+
+    fn next_clock(&mut self) -> Option<Clock> {
+        let cell_clocks = &mut self.cell_clocks;
+        let node_id = cell_clocks.node_id?;
+
+        let now = Timestamp::now();
+        let timestamp = match cell_clocks.last_timestamp {
+            Some(last) if last >= now => Timestamp::from_unix_micros(last.unix_micros() + 1),
+            _ => now,
+        };
+        cell_clocks.last_timestamp = Some(timestamp);
+
+        Some(Clock { timestamp, node_id })
+    }
+
+    // The clock of the cell of the row that was written last, `None` if no cell has a clock
+    fn newest_cell_clock(&self, key: &PrimaryKey) -> Option<Clock> {
+        self.column_identifiers()
+            .iter()
+            .filter_map(|identifier| self.cell_clock(key, identifier))
+            .max()
+    }
+
+    pub(crate) fn column_identifiers(&self) -> Vec<String> {
+        self.columns
+            .keys()
+            .filter(|identifier| *identifier != "ID")
+            .cloned()
+            .collect()
+    }
+}
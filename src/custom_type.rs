@@ -0,0 +1,137 @@
+use crate::{Cell, DataType, IntoCell, TableValue};
+use std::any::TypeId;
+use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::hash::{Hash, Hasher};
+
+/// Domain types like email addresses or country codes can implement this trait to be stored in
+/// columns of `DataType::Custom`. Values are stored in their formatted form, which is expected to
+/// be parseable again.
+pub trait CustomType: Sized + 'static {
+    const NAME: &'static str;
+
+    fn parse(raw: &str) -> Result<Self, String>;
+    fn format(&self) -> String;
+    fn compare(&self, other: &Self) -> Ordering;
+}
+
+/// Identifies a custom type and knows how to handle its values without knowing the Rust type.
+#[derive(Copy, Clone)]
+pub struct CustomTypeId {
+    // A single function pointer keeps DataType (and with that every error) small
+    descriptor: fn() -> Descriptor,
+}
+
+struct Descriptor {
+    type_id: TypeId,
+    name: &'static str,
+    canonicalize: fn(&str) -> Result<String, String>,
+    compare: fn(&str, &str) -> Ordering,
+}
+
+fn describe<T: CustomType>() -> Descriptor {
+    Descriptor {
+        type_id: TypeId::of::<T>(),
+        name: T::NAME,
+        canonicalize: |raw| T::parse(raw).map(|value| value.format()),
+        compare: |left, right| match (T::parse(left), T::parse(right)) {
+            (Ok(left), Ok(right)) => left.compare(&right),
+            // Stored values always parse, this is just a sane fallback
+            _ => left.cmp(right),
+        },
+    }
+}
+
+impl CustomTypeId {
+    pub fn of<T: CustomType>() -> Self {
+        CustomTypeId {
+            descriptor: describe::<T>,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        (self.descriptor)().name
+    }
+
+    /// Validates the raw value and turns it into a value of this type.
+    pub fn parse(&self, raw: &str) -> Result<CustomValue, String> {
+        Result::Ok(CustomValue {
+            type_id: *self,
+            canonical: ((self.descriptor)().canonicalize)(raw)?,
+        })
+    }
+
+    fn type_id(&self) -> TypeId {
+        (self.descriptor)().type_id
+    }
+}
+
+impl PartialEq for CustomTypeId {
+    fn eq(&self, other: &Self) -> bool {
+        self.type_id() == other.type_id()
+    }
+}
+
+impl Eq for CustomTypeId {}
+
+impl Hash for CustomTypeId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.type_id().hash(state)
+    }
+}
+
+impl Debug for CustomTypeId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct CustomValue {
+    type_id: CustomTypeId,
+    canonical: String,
+}
+
+impl CustomValue {
+    pub fn type_id(&self) -> CustomTypeId {
+        self.type_id
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.canonical
+    }
+
+    pub fn parse<T: CustomType>(&self) -> Option<T> {
+        if self.type_id != CustomTypeId::of::<T>() {
+            return None;
+        }
+
+        T::parse(&self.canonical).ok()
+    }
+
+    /// Compares values using the comparison of their custom type, values of different types aren't comparable.
+    pub fn compare(&self, other: &CustomValue) -> Option<Ordering> {
+        if self.type_id != other.type_id {
+            return None;
+        }
+
+        let compare = (self.type_id.descriptor)().compare;
+        Some(compare(&self.canonical, &other.canonical))
+    }
+}
+
+/// Wrapper to turn values of custom types into cells, e.g. `Custom(email).into_cell()`.
+#[derive(Debug, Clone)]
+pub struct Custom<T>(pub T);
+
+impl<T: CustomType + Clone> IntoCell for Custom<T> {
+    fn into_cell(self) -> Cell {
+        Cell {
+            data_type: DataType::Custom(CustomTypeId::of::<T>()),
+            inner: TableValue::Custom(CustomValue {
+                type_id: CustomTypeId::of::<T>(),
+                canonical: self.0.format(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use crate::query::ColumnSpecification;
+use crate::{PrimaryKey, Row, Table, TableValue};
+use prettytable::{Attr, Cell as PCell, Row as PRow, Table as PTable};
+use std::collections::BTreeSet;
+use std::iter;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CellChange {
+    pub column_identifier: String,
+    // None means that the cell was not set in the respective row
+    pub old: Option<TableValue>,
+    pub new: Option<TableValue>,
+}
+
+impl Row {
+    /// Lists all cells whose values differ between this row and the other one, ordered by column identifier.
+    pub fn diff(&self, other: &Row) -> Vec<CellChange> {
+        let identifiers = self
+            .cells
+            .keys()
+            .chain(other.cells.keys())
+            .collect::<BTreeSet<_>>();
+
+        identifiers
+            .into_iter()
+            .filter_map(|identifier| {
+                let old = self.get_cell(identifier);
+                let new = other.get_cell(identifier);
+                if old == new {
+                    return None;
+                }
+
+                Some(CellChange {
+                    column_identifier: identifier.clone(),
+                    old: old.cloned(),
+                    new: new.cloned(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// The differences between two states of a table, matched by primary key.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct TableDiff {
+    // Rows that are only in the table after, in its insertion order
+    pub added: Vec<Row>,
+    // Rows that are only in the table before, in its insertion order
+    pub removed: Vec<Row>,
+    // Rows that are in both tables, with the cells that differ
+    pub changed: Vec<(PrimaryKey, Vec<CellChange>)>,
+}
+
+impl TableDiff {
+    pub fn between(before: &Table, after: &Table) -> Self {
+        let mut diff = TableDiff::default();
+        for row in rows_of(before) {
+            match after.find_row(&row.primary_key, ColumnSpecification::All) {
+                Some(after_row) => {
+                    let changes = row.diff(&after_row);
+                    if !changes.is_empty() {
+                        diff.changed.push((row.primary_key, changes));
+                    }
+                }
+                None => diff.removed.push(row),
+            }
+        }
+        diff.added = rows_of(after)
+            .filter(|row| !before.keys.contains_key(&row.primary_key))
+            .collect();
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Renders the differences between two states of a table as a boxed table, e.g. for test
+/// failures or to review a migration. Removed rows are marked with −, changed rows with ~ and
+/// added ones with +, unchanged rows are left out. Changed cells are shown as "old → new".
+/// A summary line follows the table.
+pub fn render_diff(before: &Table, after: &Table) -> String {
+    let diff = TableDiff::between(before, after);
+    if diff.is_empty() {
+        return String::from("no differences\n");
+    }
+
+    // Columns that were dropped are shown after the ones of the table after
+    let mut identifiers = after.columns.keys().collect::<Vec<_>>();
+    identifiers.extend(
+        before
+            .columns
+            .keys()
+            .filter(|identifier| !after.columns.contains_key(*identifier)),
+    );
+    let field = |table: &Table, row: &Row, identifier: &str| match row.get_cell(identifier) {
+        Some(value) => table.display_value(identifier, value),
+        None => String::new(),
+    };
+
+    let mut display_table = PTable::new();
+    display_table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    display_table.set_titles(PRow::new(
+        iter::once(PCell::new(""))
+            .chain(
+                identifiers.iter().map(|identifier| {
+                    PCell::new(after.heading_of(identifier)).with_style(Attr::Bold)
+                }),
+            )
+            .collect(),
+    ));
+    let mut add_row = |marker: &str, fields: Vec<String>| {
+        display_table.add_row(PRow::new(
+            iter::once(marker)
+                .chain(fields.iter().map(String::as_str))
+                .map(PCell::new)
+                .collect(),
+        ));
+    };
+
+    for row in &diff.removed {
+        add_row(
+            "\u{2212}",
+            identifiers
+                .iter()
+                .map(|identifier| field(before, row, identifier))
+                .collect(),
+        );
+    }
+    for (key, changes) in &diff.changed {
+        let row = match after.find_row(key, ColumnSpecification::All) {
+            Some(row) => row,
+            None => continue,
+        };
+        let fields =
+            identifiers
+                .iter()
+                .map(|identifier| {
+                    let change = changes
+                        .iter()
+                        .find(|change| change.column_identifier == **identifier);
+                    match change {
+                        Some(change) => format!(
+                            "{} \u{2192} {}",
+                            change.old.as_ref().map_or_else(String::new, |old| before
+                                .display_value(identifier, old)),
+                            change.new.as_ref().map_or_else(String::new, |new| after
+                                .display_value(identifier, new))
+                        ),
+                        None => field(after, &row, identifier),
+                    }
+                })
+                .collect();
+        add_row("~", fields);
+    }
+    for row in &diff.added {
+        add_row(
+            "+",
+            identifiers
+                .iter()
+                .map(|identifier| field(after, row, identifier))
+                .collect(),
+        );
+    }
+
+    format!(
+        "{}{} added, {} removed, {} changed\n",
+        display_table,
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    )
+}
+
+// All rows of the table in insertion order
+fn rows_of(table: &Table) -> impl Iterator<Item = Row> + '_ {
+    (0..table.row_ids.len())
+        .filter_map(move |index| table.key_at(index))
+        .filter_map(move |key| table.find_row(&key, ColumnSpecification::All))
+}
@@ -0,0 +1,197 @@
+use crate::error::VirtualTableError;
+use crate::{Cell, ColumnDefinition, DataType, IntoCell, PrimaryKey, Row, Table, TableValue};
+use std::collections::HashMap;
+
+impl Table {
+    /// Moves the columns into a new dimension table that holds every combination of their values
+    /// only once, e.g. the address columns of orders. Returns a copy of this table with a Uuid
+    /// column referencing the dimension row in place of the columns, and the dimension table.
+    /// Rows keep their keys, dimension rows get keys from the generator of the dimension table.
+    pub fn extract_dimension(
+        &self,
+        column_identifiers: &[&str],
+        dimension_identifier: &str,
+        foreign_key_column_identifier: &str,
+    ) -> Result<(Table, Table), VirtualTableError> {
+        let definitions = self.column_definitions();
+        for (position, column_identifier) in column_identifiers.iter().enumerate() {
+            if !definitions
+                .iter()
+                .any(|definition| definition.identifier == *column_identifier)
+            {
+                return Result::Err(VirtualTableError::UnknownColumn(String::from(
+                    *column_identifier,
+                )));
+            }
+            if column_identifiers[..position].contains(column_identifier) {
+                return Result::Err(VirtualTableError::DuplicateColumn(String::from(
+                    *column_identifier,
+                )));
+            }
+        }
+        let (dimension_definitions, remaining_definitions): (Vec<_>, Vec<_>) = definitions
+            .into_iter()
+            .partition(|definition| column_identifiers.contains(&definition.identifier.as_str()));
+        if remaining_definitions
+            .iter()
+            .any(|definition| definition.identifier == foreign_key_column_identifier)
+        {
+            return Result::Err(VirtualTableError::DuplicateColumn(String::from(
+                foreign_key_column_identifier,
+            )));
+        }
+        let mut definitions = remaining_definitions.clone();
+        definitions.push(ColumnDefinition::create(
+            foreign_key_column_identifier,
+            DataType::Uuid,
+            false,
+        ));
+
+        let mut dimension = Table::create(
+            String::from(dimension_identifier),
+            dimension_definitions.clone(),
+        );
+        let mut table = Table::create(self.identifier.clone(), definitions);
+        let mut dimension_keys: HashMap<Vec<TableValue>, PrimaryKey> = HashMap::new();
+        for index in 0..self.row_ids.len() {
+            let key = match self.key_at(index) {
+                Some(key) => key,
+                None => continue,
+            };
+
+            let combination = self.values_at(index, &dimension_definitions);
+            let dimension_key = match dimension_keys.get(&combination) {
+                Some(dimension_key) => *dimension_key,
+                None => {
+                    let dimension_key = dimension.generate_key();
+                    let mut row = Row::create(&dimension, dimension_key);
+                    for (definition, value) in dimension_definitions.iter().zip(&combination) {
+                        row.set_cell(definition.identifier.clone(), cell_of(definition, value));
+                    }
+                    dimension
+                        .create_row(row)
+                        .expect("Dimension rows have the values of valid rows.");
+                    dimension_keys.insert(combination, dimension_key);
+                    dimension_key
+                }
+            };
+
+            let mut row = Row::create(&table, key);
+            let values = self.values_at(index, &remaining_definitions);
+            for (definition, value) in remaining_definitions.iter().zip(&values) {
+                row.set_cell(definition.identifier.clone(), cell_of(definition, value));
+            }
+            row.set_cell(
+                String::from(foreign_key_column_identifier),
+                dimension_key.into_cell(),
+            );
+            table
+                .create_row(row)
+                .expect("Rows of a table always match its columns.");
+        }
+
+        Result::Ok((table, dimension))
+    }
+
+    /// The inverse of `Table::extract_dimension`, returns a copy of this table with the columns of
+    /// the dimension in place of the Uuid column referencing it. Rows referencing no row of the
+    /// dimension, or with a NULL reference, get NULL values, so the dimension columns are
+    /// nullable unless every row has a match.
+    pub fn denormalize(
+        &self,
+        foreign_key_column_identifier: &str,
+        dimension: &Table,
+    ) -> Result<Table, VirtualTableError> {
+        let foreign_keys = self
+            .columns
+            .get(foreign_key_column_identifier)
+            .ok_or_else(|| {
+                VirtualTableError::UnknownColumn(String::from(foreign_key_column_identifier))
+            })?;
+        if foreign_keys.data_type != DataType::Uuid {
+            return Result::Err(VirtualTableError::InvalidDataType(
+                String::from(foreign_key_column_identifier),
+                DataType::Uuid,
+                foreign_keys.data_type,
+            ));
+        }
+        let remaining_definitions = self
+            .column_definitions()
+            .into_iter()
+            .filter(|definition| definition.identifier != foreign_key_column_identifier)
+            .collect::<Vec<_>>();
+        let mut dimension_definitions = dimension.column_definitions();
+        if let Some(definition) = dimension_definitions.iter().find(|definition| {
+            remaining_definitions
+                .iter()
+                .any(|other| other.identifier == definition.identifier)
+        }) {
+            return Result::Err(VirtualTableError::DuplicateColumn(
+                definition.identifier.clone(),
+            ));
+        }
+
+        let matches = foreign_keys
+            .values
+            .iter()
+            .map(|value| match value {
+                TableValue::Uuid(key) => dimension.keys.get(key).copied(),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if matches.iter().any(Option::is_none) {
+            dimension_definitions
+                .iter_mut()
+                .for_each(|definition| definition.is_nullable = true);
+        }
+
+        let mut definitions = remaining_definitions.clone();
+        definitions.extend(dimension_definitions.iter().cloned());
+        let mut table = Table::create(self.identifier.clone(), definitions);
+        for (index, dimension_index) in matches.into_iter().enumerate() {
+            let key = match self.key_at(index) {
+                Some(key) => key,
+                None => continue,
+            };
+
+            let mut row = Row::create(&table, key);
+            let values = self.values_at(index, &remaining_definitions);
+            for (definition, value) in remaining_definitions.iter().zip(&values) {
+                row.set_cell(definition.identifier.clone(), cell_of(definition, value));
+            }
+            let dimension_values = match dimension_index {
+                Some(dimension_index) => {
+                    dimension.values_at(dimension_index, &dimension_definitions)
+                }
+                None => vec![TableValue::Null; dimension_definitions.len()],
+            };
+            for (definition, value) in dimension_definitions.iter().zip(&dimension_values) {
+                row.set_cell(definition.identifier.clone(), cell_of(definition, value));
+            }
+            table
+                .create_row(row)
+                .expect("Rows of a table always match its columns.");
+        }
+
+        Result::Ok(table)
+    }
+
+    fn values_at(&self, index: usize, definitions: &[ColumnDefinition]) -> Vec<TableValue> {
+        definitions
+            .iter()
+            .map(|definition| {
+                self.columns[&definition.identifier]
+                    .value_at(index)
+                    .cloned()
+                    .unwrap_or(TableValue::Null)
+            })
+            .collect()
+    }
+}
+
+fn cell_of(definition: &ColumnDefinition, value: &TableValue) -> Cell {
+    Cell {
+        data_type: definition.data_type,
+        inner: value.clone(),
+    }
+}
@@ -0,0 +1,88 @@
+use crate::collation::collation_key;
+use crate::error::VirtualTableError;
+use crate::{PrimaryKey, Table, TableValue};
+use std::collections::HashMap;
+
+/// Which row of a group of duplicates `Table::dedupe` keeps, by insertion order.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum KeepPolicy {
+    First,
+    Last,
+}
+
+impl Table {
+    /// Groups the keys of rows with equal values in all of the columns, only groups with more
+    /// than one row are returned. Values are compared with the collation of their column and
+    /// NULL values equal each other, just like in SELECT DISTINCT. Groups are ordered by their
+    /// first row, their keys in insertion order.
+    pub fn find_duplicates(
+        &self,
+        column_identifiers: &[&str],
+    ) -> Result<Vec<Vec<PrimaryKey>>, VirtualTableError> {
+        let columns = column_identifiers
+            .iter()
+            .map(|column_identifier| {
+                self.columns
+                    .get(*column_identifier)
+                    .map(|column| (column, self.collation_of(column_identifier)))
+                    .ok_or_else(|| {
+                        VirtualTableError::UnknownColumn(String::from(*column_identifier))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.record_rows_scanned("find_duplicates", self.row_ids.len());
+
+        let mut positions: HashMap<Vec<TableValue>, usize> = HashMap::new();
+        let mut groups: Vec<Vec<PrimaryKey>> = Vec::new();
+        for index in 0..self.row_ids.len() {
+            let key = match self.key_at(index) {
+                Some(key) => key,
+                None => continue,
+            };
+            let values = columns
+                .iter()
+                .map(|(column, collation)| match column.value_at(index) {
+                    Some(value) => collation_key(*collation, value),
+                    None => TableValue::Null,
+                })
+                .collect::<Vec<_>>();
+
+            let position = *positions.entry(values).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[position].push(key);
+        }
+
+        Result::Ok(groups.into_iter().filter(|keys| keys.len() > 1).collect())
+    }
+
+    /// Removes all duplicates found by `Table::find_duplicates` except one row of each group,
+    /// and returns the number of removed rows.
+    pub fn dedupe(
+        &mut self,
+        column_identifiers: &[&str],
+        keep: KeepPolicy,
+    ) -> Result<usize, VirtualTableError> {
+        let groups = self.find_duplicates(column_identifiers)?;
+
+        Result::Ok(self.in_batch(|table| {
+            let mut removed = 0;
+            for mut keys in groups {
+                match keep {
+                    KeepPolicy::First => keys.remove(0),
+                    KeepPolicy::Last => {
+                        keys.pop().expect("Groups of duplicates have several rows.")
+                    }
+                };
+
+                for key in keys {
+                    table.remove_row(&key);
+                    removed += 1;
+                }
+            }
+
+            removed
+        }))
+    }
+}
@@ -0,0 +1,55 @@
+use std::convert::TryFrom;
+use std::time::Duration;
+
+// Units used for formatting and parsing, from the largest to the smallest
+const UNITS: &[(&str, u128)] = &[
+    ("d", 86_400_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+/// Formats a duration with all units that aren't zero, like "2h 15m" or "1d 30s".
+pub fn format_duration(duration: Duration) -> String {
+    let mut remaining = duration.as_nanos();
+    if remaining == 0 {
+        return String::from("0s");
+    }
+
+    let mut parts = Vec::new();
+    for (unit, nanos) in UNITS {
+        if remaining >= *nanos {
+            parts.push(format!("{}{}", remaining / nanos, unit));
+            remaining %= nanos;
+        }
+    }
+
+    parts.join(" ")
+}
+
+/// Parses durations in the format of `format_duration`, the units may be in any order.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    if value.trim().is_empty() {
+        return None;
+    }
+
+    let mut nanos = 0u128;
+    for part in value.split_whitespace() {
+        let split = part.find(|character: char| !character.is_ascii_digit())?;
+        let (amount, unit) = part.split_at(split);
+        let (_, unit_nanos) = UNITS.iter().find(|(name, _)| *name == unit)?;
+        nanos = nanos.checked_add(amount.parse::<u128>().ok()?.checked_mul(*unit_nanos)?)?;
+    }
+
+    from_nanos(nanos)
+}
+
+// None if the duration is too long
+pub(crate) fn from_nanos(nanos: u128) -> Option<Duration> {
+    let seconds = u64::try_from(nanos / 1_000_000_000).ok()?;
+
+    Some(Duration::new(seconds, (nanos % 1_000_000_000) as u32))
+}
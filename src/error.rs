@@ -1,7 +1,10 @@
-use crate::{DataType, Index, PrimaryKey};
+use crate::constraint::Constraint;
+use crate::events::EventId;
+use crate::money::Currency;
+use crate::{DataType, Index, PrimaryKey, TableValue};
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
-#[derive(Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq)]
 pub enum VirtualTableError {
     InvalidRowIndex(Index),
     InvalidDataType(String, DataType, DataType),
@@ -10,6 +13,31 @@ pub enum VirtualTableError {
     UnknownColumn(String),
     UnknownPrimaryKey(PrimaryKey),
     InvalidNullValue(String),
+    UnknownEvent(EventId),
+    InvalidExpression(String),
+    ArithmeticError(String),
+    UnknownFunction(String),
+    // Name of the function and the message it failed with
+    FunctionError(String, String),
+    MissingIndex(String),
+    // The operation was cancelled via its token or ran past its deadline
+    Cancelled,
+    // The policy of the table doesn't permit writing the row with this key
+    PolicyViolation(PrimaryKey),
+    ConstraintViolation(String, Constraint),
+    // The column has a unique index and another row already holds an equal value
+    DuplicateValue(String, TableValue),
+    // Amounts in two different currencies were combined
+    CurrencyMismatch(Currency, Currency),
+    // Name of the column and why the imported values couldn't be stored in it
+    ImportError(String, String),
+    UnknownQuery(String),
+    // The column was named more than once where every column may only appear once
+    DuplicateColumn(String),
+    // The row was changed since its ETag was read
+    PreconditionFailed(PrimaryKey),
+    // A parameter of the call is out of its range, e.g. a negative cell size or fraction
+    InvalidArgument(String),
 }
 
 impl Display for VirtualTableError {
@@ -44,6 +72,64 @@ impl Display for VirtualTableError {
                 "Did not find a row with the primary key of {}",
                 key
             )),
+            VirtualTableError::UnknownEvent(event_id) => f.write_str(&format!(
+                "Did not find an event with the id {}",
+                event_id
+            )),
+            VirtualTableError::InvalidExpression(message) => {
+                f.write_str(&format!("Invalid expression: {}", message))
+            }
+            VirtualTableError::InvalidArgument(message) => {
+                f.write_str(&format!("Invalid argument: {}", message))
+            }
+            VirtualTableError::ArithmeticError(message) => {
+                f.write_str(&format!("Arithmetic error: {}", message))
+            }
+            VirtualTableError::UnknownFunction(name) => {
+                f.write_str(&format!("Didn't find a function with name {}", name))
+            }
+            VirtualTableError::FunctionError(name, message) => {
+                f.write_str(&format!("Function {} failed: {}", name, message))
+            }
+            VirtualTableError::MissingIndex(column_identifier) => f.write_str(&format!(
+                "Column {} has no bitmap index.",
+                column_identifier
+            )),
+            VirtualTableError::Cancelled => f.write_str("The operation was cancelled."),
+            VirtualTableError::ConstraintViolation(column_identifier, constraint) => f.write_str(
+                &format!(
+                    "Value for column {} violates the constraint {}.",
+                    column_identifier, constraint
+                ),
+            ),
+            VirtualTableError::CurrencyMismatch(left, right) => f.write_str(&format!(
+                "Can't combine amounts in {} and {}.",
+                left, right
+            )),
+            VirtualTableError::DuplicateValue(column_identifier, value) => f.write_str(&format!(
+                "Column {} already contains the value {}.",
+                column_identifier,
+                String::from(value)
+            )),
+            VirtualTableError::PolicyViolation(key) => f.write_str(&format!(
+                "The policy of the table doesn't permit writing the row with the primary key of {}",
+                key
+            )),
+            VirtualTableError::DuplicateColumn(column_identifier) => f.write_str(&format!(
+                "Column {} is listed more than once.",
+                column_identifier
+            )),
+            VirtualTableError::PreconditionFailed(key) => f.write_str(&format!(
+                "The row with the primary key of {} was changed since its ETag was read.",
+                key
+            )),
+            VirtualTableError::UnknownQuery(name) => {
+                f.write_str(&format!("There is no saved query named {}.", name))
+            }
+            VirtualTableError::ImportError(column_identifier, message) => f.write_str(&format!(
+                "Unable to import values for column {}: {}",
+                column_identifier, message
+            )),
         }
     }
 }
@@ -0,0 +1,51 @@
+use crate::error::VirtualTableError;
+use crate::query::ColumnSpecification;
+use crate::{PrimaryKey, Row, Table};
+
+impl Table {
+    /// Weak ETag of the whole table derived from its version, so it changes with every written
+    /// row. It's weak because changes to the schema don't advance the version.
+    pub fn etag(&self) -> String {
+        format!("W/\"{}\"", self.current_version())
+    }
+
+    /// Strong ETag of the row with the key derived from its content hash, `None` if there is no
+    /// such row. Writing the same values again keeps the ETag.
+    pub fn row_etag(&self, key: &PrimaryKey) -> Option<String> {
+        let row = self.find_row(key, ColumnSpecification::All)?;
+
+        Some(format!("\"{:016x}\"", row.content_hash()))
+    }
+
+    /// Like `Table::update_row`, but only if the current ETag of the row is listed in the
+    /// `If-Match` header value, otherwise fails with `PreconditionFailed` and leaves the row as
+    /// it is.
+    pub fn update_row_if_match(
+        &mut self,
+        update_row: Row,
+        if_match: &str,
+    ) -> Result<(), Vec<VirtualTableError>> {
+        let primary_key = update_row.primary_key;
+        let etag = self
+            .row_etag(&primary_key)
+            .ok_or_else(|| vec![VirtualTableError::UnknownPrimaryKey(primary_key)])?;
+        if !matches(if_match, &etag) {
+            return Result::Err(vec![VirtualTableError::PreconditionFailed(primary_key)]);
+        }
+
+        self.update_row(update_row)
+    }
+}
+
+/// Whether the ETag is listed in the value of an `If-Match` header, `*` matches every ETag.
+/// Uses the strong comparison of RFC 9110, so weak ETags never match.
+pub fn matches(if_match: &str, etag: &str) -> bool {
+    if etag.starts_with("W/") {
+        return false;
+    }
+
+    if_match
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate == etag)
+}
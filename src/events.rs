@@ -0,0 +1,75 @@
+use crate::error::VirtualTableError;
+use crate::{ColumnDefinition, PrimaryKey, Row, Table};
+
+pub type EventId = usize;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum TableEvent {
+    RowCreated(Row),
+    RowUpdated(Row),
+    RowDeleted(PrimaryKey),
+}
+
+impl Table {
+    pub fn is_event_sourced(&self) -> bool {
+        self.events.is_some()
+    }
+
+    /// All events recorded so far, the position in the slice is the event id.
+    pub fn events(&self) -> &[TableEvent] {
+        self.events.as_deref().unwrap_or(&[])
+    }
+
+    /// Reconstructs the state of the table right after the event with the given id has been applied.
+    pub fn replay_to(&self, event_id: EventId) -> Result<Table, Vec<VirtualTableError>> {
+        let events = self.events();
+        if event_id >= events.len() {
+            return Result::Err(vec![VirtualTableError::UnknownEvent(event_id)]);
+        }
+
+        let mut table =
+            Table::create_event_sourced(self.identifier.clone(), self.column_definitions());
+        for event in &events[..=event_id] {
+            match event.clone() {
+                TableEvent::RowCreated(row) => table.create_row(row)?,
+                TableEvent::RowUpdated(row) => table.update_row(row)?,
+                TableEvent::RowDeleted(key) => {
+                    table
+                        .remove_row(&key)
+                        .ok_or_else(|| vec![VirtualTableError::UnknownPrimaryKey(key)])?;
+                }
+            }
+        }
+
+        Result::Ok(table)
+    }
+
+    // The event is only built if we actually keep a log, so regular tables don't pay for the clone
+    pub(crate) fn prepare_event<F>(&self, build_event: F) -> Option<TableEvent>
+    where
+        F: FnOnce() -> TableEvent,
+    {
+        self.events.as_ref().map(|_| build_event())
+    }
+
+    pub(crate) fn record_event(&mut self, event: Option<TableEvent>) {
+        if let (Some(events), Some(event)) = (self.events.as_mut(), event) {
+            events.push(event);
+            self.event_versions.push(self.version);
+        }
+    }
+
+    pub(crate) fn column_definitions(&self) -> Vec<ColumnDefinition> {
+        self.columns
+            .values()
+            .filter(|column| column.identifier != "ID") // The ID column is added by the table itself
+            .map(|column| ColumnDefinition {
+                identifier: column.identifier.clone(),
+                data_type: column.data_type,
+                is_nullable: column.is_nullable,
+                constraints: column.constraints.clone(),
+                metadata: column.metadata.clone(),
+            })
+            .collect()
+    }
+}
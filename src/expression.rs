@@ -0,0 +1,394 @@
+use crate::duration::from_nanos;
+use crate::error::VirtualTableError;
+use crate::functions::ScalarFunction;
+use crate::timestamp::TimeZone;
+use crate::{DataType, Index, Table, TableValue};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expression {
+    Column(String),
+    Literal(TableValue),
+    // Concatenates String values, like `||` in SQL
+    Concat(Vec<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Subtract(Box<Expression>, Box<Expression>),
+    Multiply(Box<Expression>, Box<Expression>),
+    Divide(Box<Expression>, Box<Expression>),
+    Function(ScalarFunction, Vec<Expression>),
+    // Calls a user defined function registered on the table
+    Call(String, Vec<Expression>),
+    // Placeholder for a value that is only known when a saved query runs, see `Table::run_query`
+    Parameter(String),
+}
+
+impl Expression {
+    pub fn column(identifier: &str) -> Self {
+        Expression::Column(String::from(identifier))
+    }
+
+    pub fn literal<T: Into<TableValue>>(value: T) -> Self {
+        Expression::Literal(value.into())
+    }
+
+    pub fn function(function: ScalarFunction, arguments: Vec<Expression>) -> Self {
+        Expression::Function(function, arguments)
+    }
+
+    pub fn call(name: &str, arguments: Vec<Expression>) -> Self {
+        Expression::Call(String::from(name), arguments)
+    }
+
+    pub fn parameter(name: &str) -> Self {
+        Expression::Parameter(String::from(name))
+    }
+
+    pub fn plus(self, other: Expression) -> Self {
+        Expression::Add(Box::new(self), Box::new(other))
+    }
+
+    pub fn minus(self, other: Expression) -> Self {
+        Expression::Subtract(Box::new(self), Box::new(other))
+    }
+
+    pub fn times(self, other: Expression) -> Self {
+        Expression::Multiply(Box::new(self), Box::new(other))
+    }
+
+    pub fn divided_by(self, other: Expression) -> Self {
+        Expression::Divide(Box::new(self), Box::new(other))
+    }
+
+    /// Infers the data type of the values this expression produces for the given table.
+    pub fn data_type(&self, table: &Table) -> Result<DataType, VirtualTableError> {
+        self.infer_type(table)?.ok_or_else(|| {
+            VirtualTableError::InvalidExpression(format!("Can't infer the data type of {}.", self))
+        })
+    }
+
+    pub fn evaluate(&self, table: &Table, index: Index) -> Result<TableValue, VirtualTableError> {
+        match self {
+            Expression::Column(identifier) => table
+                .columns
+                .get(identifier)
+                .ok_or_else(|| VirtualTableError::UnknownColumn(identifier.clone()))?
+                .value_at(index)
+                .cloned()
+                .ok_or(VirtualTableError::InvalidRowIndex(index)),
+            Expression::Literal(value) => Result::Ok(value.clone()),
+            Expression::Concat(operands) => {
+                let mut result = String::new();
+                for operand in operands {
+                    match operand.evaluate(table, index)? {
+                        // Just like in SQL, anything concatenated with NULL is NULL
+                        TableValue::Null => return Result::Ok(TableValue::Null),
+                        TableValue::String(value) => result.push_str(&value),
+                        other => return Result::Err(self.type_error(DataType::String, &other)),
+                    }
+                }
+
+                Result::Ok(TableValue::String(result))
+            }
+            Expression::Add(left, right) => {
+                self.evaluate_arithmetic(table, index, left, right, i128::checked_add)
+            }
+            Expression::Subtract(left, right) => {
+                self.evaluate_arithmetic(table, index, left, right, i128::checked_sub)
+            }
+            Expression::Multiply(left, right) => {
+                self.evaluate_arithmetic(table, index, left, right, i128::checked_mul)
+            }
+            Expression::Divide(left, right) => {
+                self.evaluate_arithmetic(table, index, left, right, i128::checked_div)
+            }
+            Expression::Function(function, arguments) => {
+                let values = arguments
+                    .iter()
+                    .map(|argument| argument.evaluate(table, index))
+                    .collect::<Result<Vec<_>, _>>()?;
+                // Date functions work in the time zone of the column they are applied to
+                let time_zone = arguments
+                    .first()
+                    .map_or(TimeZone::Utc, |argument| table.time_zone_for(argument));
+
+                function.apply(values, time_zone)
+            }
+            Expression::Call(name, arguments) => {
+                let function = table.user_function(name)?;
+                let values = arguments
+                    .iter()
+                    .map(|argument| argument.evaluate(table, index))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                function.apply(name, values)
+            }
+            Expression::Parameter(name) => Result::Err(VirtualTableError::InvalidExpression(
+                format!("No value was bound to the parameter :{}.", name),
+            )),
+        }
+    }
+
+    /// Replaces all parameters with their values, every parameter needs a value.
+    pub fn bind(
+        &self,
+        parameters: &HashMap<String, TableValue>,
+    ) -> Result<Expression, VirtualTableError> {
+        let bind_all = |expressions: &[Expression]| {
+            expressions
+                .iter()
+                .map(|expression| expression.bind(parameters))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let bind_boxed = |expression: &Expression| expression.bind(parameters).map(Box::new);
+
+        Result::Ok(match self {
+            Expression::Column(_) | Expression::Literal(_) => self.clone(),
+            Expression::Concat(operands) => Expression::Concat(bind_all(operands)?),
+            Expression::Add(left, right) => Expression::Add(bind_boxed(left)?, bind_boxed(right)?),
+            Expression::Subtract(left, right) => {
+                Expression::Subtract(bind_boxed(left)?, bind_boxed(right)?)
+            }
+            Expression::Multiply(left, right) => {
+                Expression::Multiply(bind_boxed(left)?, bind_boxed(right)?)
+            }
+            Expression::Divide(left, right) => {
+                Expression::Divide(bind_boxed(left)?, bind_boxed(right)?)
+            }
+            Expression::Function(function, arguments) => {
+                Expression::Function(*function, bind_all(arguments)?)
+            }
+            Expression::Call(name, arguments) => {
+                Expression::Call(name.clone(), bind_all(arguments)?)
+            }
+            Expression::Parameter(name) => {
+                Expression::Literal(parameters.get(name).cloned().ok_or_else(|| {
+                    VirtualTableError::InvalidExpression(format!(
+                        "Missing a value for the parameter :{}.",
+                        name
+                    ))
+                })?)
+            }
+        })
+    }
+
+    // None means that the expression always evaluates to an untyped NULL
+    pub(crate) fn infer_type(&self, table: &Table) -> Result<Option<DataType>, VirtualTableError> {
+        match self {
+            Expression::Column(identifier) => table
+                .columns
+                .get(identifier)
+                .map(|column| Some(column.data_type))
+                .ok_or_else(|| VirtualTableError::UnknownColumn(identifier.clone())),
+            Expression::Literal(value) => Result::Ok(value.data_type()),
+            Expression::Concat(operands) => {
+                for operand in operands {
+                    operand.expect_type(table, DataType::String)?;
+                }
+
+                Result::Ok(Some(DataType::String))
+            }
+            Expression::Add(left, right)
+            | Expression::Subtract(left, right)
+            | Expression::Multiply(left, right)
+            | Expression::Divide(left, right) => {
+                let (left_type, right_type) = (left.infer_type(table)?, right.infer_type(table)?);
+                if let Some(data_type) = self.timestamp_arithmetic_type(left_type, right_type) {
+                    return Result::Ok(Some(data_type));
+                }
+
+                // Both operands need the same integer type, which is also the type of the result.
+                // Durations can only be added and subtracted.
+                let data_type = left_type.or(right_type).unwrap_or(DataType::Integer);
+                let is_numeric = data_type.is_integer()
+                    || (data_type == DataType::Duration && self.is_additive());
+                if !is_numeric {
+                    return Result::Err(VirtualTableError::InvalidDataType(
+                        left.to_string(),
+                        DataType::Integer,
+                        data_type,
+                    ));
+                }
+                left.expect_type(table, data_type)?;
+                right.expect_type(table, data_type)?;
+
+                Result::Ok(Some(data_type))
+            }
+            Expression::Function(function, arguments) => {
+                let argument_types = arguments
+                    .iter()
+                    .map(|argument| argument.infer_type(table))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                function.return_type(&argument_types)
+            }
+            Expression::Call(name, arguments) => {
+                // Arguments are checked by the function itself, but they still need to be valid expressions
+                for argument in arguments {
+                    argument.infer_type(table)?;
+                }
+
+                Result::Ok(Some(table.user_function(name)?.return_type()))
+            }
+            // The type is only known once a value is bound, just like for a NULL literal
+            Expression::Parameter(_) => Result::Ok(None),
+        }
+    }
+
+    fn expect_type(&self, table: &Table, expected: DataType) -> Result<(), VirtualTableError> {
+        match self.infer_type(table)? {
+            Some(data_type) if data_type != expected => Result::Err(
+                VirtualTableError::InvalidDataType(self.to_string(), expected, data_type),
+            ),
+            _ => Result::Ok(()),
+        }
+    }
+
+    fn evaluate_arithmetic<F>(
+        &self,
+        table: &Table,
+        index: Index,
+        left: &Expression,
+        right: &Expression,
+        operation: F,
+    ) -> Result<TableValue, VirtualTableError>
+    where
+        F: Fn(i128, i128) -> Option<i128>,
+    {
+        let (left, right) = (left.evaluate(table, index)?, right.evaluate(table, index)?);
+        let overflow = || {
+            VirtualTableError::ArithmeticError(format!("{} overflowed or divided by zero.", self))
+        };
+
+        // Timestamps move by durations, and the distance between two timestamps is a duration
+        let is_addition = matches!(self, Expression::Add(_, _));
+        let is_subtraction = matches!(self, Expression::Subtract(_, _));
+        match (&left, &right) {
+            (TableValue::Timestamp(timestamp), TableValue::Duration(duration))
+            | (TableValue::Duration(duration), TableValue::Timestamp(timestamp))
+                if is_addition =>
+            {
+                return timestamp
+                    .checked_add(*duration)
+                    .map(TableValue::Timestamp)
+                    .ok_or_else(overflow)
+            }
+            (TableValue::Timestamp(timestamp), TableValue::Duration(duration))
+                if is_subtraction =>
+            {
+                return timestamp
+                    .checked_sub(*duration)
+                    .map(TableValue::Timestamp)
+                    .ok_or_else(overflow)
+            }
+            (TableValue::Timestamp(later), TableValue::Timestamp(earlier)) if is_subtraction => {
+                return later
+                    .duration_since(*earlier)
+                    .map(TableValue::Duration)
+                    .ok_or_else(overflow)
+            }
+            _ => {}
+        }
+
+        let data_type = match (&left, &right) {
+            (TableValue::Null, _) | (_, TableValue::Null) => return Result::Ok(TableValue::Null),
+            (left, right) if left.data_type() != right.data_type() => {
+                let expected = left.data_type().unwrap_or(DataType::Integer);
+                return Result::Err(self.type_error(expected, right));
+            }
+            (left, _) => left.data_type().unwrap_or(DataType::Integer),
+        };
+
+        // All integer types are computed in i128 and have to fit into their type again afterwards,
+        // durations are computed in nanoseconds
+        let operands = match (&left, &right) {
+            (TableValue::Duration(left), TableValue::Duration(right)) if self.is_additive() => {
+                Some((left.as_nanos() as i128, right.as_nanos() as i128))
+            }
+            (left, right) => left.as_i128().zip(right.as_i128()),
+        };
+        match operands {
+            Some((left, right)) => operation(left, right)
+                .and_then(|result| match data_type {
+                    DataType::Duration => u128::try_from(result)
+                        .ok()
+                        .and_then(from_nanos)
+                        .map(TableValue::Duration),
+                    _ => TableValue::from_i128(result, data_type),
+                })
+                .ok_or_else(overflow),
+            _ => Result::Err(self.type_error(DataType::Integer, &left)),
+        }
+    }
+
+    fn timestamp_arithmetic_type(
+        &self,
+        left: Option<DataType>,
+        right: Option<DataType>,
+    ) -> Option<DataType> {
+        match (self, left?, right?) {
+            (Expression::Add(_, _), DataType::Timestamp, DataType::Duration)
+            | (Expression::Add(_, _), DataType::Duration, DataType::Timestamp)
+            | (Expression::Subtract(_, _), DataType::Timestamp, DataType::Duration) => {
+                Some(DataType::Timestamp)
+            }
+            (Expression::Subtract(_, _), DataType::Timestamp, DataType::Timestamp) => {
+                Some(DataType::Duration)
+            }
+            _ => None,
+        }
+    }
+
+    fn is_additive(&self) -> bool {
+        matches!(self, Expression::Add(_, _) | Expression::Subtract(_, _))
+    }
+
+    fn type_error(&self, expected: DataType, value: &TableValue) -> VirtualTableError {
+        VirtualTableError::InvalidDataType(
+            self.to_string(),
+            expected,
+            value.data_type().unwrap_or(expected),
+        )
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Expression::Column(identifier) => f.write_str(identifier),
+            Expression::Literal(TableValue::String(value)) => {
+                f.write_str(&format!("'{}'", value.replace('\'', "''")))
+            }
+            Expression::Literal(TableValue::Null) => f.write_str("NULL"),
+            Expression::Literal(value) => f.write_str(&String::from(value)),
+            Expression::Concat(operands) => f.write_str(
+                &operands
+                    .iter()
+                    .map(|operand| operand.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" || "),
+            ),
+            Expression::Add(left, right) => f.write_str(&format!("({} + {})", left, right)),
+            Expression::Subtract(left, right) => f.write_str(&format!("({} - {})", left, right)),
+            Expression::Multiply(left, right) => f.write_str(&format!("({} * {})", left, right)),
+            Expression::Divide(left, right) => f.write_str(&format!("({} / {})", left, right)),
+            Expression::Function(function, arguments) => {
+                f.write_str(&format!("{}({})", function, join_arguments(arguments)))
+            }
+            Expression::Call(name, arguments) => {
+                f.write_str(&format!("{}({})", name, join_arguments(arguments)))
+            }
+            Expression::Parameter(name) => f.write_str(&format!(":{}", name)),
+        }
+    }
+}
+
+fn join_arguments(arguments: &[Expression]) -> String {
+    arguments
+        .iter()
+        .map(|argument| argument.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
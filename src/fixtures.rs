@@ -0,0 +1,235 @@
+use crate::duration::from_nanos;
+use crate::error::VirtualTableError;
+use crate::geo::Point;
+use crate::timestamp::Timestamp;
+use crate::{Cell, DataType, Row, Table, TableValue};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+use std::time::Duration;
+use uuid::{Builder, Uuid, Variant, Version};
+
+const FIRST_NAMES: &[&str] = &[
+    "Anna", "Ben", "Clara", "David", "Emma", "Felix", "Greta", "Hannah", "Jonas", "Lena", "Lukas",
+    "Marie", "Niklas", "Paul", "Sophie", "Tim",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Bauer",
+    "Becker",
+    "Fischer",
+    "Hoffmann",
+    "Koch",
+    "Meyer",
+    "Müller",
+    "Richter",
+    "Schmidt",
+    "Schneider",
+    "Schulz",
+    "Wagner",
+    "Weber",
+    "Wolf",
+];
+
+#[derive(Debug, Clone)]
+pub enum Generator {
+    FirstName,
+    LastName,
+    FullName,
+    // Inclusive range of integers to pick from
+    IntegerRange(i64, i64),
+    // Inclusive range of durations to pick from
+    DurationRange(Duration, Duration),
+    // Picks points up to the given number of degrees away from the center in each direction
+    PointNear(Point, f64),
+    // Inclusive range of timestamps to pick from
+    TimestampRange(Timestamp, Timestamp),
+    Uuid,
+    Constant(TableValue),
+    // Picks one of the given values at random
+    OneOf(Vec<TableValue>),
+}
+
+impl Generator {
+    pub fn default_for(data_type: DataType) -> Self {
+        match data_type {
+            DataType::Integer | DataType::BigInt | DataType::UnsignedInteger => {
+                Generator::IntegerRange(0, 1000)
+            }
+            DataType::Duration => {
+                Generator::DurationRange(Duration::from_secs(0), Duration::from_secs(86_400))
+            }
+            // Any time in the 2020s
+            DataType::Timestamp => Generator::TimestampRange(
+                Timestamp::from_unix_micros(1_577_836_800_000_000),
+                Timestamp::from_unix_micros(1_893_455_999_999_999),
+            ),
+            DataType::Point => Generator::PointNear(
+                Point::create(0.0, 0.0).expect("Null Island is a valid point."),
+                180.0,
+            ),
+            DataType::String => Generator::FullName,
+            DataType::Uuid => Generator::Uuid,
+            // We can't make up values for custom types or currencies, so those columns need a
+            // configured generator
+            DataType::Money | DataType::Custom(_) => Generator::Constant(TableValue::Null),
+        }
+    }
+
+    // Integer generators work for columns of every integer type
+    pub(crate) fn generate_cell(&self, data_type: DataType, rng: &mut StdRng) -> Cell {
+        let value = self.generate(rng);
+        let value = match value.as_i128() {
+            Some(integer) if data_type.is_integer() => {
+                TableValue::from_i128(integer, data_type).unwrap_or(value)
+            }
+            _ => value,
+        };
+
+        Cell {
+            data_type: value.data_type().unwrap_or(data_type),
+            inner: value,
+        }
+    }
+
+    // Empty ranges, empty lists and distances that aren't finite can't be picked from
+    pub(crate) fn validate(&self, column_identifier: &str) -> Result<(), VirtualTableError> {
+        let is_valid = match self {
+            Generator::IntegerRange(min, max) => min <= max,
+            Generator::DurationRange(min, max) => min <= max,
+            Generator::TimestampRange(min, max) => min.unix_micros() <= max.unix_micros(),
+            Generator::PointNear(_, degrees) => degrees.is_finite() && *degrees >= 0.0,
+            Generator::OneOf(values) => !values.is_empty(),
+            _ => true,
+        };
+        if is_valid {
+            return Result::Ok(());
+        }
+
+        Result::Err(VirtualTableError::InvalidArgument(format!(
+            "The generator {:?} for column {} has nothing to pick from.",
+            self, column_identifier
+        )))
+    }
+
+    fn generate(&self, rng: &mut StdRng) -> TableValue {
+        match self {
+            Generator::FirstName => TableValue::from(pick(FIRST_NAMES, rng)),
+            Generator::LastName => TableValue::from(pick(LAST_NAMES, rng)),
+            Generator::FullName => TableValue::String(format!(
+                "{} {}",
+                pick(FIRST_NAMES, rng),
+                pick(LAST_NAMES, rng)
+            )),
+            Generator::IntegerRange(min, max) => TableValue::Integer(rng.gen_range(*min..=*max)),
+            Generator::DurationRange(min, max) => {
+                let nanos = rng.gen_range(min.as_nanos()..=max.as_nanos());
+                from_nanos(nanos).map_or(TableValue::Null, TableValue::Duration)
+            }
+            Generator::TimestampRange(min, max) => TableValue::Timestamp(
+                Timestamp::from_unix_micros(rng.gen_range(min.unix_micros()..=max.unix_micros())),
+            ),
+            Generator::PointNear(center, degrees) => {
+                let latitude = center.latitude() + rng.gen_range(-*degrees..=*degrees);
+                let longitude = center.longitude() + rng.gen_range(-*degrees..=*degrees);
+                Point::create(latitude.clamp(-90.0, 90.0), longitude.clamp(-180.0, 180.0))
+                    .map_or(TableValue::Null, TableValue::Point)
+            }
+            Generator::Uuid => TableValue::Uuid(random_uuid(rng)),
+            Generator::Constant(value) => value.clone(),
+            Generator::OneOf(values) => values
+                .choose(rng)
+                .cloned()
+                .expect("Generators are validated before they are used."),
+        }
+    }
+}
+
+/// Populates tables with fake data. Every column uses the default generator for its data type
+/// unless a different generator has been configured for it.
+pub struct Fixtures {
+    generators: HashMap<String, Generator>,
+    rng: StdRng,
+}
+
+impl Fixtures {
+    pub fn create() -> Self {
+        Fixtures {
+            generators: HashMap::new(),
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn with_generator(mut self, column_identifier: &str, generator: Generator) -> Self {
+        self.generators
+            .insert(String::from(column_identifier), generator);
+        self
+    }
+
+    pub fn populate(
+        &mut self,
+        table: &mut Table,
+        count: usize,
+    ) -> Result<(), Vec<VirtualTableError>> {
+        let unknown_columns = self
+            .generators
+            .keys()
+            .filter(|identifier| !table.columns.contains_key(*identifier))
+            .map(|identifier| VirtualTableError::UnknownColumn(identifier.clone()))
+            .collect::<Vec<_>>();
+        if !unknown_columns.is_empty() {
+            return Result::Err(unknown_columns);
+        }
+
+        let invalid_generators = self
+            .generators
+            .iter()
+            .filter_map(|(identifier, generator)| generator.validate(identifier).err())
+            .collect::<Vec<_>>();
+        if !invalid_generators.is_empty() {
+            return Result::Err(invalid_generators);
+        }
+
+        table.in_batch(|table| {
+            for _ in 0..count {
+                let mut row = Row::create(table, random_uuid(&mut self.rng));
+                table
+                    .columns
+                    .iter()
+                    .filter(|(identifier, _)| *identifier != "ID")
+                    .for_each(|(identifier, column)| {
+                        let cell = match self.generators.get(identifier) {
+                            Some(generator) => {
+                                generator.generate_cell(column.data_type, &mut self.rng)
+                            }
+                            None => Generator::default_for(column.data_type)
+                                .generate_cell(column.data_type, &mut self.rng),
+                        };
+
+                        row.set_cell(identifier.clone(), cell);
+                    });
+
+                table.create_row(row)?;
+            }
+
+            Result::Ok(())
+        })
+    }
+}
+
+fn pick<'a>(values: &[&'a str], rng: &mut StdRng) -> &'a str {
+    values.choose(rng).expect("Name lists are never empty.")
+}
+
+fn random_uuid(rng: &mut StdRng) -> Uuid {
+    Builder::from_bytes(rng.gen())
+        .set_variant(Variant::RFC4122)
+        .set_version(Version::Random)
+        .build()
+}
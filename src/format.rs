@@ -1,47 +1,605 @@
-use crate::{DataType, Table};
+use crate::error::VirtualTableError;
+use crate::{DataType, Index, Table, TableValue};
+use prettytable::format::Alignment as PAlignment;
 use prettytable::{Attr, Cell as PCell, Row as PRow, Table as PTable};
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::iter;
 
 impl Display for Table {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let (display_table, elided) = self.display_table(self.keys.values().copied());
+        display_table.fmt(f)?;
+        f.write_str(&elision_note(elided))
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+impl Alignment {
+    // Numbers line up at their last digit, fixed width values are centered and text reads from
+    // the left
+    fn default_for(data_type: DataType) -> Self {
+        match data_type {
+            DataType::Integer
+            | DataType::BigInt
+            | DataType::UnsignedInteger
+            | DataType::Duration
+            | DataType::Money => Alignment::Right,
+            DataType::Uuid => Alignment::Center,
+            _ => Alignment::Left,
+        }
+    }
+}
+
+impl From<Alignment> for PAlignment {
+    fn from(alignment: Alignment) -> Self {
+        match alignment {
+            Alignment::Left => PAlignment::LEFT,
+            Alignment::Center => PAlignment::CENTER,
+            Alignment::Right => PAlignment::RIGHT,
+        }
+    }
+}
+
+/// How `Table::to_string_with` renders a table.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub enum FormatStyle {
+    // The boxed table that `Display` renders
+    #[default]
+    Table,
+    Csv,
+    Tsv,
+    // One block of heading and value lines per row, like the expanded display of psql
+    Vertical,
+}
+
+/// What happens to String values that are wider than their column, see
+/// `Table::set_width_budget`.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Overflow {
+    // Breaks the value into several lines, preferably between words
+    Wrap,
+    // Cuts the value off and ends it with an ellipsis
+    Truncate,
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub(crate) struct WidthBudget {
+    width: usize,
+    overflow: Overflow,
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub(crate) enum WidthSetting {
+    // Nothing was set, with the `terminal-width` feature the width of the terminal is used
+    Unset,
+    // The table is always rendered at its full width
+    Unlimited,
+    Budget(WidthBudget),
+}
+
+impl Table {
+    /// Renders the table in the given style. The delimited styles write a header line followed by
+    /// one line per row in insertion order, with NULL as an empty field, which makes them easy to
+    /// pipe into other command line tools.
+    pub fn to_string_with(&self, style: FormatStyle) -> String {
+        match style {
+            FormatStyle::Table => self.to_string(),
+            FormatStyle::Csv => self.to_delimited(',', escape_csv),
+            FormatStyle::Tsv => self.to_delimited('\t', escape_tsv),
+            FormatStyle::Vertical => self.to_vertical(),
+        }
+    }
+
+    /// Renders a single page of rows in insertion order, followed by a footer like
+    /// "showing 51–100 of 12,340 rows". Pages start at 1, a page past the last row only shows the footer.
+    pub fn display_page(&self, page: usize, page_size: usize) -> String {
+        let row_count = self.row_ids.len();
+        let start = page
+            .saturating_sub(1)
+            .saturating_mul(page_size)
+            .min(row_count);
+        let end = start.saturating_add(page_size).min(row_count);
+
+        let footer = if start == end {
+            format!("showing 0 of {} rows", group_digits(row_count))
+        } else {
+            format!(
+                "showing {}\u{2013}{} of {} rows",
+                group_digits(start + 1),
+                group_digits(end),
+                group_digits(row_count)
+            )
+        };
+
+        let (display_table, elided) = self.display_table(start..end);
+        format!("{}{}{}\n", display_table, elision_note(elided), footer)
+    }
+
+    /// Renders the table as a LaTeX `tabular` environment with one line per row in insertion
+    /// order. Columns are aligned like in `Display`, see `Table::alignment_of`.
+    pub fn to_latex(&self) -> String {
+        let column_alignments = self
+            .columns
+            .keys()
+            .map(|identifier| match self.alignment_of(identifier) {
+                Alignment::Left => 'l',
+                Alignment::Center => 'c',
+                Alignment::Right => 'r',
+            })
+            .collect::<String>();
+        let header = self
+            .columns
+            .keys()
+            .map(|identifier| escape_latex(self.heading_of(identifier)))
+            .collect::<Vec<_>>();
+
+        let mut lines = vec![
+            format!("\\begin{{tabular}}{{{}}}", column_alignments),
+            String::from("\\hline"),
+            format!("{} \\\\", header.join(" & ")),
+            String::from("\\hline"),
+        ];
+        lines.extend(self.text_rows().into_iter().map(|fields| {
+            let fields = fields
+                .into_iter()
+                .map(|field| field.map_or_else(String::new, |field| escape_latex(&field)))
+                .collect::<Vec<_>>();
+            format!("{} \\\\", fields.join(" & "))
+        }));
+        lines.push(String::from("\\hline"));
+        lines.push(String::from("\\end{tabular}"));
+
+        lines.into_iter().map(|line| line + "\n").collect()
+    }
+
+    /// Aligns the column in rendered output regardless of its data type.
+    pub fn set_column_alignment(
+        &mut self,
+        column_identifier: &str,
+        alignment: Alignment,
+    ) -> Result<(), VirtualTableError> {
+        if !self.columns.contains_key(column_identifier) {
+            return Result::Err(VirtualTableError::UnknownColumn(String::from(
+                column_identifier,
+            )));
+        }
+
+        self.column_alignments
+            .insert(String::from(column_identifier), alignment);
+        Result::Ok(())
+    }
+
+    pub fn clear_column_alignment(&mut self, column_identifier: &str) -> bool {
+        self.column_alignments.remove(column_identifier).is_some()
+    }
+
+    /// Fits the boxed table into the total width, borders included, by narrowing String columns
+    /// until they fit. The available width is shared evenly, columns that need less than their
+    /// share keep their width. Columns of other types are never narrowed, so the table can still
+    /// end up wider if they alone exceed the budget. Delimited exports are not affected.
+    ///
+    /// With the `terminal-width` feature, tables without a budget of their own are truncated to
+    /// the width of the terminal whenever stdout is one, which includes `Table::to_string`. A
+    /// budget set here or `Table::disable_width_budget` always takes precedence.
+    pub fn set_width_budget(&mut self, width: usize, overflow: Overflow) {
+        self.width_budget = WidthSetting::Budget(WidthBudget { width, overflow });
+    }
+
+    /// Renders the boxed table at its full width, even with the `terminal-width` feature, e.g.
+    /// so the output doesn't depend on where the program runs.
+    pub fn disable_width_budget(&mut self) {
+        self.width_budget = WidthSetting::Unlimited;
+    }
+
+    /// Goes back to the default of no budget of its own, which is the terminal width with the
+    /// `terminal-width` feature. Returns false if nothing was set.
+    pub fn clear_width_budget(&mut self) -> bool {
+        std::mem::replace(&mut self.width_budget, WidthSetting::Unset) != WidthSetting::Unset
+    }
+
+    /// Pins columns for tables rendered with a width budget. Pinned columns are always shown,
+    /// the others in their order as long as they fit at their full width. Columns after that are
+    /// left out and counted in a note like "… +7 more columns" below the table. The ID column
+    /// isn't pinned by default, but can be pinned to keep rows recognizable.
+    pub fn pin_columns(&mut self, identifiers: Vec<String>) -> Result<(), VirtualTableError> {
+        if let Some(identifier) = identifiers
+            .iter()
+            .find(|identifier| !self.columns.contains_key(*identifier))
+        {
+            return Result::Err(VirtualTableError::UnknownColumn(identifier.clone()));
+        }
+
+        self.pinned_columns = identifiers;
+        Result::Ok(())
+    }
+
+    pub fn unpin_columns(&mut self) -> bool {
+        let was_pinned = !self.pinned_columns.is_empty();
+        self.pinned_columns.clear();
+
+        was_pinned
+    }
+
+    /// Sets how columns of the data type are aligned in rendered output, unless the column has an
+    /// alignment of its own.
+    pub fn set_type_alignment(&mut self, data_type: DataType, alignment: Alignment) {
+        self.type_alignments
+            .retain(|(other, _)| *other != data_type);
+        self.type_alignments.push((data_type, alignment));
+    }
+
+    pub fn alignment_of(&self, column_identifier: &str) -> Alignment {
+        if let Some(alignment) = self.column_alignments.get(column_identifier) {
+            return *alignment;
+        }
+
+        let data_type = match self.columns.get(column_identifier) {
+            Some(column) => column.data_type,
+            None => return Alignment::Left,
+        };
+
+        self.type_alignments
+            .iter()
+            .find(|(other, _)| *other == data_type)
+            .map_or_else(
+                || Alignment::default_for(data_type),
+                |(_, alignment)| *alignment,
+            )
+    }
+
+    fn to_delimited<F>(&self, delimiter: char, escape: F) -> String
+    where
+        F: Fn(&str, char) -> String,
+    {
+        let separator = delimiter.to_string();
+        let mut lines = vec![self
+            .columns
+            .keys()
+            .map(|identifier| escape(self.heading_of(identifier), delimiter))
+            .collect::<Vec<_>>()
+            .join(&separator)];
+
+        lines.extend(self.text_rows().into_iter().map(|fields| {
+            fields
+                .into_iter()
+                .map(|field| field.map_or_else(String::new, |field| escape(&field, delimiter)))
+                .collect::<Vec<_>>()
+                .join(&separator)
+        }));
+
+        lines.into_iter().map(|line| line + "\n").collect()
+    }
+
+    fn to_vertical(&self) -> String {
+        let headings = self
+            .columns
+            .keys()
+            .map(|identifier| self.heading_of(identifier))
+            .collect::<Vec<_>>();
+        let rows = self.text_rows();
+        // The headings are padded so the separator lines fit in front of the border like in psql
+        let label_width = format!("-[ RECORD {} ]", rows.len()).len() - 1;
+        let heading_width = headings
+            .iter()
+            .map(|heading| heading.chars().count())
+            .chain(iter::once(label_width))
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = Vec::new();
+        for (number, fields) in rows.into_iter().enumerate() {
+            let fields = fields
+                .into_iter()
+                .map(|field| field.unwrap_or_else(|| String::from("*NULL*")))
+                .collect::<Vec<_>>();
+            let value_width = fields
+                .iter()
+                .flat_map(|field| field.lines())
+                .map(|line| line.chars().count())
+                .max()
+                .unwrap_or(0);
+
+            // The separator line marks the border between headings and values with a +
+            let label = format!("-[ RECORD {} ]", number + 1);
+            let padding = (heading_width + 1).saturating_sub(label.chars().count());
+            lines.push(format!(
+                "{}{}+{}",
+                label,
+                "-".repeat(padding),
+                "-".repeat(value_width + 1)
+            ));
+
+            for (heading, field) in headings.iter().zip(&fields) {
+                // Continuation lines of values with line breaks leave the heading empty
+                for (line_number, line) in field.split('\n').enumerate() {
+                    let heading = if line_number == 0 { heading } else { "" };
+                    lines.push(format!(
+                        "{:width$} | {}",
+                        heading,
+                        line,
+                        width = heading_width
+                    ));
+                }
+            }
+        }
+
+        lines.into_iter().map(|line| line + "\n").collect()
+    }
+
+    // The boxed table with the columns that fit into the width budget and the number of the
+    // columns that were left out
+    pub(crate) fn display_table<I>(&self, indexes: I) -> (PTable, usize)
+    where
+        I: IntoIterator<Item = Index>,
+    {
+        let indexes = indexes.into_iter().collect::<Vec<_>>();
+        let (identifiers, widths) = self.layout(&indexes);
+        let budget = self.effective_width_budget();
+        let fit = |identifier: &str, text: &str| match (widths.get(identifier), budget) {
+            (Some(width), Some(budget)) => fit_into(text, *width, budget.overflow),
+            _ => String::from(text),
+        };
+
         let mut display_table = PTable::new();
         display_table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
 
         // Create the header row first
         let header_row = PRow::new(
-            self.columns
-                .keys()
+            identifiers
+                .iter()
                 .map(|identifier| {
-                    PCell::new(identifier)
-                        .with_style(Attr::Bold)
-                        .with_style(Attr::ForegroundColor(prettytable::color::GREEN))
+                    PCell::new_align(
+                        &fit(identifier, self.heading_of(identifier)),
+                        self.alignment_of(identifier).into(),
+                    )
+                    .with_style(Attr::Bold)
+                    .with_style(Attr::ForegroundColor(prettytable::color::GREEN))
                 })
                 .collect(),
         );
         display_table.set_titles(header_row);
 
         // Fill in the values
-        self.keys.iter().for_each(|(_, index)| {
+        indexes.into_iter().for_each(|index| {
             let mut row = PRow::empty();
-            self.columns.iter().for_each(|(_, column)| {
-                let val = column.value_at(*index).unwrap();
-                println!("{:?}", val);
-                row.add_cell(PCell::new(&String::from(val)))
+            identifiers.iter().for_each(|identifier| {
+                let val = self.columns[*identifier].value_at(index).unwrap();
+                let cell = PCell::new_align(
+                    &fit(identifier, &self.display_value(identifier, val)),
+                    self.alignment_of(identifier).into(),
+                );
+                row.add_cell(
+                    self.highlights_at(index, identifier)
+                        .into_iter()
+                        .fold(cell, |cell, highlight| cell.with_style(highlight.attr())),
+                )
             });
 
             display_table.add_row(row);
         });
 
-        display_table.fmt(f)
+        (display_table, self.columns.len() - identifiers.len())
     }
+
+    // The columns that are shown within the width budget and the narrowed width of every String
+    // column among them that doesn't fit as it is
+    fn layout(&self, indexes: &[Index]) -> (Vec<&String>, HashMap<String, usize>) {
+        let budget = match self.effective_width_budget() {
+            Some(budget) => budget,
+            None => return (self.columns.keys().collect(), HashMap::new()),
+        };
+
+        let columns = self
+            .columns
+            .iter()
+            .map(|(identifier, column)| {
+                let width = indexes
+                    .iter()
+                    .filter_map(|index| column.value_at(*index))
+                    .map(|value| self.display_value(identifier, value).chars().count())
+                    .chain(iter::once(self.heading_of(identifier).chars().count()))
+                    .max()
+                    .unwrap_or(0);
+                (identifier, column.data_type, width)
+            })
+            .collect::<Vec<_>>();
+        let is_pinned = |identifier: &String| self.pinned_columns.contains(identifier);
+
+        // Every column is padded by a space on both sides and followed by a border
+        let visible = if self.pinned_columns.is_empty() {
+            columns
+        } else {
+            let mut used = 1 + columns
+                .iter()
+                .filter(|(identifier, _, _)| is_pinned(identifier))
+                .map(|(_, _, width)| width + 3)
+                .sum::<usize>();
+            let mut is_full = false;
+            columns
+                .into_iter()
+                .filter(|(identifier, _, width)| {
+                    if is_pinned(identifier) {
+                        return true;
+                    }
+                    is_full = is_full || used + width + 3 > budget.width;
+                    if !is_full {
+                        used += width + 3;
+                    }
+
+                    !is_full
+                })
+                .collect()
+        };
+        let identifiers = visible
+            .iter()
+            .map(|(identifier, _, _)| *identifier)
+            .collect::<Vec<_>>();
+
+        let decoration = 3 * visible.len() + 1;
+        let (mut narrowable, fixed): (Vec<_>, Vec<_>) = visible
+            .into_iter()
+            .partition(|(_, data_type, _)| *data_type == DataType::String);
+        let mut available = budget
+            .width
+            .saturating_sub(decoration)
+            .saturating_sub(fixed.iter().map(|(_, _, width)| width).sum());
+
+        // Narrow columns go first, so the width they don't need is left to the wider ones
+        narrowable.sort_by_key(|(_, _, width)| *width);
+        let mut remaining = narrowable.len();
+        let mut widths = HashMap::new();
+        for (identifier, _, width) in narrowable {
+            let share = (available / remaining).max(1);
+            if width > share {
+                widths.insert(identifier.clone(), share);
+            }
+            available = available.saturating_sub(width.min(share));
+            remaining -= 1;
+        }
+
+        (identifiers, widths)
+    }
+
+    fn effective_width_budget(&self) -> Option<WidthBudget> {
+        match self.width_budget {
+            WidthSetting::Budget(budget) => Some(budget),
+            WidthSetting::Unlimited => None,
+            #[cfg(feature = "terminal-width")]
+            WidthSetting::Unset => {
+                terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| WidthBudget {
+                    width: usize::from(width),
+                    overflow: Overflow::Truncate,
+                })
+            }
+            #[cfg(not(feature = "terminal-width"))]
+            WidthSetting::Unset => None,
+        }
+    }
+
+    // The displayed values of every row in insertion order, None for NULL
+    pub(crate) fn text_rows(&self) -> Vec<Vec<Option<String>>> {
+        (0..self.row_ids.len())
+            .map(|index| {
+                self.columns
+                    .iter()
+                    .map(|(identifier, column)| match column.value_at(index) {
+                        Some(TableValue::Null) | None => None,
+                        Some(value) => Some(self.display_value(identifier, value)),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+// Quotes fields the way RFC 4180 asks for, only when they would otherwise break the line
+fn escape_csv(field: &str, delimiter: char) -> String {
+    if field.contains(&[delimiter, '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        String::from(field)
+    }
+}
+
+// TSV has no quoting, so tabs and line breaks are escaped with backslashes instead
+fn escape_tsv(field: &str, _: char) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn fit_into(text: &str, width: usize, overflow: Overflow) -> String {
+    if text.chars().count() <= width {
+        return String::from(text);
+    }
+
+    match overflow {
+        Overflow::Truncate => text
+            .chars()
+            .take(width.saturating_sub(1))
+            .chain(iter::once('\u{2026}'))
+            .collect(),
+        Overflow::Wrap => {
+            let mut lines = Vec::new();
+            let mut line = Vec::new();
+            for word in text.split(' ') {
+                let word = word.chars().collect::<Vec<_>>();
+                if !line.is_empty() && line.len() + 1 + word.len() <= width {
+                    line.push(' ');
+                    line.extend(word);
+                    continue;
+                }
+
+                // Words that are too long for a line of their own are broken up
+                for chunk in word.chunks(width) {
+                    if !line.is_empty() {
+                        lines.push(line.iter().collect::<String>());
+                    }
+                    line = chunk.to_vec();
+                }
+            }
+            lines.push(line.iter().collect::<String>());
+
+            lines.join("\n")
+        }
+    }
+}
+
+// Empty if no columns were left out
+pub(crate) fn elision_note(elided: usize) -> String {
+    match elided {
+        0 => String::new(),
+        1 => String::from("\u{2026} +1 more column\n"),
+        elided => format!("\u{2026} +{} more columns\n", elided),
+    }
+}
+
+// Writes 12340 as "12,340"
+fn group_digits(number: usize) -> String {
+    let digits = number.to_string().chars().rev().collect::<Vec<_>>();
+    let groups = digits
+        .chunks(3)
+        .rev()
+        .map(|group| group.iter().rev().collect::<String>())
+        .collect::<Vec<_>>();
+
+    groups.join(",")
+}
+
+fn escape_latex(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => format!("\\{}", c),
+            '~' => String::from("\\textasciitilde{}"),
+            '^' => String::from("\\textasciicircum{}"),
+            '\\' => String::from("\\textbackslash{}"),
+            c => c.to_string(),
+        })
+        .collect()
 }
 
 impl Display for DataType {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
             DataType::Integer => f.write_str("INTEGER"),
+            DataType::BigInt => f.write_str("BIGINT"),
+            DataType::UnsignedInteger => f.write_str("UNSIGNED INTEGER"),
+            DataType::Duration => f.write_str("DURATION"),
+            DataType::Timestamp => f.write_str("TIMESTAMP"),
+            DataType::Money => f.write_str("MONEY"),
+            DataType::Point => f.write_str("POINT"),
             DataType::String => f.write_str("STRING"),
             DataType::Uuid => f.write_str("UUID"),
+            DataType::Custom(type_id) => f.write_str(type_id.name()),
         }
     }
 }
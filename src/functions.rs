@@ -0,0 +1,321 @@
+use crate::error::VirtualTableError;
+use crate::fuzzy::{levenshtein_distance, trigram_similarity};
+use crate::timestamp::{TimeZone, Timestamp};
+use crate::{DataType, Table, TableValue};
+use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+
+type UserFunctionBody = dyn Fn(&[TableValue]) -> Result<TableValue, String> + Send + Sync;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScalarFunction {
+    Upper,
+    Lower,
+    Trim,
+    // Number of characters in a String
+    Length,
+    // substr(value, start, [length]) with a 1-based start, just like in SQL
+    Substr,
+    // Concatenates all arguments, NULL values are skipped
+    Concat,
+    // Returns the first argument that is not NULL
+    Coalesce,
+    // Date of a timestamp as "YYYY-MM-DD", in the time zone of its column
+    Date,
+    // Hour of a timestamp from 0 to 23, in the time zone of its column
+    Hour,
+    // Edit distance between two Strings in characters
+    Levenshtein,
+    // Trigram similarity of two Strings in percent, see `Predicate::similar`
+    Similarity,
+}
+
+impl ScalarFunction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScalarFunction::Upper => "upper",
+            ScalarFunction::Lower => "lower",
+            ScalarFunction::Trim => "trim",
+            ScalarFunction::Length => "length",
+            ScalarFunction::Substr => "substr",
+            ScalarFunction::Concat => "concat",
+            ScalarFunction::Coalesce => "coalesce",
+            ScalarFunction::Date => "date",
+            ScalarFunction::Hour => "hour",
+            ScalarFunction::Levenshtein => "levenshtein",
+            ScalarFunction::Similarity => "similarity",
+        }
+    }
+
+    // Argument types of None stand for untyped NULL literals
+    pub(crate) fn return_type(
+        &self,
+        argument_types: &[Option<DataType>],
+    ) -> Result<Option<DataType>, VirtualTableError> {
+        match self {
+            ScalarFunction::Upper | ScalarFunction::Lower | ScalarFunction::Trim => {
+                self.expect_arguments(argument_types, &[DataType::String], 0)?;
+                Result::Ok(Some(DataType::String))
+            }
+            ScalarFunction::Length => {
+                self.expect_arguments(argument_types, &[DataType::String], 0)?;
+                Result::Ok(Some(DataType::Integer))
+            }
+            ScalarFunction::Levenshtein | ScalarFunction::Similarity => {
+                self.expect_arguments(argument_types, &[DataType::String, DataType::String], 0)?;
+                Result::Ok(Some(DataType::Integer))
+            }
+            ScalarFunction::Substr => {
+                self.expect_arguments(
+                    argument_types,
+                    &[DataType::String, DataType::Integer, DataType::Integer],
+                    1,
+                )?;
+                Result::Ok(Some(DataType::String))
+            }
+            ScalarFunction::Date => {
+                self.expect_arguments(argument_types, &[DataType::Timestamp], 0)?;
+                Result::Ok(Some(DataType::String))
+            }
+            ScalarFunction::Hour => {
+                self.expect_arguments(argument_types, &[DataType::Timestamp], 0)?;
+                Result::Ok(Some(DataType::Integer))
+            }
+            ScalarFunction::Concat => {
+                let expected = vec![DataType::String; argument_types.len()];
+                self.expect_arguments(argument_types, &expected, 0)?;
+                Result::Ok(Some(DataType::String))
+            }
+            ScalarFunction::Coalesce => {
+                let return_type = argument_types.iter().find_map(|data_type| *data_type);
+                if let Some(return_type) = return_type {
+                    let expected = vec![return_type; argument_types.len()];
+                    self.expect_arguments(argument_types, &expected, 0)?;
+                }
+
+                Result::Ok(return_type)
+            }
+        }
+    }
+
+    pub(crate) fn apply(
+        &self,
+        arguments: Vec<TableValue>,
+        time_zone: TimeZone,
+    ) -> Result<TableValue, VirtualTableError> {
+        match self {
+            ScalarFunction::Concat => Result::Ok(TableValue::String(
+                arguments
+                    .iter()
+                    .filter_map(|argument| match argument {
+                        TableValue::String(value) => Some(value.as_str()),
+                        _ => None,
+                    })
+                    .collect(),
+            )),
+            ScalarFunction::Coalesce => Result::Ok(
+                arguments
+                    .into_iter()
+                    .find(|argument| *argument != TableValue::Null)
+                    .unwrap_or(TableValue::Null),
+            ),
+            // All other functions return NULL as soon as one of their arguments is NULL
+            _ if arguments.contains(&TableValue::Null) => Result::Ok(TableValue::Null),
+            ScalarFunction::Upper => Result::Ok(TableValue::String(
+                self.string_argument(&arguments, 0)?.to_uppercase(),
+            )),
+            ScalarFunction::Lower => Result::Ok(TableValue::String(
+                self.string_argument(&arguments, 0)?.to_lowercase(),
+            )),
+            ScalarFunction::Trim => Result::Ok(TableValue::from(
+                self.string_argument(&arguments, 0)?.trim(),
+            )),
+            ScalarFunction::Length => Result::Ok(TableValue::Integer(
+                self.string_argument(&arguments, 0)?.chars().count() as i64,
+            )),
+            ScalarFunction::Levenshtein => Result::Ok(TableValue::Integer(levenshtein_distance(
+                self.string_argument(&arguments, 0)?,
+                self.string_argument(&arguments, 1)?,
+            ) as i64)),
+            ScalarFunction::Similarity => {
+                Result::Ok(TableValue::Integer(i64::from(trigram_similarity(
+                    self.string_argument(&arguments, 0)?,
+                    self.string_argument(&arguments, 1)?,
+                ))))
+            }
+            ScalarFunction::Date => {
+                let local = self.timestamp_argument(&arguments, 0)?.to_local(time_zone);
+                Result::Ok(TableValue::String(format!(
+                    "{:04}-{:02}-{:02}",
+                    local.year, local.month, local.day
+                )))
+            }
+            ScalarFunction::Hour => Result::Ok(TableValue::Integer(i64::from(
+                self.timestamp_argument(&arguments, 0)?
+                    .to_local(time_zone)
+                    .hour,
+            ))),
+            ScalarFunction::Substr => {
+                let value = self.string_argument(&arguments, 0)?;
+                let start = match arguments.get(1) {
+                    Some(TableValue::Integer(start)) => *start,
+                    _ => return Result::Err(self.arity_error()),
+                };
+                let length = match arguments.get(2) {
+                    Some(TableValue::Integer(length)) if *length < 0 => {
+                        return Result::Err(VirtualTableError::InvalidExpression(String::from(
+                            "Negative substring length not allowed.",
+                        )))
+                    }
+                    Some(TableValue::Integer(length)) => Some(*length),
+                    _ => None,
+                };
+
+                // Positions before the first character count towards the length, like in SQL
+                let skip = start.max(1) - 1;
+                let take = length
+                    .map(|length| start.saturating_add(length).saturating_sub(1 + skip).max(0));
+                Result::Ok(TableValue::String(
+                    value
+                        .chars()
+                        .skip(skip as usize)
+                        .take(take.map_or(usize::MAX, |take| take as usize))
+                        .collect(),
+                ))
+            }
+        }
+    }
+
+    fn string_argument<'a>(
+        &self,
+        arguments: &'a [TableValue],
+        position: usize,
+    ) -> Result<&'a str, VirtualTableError> {
+        match arguments.get(position) {
+            Some(TableValue::String(value)) => Result::Ok(value),
+            Some(other) => Result::Err(VirtualTableError::InvalidDataType(
+                format!("argument {} of {}", position + 1, self.name()),
+                DataType::String,
+                other.data_type().unwrap_or(DataType::String),
+            )),
+            None => Result::Err(self.arity_error()),
+        }
+    }
+
+    fn timestamp_argument(
+        &self,
+        arguments: &[TableValue],
+        position: usize,
+    ) -> Result<Timestamp, VirtualTableError> {
+        match arguments.get(position) {
+            Some(TableValue::Timestamp(value)) => Result::Ok(*value),
+            Some(other) => Result::Err(VirtualTableError::InvalidDataType(
+                format!("argument {} of {}", position + 1, self.name()),
+                DataType::Timestamp,
+                other.data_type().unwrap_or(DataType::Timestamp),
+            )),
+            None => Result::Err(self.arity_error()),
+        }
+    }
+
+    fn expect_arguments(
+        &self,
+        argument_types: &[Option<DataType>],
+        expected_types: &[DataType],
+        optional_count: usize,
+    ) -> Result<(), VirtualTableError> {
+        if argument_types.len() > expected_types.len()
+            || argument_types.len() + optional_count < expected_types.len()
+        {
+            return Result::Err(self.arity_error());
+        }
+
+        argument_types
+            .iter()
+            .zip(expected_types)
+            .enumerate()
+            .try_for_each(|(position, (actual, expected))| match actual {
+                Some(actual) if actual != expected => {
+                    Result::Err(VirtualTableError::InvalidDataType(
+                        format!("argument {} of {}", position + 1, self.name()),
+                        *expected,
+                        *actual,
+                    ))
+                }
+                _ => Result::Ok(()),
+            })
+    }
+
+    fn arity_error(&self) -> VirtualTableError {
+        VirtualTableError::InvalidExpression(format!(
+            "Wrong number of arguments for {}.",
+            self.name()
+        ))
+    }
+}
+
+impl Display for ScalarFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(self.name())
+    }
+}
+
+/// A custom scalar function registered via `Table::register_function`.
+pub struct UserFunction {
+    return_type: DataType,
+    body: Box<UserFunctionBody>,
+}
+
+impl UserFunction {
+    pub fn return_type(&self) -> DataType {
+        self.return_type
+    }
+
+    pub(crate) fn apply(
+        &self,
+        name: &str,
+        arguments: Vec<TableValue>,
+    ) -> Result<TableValue, VirtualTableError> {
+        let value = (self.body)(&arguments)
+            .map_err(|message| VirtualTableError::FunctionError(String::from(name), message))?;
+
+        match value.data_type() {
+            Some(data_type) if data_type != self.return_type => Result::Err(
+                VirtualTableError::InvalidDataType(String::from(name), self.return_type, data_type),
+            ),
+            _ => Result::Ok(value),
+        }
+    }
+}
+
+impl Debug for UserFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("UserFunction")
+            .field("return_type", &self.return_type)
+            .finish()
+    }
+}
+
+impl Table {
+    /// Registers a custom scalar function that can be called from expressions via `Expression::Call`.
+    /// The function validates its own arguments, its result has to match the given return type.
+    /// Registering a function with an existing name replaces the previous one.
+    pub fn register_function<F>(&mut self, name: &str, return_type: DataType, body: F)
+    where
+        F: Fn(&[TableValue]) -> Result<TableValue, String> + Send + Sync + 'static,
+    {
+        self.functions.insert(
+            String::from(name),
+            UserFunction {
+                return_type,
+                body: Box::new(body),
+            },
+        );
+    }
+
+    pub(crate) fn user_function(&self, name: &str) -> Result<&UserFunction, VirtualTableError> {
+        self.functions
+            .get(name)
+            .ok_or_else(|| VirtualTableError::UnknownFunction(String::from(name)))
+    }
+}
@@ -0,0 +1,95 @@
+use crate::error::VirtualTableError;
+use crate::expression::Expression;
+use crate::functions::ScalarFunction;
+use crate::predicate::Predicate;
+use crate::query::ColumnSpecification;
+use crate::{DataType, Row, Table};
+use std::collections::HashSet;
+
+impl Predicate {
+    /// The String value has a trigram similarity of at least the threshold to the needle, in
+    /// percent from 0 to 100. Useful to match names that were typed in slightly differently.
+    pub fn similar(expression: Expression, needle: &str, threshold: u8) -> Self {
+        Predicate::GreaterThanOrEquals(
+            Expression::function(
+                ScalarFunction::Similarity,
+                vec![expression, Expression::literal(needle)],
+            ),
+            Expression::literal(i64::from(threshold)),
+        )
+    }
+}
+
+impl Table {
+    /// Finds all rows whose value in the String column is similar to the needle, see
+    /// `Predicate::similar`.
+    pub fn fuzzy_find(
+        &self,
+        column_identifier: &str,
+        needle: &str,
+        threshold: u8,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let column = self
+            .columns
+            .get(column_identifier)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(String::from(column_identifier)))?;
+        if column.data_type != DataType::String {
+            return Result::Err(VirtualTableError::InvalidDataType(
+                String::from(column_identifier),
+                DataType::String,
+                column.data_type,
+            ));
+        }
+
+        let predicate =
+            Predicate::similar(Expression::column(column_identifier), needle, threshold);
+        self.find_rows(&predicate, ColumnSpecification::All)
+    }
+}
+
+// Number of single character insertions, deletions and substitutions turning one value into the
+// other, counted in characters
+pub(crate) fn levenshtein_distance(left: &str, right: &str) -> usize {
+    let right = right.chars().collect::<Vec<_>>();
+    let mut previous = (0..=right.len()).collect::<Vec<_>>();
+    let mut current = vec![0; right.len() + 1];
+    for (i, left_char) in left.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, right_char) in right.iter().enumerate() {
+            let substitution = previous[j] + usize::from(left_char != *right_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[right.len()]
+}
+
+// Shared trigrams of the values relative to all their trigrams in percent, like pg_trgm. Case is
+// ignored, and every word is padded so that its start weighs more than its end.
+pub(crate) fn trigram_similarity(left: &str, right: &str) -> u8 {
+    let left = trigrams(left);
+    let right = trigrams(right);
+    let union = left.union(&right).count();
+    if union == 0 {
+        return 0;
+    }
+
+    let shared = left.intersection(&right).count();
+    (shared * 100 / union) as u8
+}
+
+fn trigrams(value: &str) -> HashSet<[char; 3]> {
+    value
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .flat_map(|word| {
+            let padded = format!("  {} ", word).chars().collect::<Vec<_>>();
+            padded
+                .windows(3)
+                .map(|window| [window[0], window[1], window[2]])
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
@@ -0,0 +1,376 @@
+use crate::error::VirtualTableError;
+use crate::instrumentation::OperationSpan;
+use crate::query::ColumnSpecification;
+use crate::row_id::RowId;
+use crate::{DataType, Index, Row, Table, TableValue};
+use roaring::RoaringBitmap;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::hash::{Hash, Hasher};
+
+// Mean radius of the earth
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+// Length of a degree of latitude, and of longitude at the equator
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// A location on earth in degrees. Points are only created with valid coordinates, so they can
+/// be compared and hashed by their exact values.
+#[derive(Debug, Copy, Clone)]
+pub struct Point {
+    latitude: f64,
+    longitude: f64,
+}
+
+impl Point {
+    /// Creates the point, None if the latitude isn't within ±90° or the longitude within ±180°.
+    pub fn create(latitude: f64, longitude: f64) -> Option<Self> {
+        if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+            return None;
+        }
+
+        // Adding zero turns -0.0 into 0.0, so equal points have equal bits
+        Some(Point {
+            latitude: latitude + 0.0,
+            longitude: longitude + 0.0,
+        })
+    }
+
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    /// Great-circle distance to the other point in meters, using the haversine formula.
+    pub fn distance_to(&self, other: &Point) -> f64 {
+        let (latitude, other_latitude) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let latitude_delta = other_latitude - latitude;
+        let longitude_delta = (other.longitude - self.longitude).to_radians();
+
+        let a = (latitude_delta / 2.0).sin().powi(2)
+            + latitude.cos() * other_latitude.cos() * (longitude_delta / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_METERS * a.sqrt().min(1.0).asin()
+    }
+
+    /// Parses points in the format of their Display implementation, like "52.52, 13.405".
+    pub fn parse(value: &str) -> Option<Self> {
+        let (latitude, longitude) = value.split_once(',')?;
+        Point::create(
+            latitude.trim().parse().ok()?,
+            longitude.trim().parse().ok()?,
+        )
+    }
+}
+
+impl PartialEq for Point {
+    fn eq(&self, other: &Self) -> bool {
+        self.latitude.to_bits() == other.latitude.to_bits()
+            && self.longitude.to_bits() == other.longitude.to_bits()
+    }
+}
+
+impl Eq for Point {}
+
+impl Hash for Point {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.latitude.to_bits().hash(state);
+        self.longitude.to_bits().hash(state);
+    }
+}
+
+impl Display for Point {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(&format!("{}, {}", self.latitude, self.longitude))
+    }
+}
+
+/// Groups the rows by the grid cell their point lies in, so a radius query only has to look at
+/// the rows in the cells around its center.
+#[derive(Debug, PartialEq)]
+pub(crate) struct GridIndex {
+    // Edge length of a cell in degrees
+    cell_size: f64,
+    cells: HashMap<(i64, i64), RoaringBitmap>,
+}
+
+impl GridIndex {
+    fn cell_of(&self, point: &Point) -> (i64, i64) {
+        (
+            ((point.latitude + 90.0) / self.cell_size).floor() as i64,
+            ((point.longitude + 180.0) / self.cell_size).floor() as i64 % self.longitude_cells(),
+        )
+    }
+
+    fn longitude_cells(&self) -> i64 {
+        (360.0 / self.cell_size).round() as i64
+    }
+
+    pub(crate) fn insert(&mut self, value: &TableValue, row_id: RowId) {
+        if let TableValue::Point(point) = value {
+            let cell = self.cell_of(point);
+            self.cells.entry(cell).or_default().insert(row_id.0 as u32);
+        }
+    }
+
+    pub(crate) fn remove(&mut self, value: &TableValue, row_id: RowId) {
+        if let TableValue::Point(point) = value {
+            let cell = self.cell_of(point);
+            if let Some(bitmap) = self.cells.get_mut(&cell) {
+                bitmap.remove(row_id.0 as u32);
+                if bitmap.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    // Rows in all cells that overlap the bounding box of the circle
+    fn candidates(&self, center: &Point, meters: f64) -> RoaringBitmap {
+        let latitude_delta = meters / METERS_PER_DEGREE;
+        let min_latitude = (center.latitude - latitude_delta).max(-90.0);
+        let max_latitude = (center.latitude + latitude_delta).min(90.0);
+
+        // Degrees of longitude get shorter towards the poles, so the widest part of the box counts
+        let narrowest = min_latitude
+            .abs()
+            .max(max_latitude.abs())
+            .to_radians()
+            .cos();
+        let longitude_delta = meters / (METERS_PER_DEGREE * narrowest);
+        let longitude_cells = if narrowest <= f64::EPSILON || longitude_delta >= 180.0 {
+            0..self.longitude_cells()
+        } else {
+            let first = ((center.longitude - longitude_delta + 180.0) / self.cell_size).floor();
+            let last = ((center.longitude + longitude_delta + 180.0) / self.cell_size).floor();
+            first as i64..last as i64 + 1
+        };
+
+        let first_latitude_cell = ((min_latitude + 90.0) / self.cell_size).floor() as i64;
+        let last_latitude_cell = ((max_latitude + 90.0) / self.cell_size).floor() as i64;
+        let latitude_cells = first_latitude_cell..=last_latitude_cell;
+        // Cells past the antimeridian continue on the other side
+        let longitude_count = longitude_cells.end - longitude_cells.start;
+        let covers_longitude = |longitude_cell: i64| {
+            (longitude_cell - longitude_cells.start).rem_euclid(self.longitude_cells())
+                < longitude_count
+        };
+
+        let mut candidates = RoaringBitmap::new();
+        // Large boxes, e.g. around the poles, have more cells than are populated, so we look at
+        // the populated ones instead
+        let box_cells =
+            (last_latitude_cell - first_latitude_cell + 1).saturating_mul(longitude_count);
+        if box_cells > self.cells.len() as i64 {
+            self.cells
+                .iter()
+                .filter(|((latitude_cell, longitude_cell), _)| {
+                    latitude_cells.contains(latitude_cell) && covers_longitude(*longitude_cell)
+                })
+                .for_each(|(_, bitmap)| candidates |= bitmap);
+
+            return candidates;
+        }
+
+        for latitude_cell in latitude_cells {
+            for longitude_cell in longitude_cells.clone() {
+                let cell = (
+                    latitude_cell,
+                    longitude_cell.rem_euclid(self.longitude_cells()),
+                );
+                if let Some(bitmap) = self.cells.get(&cell) {
+                    candidates |= bitmap;
+                }
+            }
+        }
+
+        candidates
+    }
+}
+
+impl Table {
+    /// Creates a grid index for a Point column with cells of roughly the given edge length, which
+    /// should be in the order of the radiuses queried with `Table::find_rows_within_radius`.
+    pub fn create_spatial_index(
+        &mut self,
+        column_identifier: &str,
+        cell_size_meters: f64,
+    ) -> Result<(), VirtualTableError> {
+        let column = self
+            .columns
+            .get(column_identifier)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(String::from(column_identifier)))?;
+        if column.data_type != DataType::Point {
+            return Result::Err(VirtualTableError::InvalidDataType(
+                String::from(column_identifier),
+                DataType::Point,
+                column.data_type,
+            ));
+        }
+        if cell_size_meters.is_nan() || cell_size_meters <= 0.0 {
+            return Result::Err(VirtualTableError::InvalidArgument(String::from(
+                "The cell size of a spatial index has to be positive.",
+            )));
+        }
+
+        // The cells have to divide the circumference exactly, so they line up at the antimeridian
+        let longitude_cells = (360.0 * METERS_PER_DEGREE / cell_size_meters)
+            .ceil()
+            .max(2.0);
+        let mut index = GridIndex {
+            cell_size: 360.0 / longitude_cells,
+            cells: HashMap::new(),
+        };
+        column
+            .values
+            .iter()
+            .zip(&self.row_ids)
+            .for_each(|(value, row_id)| index.insert(value, *row_id));
+        self.spatial_indexes
+            .insert(String::from(column_identifier), index);
+
+        Result::Ok(())
+    }
+
+    pub(crate) fn rebuild_spatial_indexes(&mut self) {
+        let indexes = self
+            .spatial_indexes
+            .keys()
+            .filter_map(|column_identifier| {
+                let index = self.spatial_index_of(column_identifier)?;
+                Some((column_identifier.clone(), index))
+            })
+            .collect::<Vec<_>>();
+        self.spatial_indexes.extend(indexes);
+    }
+
+    // Indexes the values the column holds right now, with the cells of its existing index
+    pub(crate) fn spatial_index_of(&self, column_identifier: &str) -> Option<GridIndex> {
+        let mut index = GridIndex {
+            cell_size: self.spatial_indexes.get(column_identifier)?.cell_size,
+            cells: HashMap::new(),
+        };
+        self.columns
+            .get(column_identifier)?
+            .values
+            .iter()
+            .zip(&self.row_ids)
+            .for_each(|(value, row_id)| index.insert(value, *row_id));
+
+        Some(index)
+    }
+
+    pub fn drop_spatial_index(&mut self, column_identifier: &str) -> bool {
+        self.spatial_indexes.remove(column_identifier).is_some()
+    }
+
+    pub fn has_spatial_index(&self, column_identifier: &str) -> bool {
+        self.spatial_indexes.contains_key(column_identifier)
+    }
+
+    /// Finds all rows whose point in the column is at most the given distance away from the
+    /// center, in insertion order. Uses the spatial index of the column if there is one.
+    pub fn find_rows_within_radius(
+        &self,
+        column_identifier: &str,
+        center: &Point,
+        meters: f64,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let span = OperationSpan::start("find_rows_within_radius", self);
+        self.statistics.record_query();
+        let result = self.collect_rows_within_radius(
+            column_identifier,
+            center,
+            meters,
+            column_specification,
+        );
+        span.finish(&result, result.as_ref().map_or(0, |rows| rows.len()));
+
+        result
+    }
+
+    fn collect_rows_within_radius(
+        &self,
+        column_identifier: &str,
+        center: &Point,
+        meters: f64,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let column = self
+            .columns
+            .get(column_identifier)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(String::from(column_identifier)))?;
+
+        let mut indexes: Vec<Index> = match self.spatial_indexes.get(column_identifier) {
+            Some(index) => {
+                self.record_index_lookup("spatial_index", true);
+                index
+                    .candidates(center, meters)
+                    .iter()
+                    .filter_map(|row_id| self.index_of(RowId(row_id as usize)))
+                    .collect()
+            }
+            None => (0..column.values.len()).collect(),
+        };
+        indexes.sort_unstable();
+        self.record_rows_scanned("find_rows_within_radius", indexes.len());
+
+        let mut rows = Vec::new();
+        for index in indexes {
+            let is_within_radius = match column.value_at(index) {
+                Some(TableValue::Point(point)) => point.distance_to(center) <= meters,
+                _ => false,
+            };
+            if let (true, Some(key)) = (is_within_radius, self.key_at(index)) {
+                rows.extend(self.try_find_row(&key, column_specification.clone())?);
+            }
+        }
+
+        Result::Ok(rows)
+    }
+
+    pub(crate) fn update_spatial_indexes<F>(&mut self, index: Index, update: F)
+    where
+        F: Fn(&mut GridIndex, &TableValue, RowId),
+    {
+        let row_id = match self.row_ids.get(index) {
+            Some(row_id) => *row_id,
+            None => return,
+        };
+
+        let columns = &self.columns;
+        self.spatial_indexes
+            .iter_mut()
+            .for_each(|(column_identifier, grid_index)| {
+                if let Some(value) = columns
+                    .get(column_identifier)
+                    .and_then(|column| column.value_at(index))
+                {
+                    update(grid_index, value, row_id);
+                }
+            });
+    }
+}
+
+// Points are written as a pair of latitude and longitude, which is validated again when reading it
+#[cfg(feature = "serde")]
+impl serde::Serialize for Point {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&(self.latitude, self.longitude), serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Point {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (latitude, longitude) = <(f64, f64)>::deserialize(deserializer)?;
+        Point::create(latitude, longitude).ok_or_else(|| {
+            serde::de::Error::custom(format!(
+                "({}, {}) isn't a valid point.",
+                latitude, longitude
+            ))
+        })
+    }
+}
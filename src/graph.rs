@@ -0,0 +1,186 @@
+use crate::error::VirtualTableError;
+use crate::{Table, TableValue};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// A directed graph read from a table whose rows are edges, one column holding the source and
+/// another one the target of each edge. Nodes are the values of these columns, rows with a NULL
+/// value are skipped. The graph is a copy, later changes of the table aren't reflected in it.
+#[derive(Debug, Clone)]
+pub struct Graph {
+    // Nodes in the order they first occur in the table, the positions identify them below
+    nodes: Vec<TableValue>,
+    positions: HashMap<TableValue, usize>,
+    // Targets of the outgoing edges of every node
+    outgoing: Vec<Vec<usize>>,
+    // Sources of the incoming edges of every node, needed for connected components
+    incoming: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    pub fn nodes(&self) -> &[TableValue] {
+        &self.nodes
+    }
+
+    /// Targets of the edges leaving the node, in the order of their rows. Empty for unknown nodes.
+    pub fn neighbors(&self, node: &TableValue) -> Vec<TableValue> {
+        match self.positions.get(node) {
+            Some(position) => self.values_of(&self.outgoing[*position]),
+            None => Vec::new(),
+        }
+    }
+
+    /// All nodes reachable from the node along the edges, nearest first and starting with the
+    /// node itself. Empty for unknown nodes.
+    pub fn breadth_first(&self, start: &TableValue) -> Vec<TableValue> {
+        let start = match self.positions.get(start) {
+            Some(start) => *start,
+            None => return Vec::new(),
+        };
+
+        let mut visited = vec![false; self.nodes.len()];
+        visited[start] = true;
+        let mut queue = VecDeque::from(vec![start]);
+        let mut order = Vec::new();
+        while let Some(node) = queue.pop_front() {
+            order.push(node);
+            for target in &self.outgoing[node] {
+                if !visited[*target] {
+                    visited[*target] = true;
+                    queue.push_back(*target);
+                }
+            }
+        }
+
+        self.values_of(&order)
+    }
+
+    /// All nodes reachable from the node along the edges, following every path as deep as
+    /// possible before the next one and starting with the node itself. Empty for unknown nodes.
+    pub fn depth_first(&self, start: &TableValue) -> Vec<TableValue> {
+        let start = match self.positions.get(start) {
+            Some(start) => *start,
+            None => return Vec::new(),
+        };
+
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack = vec![start];
+        let mut order = Vec::new();
+        while let Some(node) = stack.pop() {
+            if visited[node] {
+                continue;
+            }
+            visited[node] = true;
+            order.push(node);
+            // Reversed, so the first neighbor is visited first
+            stack.extend(
+                self.outgoing[node]
+                    .iter()
+                    .rev()
+                    .filter(|target| !visited[**target]),
+            );
+        }
+
+        self.values_of(&order)
+    }
+
+    /// Groups the nodes that are connected by edges in either direction. Components are ordered
+    /// by their first node, their nodes in the order they first occur in the table.
+    pub fn connected_components(&self) -> Vec<Vec<TableValue>> {
+        let mut component_of = vec![None; self.nodes.len()];
+        let mut components: Vec<Vec<usize>> = Vec::new();
+        for start in 0..self.nodes.len() {
+            if component_of[start].is_some() {
+                continue;
+            }
+
+            let component = components.len();
+            component_of[start] = Some(component);
+            let mut members = Vec::new();
+            let mut stack = vec![start];
+            while let Some(node) = stack.pop() {
+                members.push(node);
+                for other in self.outgoing[node].iter().chain(&self.incoming[node]) {
+                    if component_of[*other].is_none() {
+                        component_of[*other] = Some(component);
+                        stack.push(*other);
+                    }
+                }
+            }
+            members.sort_unstable();
+            components.push(members);
+        }
+
+        components
+            .iter()
+            .map(|members| self.values_of(members))
+            .collect()
+    }
+
+    fn values_of(&self, positions: &[usize]) -> Vec<TableValue> {
+        positions
+            .iter()
+            .map(|position| self.nodes[*position].clone())
+            .collect()
+    }
+
+    fn position_of(&mut self, node: &TableValue) -> usize {
+        if let Some(position) = self.positions.get(node) {
+            return *position;
+        }
+
+        let position = self.nodes.len();
+        self.nodes.push(node.clone());
+        self.positions.insert(node.clone(), position);
+        self.outgoing.push(Vec::new());
+        self.incoming.push(Vec::new());
+        position
+    }
+}
+
+impl Table {
+    /// Reads the rows as edges from the source to the target column, see `Graph`. Both columns
+    /// need the same data type. Duplicate rows add the edge only once.
+    pub fn graph(
+        &self,
+        source_column_identifier: &str,
+        target_column_identifier: &str,
+    ) -> Result<Graph, VirtualTableError> {
+        let column = |identifier: &str| {
+            self.columns
+                .get(identifier)
+                .ok_or_else(|| VirtualTableError::UnknownColumn(String::from(identifier)))
+        };
+        let sources = column(source_column_identifier)?;
+        let targets = column(target_column_identifier)?;
+        if sources.data_type != targets.data_type {
+            return Result::Err(VirtualTableError::InvalidDataType(
+                String::from(target_column_identifier),
+                sources.data_type,
+                targets.data_type,
+            ));
+        }
+        self.record_rows_scanned("graph", sources.values.len());
+
+        let mut graph = Graph {
+            nodes: Vec::new(),
+            positions: HashMap::new(),
+            outgoing: Vec::new(),
+            incoming: Vec::new(),
+        };
+        let mut edges = HashSet::new();
+        for (source, target) in sources.values.iter().zip(targets.values.iter()) {
+            if *source == TableValue::Null || *target == TableValue::Null {
+                continue;
+            }
+
+            let source = graph.position_of(source);
+            let target = graph.position_of(target);
+            if edges.insert((source, target)) {
+                graph.outgoing[source].push(target);
+                graph.incoming[target].push(source);
+            }
+        }
+
+        Result::Ok(graph)
+    }
+}
@@ -0,0 +1,62 @@
+use crate::error::VirtualTableError;
+use crate::query::ColumnSpecification;
+use crate::{DataType, PrimaryKey, Row, Table, TableValue};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+impl Table {
+    /// Finds all rows below the row with the key in a hierarchy, where the Uuid column holds the
+    /// key of the parent row, like a "manager_id" column. Rows are returned level by level, the
+    /// children of a row in insertion order. Every row is visited only once, so cycles in the
+    /// hierarchy end the traversal instead of running forever. The row itself is never returned.
+    pub fn descendants(
+        &self,
+        key: &PrimaryKey,
+        parent_column_identifier: &str,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let column = self.columns.get(parent_column_identifier).ok_or_else(|| {
+            VirtualTableError::UnknownColumn(String::from(parent_column_identifier))
+        })?;
+        if column.data_type != DataType::Uuid {
+            return Result::Err(VirtualTableError::InvalidDataType(
+                String::from(parent_column_identifier),
+                DataType::Uuid,
+                column.data_type,
+            ));
+        }
+        if !self.keys.contains_key(key) {
+            return Result::Err(VirtualTableError::UnknownPrimaryKey(*key));
+        }
+        self.statistics.record_query();
+        self.record_rows_scanned("descendants", column.values.len());
+
+        let mut children: HashMap<PrimaryKey, Vec<PrimaryKey>> = HashMap::new();
+        for (index, value) in column.values.iter().enumerate() {
+            if let (TableValue::Uuid(parent), Some(child)) = (value, self.key_at(index)) {
+                children.entry(*parent).or_default().push(child);
+            }
+        }
+
+        let mut visited = HashSet::new();
+        visited.insert(*key);
+        let mut queue = VecDeque::new();
+        queue.push_back(*key);
+        let mut descendants = Vec::new();
+        while let Some(parent) = queue.pop_front() {
+            for child in children.get(&parent).into_iter().flatten() {
+                if visited.insert(*child) {
+                    descendants.push(*child);
+                    queue.push_back(*child);
+                }
+            }
+        }
+
+        descendants
+            .iter()
+            .map(|key| {
+                self.try_find_row(key, column_specification.clone())
+                    .map(|row| row.expect("Keys of the table always have a row."))
+            })
+            .collect()
+    }
+}
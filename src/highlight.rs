@@ -0,0 +1,117 @@
+use crate::error::VirtualTableError;
+use crate::format::elision_note;
+use crate::predicate::{Predicate, Truth};
+use crate::{Index, Table};
+use prettytable::Attr;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Highlight {
+    Bold,
+    Color(Color),
+}
+
+impl Highlight {
+    pub(crate) fn attr(self) -> Attr {
+        match self {
+            Highlight::Bold => Attr::Bold,
+            Highlight::Color(color) => Attr::ForegroundColor(match color {
+                Color::Red => prettytable::color::RED,
+                Color::Green => prettytable::color::GREEN,
+                Color::Yellow => prettytable::color::YELLOW,
+                Color::Blue => prettytable::color::BLUE,
+                Color::Magenta => prettytable::color::MAGENTA,
+                Color::Cyan => prettytable::color::CYAN,
+            }),
+        }
+    }
+
+    pub(crate) fn css(self) -> &'static str {
+        match self {
+            Highlight::Bold => "font-weight: bold",
+            Highlight::Color(Color::Red) => "color: red",
+            Highlight::Color(Color::Green) => "color: green",
+            Highlight::Color(Color::Yellow) => "color: yellow",
+            Highlight::Color(Color::Blue) => "color: blue",
+            Highlight::Color(Color::Magenta) => "color: magenta",
+            Highlight::Color(Color::Cyan) => "color: cyan",
+        }
+    }
+}
+
+/// Highlights the rows the predicate is true for, or only one of their cells.
+#[derive(Debug, Clone)]
+pub struct HighlightRule {
+    predicate: Predicate,
+    // Highlights the whole row if not set
+    column_identifier: Option<String>,
+    highlight: Highlight,
+}
+
+impl HighlightRule {
+    pub fn row(predicate: Predicate, highlight: Highlight) -> Self {
+        HighlightRule {
+            predicate,
+            column_identifier: None,
+            highlight,
+        }
+    }
+
+    pub fn cell(column_identifier: &str, predicate: Predicate, highlight: Highlight) -> Self {
+        HighlightRule {
+            predicate,
+            column_identifier: Some(String::from(column_identifier)),
+            highlight,
+        }
+    }
+}
+
+impl Table {
+    /// Adds a rule that styles values in `Table::print` and in HTML reports. Rules apply in the
+    /// order they were added, so a later color wins over an earlier one.
+    pub fn add_highlight_rule(&mut self, rule: HighlightRule) -> Result<(), VirtualTableError> {
+        rule.predicate.validate(self)?;
+        if let Some(column_identifier) = &rule.column_identifier {
+            if !self.columns.contains_key(column_identifier) {
+                return Result::Err(VirtualTableError::UnknownColumn(column_identifier.clone()));
+            }
+        }
+
+        self.highlight_rules.push(rule);
+        Result::Ok(())
+    }
+
+    pub fn clear_highlight_rules(&mut self) -> usize {
+        self.highlight_rules.drain(..).count()
+    }
+
+    /// Prints the boxed table to stdout. Unlike `Display`, which only produces plain text, this
+    /// shows the header and highlight styles when stdout is a terminal.
+    pub fn print(&self) {
+        let (display_table, elided) = self.display_table(self.keys.values().copied());
+        display_table.printstd();
+        print!("{}", elision_note(elided));
+    }
+
+    // The highlights of the cell in the row at the index, the predicate has to be true for them
+    pub(crate) fn highlights_at(&self, index: Index, column_identifier: &str) -> Vec<Highlight> {
+        self.highlight_rules
+            .iter()
+            .filter(|rule| match &rule.column_identifier {
+                Some(other) => other == column_identifier,
+                None => true,
+            })
+            .filter(|rule| rule.predicate.evaluate(self, index) == Result::Ok(Truth::True))
+            .map(|rule| rule.highlight)
+            .collect()
+    }
+}
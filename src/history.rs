@@ -0,0 +1,247 @@
+use crate::error::VirtualTableError;
+use crate::{Row, Table};
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Operation {
+    Insert(Row),
+    // Holds the affected cells before and after the update
+    Update(Row, Row),
+    // Holds all cells of the deleted row
+    Delete(Row),
+    // All operations of one bulk call like `Table::ingest`, undone and redone as one step
+    Batch(Vec<Operation>),
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    undo_stack: Vec<Operation>,
+    redo_stack: Vec<Operation>,
+    // Collects the operations of the bulk call that is running, see `Table::in_batch`
+    batch: Option<Vec<Operation>>,
+}
+
+impl History {
+    // Passes every row an operation holds to `update`, e.g. to replace values that must be forgotten
+    pub(crate) fn for_each_row<F: FnMut(&mut Row)>(&mut self, update: &mut F) {
+        self.undo_stack
+            .iter_mut()
+            .chain(self.redo_stack.iter_mut())
+            .chain(self.batch.iter_mut().flatten())
+            .for_each(|operation| operation.for_each_row(update));
+    }
+}
+
+impl Operation {
+    fn for_each_row<F: FnMut(&mut Row)>(&mut self, update: &mut F) {
+        match self {
+            Operation::Insert(row) | Operation::Delete(row) => update(row),
+            Operation::Update(before, after) => {
+                update(before);
+                update(after);
+            }
+            Operation::Batch(operations) => operations
+                .iter_mut()
+                .for_each(|operation| operation.for_each_row(update)),
+        }
+    }
+}
+
+impl Table {
+    /// Starts recording inserts, updates and deletes, so they can be reverted via `Table::undo`.
+    /// Bulk calls like `Table::ingest` or `Table::dedupe` are reverted as a whole.
+    pub fn enable_history(&mut self) {
+        if self.history.is_none() {
+            self.history = Some(History::default());
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        matches!(&self.history, Some(history) if !history.undo_stack.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        matches!(&self.history, Some(history) if !history.redo_stack.is_empty())
+    }
+
+    /// Reverts the last recorded operation. Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> Result<bool, Vec<VirtualTableError>> {
+        self.step_history(
+            |history| &mut history.undo_stack,
+            |history| &mut history.redo_stack,
+            Table::revert,
+        )
+    }
+
+    /// Reapplies the last undone operation. Returns false if there was nothing to redo.
+    pub fn redo(&mut self) -> Result<bool, Vec<VirtualTableError>> {
+        self.step_history(
+            |history| &mut history.redo_stack,
+            |history| &mut history.undo_stack,
+            Table::apply,
+        )
+    }
+
+    pub(crate) fn prepare_history<T, F>(&self, build: F) -> Option<T>
+    where
+        F: FnOnce() -> T,
+    {
+        self.history.as_ref().map(|_| build())
+    }
+
+    pub(crate) fn record_operation(&mut self, operation: Operation) {
+        if let Some(history) = self.history.as_mut() {
+            match history.batch.as_mut() {
+                Some(batch) => batch.push(operation),
+                None => history.undo_stack.push(operation),
+            }
+            // A new operation invalidates everything that has been undone before
+            history.redo_stack.clear();
+        }
+    }
+
+    // Runs a bulk call so all of its mutations are recorded as one batch, which is undone in a
+    // single step. Batches started while another one is running join the outer one.
+    pub(crate) fn in_batch<T, F>(&mut self, run: F) -> T
+    where
+        F: FnOnce(&mut Table) -> T,
+    {
+        let is_outermost = match self.history.as_mut() {
+            Some(history) if history.batch.is_none() => {
+                history.batch = Some(Vec::new());
+                true
+            }
+            _ => false,
+        };
+
+        let result = run(self);
+
+        if let Some(history) = self.history.as_mut().filter(|_| is_outermost) {
+            let mut operations = history.batch.take().unwrap_or_default();
+            match operations.len() {
+                0 => {}
+                1 => history.undo_stack.append(&mut operations),
+                _ => history.undo_stack.push(Operation::Batch(operations)),
+            }
+        }
+
+        result
+    }
+
+    // Like `Table::in_batch`, but a failing call is reverted, so its mutations are either kept
+    // completely or not at all
+    pub(crate) fn in_atomic_batch<T, E, F>(&mut self, run: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Table) -> Result<T, E>,
+    {
+        // The mutations are collected in a history of their own, even if the table has none
+        let history = self.history.replace(History {
+            batch: Some(Vec::new()),
+            ..History::default()
+        });
+        let result = run(self);
+        let operations = self
+            .history
+            .take()
+            .and_then(|history| history.batch)
+            .unwrap_or_default();
+
+        if result.is_err() {
+            // Reverted before the history is put back, so the revert isn't recorded
+            let _ = self.revert(&Operation::Batch(operations));
+            self.history = history;
+        } else {
+            self.history = history;
+            self.in_batch(|table| {
+                operations
+                    .into_iter()
+                    .for_each(|operation| table.record_operation(operation))
+            });
+        }
+
+        result
+    }
+
+    fn step_history<From, To, Step>(
+        &mut self,
+        from: From,
+        to: To,
+        step: Step,
+    ) -> Result<bool, Vec<VirtualTableError>>
+    where
+        From: Fn(&mut History) -> &mut Vec<Operation>,
+        To: Fn(&mut History) -> &mut Vec<Operation>,
+        Step: Fn(&mut Table, &Operation) -> Result<(), Vec<VirtualTableError>>,
+    {
+        // We take the history out of the table while stepping, so the mutations we do here aren't recorded
+        let mut history = match self.history.take() {
+            Some(history) => history,
+            None => return Result::Ok(false),
+        };
+
+        let operation = match from(&mut history).pop() {
+            Some(operation) => operation,
+            None => {
+                self.history = Some(history);
+                return Result::Ok(false);
+            }
+        };
+
+        let result = step(self, &operation);
+        if result.is_ok() {
+            to(&mut history).push(operation);
+        } else {
+            from(&mut history).push(operation);
+        }
+        self.history = Some(history);
+
+        result.map(|_| true)
+    }
+
+    fn revert(&mut self, operation: &Operation) -> Result<(), Vec<VirtualTableError>> {
+        match operation {
+            Operation::Insert(row) => self
+                .remove_row(&row.primary_key)
+                .map(|_| ())
+                .ok_or_else(|| vec![VirtualTableError::UnknownPrimaryKey(row.primary_key)]),
+            Operation::Update(before, _) => self.apply_update(before.clone()),
+            Operation::Delete(row) => self.insert_row(row.clone()),
+            // The operations are reverted last to first, a failure puts back the ones already
+            // reverted, so the batch is never left half undone
+            Operation::Batch(operations) => {
+                for (position, operation) in operations.iter().enumerate().rev() {
+                    if let Result::Err(errors) = self.revert(operation) {
+                        operations[position + 1..].iter().for_each(|operation| {
+                            let _ = self.apply(operation);
+                        });
+                        return Result::Err(errors);
+                    }
+                }
+
+                Result::Ok(())
+            }
+        }
+    }
+
+    fn apply(&mut self, operation: &Operation) -> Result<(), Vec<VirtualTableError>> {
+        match operation {
+            Operation::Insert(row) => self.insert_row(row.clone()),
+            Operation::Update(_, after) => self.apply_update(after.clone()),
+            Operation::Delete(row) => self
+                .remove_row(&row.primary_key)
+                .map(|_| ())
+                .ok_or_else(|| vec![VirtualTableError::UnknownPrimaryKey(row.primary_key)]),
+            Operation::Batch(operations) => {
+                for (position, operation) in operations.iter().enumerate() {
+                    if let Result::Err(errors) = self.apply(operation) {
+                        operations[..position].iter().rev().for_each(|operation| {
+                            let _ = self.revert(operation);
+                        });
+                        return Result::Err(errors);
+                    }
+                }
+
+                Result::Ok(())
+            }
+        }
+    }
+}
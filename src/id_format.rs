@@ -0,0 +1,58 @@
+use crate::{PrimaryKey, Table};
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BASE62_ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// How the primary keys in the ID column are rendered when the table is printed or exported.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub enum IdFormat {
+    // The hyphenated form, e.g. 67e55044-10b1-426f-9247-bb680e5fe0c8
+    #[default]
+    Full,
+    // The given number of leading hex digits, e.g. 67e55044 for 8
+    Short(usize),
+    // Bitcoin's alphabet without the look-alikes 0, O, I and l, at most 22 characters
+    Base58,
+    // Digits and letters, at most 22 characters
+    Base62,
+}
+
+impl IdFormat {
+    pub fn apply(&self, key: &PrimaryKey) -> String {
+        match self {
+            IdFormat::Full => key.to_string(),
+            IdFormat::Short(length) => format!("{:032x}", key.as_u128())
+                .chars()
+                .take(*length)
+                .collect(),
+            IdFormat::Base58 => encode(key.as_u128(), BASE58_ALPHABET),
+            IdFormat::Base62 => encode(key.as_u128(), BASE62_ALPHABET),
+        }
+    }
+}
+
+impl Table {
+    /// Renders the primary keys in the format whenever the table is printed or exported.
+    pub fn set_id_format(&mut self, id_format: IdFormat) {
+        self.id_format = id_format;
+    }
+
+    pub fn id_format(&self) -> IdFormat {
+        self.id_format
+    }
+}
+
+fn encode(mut number: u128, alphabet: &[u8]) -> String {
+    let base = alphabet.len() as u128;
+    let mut digits = Vec::new();
+    loop {
+        digits.push(alphabet[(number % base) as usize]);
+        number /= base;
+        if number == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+
+    String::from_utf8(digits).unwrap()
+}
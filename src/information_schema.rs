@@ -0,0 +1,220 @@
+use crate::{ColumnDefinition, DataType, IntoCell, Row, Table};
+use uuid::Uuid;
+
+/// Describes the given tables with one row per table, so generic tooling can discover them via the regular table API.
+pub fn tables(tables: &[&Table]) -> Table {
+    let mut result = Table::create(
+        String::from("tables"),
+        vec![
+            string_column("table_name"),
+            integer_column("column_count"),
+            integer_column("row_count"),
+        ],
+    );
+
+    tables.iter().for_each(|table| {
+        let mut row = Row::create(&result, Uuid::new_v4());
+        row.set_cell(
+            String::from("table_name"),
+            table.identifier.as_str().into_cell(),
+        );
+        row.set_cell(
+            String::from("column_count"),
+            (table.columns.len() as i64).into_cell(),
+        );
+        row.set_cell(
+            String::from("row_count"),
+            (table.keys.len() as i64).into_cell(),
+        );
+
+        result
+            .create_row(row)
+            .expect("Metadata rows always match the schema.");
+    });
+
+    result
+}
+
+/// Describes all columns of the given tables with one row per column.
+pub fn columns(tables: &[&Table]) -> Table {
+    let mut result = Table::create(
+        String::from("columns"),
+        vec![
+            string_column("table_name"),
+            string_column("column_name"),
+            integer_column("ordinal_position"),
+            string_column("data_type"),
+            string_column("is_nullable"),
+            string_column("constraints"),
+            string_column("description"),
+            string_column("label"),
+            string_column("unit"),
+            string_column("tags"),
+        ],
+    );
+
+    tables.iter().for_each(|table| {
+        table
+            .columns
+            .values()
+            .enumerate()
+            .for_each(|(position, column)| {
+                let is_nullable = if column.is_nullable { "YES" } else { "NO" };
+
+                let mut row = Row::create(&result, Uuid::new_v4());
+                row.set_cell(
+                    String::from("table_name"),
+                    table.identifier.as_str().into_cell(),
+                );
+                row.set_cell(
+                    String::from("column_name"),
+                    column.identifier.as_str().into_cell(),
+                );
+                row.set_cell(
+                    String::from("ordinal_position"),
+                    (position as i64 + 1).into_cell(),
+                );
+                row.set_cell(
+                    String::from("data_type"),
+                    column.data_type.to_string().into_cell(),
+                );
+                row.set_cell(String::from("is_nullable"), is_nullable.into_cell());
+                row.set_cell(
+                    String::from("constraints"),
+                    column
+                        .constraints
+                        .iter()
+                        .map(|constraint| constraint.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                        .into_cell(),
+                );
+                // Missing metadata is an empty string, just like missing constraints
+                let metadata = &column.metadata;
+                [
+                    ("description", &metadata.description),
+                    ("label", &metadata.label),
+                    ("unit", &metadata.unit),
+                ]
+                .iter()
+                .for_each(|(identifier, value)| {
+                    row.set_cell(
+                        String::from(*identifier),
+                        value.as_deref().unwrap_or("").into_cell(),
+                    )
+                });
+                row.set_cell(String::from("tags"), metadata.tags.join(", ").into_cell());
+
+                result
+                    .create_row(row)
+                    .expect("Metadata rows always match the schema.");
+            });
+    });
+
+    result
+}
+
+/// Describes the constraints of all columns of the given tables with one row per constraint.
+pub fn constraints(tables: &[&Table]) -> Table {
+    let mut result = Table::create(
+        String::from("constraints"),
+        vec![
+            string_column("table_name"),
+            string_column("column_name"),
+            string_column("constraint"),
+        ],
+    );
+
+    tables.iter().for_each(|table| {
+        table.columns.values().for_each(|column| {
+            column.constraints.iter().for_each(|constraint| {
+                let mut row = Row::create(&result, Uuid::new_v4());
+                row.set_cell(
+                    String::from("table_name"),
+                    table.identifier.as_str().into_cell(),
+                );
+                row.set_cell(
+                    String::from("column_name"),
+                    column.identifier.as_str().into_cell(),
+                );
+                row.set_cell(
+                    String::from("constraint"),
+                    constraint.to_string().into_cell(),
+                );
+
+                result
+                    .create_row(row)
+                    .expect("Metadata rows always match the schema.");
+            });
+        });
+    });
+
+    result
+}
+
+/// Describes the indexes of the given tables with one row per index. Bitmap indexes that are
+/// still being built are listed as well, the ordered keys are listed as an index of the ID column.
+pub fn indexes(tables: &[&Table]) -> Table {
+    let mut result = Table::create(
+        String::from("indexes"),
+        vec![
+            string_column("table_name"),
+            string_column("column_name"),
+            string_column("index_type"),
+            string_column("is_partial"),
+        ],
+    );
+
+    tables.iter().for_each(|table| {
+        table.columns.keys().for_each(|identifier| {
+            let is_partial = table
+                .unique_indexes
+                .get(identifier)
+                .map(|index| index.is_partial());
+            let is_ordered = identifier == "ID" && table.ordered_keys.is_some();
+            [
+                (
+                    "BITMAP",
+                    table.bitmap_indexes.contains_key(identifier)
+                        || table.pending_bitmap_indexes.contains_key(identifier),
+                    false,
+                ),
+                ("UNIQUE", is_partial.is_some(), is_partial == Some(true)),
+                (
+                    "SPATIAL",
+                    table.spatial_indexes.contains_key(identifier),
+                    false,
+                ),
+                ("ORDERED", is_ordered, false),
+            ]
+            .iter()
+            .filter(|(_, exists, _)| *exists)
+            .for_each(|(index_type, _, is_partial)| {
+                let is_partial = if *is_partial { "YES" } else { "NO" };
+
+                let mut row = Row::create(&result, Uuid::new_v4());
+                row.set_cell(
+                    String::from("table_name"),
+                    table.identifier.as_str().into_cell(),
+                );
+                row.set_cell(String::from("column_name"), identifier.as_str().into_cell());
+                row.set_cell(String::from("index_type"), index_type.into_cell());
+                row.set_cell(String::from("is_partial"), is_partial.into_cell());
+
+                result
+                    .create_row(row)
+                    .expect("Metadata rows always match the schema.");
+            });
+        });
+    });
+
+    result
+}
+
+fn string_column(identifier: &str) -> ColumnDefinition {
+    ColumnDefinition::create(identifier, DataType::String, false)
+}
+
+fn integer_column(identifier: &str) -> ColumnDefinition {
+    ColumnDefinition::create(identifier, DataType::Integer, false)
+}
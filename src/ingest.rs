@@ -0,0 +1,64 @@
+use crate::error::VirtualTableError;
+use crate::instrumentation::OperationSpan;
+use crate::{Row, Table};
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct IngestProgress {
+    pub rows_ingested: usize,
+    pub batches: usize,
+    // Bitmap index builds that haven't caught up with the table yet
+    pub pending_index_builds: usize,
+}
+
+impl Table {
+    /// Creates the rows in batches, pulling them from the iterator only when the previous batch
+    /// is written, so a slow source is never buffered. After every batch, bitmap indexes started
+    /// with `Table::start_bitmap_index_build` advance by one batch and the progress is reported.
+    ///
+    /// Since there are no transactions, rows created before an invalid row are kept.
+    pub fn ingest<I, F>(
+        &mut self,
+        rows: I,
+        batch_size: usize,
+        mut on_progress: F,
+    ) -> Result<IngestProgress, Vec<VirtualTableError>>
+    where
+        I: IntoIterator<Item = Row>,
+        F: FnMut(IngestProgress),
+    {
+        let span = OperationSpan::start("ingest", self);
+        let mut progress = IngestProgress::default();
+        let result = self.in_batch(|table| {
+            table.ingest_batches(rows, batch_size.max(1), &mut progress, &mut on_progress)
+        });
+        span.finish(&result, progress.rows_ingested);
+
+        result.map(|_| progress)
+    }
+
+    fn ingest_batches<I, F>(
+        &mut self,
+        rows: I,
+        batch_size: usize,
+        progress: &mut IngestProgress,
+        on_progress: &mut F,
+    ) -> Result<(), Vec<VirtualTableError>>
+    where
+        I: IntoIterator<Item = Row>,
+        F: FnMut(IngestProgress),
+    {
+        let mut rows = rows.into_iter().peekable();
+        while rows.peek().is_some() {
+            for row in rows.by_ref().take(batch_size) {
+                self.create_row(row)?;
+                progress.rows_ingested += 1;
+            }
+
+            progress.batches += 1;
+            progress.pending_index_builds = self.continue_bitmap_index_builds(batch_size);
+            on_progress(*progress);
+        }
+
+        Result::Ok(())
+    }
+}
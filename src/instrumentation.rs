@@ -0,0 +1,51 @@
+use crate::metrics::Metrics;
+use crate::Table;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Covers a single table operation and reports it to the metrics of the table. With the `tracing`
+/// feature enabled, the operation also runs inside a span and emits an event with the affected
+/// rows and its duration once it's finished.
+pub(crate) struct OperationSpan {
+    operation: &'static str,
+    metrics: Option<Arc<dyn Metrics>>,
+    started_at: Instant,
+    #[cfg(feature = "tracing")]
+    span: tracing::span::EnteredSpan,
+}
+
+impl OperationSpan {
+    pub(crate) fn start(operation: &'static str, table: &Table) -> Self {
+        OperationSpan {
+            operation,
+            metrics: table.metrics.clone(),
+            started_at: Instant::now(),
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!("table_operation", operation, table = %table.identifier)
+                .entered(),
+        }
+    }
+
+    /// Ends the operation, rows are the number of rows that were written or returned.
+    pub(crate) fn finish<T, E: Debug>(self, result: &Result<T, E>, rows: usize) {
+        let duration = self.started_at.elapsed();
+        if let Some(metrics) = &self.metrics {
+            match result {
+                Ok(_) => metrics.operation_finished(self.operation, duration, rows),
+                Err(_) => metrics.operation_failed(self.operation, duration),
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        {
+            let duration_us = duration.as_micros() as u64;
+            match result {
+                Ok(_) => tracing::debug!(rows, duration_us, "operation finished"),
+                Err(error) => tracing::debug!(?error, duration_us, "operation failed"),
+            }
+            // The events above belong to the span, so it's only left now
+            drop(self.span);
+        }
+    }
+}
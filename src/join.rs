@@ -0,0 +1,125 @@
+use crate::collation::collation_key;
+use crate::error::VirtualTableError;
+use crate::instrumentation::OperationSpan;
+use crate::query::ColumnSpecification;
+use crate::{Row, Table, TableValue};
+use std::collections::HashSet;
+
+impl Table {
+    /// Finds the rows whose value in the column also occurs in the column of the other table, like
+    /// `WHERE EXISTS` in SQL. Every row is returned at most once, no matter how many rows of the
+    /// other table it matches. NULL values never match.
+    pub fn semi_join(
+        &self,
+        column_identifier: &str,
+        other: &Table,
+        other_column_identifier: &str,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        self.join_rows(
+            "semi_join",
+            column_identifier,
+            other,
+            other_column_identifier,
+            true,
+            column_specification,
+        )
+    }
+
+    /// Finds the rows whose value in the column doesn't occur in the column of the other table,
+    /// like `WHERE NOT EXISTS` in SQL, e.g. the customers without orders. Since NULL values never
+    /// match, rows with a NULL value are always returned.
+    pub fn anti_join(
+        &self,
+        column_identifier: &str,
+        other: &Table,
+        other_column_identifier: &str,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        self.join_rows(
+            "anti_join",
+            column_identifier,
+            other,
+            other_column_identifier,
+            false,
+            column_specification,
+        )
+    }
+
+    fn join_rows(
+        &self,
+        operation: &'static str,
+        column_identifier: &str,
+        other: &Table,
+        other_column_identifier: &str,
+        is_semi_join: bool,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let span = OperationSpan::start(operation, self);
+        self.statistics.record_query();
+        let result = self.collect_joined_rows(
+            operation,
+            column_identifier,
+            other,
+            other_column_identifier,
+            is_semi_join,
+            column_specification,
+        );
+        span.finish(&result, result.as_ref().map_or(0, |rows| rows.len()));
+
+        result
+    }
+
+    fn collect_joined_rows(
+        &self,
+        operation: &'static str,
+        column_identifier: &str,
+        other: &Table,
+        other_column_identifier: &str,
+        is_semi_join: bool,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let column = self
+            .columns
+            .get(column_identifier)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(String::from(column_identifier)))?;
+        let other_column = other.columns.get(other_column_identifier).ok_or_else(|| {
+            VirtualTableError::UnknownColumn(String::from(other_column_identifier))
+        })?;
+        if other_column.data_type != column.data_type {
+            return Result::Err(VirtualTableError::InvalidDataType(
+                String::from(other_column_identifier),
+                column.data_type,
+                other_column.data_type,
+            ));
+        }
+
+        // The values of the other table are hashed with the collation of this column, so both
+        // sides compare the way this column does
+        let collation = self.collation_of(column_identifier);
+        other.record_rows_scanned(operation, other_column.values.len());
+        let other_keys = other_column
+            .values
+            .iter()
+            .filter(|value| **value != TableValue::Null)
+            .map(|value| collation_key(collation, value))
+            .collect::<HashSet<_>>();
+
+        self.record_rows_scanned(operation, column.values.len());
+        column
+            .values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| {
+                let is_match = **value != TableValue::Null
+                    && other_keys.contains(&collation_key(collation, value));
+                is_match == is_semi_join
+            })
+            .filter_map(|(index, _)| self.key_at(index))
+            .map(|key| {
+                self.try_find_row(&key, column_specification.clone())
+                    .map(|row| row.expect("Rows at valid indexes always exist."))
+            })
+            .collect()
+    }
+}
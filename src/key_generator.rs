@@ -0,0 +1,87 @@
+use crate::{ColumnDefinition, PrimaryKey, Table};
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+const CROCKFORD_ALPHABET: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Strategy a table uses to generate keys for new rows, see `Table::generate_key`. All keys are
+/// stored as 128 bit UUIDs, so every strategy works with the rest of the table.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub enum KeyGenerator {
+    // Random keys without any order
+    #[default]
+    UuidV4,
+    // Keys that start with the creation time in milliseconds, so they sort by creation time
+    UuidV7,
+    // Like UUIDv7, but with 80 random bits and without version bits
+    Ulid,
+    // 1, 2, 3, ... counted per table
+    Sequence,
+}
+
+impl KeyGenerator {
+    fn generate(self, last_sequence_value: &mut u128) -> PrimaryKey {
+        match self {
+            KeyGenerator::UuidV4 => Uuid::new_v4(),
+            KeyGenerator::UuidV7 => {
+                let random = rand::random::<u128>();
+                let bits = (unix_millis() << 80)
+                    | (0x7 << 76)
+                    | (random & (0xfff << 64))
+                    | (0x2 << 62)
+                    | (random & ((1 << 62) - 1));
+
+                Uuid::from_u128(bits)
+            }
+            KeyGenerator::Ulid => {
+                Uuid::from_u128((unix_millis() << 80) | (rand::random::<u128>() & ((1 << 80) - 1)))
+            }
+            KeyGenerator::Sequence => {
+                *last_sequence_value += 1;
+                Uuid::from_u128(*last_sequence_value)
+            }
+        }
+    }
+}
+
+fn unix_millis() -> u128 {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis());
+
+    // Only 48 bits are available for the timestamp
+    millis & ((1 << 48) - 1)
+}
+
+/// Formats a key as the 26 characters of a ULID in Crockford's Base32.
+pub fn to_ulid_string(key: &PrimaryKey) -> String {
+    let bits = key.as_u128();
+    (0..26)
+        .rev()
+        .map(|position| CROCKFORD_ALPHABET[((bits >> (position * 5)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+impl Table {
+    /// Creates a table that generates keys for new rows with the given strategy.
+    pub fn create_with_key_generator(
+        identifier: String,
+        columns: Vec<ColumnDefinition>,
+        key_generator: KeyGenerator,
+    ) -> Self {
+        let mut table = Table::create(identifier, columns);
+        table.key_generator = key_generator;
+
+        table
+    }
+
+    pub fn key_generator(&self) -> KeyGenerator {
+        self.key_generator
+    }
+
+    /// Generates a key for a new row. Keys of a sequence are never handed out twice, even if the
+    /// row is never created.
+    pub fn generate_key(&mut self) -> PrimaryKey {
+        self.key_generator.generate(&mut self.last_sequence_value)
+    }
+}
@@ -0,0 +1,179 @@
+use crate::error::VirtualTableError;
+use crate::query::ColumnSpecification;
+use crate::{Cell, DataType, IntoCell, PrimaryKey, Row, Table, TableValue};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Key/value access to a table with a String key column and a value column, like a small Redis.
+/// Values are still stored as rows, so the data types and constraints of the table apply. Keys
+/// can expire, expired keys behave as if they were deleted and are removed on the next write or
+/// by `KeyValueView::purge_expired`.
+#[derive(Debug)]
+pub struct KeyValueView {
+    table: Table,
+    key_column: String,
+    value_column: String,
+    keys: HashMap<String, PrimaryKey>,
+    expirations: HashMap<String, Instant>,
+}
+
+impl KeyValueView {
+    /// Wraps the table, existing rows become entries. Every key may only be used by one row.
+    pub fn create(
+        table: Table,
+        key_column: &str,
+        value_column: &str,
+    ) -> Result<Self, VirtualTableError> {
+        let key_type = table
+            .columns
+            .get(key_column)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(String::from(key_column)))?
+            .data_type;
+        if key_type != DataType::String {
+            return Result::Err(VirtualTableError::InvalidDataType(
+                String::from(key_column),
+                DataType::String,
+                key_type,
+            ));
+        }
+        if !table.columns.contains_key(value_column) {
+            return Result::Err(VirtualTableError::UnknownColumn(String::from(value_column)));
+        }
+
+        let mut keys = HashMap::new();
+        for (primary_key, index) in &table.keys {
+            if let Some(TableValue::String(key)) = table.columns[key_column].value_at(*index) {
+                if keys.insert(key.clone(), *primary_key).is_some() {
+                    return Result::Err(VirtualTableError::DuplicateValue(
+                        String::from(key_column),
+                        TableValue::from(key.as_str()),
+                    ));
+                }
+            }
+        }
+
+        Result::Ok(KeyValueView {
+            table,
+            key_column: String::from(key_column),
+            value_column: String::from(value_column),
+            keys,
+            expirations: HashMap::new(),
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<TableValue> {
+        let primary_key = self.live_key(key)?;
+        self.table
+            .find_row(&primary_key, ColumnSpecification::All)
+            .and_then(|row| row.get_cell(&self.value_column).cloned())
+    }
+
+    /// Stores the value under the key. Just like in Redis, this removes an expiration of the key.
+    pub fn set(&mut self, key: &str, value: Cell) -> Result<(), Vec<VirtualTableError>> {
+        self.remove_if_expired(key);
+
+        match self.keys.get(key) {
+            Some(primary_key) => {
+                let mut row = Row::create(&self.table, *primary_key);
+                row.set_cell(self.value_column.clone(), value);
+                self.table.update_row(row)?;
+            }
+            None => {
+                let primary_key = self.table.generate_key();
+                let mut row = Row::create(&self.table, primary_key);
+                row.set_cell(self.key_column.clone(), key.into_cell());
+                row.set_cell(self.value_column.clone(), value);
+                self.table.create_row(row)?;
+                self.keys.insert(String::from(key), primary_key);
+            }
+        }
+        self.expirations.remove(key);
+
+        Result::Ok(())
+    }
+
+    /// Removes the key and its row, false if there was no such key.
+    pub fn delete(&mut self, key: &str) -> bool {
+        let is_live = self.live_key(key).is_some();
+        self.remove(key);
+
+        is_live
+    }
+
+    /// Lets the key expire after the duration, false if there is no such key. Durations too long
+    /// to be represented let the key never expire.
+    pub fn expire(&mut self, key: &str, time_to_live: Duration) -> bool {
+        if self.live_key(key).is_none() {
+            return false;
+        }
+
+        match Instant::now().checked_add(time_to_live) {
+            Some(expiration) => self.expirations.insert(String::from(key), expiration),
+            None => self.expirations.remove(key),
+        };
+        true
+    }
+
+    /// The time until the key expires, None if the key doesn't exist or never expires.
+    pub fn time_to_live(&self, key: &str) -> Option<Duration> {
+        self.live_key(key)?;
+        self.expirations
+            .get(key)
+            .map(|expiration| expiration.saturating_duration_since(Instant::now()))
+    }
+
+    /// Removes the rows of all expired keys and returns how many there were.
+    pub fn purge_expired(&mut self) -> usize {
+        let now = Instant::now();
+        let expired = self
+            .expirations
+            .iter()
+            .filter(|(_, expiration)| **expiration <= now)
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        expired.iter().for_each(|key| self.remove(key));
+
+        expired.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys
+            .keys()
+            .filter(|key| self.live_key(key).is_some())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The table underneath, for everything this view doesn't cover.
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    pub fn into_table(self) -> Table {
+        self.table
+    }
+
+    fn live_key(&self, key: &str) -> Option<PrimaryKey> {
+        match self.expirations.get(key) {
+            Some(expiration) if *expiration <= Instant::now() => None,
+            _ => self.keys.get(key).copied(),
+        }
+    }
+
+    fn remove_if_expired(&mut self, key: &str) {
+        if self.keys.contains_key(key) && self.live_key(key).is_none() {
+            self.remove(key);
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        self.expirations.remove(key);
+        // Deleted like any other row, so the history and the statistics of the table see it
+        if let Some(primary_key) = self.keys.remove(key) {
+            let _ = self.table.delete_row(&primary_key);
+        }
+    }
+}
@@ -1,12 +1,110 @@
+pub mod aggregate;
+pub mod anonymization;
+#[cfg(feature = "arrow")]
+pub mod arrow_import;
+pub mod audit;
+pub mod bitmap_index;
+pub mod bloom_filter;
+pub mod cancellation;
+pub mod checksum;
+pub mod coercion;
+pub mod collation;
+pub mod consistency;
+pub mod constraint;
+pub mod crdt;
+pub mod custom_type;
+pub mod diff;
+pub mod dimension;
+pub mod duplicates;
+pub mod duration;
 pub mod error;
+pub mod etag;
+pub mod events;
+pub mod expression;
+pub mod fixtures;
 pub mod format;
+pub mod functions;
+pub mod fuzzy;
+pub mod geo;
+pub mod graph;
+pub mod hierarchy;
+pub mod highlight;
+pub mod history;
+pub mod hyperloglog;
+pub mod id_format;
+pub mod information_schema;
+pub mod ingest;
+pub mod join;
+pub mod key_generator;
+pub mod key_value;
+pub mod linkage;
+pub mod masking;
+pub mod metadata;
+pub mod merkle;
+pub mod metrics;
+pub mod money;
+pub mod normalization;
+pub mod null_bitmap;
+pub mod ordered_keys;
+pub mod predicate;
+pub mod profiling;
 pub mod query;
+pub mod report;
+pub mod row_id;
+pub mod sampling;
+pub mod saved_query;
+pub mod schema;
+pub mod security;
+pub mod sorting;
+pub mod statistics;
+pub mod timestamp;
+pub mod transformer;
+pub mod typed_table;
+pub mod unique_index;
+pub mod version;
+#[cfg(feature = "testing")]
+pub mod testing;
 
+mod instrumentation;
+
+use crate::bitmap_index::{BitmapIndex, PendingBitmapIndex};
+use crate::bloom_filter::BloomFilter;
+use crate::collation::Collation;
+use crate::constraint::Constraint;
+use crate::crdt::CellClocks;
+use crate::custom_type::{CustomTypeId, CustomValue};
 use crate::error::VirtualTableError;
+use crate::events::TableEvent;
+use crate::format::{Alignment, WidthSetting};
+use crate::functions::UserFunction;
+use crate::geo::{GridIndex, Point};
+use crate::highlight::HighlightRule;
+use crate::history::{History, Operation};
+use crate::id_format::IdFormat;
+use crate::instrumentation::OperationSpan;
+use crate::audit::AuditColumns;
+use crate::key_generator::KeyGenerator;
+use crate::masking::Mask;
+use crate::metadata::ColumnMetadata;
+use crate::metrics::Metrics;
+use crate::money::Money;
+use crate::normalization::NormalizationForm;
+use crate::null_bitmap::ColumnValues;
 use linked_hash_map::LinkedHashMap;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryFrom;
+use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 use crate::query::ColumnSpecification;
+use crate::row_id::RowId;
+use crate::saved_query::SavedQuery;
+use crate::unique_index::UniqueIndex;
+use crate::security::Policy;
+use crate::statistics::{Modification, Statistics};
+use crate::timestamp::{TimeZone, Timestamp};
+use crate::transformer::{RowTransformer, WriteStage};
+use crate::version::Version;
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct Column {
@@ -15,11 +113,14 @@ pub struct Column {
     // The data type must be enforced over the whole column
     data_type: DataType,
     is_nullable: bool,
+    constraints: Vec<Constraint>,
+    metadata: ColumnMetadata,
 
     // The values are stored in a vec, so its only accessible via its index.
     // This implies, that one can only effectively access a column value via the table,
-    //  since the table stores a mapping between PK and Index
-    values: Vec<Cell>,
+    //  since the table stores a mapping between PK and Index.
+    // NULL values only take up a bit in the null bitmap of the column.
+    values: ColumnValues,
 }
 
 impl Column {
@@ -28,11 +129,23 @@ impl Column {
             identifier,
             data_type,
             is_nullable,
-            values: Vec::new(),
+            constraints: Vec::new(),
+            metadata: ColumnMetadata::default(),
+            values: ColumnValues::default(),
         }
     }
 
     pub(crate) fn set_cell(&mut self, index: Index, cell: Cell) -> Result<(), VirtualTableError> {
+        self.validate(&cell)?;
+
+        // Existing cells get replaced, new ones are appended to the column
+        self.values.set(index, cell.inner);
+
+        Result::Ok(())
+    }
+
+    // Checks whether the cell may be stored in this column
+    pub(crate) fn validate(&self, cell: &Cell) -> Result<(), VirtualTableError> {
         if self.data_type != cell.data_type {
             return Result::Err(VirtualTableError::InvalidDataType(
                 self.identifier.clone(),
@@ -46,7 +159,17 @@ impl Column {
             return Result::Err(VirtualTableError::InvalidNullValue(self.identifier.clone()));
         }
 
-        self.values.insert(index, cell);
+        if let Some(constraint) = self
+            .constraints
+            .iter()
+            .find(|constraint| !constraint.is_satisfied_by(&cell.inner))
+        {
+            return Result::Err(VirtualTableError::ConstraintViolation(
+                self.identifier.clone(),
+                constraint.clone(),
+            ));
+        }
+
         Result::Ok(())
     }
 
@@ -56,20 +179,89 @@ impl Column {
             return Result::Err(VirtualTableError::InvalidRowIndex(index));
         }
 
-        Result::Ok(self.values.remove(index))
+        Result::Ok(Cell {
+            data_type: self.data_type,
+            inner: self.values.remove(index).expect("Index was checked before."),
+        })
     }
 
     pub fn value_at(&self, index: Index) -> Option<&TableValue> {
-        self.values.get(index).map(|cell| &cell.inner)
+        self.values.get(index)
+    }
+
+    pub(crate) fn cell_at(&self, index: Index) -> Option<Cell> {
+        self.value_at(index).map(|value| Cell {
+            data_type: self.data_type,
+            inner: value.clone(),
+        })
     }
 }
 
 pub type Index = usize;
 
+#[derive(Debug)]
 pub struct Table {
     identifier: String,
     columns: LinkedHashMap<String, Column>,
     keys: HashMap<PrimaryKey, Index>,
+    // Only tables in event-sourced mode keep a log of their mutations
+    events: Option<Vec<TableEvent>>,
+    // The version every event produced, aligned with the events
+    event_versions: Vec<Version>,
+    // Increased by every mutation, see `Table::current_version`
+    version: Version,
+    history: Option<History>,
+    // Optional index to iterate rows in key order, see `Table::enable_ordered_keys`
+    ordered_keys: Option<BTreeSet<PrimaryKey>>,
+    // Maps a row id to the current index of its row, removed rows leave an empty slot behind
+    row_slots: Vec<Option<Index>>,
+    // The row id for every index, so it's aligned with the column values
+    row_ids: Vec<RowId>,
+    functions: HashMap<String, UserFunction>,
+    bitmap_indexes: HashMap<String, BitmapIndex>,
+    // Bitmap indexes that are built in batches, see `Table::start_bitmap_index_build`
+    pending_bitmap_indexes: HashMap<String, PendingBitmapIndex>,
+    // Optional filter to reject unknown keys quickly, see `Table::enable_bloom_filter`
+    bloom_filter: Option<BloomFilter>,
+    metrics: Option<Arc<dyn Metrics>>,
+    // Decides which rows are visible to the `*_as` methods, see `Table::set_policy`
+    policy: Option<Policy>,
+    // Masks applied to column values when the table is printed
+    masks: HashMap<String, Mask>,
+    // Converts values to the column type on writes, see `Table::enable_coercion`
+    coerce_values: bool,
+    // Collations of String columns, all others compare binary
+    collations: HashMap<String, Collation>,
+    // Normalization applied to String values on writes, see `Table::enable_normalization`
+    normalization: Option<NormalizationForm>,
+    unique_indexes: HashMap<String, UniqueIndex>,
+    // Time zones timestamp columns are displayed in, all others are displayed in UTC
+    time_zones: HashMap<String, TimeZone>,
+    spatial_indexes: HashMap<String, GridIndex>,
+    // Generates the keys for `Table::generate_key`, sequences count up from the last value
+    key_generator: KeyGenerator,
+    last_sequence_value: u128,
+    // Overrides the default alignment of data types in rendered output
+    type_alignments: Vec<(DataType, Alignment)>,
+    column_alignments: HashMap<String, Alignment>,
+    // How the keys in the ID column are rendered, see `Table::set_id_format`
+    id_format: IdFormat,
+    // Total width the boxed table is fitted into, see `Table::set_width_budget`
+    width_budget: WidthSetting,
+    // Always shown within the width budget, see `Table::pin_columns`
+    pinned_columns: Vec<String>,
+    // Styles values in printed tables and HTML reports, see `Table::add_highlight_rule`
+    highlight_rules: Vec<HighlightRule>,
+    // Queries that can be run by name, see `Table::define_query`
+    saved_queries: HashMap<String, SavedQuery>,
+    // Rewrite rows before they are written, see `Table::add_row_transformer`
+    row_transformers: Vec<RowTransformer>,
+    // Maintained on every write when enabled, see `Table::enable_audit_columns`
+    audit_columns: Option<AuditColumns>,
+    // Totals of reads and writes, see `Table::stats`
+    statistics: Statistics,
+    // Last writer of every cell, see `Table::enable_crdt`
+    cell_clocks: CellClocks,
 }
 
 impl Table {
@@ -78,21 +270,98 @@ impl Table {
             identifier,
             columns: Table::create_columns_from_definition(columns),
             keys: HashMap::new(),
+            events: None,
+            event_versions: Vec::new(),
+            version: 0,
+            history: None,
+            ordered_keys: None,
+            row_slots: Vec::new(),
+            row_ids: Vec::new(),
+            functions: HashMap::new(),
+            bitmap_indexes: HashMap::new(),
+            pending_bitmap_indexes: HashMap::new(),
+            bloom_filter: None,
+            metrics: None,
+            policy: None,
+            masks: HashMap::new(),
+            coerce_values: false,
+            collations: HashMap::new(),
+            normalization: None,
+            unique_indexes: HashMap::new(),
+            time_zones: HashMap::new(),
+            spatial_indexes: HashMap::new(),
+            key_generator: KeyGenerator::default(),
+            last_sequence_value: 0,
+            type_alignments: Vec::new(),
+            column_alignments: HashMap::new(),
+            id_format: IdFormat::default(),
+            width_budget: WidthSetting::Unset,
+            pinned_columns: Vec::new(),
+            highlight_rules: Vec::new(),
+            saved_queries: HashMap::new(),
+            row_transformers: Vec::new(),
+            audit_columns: None,
+            statistics: Statistics::default(),
+            cell_clocks: CellClocks::default(),
         }
     }
 
+    /// Creates a table that records every successful mutation as an event, see `Table::replay_to`.
+    pub fn create_event_sourced(identifier: String, columns: Vec<ColumnDefinition>) -> Self {
+        let mut table = Table::create(identifier, columns);
+        table.events = Some(Vec::new());
+
+        table
+    }
+
     // TODO: This should be "transactional" I guess.
     pub fn create_row(&mut self, row: Row) -> Result<(), Vec<VirtualTableError>> {
-        if self.keys.contains_key(&row.primary_key) {
+        self.create_row_by(row, None)
+    }
+
+    // The principal is the one recorded in the audit columns, if there is one
+    pub(crate) fn create_row_by(
+        &mut self,
+        row: Row,
+        principal: Option<&str>,
+    ) -> Result<(), Vec<VirtualTableError>> {
+        let span = OperationSpan::start("create_row", self);
+        let row = self.transform_row(WriteStage::BeforeInsert, row);
+        let row = self.stamp_audit_columns(WriteStage::BeforeInsert, row, principal);
+        let key = row.primary_key;
+        let result = self.insert_row(row);
+        if result.is_ok() && self.is_crdt_enabled() {
+            let identifiers = self.column_identifiers();
+            self.stamp_cell_clocks(key, identifiers);
+        }
+        span.finish(&result, 1);
+
+        result
+    }
+
+    pub(crate) fn insert_row(&mut self, row: Row) -> Result<(), Vec<VirtualTableError>> {
+        let row = self.normalize_row(self.coerce_row(row));
+        if self.contains_key(&row.primary_key) {
             return Result::Err(vec![VirtualTableError::DuplicatePrimaryKey(
                 row.primary_key,
             )]);
         }
+        let duplicates = self.check_unique_values(&row);
+        if !duplicates.is_empty() {
+            return Result::Err(duplicates);
+        }
 
+        let event = self.prepare_event(|| TableEvent::RowCreated(row.clone()));
+        let operation = self.prepare_history(|| Operation::Insert(row.clone()));
         let new_index = self.keys.len();
         self.keys.insert(row.primary_key, new_index);
-        let errors = row
-            .cells
+        // Columns the row has no cell for at all, e.g. because they were added after the row was
+        // recorded in an event, get NULL as well, so all columns stay the same length
+        let mut cells = row.cells;
+        for identifier in self.columns.keys() {
+            cells.entry(identifier.clone()).or_insert(None);
+        }
+        let mut errors = cells
             .into_iter()
             .flat_map(|(identifier, cell_option)| {
                 let column_option = self.columns.get_mut(&identifier);
@@ -114,6 +383,9 @@ impl Table {
                 col.set_cell(new_index, cell).err()
             })
             .collect::<Vec<_>>();
+        if errors.is_empty() {
+            errors = self.check_partial_unique_values(new_index, row.primary_key);
+        }
 
         if !errors.is_empty() {
             // If we experienced any errors, we should reject all values from this column to avoid subsequent panics
@@ -121,6 +393,22 @@ impl Table {
             return Result::Err(errors);
         }
 
+        if let Some(ordered_keys) = self.ordered_keys.as_mut() {
+            ordered_keys.insert(row.primary_key);
+        }
+        if let Some(bloom_filter) = self.bloom_filter.as_mut() {
+            bloom_filter.insert(&row.primary_key);
+        }
+        self.assign_row_id(new_index);
+        self.index_row(new_index);
+        self.advance_version();
+        self.record_modification(Modification::Insert);
+
+        self.record_event(event);
+        if let Some(operation) = operation {
+            self.record_operation(operation);
+        }
+
         Result::Ok(())
     }
 
@@ -130,94 +418,279 @@ impl Table {
         // Extend the definitions by a first column "ID" which contains the PK
         definitions.insert(
             0,
-            ColumnDefinition {
-                data_type: DataType::Uuid,
-                is_nullable: false,
-                identifier: String::from("ID"),
-            },
+            ColumnDefinition::create("ID", DataType::Uuid, false),
         );
 
         definitions
             .into_iter()
             .map(|def| {
-                (
-                    def.identifier.clone(),
-                    Column::create(def.identifier, def.data_type, def.is_nullable),
-                )
+                let mut column = Column::create(def.identifier, def.data_type, def.is_nullable);
+                column.constraints = def.constraints;
+                column.metadata = def.metadata;
+
+                (column.identifier.clone(), column)
             })
             .collect()
     }
 
     pub fn update_row(&mut self, update_row: Row) -> Result<(), Vec<VirtualTableError>> {
-        if !self.keys.contains_key(&update_row.primary_key) {
-            return Result::Err(vec![VirtualTableError::UnknownPrimaryKey(
-                update_row.primary_key,
-            )]);
+        self.update_row_by(update_row, None)
+    }
+
+    pub(crate) fn update_row_by(
+        &mut self,
+        update_row: Row,
+        principal: Option<&str>,
+    ) -> Result<(), Vec<VirtualTableError>> {
+        let span = OperationSpan::start("update_row", self);
+        let update_row = self.transform_row(WriteStage::BeforeUpdate, update_row);
+        let update_row = self.stamp_audit_columns(WriteStage::BeforeUpdate, update_row, principal);
+        let key = update_row.primary_key;
+        let identifiers = if self.is_crdt_enabled() {
+            update_row.written_identifiers()
+        } else {
+            Vec::new()
+        };
+        let result = self.apply_update(update_row);
+        if result.is_ok() && self.is_crdt_enabled() {
+            self.stamp_cell_clocks(key, identifiers);
+        }
+        span.finish(&result, 1);
+
+        result
+    }
+
+    /// Deletes the row with the given key and returns all of its cells. The rows behind it move
+    /// up by one, their keys, row ids and index entries follow them.
+    pub fn delete_row(&mut self, key: &PrimaryKey) -> Result<Row, Vec<VirtualTableError>> {
+        let span = OperationSpan::start("delete_row", self);
+        let result = match self.find_row(key, ColumnSpecification::All) {
+            Some(row) => {
+                self.remove_row(key);
+                Result::Ok(row)
+            }
+            None => Result::Err(vec![VirtualTableError::UnknownPrimaryKey(*key)]),
+        };
+        span.finish(&result, 1);
+
+        result
+    }
+
+    pub(crate) fn apply_update(&mut self, update_row: Row) -> Result<(), Vec<VirtualTableError>> {
+        let update_row = self.normalize_row(self.coerce_row(update_row));
+        let row_index = match self.keys.get(&update_row.primary_key) {
+            Some(index) => *index,
+            None => {
+                return Result::Err(vec![VirtualTableError::UnknownPrimaryKey(
+                    update_row.primary_key,
+                )])
+            }
+        };
+        let duplicates = self.check_unique_values(&update_row);
+        if !duplicates.is_empty() {
+            return Result::Err(duplicates);
         }
 
-        let row_index = self.keys.get(&update_row.primary_key).unwrap().clone();
+        let event = self.prepare_event(|| TableEvent::RowUpdated(update_row.clone()));
+        let after = self.prepare_history(|| update_row.clone());
+        let primary_key = update_row.primary_key;
+
+        // The row is indexed again with its new values once the update is through
+        self.unindex_row(row_index);
 
-        let errors = update_row
+        // We keep the previous cells around to restore them on errors and for the undo history
+        let mut previous_cells = Vec::new();
+        let mut errors = update_row
             .cells
             .into_iter()
-            .map(|(identifier, cell_option)| {
+            .filter_map(|(identifier, cell_option)| {
                 // if we see a None cell in the update row, we ignore it since that means the cell should not be updated (= partial update)
-                if cell_option.is_none() {
-                    return None;
-                }
+                let cell = cell_option?;
 
-                let column_option = self.columns.get_mut(&identifier);
-                if column_option.is_none() {
-                    return Some(VirtualTableError::UnknownColumn(String::from(identifier)));
+                let col = match self.columns.get_mut(&identifier) {
+                    Some(col) => col,
+                    None => return Some(VirtualTableError::UnknownColumn(identifier)),
+                };
+
+                let previous_cell = col.cell_at(row_index);
+                if let Err(error) = col.set_cell(row_index, cell) {
+                    return Some(error);
                 }
 
-                let col = column_option.unwrap();
+                if let Some(previous_cell) = previous_cell {
+                    previous_cells.push((identifier, previous_cell));
+                }
 
-                let cell = cell_option.unwrap();
-                col.set_cell(row_index, cell).err()
+                None
             })
-            .flatten()
             .collect::<Vec<_>>();
+        if errors.is_empty() {
+            errors = self.check_partial_unique_values(row_index, primary_key);
+        }
 
         if !errors.is_empty() {
-            // If we experienced any errors, we should reject all values from this column to avoid subsequent panics
-            self.rollback_at_index(&update_row.primary_key, row_index);
+            // If we experienced any errors, we restore the cells we already changed so the row stays untouched
+            previous_cells.into_iter().for_each(|(identifier, cell)| {
+                if let Some(col) = self.columns.get_mut(&identifier) {
+                    col.values.set(row_index, cell.inner);
+                }
+            });
+            self.index_row(row_index);
             return Result::Err(errors);
         }
 
+        self.index_row(row_index);
+        self.advance_version();
+        self.record_modification(Modification::Update);
+        self.record_event(event);
+        if let Some(after) = after {
+            let before = Row {
+                primary_key,
+                cells: previous_cells
+                    .into_iter()
+                    .map(|(identifier, cell)| (identifier, Some(cell)))
+                    .collect(),
+            };
+            self.record_operation(Operation::Update(before, after));
+        }
+
         Result::Ok(())
     }
 
+    /// Fetches the row with the given key. Projection errors are swallowed here, use `Table::try_find_row` to see them.
     pub fn find_row(&self, key: &PrimaryKey, column_specification: ColumnSpecification) -> Option<Row> {
-        let row_index = *self.keys.get(key)?;
+        self.try_find_row(key, column_specification).ok().flatten()
+    }
+
+    pub fn try_find_row(
+        &self,
+        key: &PrimaryKey,
+        column_specification: ColumnSpecification,
+    ) -> Result<Option<Row>, VirtualTableError> {
+        let span = OperationSpan::start("find_row", self);
+        let result = self.project_row(key, column_specification);
+        span.finish(&result, result.as_ref().map_or(0, |row| row.is_some() as usize));
+
+        result
+    }
+
+    fn project_row(
+        &self,
+        key: &PrimaryKey,
+        column_specification: ColumnSpecification,
+    ) -> Result<Option<Row>, VirtualTableError> {
+        if !self.contains_key(key) {
+            return Result::Ok(None);
+        }
+
+        let row_index = match self.keys.get(key) {
+            Some(index) => *index,
+            None => return Result::Ok(None),
+        };
 
-        let fetch_columns: Vec<&Column> = match column_specification {
-            ColumnSpecification::All => self.columns.values().collect(),
+        let cell_at = |column: &Column| Cell {
+            data_type: column.data_type,
+            inner: column
+                .value_at(row_index)
+                .expect("TODO: Implement error handling here.")
+                .clone(),
+        };
+
+        // Pairs of the identifier in the resulting row and the cell to put there
+        let cells: Vec<(String, Cell)> = match &column_specification {
+            ColumnSpecification::All => self
+                .columns
+                .iter()
+                .map(|(identifier, column)| (identifier.clone(), cell_at(column)))
+                .collect(),
             ColumnSpecification::Some(column_names) => {
                 self.columns
                     .iter()
                     .filter_map(|(identifier, column)| {
                         if column_names.contains(identifier) {
-                            return Some(column);
+                            return Some((identifier.clone(), cell_at(column)));
                         }
 
                         None
                     })
                     .collect()
             }
+            ColumnSpecification::Aliased(aliases) => aliases
+                .iter()
+                .filter_map(|(identifier, alias)| Some((alias.clone(), cell_at(self.columns.get(identifier)?))))
+                .collect(),
+            ColumnSpecification::Computed(projections) => projections
+                .iter()
+                .map(|(expression, identifier)| {
+                    let cell = Cell {
+                        data_type: expression.data_type(self)?,
+                        inner: expression.evaluate(self, row_index)?,
+                    };
+
+                    Result::Ok((identifier.clone(), cell))
+                })
+                .collect::<Result<_, VirtualTableError>>()?,
         };
 
-        let mut row = Row::create(&self, *key);
-        fetch_columns.iter().for_each(|column| {
-            let value = column.value_at(row_index).expect("TODO: Implement error handling here.");
+        let mut row = match column_specification {
+            ColumnSpecification::All | ColumnSpecification::Some(_) => Row::create(self, *key),
+            // Projected rows only contain the requested cells, since their identifiers aren't columns of this table
+            _ => Row {
+                primary_key: *key,
+                cells: HashMap::new(),
+            },
+        };
+        cells
+            .into_iter()
+            .for_each(|(identifier, cell)| row.set_cell(identifier, cell));
 
-            row.set_cell(column.identifier.clone(), Cell {
-                data_type: column.data_type,
-                inner: value.clone(),
-            })
+        Result::Ok(Some(row))
+    }
+
+    // Removes the row and moves all rows behind it up by one, so the indexes stay dense
+    pub(crate) fn remove_row(&mut self, key: &PrimaryKey) -> Option<Index> {
+        // Undo and redo take the history out of the table, so their removals aren't recorded
+        let operation = self
+            .prepare_history(|| self.project_row(key, ColumnSpecification::All))
+            .and_then(|row| row.ok().flatten())
+            .map(Operation::Delete);
+        let index = self.keys.remove(key)?;
+        self.unindex_row(index);
+        if let Some(ordered_keys) = self.ordered_keys.as_mut() {
+            ordered_keys.remove(key);
+        }
+        self.columns.iter_mut().for_each(|(_, col)| {
+            let _ = col.destroy_cell(index);
         });
+        self.keys
+            .values_mut()
+            .filter(|row_index| **row_index > index)
+            .for_each(|row_index| *row_index -= 1);
+        self.release_row_id(index);
+        self.forget_cell_clocks(key);
+        self.advance_version();
+        self.record_modification(Modification::Delete);
+
+        let event = self.prepare_event(|| TableEvent::RowDeleted(*key));
+        self.record_event(event);
+        if let Some(operation) = operation {
+            self.record_operation(operation);
+        }
+
+        Some(index)
+    }
 
-        Some(row)
+    // Keeps the secondary indexes in sync with the values of the row at the index
+    pub(crate) fn index_row(&mut self, index: Index) {
+        self.update_bitmap_indexes(index, BitmapIndex::insert);
+        self.update_unique_indexes(index, true);
+        self.update_spatial_indexes(index, GridIndex::insert);
+    }
+
+    pub(crate) fn unindex_row(&mut self, index: Index) {
+        self.update_bitmap_indexes(index, BitmapIndex::remove);
+        self.update_unique_indexes(index, false);
+        self.update_spatial_indexes(index, GridIndex::remove);
     }
 
     fn rollback_at_index(&mut self, key: &PrimaryKey, index: Index) {
@@ -228,10 +701,35 @@ impl Table {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct ColumnDefinition {
     pub identifier: String,
     pub data_type: DataType,
     pub is_nullable: bool,
+    pub constraints: Vec<Constraint>,
+    pub metadata: ColumnMetadata,
+}
+
+impl ColumnDefinition {
+    pub fn create(identifier: &str, data_type: DataType, is_nullable: bool) -> Self {
+        ColumnDefinition {
+            identifier: String::from(identifier),
+            data_type,
+            is_nullable,
+            constraints: Vec::new(),
+            metadata: ColumnMetadata::default(),
+        }
+    }
+
+    pub fn with_constraint(mut self, constraint: Constraint) -> Self {
+        self.constraints.push(constraint);
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: ColumnMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -264,14 +762,110 @@ impl Row {
     pub fn set_cell(&mut self, column_identifier: String, cell: Cell) {
         self.cells.insert(column_identifier, Some(cell));
     }
+
+    /// Sets the cell only if the table has such a column and the cell could be stored in it, so
+    /// mistakes show up right away instead of in the errors of `Table::create_row`. Coercion
+    /// isn't applied here, the cell needs the exact data type of the column.
+    pub fn try_set_cell(
+        &mut self,
+        table: &Table,
+        column_identifier: String,
+        cell: Cell,
+    ) -> Result<(), VirtualTableError> {
+        table
+            .columns
+            .get(&column_identifier)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(column_identifier.clone()))?
+            .validate(&cell)?;
+        self.set_cell(column_identifier, cell);
+
+        Result::Ok(())
+    }
+
+    /// Returns the value of the cell, or None if the row has no value for this column.
+    pub fn get_cell(&self, column_identifier: &str) -> Option<&TableValue> {
+        self.cells
+            .get(column_identifier)
+            .and_then(|cell| cell.as_ref())
+            .map(|cell| &cell.inner)
+    }
+
+    pub fn primary_key(&self) -> PrimaryKey {
+        self.primary_key
+    }
+
+    // Identifiers of the cells that are set, the ones an update writes
+    pub(crate) fn written_identifiers(&self) -> Vec<String> {
+        self.cells
+            .iter()
+            .filter(|(identifier, cell)| *identifier != "ID" && cell.is_some())
+            .map(|(identifier, _)| identifier.clone())
+            .collect()
+    }
+
+    /// Iterates the cells that have a value, in no particular order.
+    pub fn cells(&self) -> impl Iterator<Item = (&str, &TableValue)> + '_ {
+        self.cells.iter().filter_map(|(identifier, cell)| {
+            cell.as_ref().map(|cell| (identifier.as_str(), &cell.inner))
+        })
+    }
 }
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TableValue {
     Null,
     Integer(i64),
+    BigInt(i128),
+    UnsignedInteger(u64),
+    Duration(Duration),
+    Timestamp(Timestamp),
+    Money(Money),
+    Point(Point),
     String(String),
     Uuid(Uuid),
+    // Custom types can't be looked up by name, so their values can't be deserialized
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(CustomValue),
+}
+
+impl TableValue {
+    // NULL values don't carry a data type, they fit into every nullable column
+    pub fn data_type(&self) -> Option<DataType> {
+        match self {
+            TableValue::Null => None,
+            TableValue::Integer(_) => Some(DataType::Integer),
+            TableValue::BigInt(_) => Some(DataType::BigInt),
+            TableValue::UnsignedInteger(_) => Some(DataType::UnsignedInteger),
+            TableValue::Duration(_) => Some(DataType::Duration),
+            TableValue::Timestamp(_) => Some(DataType::Timestamp),
+            TableValue::Money(_) => Some(DataType::Money),
+            TableValue::Point(_) => Some(DataType::Point),
+            TableValue::String(_) => Some(DataType::String),
+            TableValue::Uuid(_) => Some(DataType::Uuid),
+            TableValue::Custom(value) => Some(DataType::Custom(value.type_id())),
+        }
+    }
+
+    // Widens values of all integer types, so they can be compared and computed with
+    pub(crate) fn as_i128(&self) -> Option<i128> {
+        match self {
+            TableValue::Integer(value) => Some(i128::from(*value)),
+            TableValue::BigInt(value) => Some(*value),
+            TableValue::UnsignedInteger(value) => Some(i128::from(*value)),
+            _ => None,
+        }
+    }
+
+    // Narrows the value to the integer type, None if it doesn't fit or the type isn't an integer type
+    pub(crate) fn from_i128(value: i128, data_type: DataType) -> Option<TableValue> {
+        match data_type {
+            DataType::Integer => i64::try_from(value).ok().map(TableValue::Integer),
+            DataType::BigInt => Some(TableValue::BigInt(value)),
+            DataType::UnsignedInteger => u64::try_from(value).ok().map(TableValue::UnsignedInteger),
+            _ => None,
+        }
+    }
 }
 
 impl From<&TableValue> for String {
@@ -279,8 +873,15 @@ impl From<&TableValue> for String {
         match value {
             TableValue::Null => String::from("*NULL*"),
             TableValue::Integer(i) => format!("{}", i),
+            TableValue::BigInt(i) => format!("{}", i),
+            TableValue::UnsignedInteger(i) => format!("{}", i),
+            TableValue::Duration(duration) => duration::format_duration(*duration),
+            TableValue::Timestamp(timestamp) => timestamp.to_string(),
+            TableValue::Money(money) => money.to_string(),
+            TableValue::Point(point) => point.to_string(),
             TableValue::String(str) => str.clone(),
             TableValue::Uuid(uuid) => format!("{}", uuid),
+            TableValue::Custom(value) => String::from(value.as_str()),
         }
     }
 }
@@ -288,8 +889,24 @@ impl From<&TableValue> for String {
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum DataType {
     Integer,
+    BigInt,
+    UnsignedInteger,
+    Duration,
+    Timestamp,
+    Money,
+    Point,
     String,
     Uuid,
+    Custom(CustomTypeId),
+}
+
+impl DataType {
+    pub fn is_integer(self) -> bool {
+        matches!(
+            self,
+            DataType::Integer | DataType::BigInt | DataType::UnsignedInteger
+        )
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -298,6 +915,32 @@ pub struct Cell {
     inner: TableValue,
 }
 
+impl Cell {
+    /// Creates a NULL cell, e.g. to clear a value in `Table::update_row`.
+    pub fn null(data_type: DataType) -> Self {
+        Cell {
+            data_type,
+            inner: TableValue::Null,
+        }
+    }
+
+    /// Creates a cell of the integer type, or fails if the value doesn't fit into it.
+    pub fn try_integer<T: Into<i128>>(
+        value: T,
+        data_type: DataType,
+    ) -> Result<Cell, VirtualTableError> {
+        let value = value.into();
+        TableValue::from_i128(value, data_type)
+            .map(|inner| Cell { data_type, inner })
+            .ok_or_else(|| {
+                VirtualTableError::ArithmeticError(format!(
+                    "{} doesn't fit into {}.",
+                    value, data_type
+                ))
+            })
+    }
+}
+
 impl From<i64> for TableValue {
     fn from(i: i64) -> TableValue {
         TableValue::Integer(i)
@@ -341,6 +984,73 @@ impl IntoCell for i64 {
     }
 }
 
+// Smaller integers always fit into an INTEGER
+impl IntoCell for i32 {
+    fn into_cell(self) -> Cell {
+        i64::from(self).into_cell()
+    }
+}
+
+impl IntoCell for u32 {
+    fn into_cell(self) -> Cell {
+        i64::from(self).into_cell()
+    }
+}
+
+impl IntoCell for i128 {
+    fn into_cell(self) -> Cell {
+        Cell {
+            data_type: DataType::BigInt,
+            inner: TableValue::BigInt(self),
+        }
+    }
+}
+
+impl IntoCell for u64 {
+    fn into_cell(self) -> Cell {
+        Cell {
+            data_type: DataType::UnsignedInteger,
+            inner: TableValue::UnsignedInteger(self),
+        }
+    }
+}
+
+impl IntoCell for Duration {
+    fn into_cell(self) -> Cell {
+        Cell {
+            data_type: DataType::Duration,
+            inner: TableValue::Duration(self),
+        }
+    }
+}
+
+impl IntoCell for Timestamp {
+    fn into_cell(self) -> Cell {
+        Cell {
+            data_type: DataType::Timestamp,
+            inner: TableValue::Timestamp(self),
+        }
+    }
+}
+
+impl IntoCell for Money {
+    fn into_cell(self) -> Cell {
+        Cell {
+            data_type: DataType::Money,
+            inner: TableValue::Money(self),
+        }
+    }
+}
+
+impl IntoCell for Point {
+    fn into_cell(self) -> Cell {
+        Cell {
+            data_type: DataType::Point,
+            inner: TableValue::Point(self),
+        }
+    }
+}
+
 impl IntoCell for String {
     fn into_cell(self) -> Cell {
         Cell {
@@ -0,0 +1,146 @@
+use crate::error::VirtualTableError;
+use crate::fuzzy::trigram_similarity;
+use crate::{ColumnDefinition, DataType, IntoCell, Row, Table, TableValue};
+use uuid::Uuid;
+
+pub const LEFT_KEY: &str = "left_key";
+pub const RIGHT_KEY: &str = "right_key";
+pub const SCORE: &str = "score";
+
+/// Compares a column of the left with a column of the right table in `link_tables`. A matching
+/// rule adds its weight to the score of a pair of rows, NULL values never match.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct LinkRule {
+    left_column: String,
+    right_column: String,
+    // Trigram similarity in percent the String values need at least, None for equal values
+    threshold: Option<u8>,
+    weight: u32,
+}
+
+impl LinkRule {
+    /// Matches if both values are equal.
+    pub fn exact(left_column: &str, right_column: &str) -> Self {
+        LinkRule {
+            left_column: String::from(left_column),
+            right_column: String::from(right_column),
+            threshold: None,
+            weight: 1,
+        }
+    }
+
+    /// Matches if both String values are similar, see `Predicate::similar`.
+    pub fn fuzzy(left_column: &str, right_column: &str, threshold: u8) -> Self {
+        LinkRule {
+            threshold: Some(threshold),
+            ..LinkRule::exact(left_column, right_column)
+        }
+    }
+
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    fn matches(&self, left: &TableValue, right: &TableValue) -> bool {
+        match (left, right, self.threshold) {
+            (TableValue::Null, _, _) | (_, TableValue::Null, _) => false,
+            (TableValue::String(left), TableValue::String(right), Some(threshold)) => {
+                trigram_similarity(left, right) >= threshold
+            }
+            (_, _, Some(_)) => false,
+            (left, right, None) => left == right,
+        }
+    }
+}
+
+/// Finds candidate matches between the rows of two tables, e.g. to find the customers of an
+/// import that already exist. Every pair of rows is scored with the rules, pairs with a score of
+/// at least `min_score` are returned as rows with the keys of both rows and the score. They are
+/// ordered by the left row, candidates for the same row by descending score.
+pub fn link_tables(
+    left: &Table,
+    right: &Table,
+    rules: &[LinkRule],
+    min_score: u32,
+) -> Result<Table, VirtualTableError> {
+    let mut columns = Vec::new();
+    for rule in rules {
+        let left_column = left
+            .columns
+            .get(&rule.left_column)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(rule.left_column.clone()))?;
+        let right_column = right
+            .columns
+            .get(&rule.right_column)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(rule.right_column.clone()))?;
+        let expected_type = match rule.threshold {
+            Some(_) => DataType::String,
+            None => left_column.data_type,
+        };
+        if let Some((identifier, data_type)) = [
+            (&rule.left_column, left_column.data_type),
+            (&rule.right_column, right_column.data_type),
+        ]
+        .iter()
+        .find(|(_, data_type)| *data_type != expected_type)
+        {
+            return Result::Err(VirtualTableError::InvalidDataType(
+                (*identifier).clone(),
+                expected_type,
+                *data_type,
+            ));
+        }
+        columns.push((rule, left_column, right_column));
+    }
+
+    let mut links = Table::create(
+        String::from("links"),
+        vec![
+            ColumnDefinition::create(LEFT_KEY, DataType::Uuid, false),
+            ColumnDefinition::create(RIGHT_KEY, DataType::Uuid, false),
+            ColumnDefinition::create(SCORE, DataType::UnsignedInteger, false),
+        ],
+    );
+    for left_index in 0..left.row_ids.len() {
+        let left_key = match left.key_at(left_index) {
+            Some(key) => key,
+            None => continue,
+        };
+
+        let mut candidates = (0..right.row_ids.len())
+            .filter_map(|right_index| {
+                let score = columns
+                    .iter()
+                    .filter(|(rule, left_column, right_column)| {
+                        match (
+                            left_column.value_at(left_index),
+                            right_column.value_at(right_index),
+                        ) {
+                            (Some(left), Some(right)) => rule.matches(left, right),
+                            _ => false,
+                        }
+                    })
+                    .map(|(rule, _, _)| rule.weight)
+                    .sum::<u32>();
+                let right_key = right.key_at(right_index)?;
+
+                (score > 0 && score >= min_score).then_some((right_key, score))
+            })
+            .collect::<Vec<_>>();
+        // The sort is stable, so candidates with the same score keep the order of the right table
+        candidates.sort_by(|(_, left_score), (_, right_score)| right_score.cmp(left_score));
+
+        for (right_key, score) in candidates {
+            let mut row = Row::create(&links, Uuid::new_v4());
+            row.set_cell(String::from(LEFT_KEY), left_key.into_cell());
+            row.set_cell(String::from(RIGHT_KEY), right_key.into_cell());
+            row.set_cell(String::from(SCORE), u64::from(score).into_cell());
+            links
+                .create_row(row)
+                .expect("Link rows always match the schema.");
+        }
+    }
+
+    Result::Ok(links)
+}
@@ -0,0 +1,74 @@
+use crate::checksum::{fnv1a, FNV_OFFSET_BASIS};
+use crate::error::VirtualTableError;
+use crate::{Table, TableValue};
+
+const REDACTED: &str = "***";
+
+/// Hides values of a column when the table is printed. Programmatic reads like
+/// `Table::find_row` always return the actual values. NULL values are never masked.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Mask {
+    // Replaces the value with ***
+    Redact,
+    // Only shows the given number of trailing characters, e.g. ****1234
+    LastCharacters(usize),
+    // Replaces the value with a hash, so equal values can still be recognized, also in output of
+    // other builds. The hash isn't cryptographically secure, so it mustn't be used for
+    // low-entropy secrets.
+    Hash,
+}
+
+impl Mask {
+    pub fn apply(&self, value: &TableValue) -> String {
+        let formatted = String::from(value);
+        if *value == TableValue::Null {
+            return formatted;
+        }
+
+        match self {
+            Mask::Redact => String::from(REDACTED),
+            Mask::LastCharacters(count) => {
+                let length = formatted.chars().count();
+                let hidden = length.saturating_sub(*count);
+                "*".repeat(hidden) + &formatted.chars().skip(hidden).collect::<String>()
+            }
+            Mask::Hash => format!("{:016x}", fnv1a(FNV_OFFSET_BASIS, formatted.as_bytes())),
+        }
+    }
+}
+
+impl Table {
+    /// Masks the values of the column whenever the table is printed.
+    pub fn set_mask(
+        &mut self,
+        column_identifier: &str,
+        mask: Mask,
+    ) -> Result<(), VirtualTableError> {
+        if !self.columns.contains_key(column_identifier) {
+            return Result::Err(VirtualTableError::UnknownColumn(String::from(
+                column_identifier,
+            )));
+        }
+
+        self.masks.insert(String::from(column_identifier), mask);
+        Result::Ok(())
+    }
+
+    pub fn clear_mask(&mut self, column_identifier: &str) -> bool {
+        self.masks.remove(column_identifier).is_some()
+    }
+
+    /// Formats the value for output, masked if the column has a mask.
+    pub(crate) fn display_value(&self, column_identifier: &str, value: &TableValue) -> String {
+        match self.masks.get(column_identifier) {
+            Some(mask) => mask.apply(value),
+            None => match value {
+                TableValue::Uuid(key) if column_identifier == "ID" => self.id_format.apply(key),
+                TableValue::Timestamp(timestamp) => {
+                    timestamp.format(self.time_zone_of(column_identifier))
+                }
+                value => String::from(value),
+            },
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::checksum::{fnv1a, FNV_OFFSET_BASIS};
+use crate::{PrimaryKey, Table};
+use std::ops::RangeInclusive;
+use uuid::Uuid;
+
+// 2^20 leaves are enough for millions of rows and still fit into a few megabytes
+const MAX_DEPTH: u8 = 20;
+
+/// Hash tree over the rows of a table, split into 2^depth ranges of primary keys of equal size.
+/// Every leaf hashes the keys and content hashes of the rows in its range, every other node its
+/// two children. Two copies of a table only need to compare the nodes below differing ones to
+/// find the ranges that differ, instead of comparing every row.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct MerkleTree {
+    // The root first, the leaves last
+    levels: Vec<Vec<u64>>,
+}
+
+impl MerkleTree {
+    pub fn depth(&self) -> u8 {
+        (self.levels.len() - 1) as u8
+    }
+
+    pub fn root(&self) -> u64 {
+        self.levels[0][0]
+    }
+
+    /// The hash of a node, level 0 holds the root and each level twice as many nodes as the
+    /// one above it. None if there is no such node.
+    pub fn hash_at(&self, level: u8, position: usize) -> Option<u64> {
+        self.levels.get(usize::from(level))?.get(position).copied()
+    }
+
+    /// The ranges of keys in which the rows of the two trees differ, in key order. Adjacent
+    /// ranges aren't merged. Trees of different depths can't be compared, so all keys differ.
+    pub fn differing_ranges(&self, other: &MerkleTree) -> Vec<RangeInclusive<PrimaryKey>> {
+        if self.depth() != other.depth() {
+            return vec![key_range(0, 0)];
+        }
+
+        let mut ranges = Vec::new();
+        let mut pending = vec![(0, 0)];
+        while let Some((level, position)) = pending.pop() {
+            if self.levels[level][position] == other.levels[level][position] {
+                continue;
+            }
+
+            if level + 1 == self.levels.len() {
+                ranges.push(key_range(self.depth(), position));
+            } else {
+                // The right child first, so the left one is compared first
+                pending.push((level + 1, position * 2 + 1));
+                pending.push((level + 1, position * 2));
+            }
+        }
+
+        ranges
+    }
+}
+
+impl Table {
+    /// Builds a hash tree with 2^depth ranges of keys, see `MerkleTree`. Depths above 20 are
+    /// reduced to 20.
+    pub fn merkle_tree(&self, depth: u8) -> MerkleTree {
+        let depth = depth.min(MAX_DEPTH);
+        let mut buckets = vec![Vec::new(); 1 << depth];
+        for (key, hash) in self.row_hashes() {
+            buckets[bucket_of(&key, depth)].push((key, hash));
+        }
+
+        let leaves = buckets
+            .into_iter()
+            .map(|mut rows| {
+                // The hash must not depend on the insertion order of the rows
+                rows.sort_unstable_by_key(|(key, _)| *key);
+                rows.iter().fold(FNV_OFFSET_BASIS, |leaf, (key, hash)| {
+                    fnv1a(fnv1a(leaf, key.as_bytes()), &hash.to_le_bytes())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut levels = vec![leaves];
+        while levels[0].len() > 1 {
+            let parents = levels[0]
+                .chunks(2)
+                .map(|children| {
+                    children.iter().fold(FNV_OFFSET_BASIS, |parent, child| {
+                        fnv1a(parent, &child.to_le_bytes())
+                    })
+                })
+                .collect();
+            levels.insert(0, parents);
+        }
+
+        MerkleTree { levels }
+    }
+}
+
+// Leaves are picked by the leading bits of the key
+fn bucket_of(key: &PrimaryKey, depth: u8) -> usize {
+    match depth {
+        0 => 0,
+        depth => (key.as_u128() >> (128 - u32::from(depth))) as usize,
+    }
+}
+
+fn key_range(depth: u8, position: usize) -> RangeInclusive<PrimaryKey> {
+    let (start, end) = match depth {
+        0 => (0, u128::MAX),
+        depth => {
+            let width = 128 - u32::from(depth);
+            let start = (position as u128) << width;
+            (start, start | ((1u128 << width) - 1))
+        }
+    };
+
+    Uuid::from_u128(start)..=Uuid::from_u128(end)
+}
@@ -0,0 +1,67 @@
+use crate::Table;
+
+/// Describes a column for people and tools, it has no effect on the values stored in it. The
+/// label replaces the identifier in headings of rendered and exported tables.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct ColumnMetadata {
+    pub description: Option<String>,
+    pub label: Option<String>,
+    // Unit of the values, e.g. "kg" or "ms"
+    pub unit: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl ColumnMetadata {
+    pub fn create() -> Self {
+        ColumnMetadata::default()
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = Some(String::from(description));
+        self
+    }
+
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = Some(String::from(label));
+        self
+    }
+
+    pub fn with_unit(mut self, unit: &str) -> Self {
+        self.unit = Some(String::from(unit));
+        self
+    }
+
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.tags.push(String::from(tag));
+        self
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|other| other == tag)
+    }
+}
+
+impl Table {
+    /// The metadata of the column, None if there is no such column.
+    pub fn column_metadata(&self, column_identifier: &str) -> Option<&ColumnMetadata> {
+        self.columns
+            .get(column_identifier)
+            .map(|column| &column.metadata)
+    }
+
+    /// Identifiers of all columns with the tag, in column order.
+    pub fn columns_tagged(&self, tag: &str) -> Vec<String> {
+        self.columns
+            .values()
+            .filter(|column| column.metadata.has_tag(tag))
+            .map(|column| column.identifier.clone())
+            .collect()
+    }
+
+    /// The heading of the column in rendered output, its label or else its identifier.
+    pub fn heading_of<'a>(&'a self, column_identifier: &'a str) -> &'a str {
+        self.column_metadata(column_identifier)
+            .and_then(|metadata| metadata.label.as_deref())
+            .unwrap_or(column_identifier)
+    }
+}
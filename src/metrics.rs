@@ -0,0 +1,46 @@
+use crate::Table;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Receives measurements about the operations of a table, so they can be forwarded to whatever
+/// metrics system the embedding application uses. All methods do nothing by default.
+pub trait Metrics: Send + Sync {
+    /// A table operation like `create_row` succeeded, rows are the number of rows written or returned.
+    fn operation_finished(&self, _operation: &'static str, _duration: Duration, _rows: usize) {}
+
+    fn operation_failed(&self, _operation: &'static str, _duration: Duration) {}
+
+    /// Rows that had to be visited to answer a query.
+    fn rows_scanned(&self, _operation: &'static str, _rows: usize) {}
+
+    /// An index was consulted. It's a hit if the index could answer the lookup on its own.
+    fn index_lookup(&self, _index: &'static str, _hit: bool) {}
+}
+
+impl Debug for dyn Metrics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str("Metrics")
+    }
+}
+
+impl Table {
+    /// Reports the operations of this table to the given metrics, replacing previous ones.
+    pub fn set_metrics(&mut self, metrics: Arc<dyn Metrics>) {
+        self.metrics = Some(metrics);
+    }
+
+    pub(crate) fn record_rows_scanned(&self, operation: &'static str, rows: usize) {
+        self.statistics.record_scan();
+        if let Some(metrics) = &self.metrics {
+            metrics.rows_scanned(operation, rows);
+        }
+    }
+
+    pub(crate) fn record_index_lookup(&self, index: &'static str, hit: bool) {
+        self.statistics.record_index_lookup();
+        if let Some(metrics) = &self.metrics {
+            metrics.index_lookup(index, hit);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+// Currencies with other than two decimal places, all others have two
+const DECIMAL_PLACES: &[(&str, u32)] = &[
+    ("BHD", 3),
+    ("CLP", 0),
+    ("IQD", 3),
+    ("ISK", 0),
+    ("JOD", 3),
+    ("JPY", 0),
+    ("KRW", 0),
+    ("KWD", 3),
+    ("LYD", 3),
+    ("OMR", 3),
+    ("TND", 3),
+    ("UGX", 0),
+    ("VND", 0),
+];
+
+/// An ISO 4217 currency code like "EUR".
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct Currency([u8; 3]);
+
+/// A fixed-point amount of money in the minor unit of its currency, e.g. cents for EUR.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Money {
+    amount: i64,
+    currency: Currency,
+}
+
+impl Currency {
+    /// Creates the currency from its code, None if it's not three uppercase letters.
+    pub fn create(code: &str) -> Option<Self> {
+        match code.as_bytes() {
+            [a, b, c] if code.bytes().all(|byte| byte.is_ascii_uppercase()) => {
+                Some(Currency([*a, *b, *c]))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        std::str::from_utf8(&self.0).expect("Currency codes are always ASCII.")
+    }
+
+    pub fn decimal_places(&self) -> u32 {
+        DECIMAL_PLACES
+            .iter()
+            .find(|(code, _)| *code == self.code())
+            .map_or(2, |(_, decimal_places)| *decimal_places)
+    }
+}
+
+impl Display for Currency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(self.code())
+    }
+}
+
+impl Money {
+    pub fn create(amount: i64, currency: Currency) -> Self {
+        Money { amount, currency }
+    }
+
+    /// The amount in the minor unit of the currency.
+    pub fn amount(&self) -> i64 {
+        self.amount
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// Parses amounts like "12.34 EUR", which may not have more decimal places than the currency.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (amount, code) = value.trim().rsplit_once(' ')?;
+        let currency = Currency::create(code)?;
+        let (sign, amount) = match amount.strip_prefix('-') {
+            Some(amount) => (-1, amount),
+            None => (1, amount),
+        };
+        let (units, fraction) = match amount.split_once('.') {
+            Some((units, fraction)) => (units, Some(fraction)),
+            None => (amount, None),
+        };
+
+        // Only plain digits, `i64::from_str` would accept another sign in either part
+        let is_digits =
+            |part: &str| !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_digit());
+        if !is_digits(units) || !fraction.into_iter().all(is_digits) {
+            return None;
+        }
+        let fraction = fraction.unwrap_or("");
+        let decimal_places = currency.decimal_places();
+        if fraction.len() > decimal_places as usize {
+            return None;
+        }
+        let scale = 10i64.pow(decimal_places);
+        let fraction = match fraction {
+            "" => 0,
+            fraction => {
+                i64::from_str(fraction).ok()? * 10i64.pow(decimal_places - fraction.len() as u32)
+            }
+        };
+        let amount = i64::from_str(units)
+            .ok()?
+            .checked_mul(scale)?
+            .checked_add(fraction)?;
+
+        Some(Money::create(sign * amount, currency))
+    }
+
+    /// Adds both amounts, None if the currencies differ or the sum overflows.
+    pub fn checked_add(&self, other: Money) -> Option<Self> {
+        if self.currency != other.currency {
+            return None;
+        }
+
+        self.amount
+            .checked_add(other.amount)
+            .map(|amount| Money::create(amount, self.currency))
+    }
+}
+
+impl Display for Money {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let decimal_places = self.currency.decimal_places();
+        let sign = if self.amount < 0 { "-" } else { "" };
+        let amount = self.amount.unsigned_abs();
+        if decimal_places == 0 {
+            return f.write_str(&format!("{}{} {}", sign, amount, self.currency));
+        }
+
+        let scale = 10u64.pow(decimal_places);
+        f.write_str(&format!(
+            "{}{}.{:0width$} {}",
+            sign,
+            amount / scale,
+            amount % scale,
+            self.currency,
+            width = decimal_places as usize
+        ))
+    }
+}
+
+// Currencies are written as their code, which is validated again when reading them
+#[cfg(feature = "serde")]
+impl serde::Serialize for Currency {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Currency {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Currency::create(&code)
+            .ok_or_else(|| serde::de::Error::custom(format!("'{}' isn't a currency code.", code)))
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{Row, Table, TableValue};
+use unicode_normalization::UnicodeNormalization;
+
+/// Unicode normalization forms for String values. Visually identical strings can consist of
+/// different code points, e.g. "é" as a single character or as "e" with a combining accent.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum NormalizationForm {
+    // Canonical composition, keeps the meaning of all characters
+    Nfc,
+    // Compatibility composition, also folds variants like "ﬁ" into "fi"
+    Nfkc,
+}
+
+impl NormalizationForm {
+    pub fn normalize(&self, value: &str) -> String {
+        match self {
+            NormalizationForm::Nfc => value.nfc().collect(),
+            NormalizationForm::Nfkc => value.nfkc().collect(),
+        }
+    }
+}
+
+impl Table {
+    /// Normalizes all String values on insert and update, so equal looking strings are also equal
+    /// for predicates and lookups.
+    pub fn enable_normalization(&mut self, form: NormalizationForm) {
+        self.normalization = Some(form);
+    }
+
+    pub fn disable_normalization(&mut self) {
+        self.normalization = None;
+    }
+
+    pub fn normalization(&self) -> Option<NormalizationForm> {
+        self.normalization
+    }
+
+    pub(crate) fn normalize_row(&self, mut row: Row) -> Row {
+        let form = match self.normalization {
+            Some(form) => form,
+            None => return row,
+        };
+
+        row.cells
+            .values_mut()
+            .filter_map(|cell_option| cell_option.as_mut())
+            .for_each(|cell| {
+                if let TableValue::String(value) = &cell.inner {
+                    cell.inner = TableValue::String(form.normalize(value));
+                }
+            });
+
+        row
+    }
+}
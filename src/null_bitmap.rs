@@ -0,0 +1,221 @@
+use crate::error::VirtualTableError;
+use crate::instrumentation::OperationSpan;
+use crate::query::ColumnSpecification;
+use crate::{Index, Row, Table, TableValue};
+
+const WORD_BITS: usize = 64;
+
+static NULL: TableValue = TableValue::Null;
+
+/// Storage for the values of a column. NULL values are only tracked in a bitmap, all other
+/// values are stored densely in index order, split up by the words of the bitmap. Finding the
+/// position of a value only needs the bits of its own word, and setting a value only moves the
+/// values of that word.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub(crate) struct ColumnValues {
+    // One bit per row, it's set if the value of the row is NULL
+    nulls: Vec<u64>,
+    len: usize,
+    // The values other than NULL of the rows of each word of the bitmap
+    values: Vec<Vec<TableValue>>,
+}
+
+impl ColumnValues {
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn get(&self, index: Index) -> Option<&TableValue> {
+        if index >= self.len {
+            return None;
+        }
+
+        if self.is_null(index) {
+            Some(&NULL)
+        } else {
+            self.values[index / WORD_BITS].get(self.dense_position(index))
+        }
+    }
+
+    /// Replaces the value at the index, or appends it if the index is right behind the last value.
+    pub(crate) fn set(&mut self, index: Index, value: TableValue) {
+        assert!(index <= self.len, "Values can only be appended to the end.");
+        if index == self.len {
+            self.len += 1;
+            self.nulls.resize(self.len.div_ceil(WORD_BITS), 0);
+            self.values.resize_with(self.nulls.len(), Vec::new);
+            self.set_null_bit(index, true);
+        }
+
+        let word = index / WORD_BITS;
+        let position = self.dense_position(index);
+        match (self.is_null(index), value) {
+            (true, TableValue::Null) => {}
+            (true, value) => {
+                self.values[word].insert(position, value);
+                self.set_null_bit(index, false);
+            }
+            (false, TableValue::Null) => {
+                self.values[word].remove(position);
+                self.set_null_bit(index, true);
+            }
+            (false, value) => self.values[word][position] = value,
+        }
+    }
+
+    /// Removes the value at the index, all values behind it move up by one.
+    pub(crate) fn remove(&mut self, index: Index) -> Option<TableValue> {
+        if index >= self.len {
+            return None;
+        }
+
+        let value = if self.is_null(index) {
+            TableValue::Null
+        } else {
+            let position = self.dense_position(index);
+            self.values[index / WORD_BITS].remove(position)
+        };
+
+        // Shift all bits behind the index down by one, carrying over the lowest bit of the next word
+        let first_word = index / WORD_BITS;
+        let lower_mask = (1u64 << (index % WORD_BITS)) - 1;
+        let bits = self.nulls[first_word];
+        self.nulls[first_word] = (bits & lower_mask) | ((bits >> 1) & !lower_mask);
+        for word in first_word..self.nulls.len() {
+            if word > first_word {
+                self.nulls[word] >>= 1;
+            }
+            let carry = self.nulls.get(word + 1).map_or(0, |next| next & 1);
+            self.nulls[word] |= carry << (WORD_BITS - 1);
+            // Along with its bit, the first row of the next word moves over, and its value too
+            if word + 1 < self.nulls.len() && carry == 0 {
+                let value = self.values[word + 1].remove(0);
+                self.values[word].push(value);
+            }
+        }
+
+        self.len -= 1;
+        self.nulls.truncate(self.len.div_ceil(WORD_BITS));
+        self.values.truncate(self.nulls.len());
+
+        Some(value)
+    }
+
+    /// Iterates the values in index order, walking the bitmap and the values side by side.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &TableValue> + '_ {
+        self.nulls
+            .iter()
+            .zip(&self.values)
+            .enumerate()
+            .flat_map(move |(word, (bits, values))| {
+                let mut values = values.iter();
+                (0..(self.len - word * WORD_BITS).min(WORD_BITS)).map(move |bit| {
+                    if bits & (1 << bit) != 0 {
+                        &NULL
+                    } else {
+                        values
+                            .next()
+                            .expect("Every row without a NULL bit has a value.")
+                    }
+                })
+            })
+    }
+
+    pub(crate) fn is_null(&self, index: Index) -> bool {
+        self.nulls[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
+
+    pub(crate) fn null_count(&self) -> usize {
+        self.nulls
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Iterates the indexes with a NULL value, or the ones without if `is_null` is false.
+    pub(crate) fn null_indexes(&self, is_null: bool) -> impl Iterator<Item = Index> + '_ {
+        (0..self.len).filter(move |index| self.is_null(*index) == is_null)
+    }
+
+    // Position of the value in the values of its word, which is the number of non-NULL values
+    // before it in the word
+    fn dense_position(&self, index: Index) -> usize {
+        let lower_mask = (1u64 << (index % WORD_BITS)) - 1;
+
+        (!self.nulls[index / WORD_BITS] & lower_mask).count_ones() as usize
+    }
+
+    fn set_null_bit(&mut self, index: Index, is_null: bool) {
+        let mask = 1 << (index % WORD_BITS);
+        if is_null {
+            self.nulls[index / WORD_BITS] |= mask;
+        } else {
+            self.nulls[index / WORD_BITS] &= !mask;
+        }
+    }
+}
+
+impl Table {
+    pub fn null_count(&self, column_identifier: &str) -> Result<usize, VirtualTableError> {
+        self.columns
+            .get(column_identifier)
+            .map(|column| column.values.null_count())
+            .ok_or_else(|| VirtualTableError::UnknownColumn(String::from(column_identifier)))
+    }
+
+    /// Finds all rows with a NULL value in the column, which only needs to scan its null bitmap.
+    pub fn find_null_rows(
+        &self,
+        column_identifier: &str,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        self.find_rows_by_nullness(column_identifier, true, column_specification)
+    }
+
+    /// Finds all rows with a value other than NULL in the column.
+    pub fn find_non_null_rows(
+        &self,
+        column_identifier: &str,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        self.find_rows_by_nullness(column_identifier, false, column_specification)
+    }
+
+    fn find_rows_by_nullness(
+        &self,
+        column_identifier: &str,
+        is_null: bool,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let span = OperationSpan::start("find_rows_by_nullness", self);
+        self.statistics.record_query();
+        let result =
+            self.collect_rows_by_nullness(column_identifier, is_null, column_specification);
+        span.finish(&result, result.as_ref().map_or(0, |rows| rows.len()));
+
+        result
+    }
+
+    fn collect_rows_by_nullness(
+        &self,
+        column_identifier: &str,
+        is_null: bool,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let column = self
+            .columns
+            .get(column_identifier)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(String::from(column_identifier)))?;
+
+        self.record_rows_scanned("find_rows_by_nullness", column.values.len());
+        column
+            .values
+            .null_indexes(is_null)
+            .filter_map(|index| self.key_at(index))
+            .map(|key| {
+                self.try_find_row(&key, column_specification.clone())
+                    .map(|row| row.expect("Rows at valid indexes always exist."))
+            })
+            .collect()
+    }
+}
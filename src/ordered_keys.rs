@@ -0,0 +1,70 @@
+use crate::query::ColumnSpecification;
+use crate::{PrimaryKey, Row, Table};
+use std::collections::BTreeSet;
+use std::ops::RangeBounds;
+
+impl Table {
+    /// Maintains an ordered index over the primary keys, which makes range scans and
+    /// "first/last N" queries cheap. Useful for time-sortable keys like UUIDv7.
+    pub fn enable_ordered_keys(&mut self) {
+        if self.ordered_keys.is_none() {
+            self.ordered_keys = Some(self.keys.keys().copied().collect::<BTreeSet<_>>());
+        }
+    }
+
+    pub fn has_ordered_keys(&self) -> bool {
+        self.ordered_keys.is_some()
+    }
+
+    /// Iterates all rows whose primary key is inside the given range, in key order.
+    /// Without the ordered index, the matching keys have to be sorted on every call.
+    pub fn range<R>(&self, range: R) -> impl Iterator<Item = Row> + '_
+    where
+        R: RangeBounds<PrimaryKey>,
+    {
+        self.keys_in_range(range)
+            .filter_map(move |key| self.find_row(key, ColumnSpecification::All))
+    }
+
+    /// Returns the rows with the lowest primary keys, in key order.
+    pub fn first_rows(&self, count: usize) -> Vec<Row> {
+        self.keys_in_range(..)
+            .take(count)
+            .filter_map(|key| self.find_row(key, ColumnSpecification::All))
+            .collect()
+    }
+
+    /// Returns the rows with the highest primary keys, in key order.
+    pub fn last_rows(&self, count: usize) -> Vec<Row> {
+        let mut rows = self
+            .keys_in_range(..)
+            .rev()
+            .take(count)
+            .filter_map(|key| self.find_row(key, ColumnSpecification::All))
+            .collect::<Vec<_>>();
+        rows.reverse();
+
+        rows
+    }
+
+    fn keys_in_range<R>(&self, range: R) -> Box<dyn DoubleEndedIterator<Item = &PrimaryKey> + '_>
+    where
+        R: RangeBounds<PrimaryKey>,
+    {
+        self.record_index_lookup("ordered_keys", self.ordered_keys.is_some());
+        match &self.ordered_keys {
+            Some(ordered_keys) => Box::new(ordered_keys.range(range)),
+            None => {
+                self.record_rows_scanned("range", self.keys.len());
+                let mut keys = self
+                    .keys
+                    .keys()
+                    .filter(|key| range.contains(key))
+                    .collect::<Vec<_>>();
+                keys.sort();
+
+                Box::new(keys.into_iter())
+            }
+        }
+    }
+}
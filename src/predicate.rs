@@ -0,0 +1,327 @@
+use crate::collation::Collation;
+use crate::error::VirtualTableError;
+use crate::expression::Expression;
+use crate::geo::Point;
+use crate::instrumentation::OperationSpan;
+use crate::query::ColumnSpecification;
+use crate::{DataType, Index, Row, Table, TableValue};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Result of a predicate in SQL's three-valued logic. Comparisons involving NULL are `Unknown`.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Truth {
+    True,
+    False,
+    Unknown,
+}
+
+impl Truth {
+    pub fn and(self, other: Truth) -> Truth {
+        match (self, other) {
+            (Truth::False, _) | (_, Truth::False) => Truth::False,
+            (Truth::True, Truth::True) => Truth::True,
+            _ => Truth::Unknown,
+        }
+    }
+
+    pub fn or(self, other: Truth) -> Truth {
+        match (self, other) {
+            (Truth::True, _) | (_, Truth::True) => Truth::True,
+            (Truth::False, Truth::False) => Truth::False,
+            _ => Truth::Unknown,
+        }
+    }
+
+    pub fn negate(self) -> Truth {
+        match self {
+            Truth::True => Truth::False,
+            Truth::False => Truth::True,
+            Truth::Unknown => Truth::Unknown,
+        }
+    }
+}
+
+impl From<bool> for Truth {
+    fn from(value: bool) -> Self {
+        if value {
+            Truth::True
+        } else {
+            Truth::False
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Predicate {
+    Equals(Expression, Expression),
+    NotEquals(Expression, Expression),
+    LessThan(Expression, Expression),
+    LessThanOrEquals(Expression, Expression),
+    GreaterThan(Expression, Expression),
+    GreaterThanOrEquals(Expression, Expression),
+    // The point is at most this many meters away from the center, using great-circle distance
+    WithinRadius(Expression, Point, u64),
+    // The only predicates that are never Unknown
+    IsNull(Expression),
+    IsNotNull(Expression),
+    And(Vec<Predicate>),
+    Or(Vec<Predicate>),
+    Not(Box<Predicate>),
+}
+
+impl Predicate {
+    pub fn negate(self) -> Self {
+        Predicate::Not(Box::new(self))
+    }
+
+    pub fn within_radius(expression: Expression, center: Point, meters: u64) -> Self {
+        Predicate::WithinRadius(expression, center, meters)
+    }
+
+    /// Replaces all parameters in the expressions with their values, see `Expression::bind`.
+    pub fn bind(
+        &self,
+        parameters: &HashMap<String, TableValue>,
+    ) -> Result<Predicate, VirtualTableError> {
+        let bind_all = |predicates: &[Predicate]| {
+            predicates
+                .iter()
+                .map(|predicate| predicate.bind(parameters))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Result::Ok(match self {
+            Predicate::Equals(left, right) => {
+                Predicate::Equals(left.bind(parameters)?, right.bind(parameters)?)
+            }
+            Predicate::NotEquals(left, right) => {
+                Predicate::NotEquals(left.bind(parameters)?, right.bind(parameters)?)
+            }
+            Predicate::LessThan(left, right) => {
+                Predicate::LessThan(left.bind(parameters)?, right.bind(parameters)?)
+            }
+            Predicate::LessThanOrEquals(left, right) => {
+                Predicate::LessThanOrEquals(left.bind(parameters)?, right.bind(parameters)?)
+            }
+            Predicate::GreaterThan(left, right) => {
+                Predicate::GreaterThan(left.bind(parameters)?, right.bind(parameters)?)
+            }
+            Predicate::GreaterThanOrEquals(left, right) => {
+                Predicate::GreaterThanOrEquals(left.bind(parameters)?, right.bind(parameters)?)
+            }
+            Predicate::WithinRadius(expression, center, meters) => {
+                Predicate::WithinRadius(expression.bind(parameters)?, *center, *meters)
+            }
+            Predicate::IsNull(expression) => Predicate::IsNull(expression.bind(parameters)?),
+            Predicate::IsNotNull(expression) => Predicate::IsNotNull(expression.bind(parameters)?),
+            Predicate::And(predicates) => Predicate::And(bind_all(predicates)?),
+            Predicate::Or(predicates) => Predicate::Or(bind_all(predicates)?),
+            Predicate::Not(predicate) => Predicate::Not(Box::new(predicate.bind(parameters)?)),
+        })
+    }
+
+    /// Checks that all expressions refer to columns of the table and have valid types, e.g. before
+    /// running a predicate that was deserialized from a config file.
+    pub fn validate(&self, table: &Table) -> Result<(), VirtualTableError> {
+        match self {
+            Predicate::Equals(left, right)
+            | Predicate::NotEquals(left, right)
+            | Predicate::LessThan(left, right)
+            | Predicate::LessThanOrEquals(left, right)
+            | Predicate::GreaterThan(left, right)
+            | Predicate::GreaterThanOrEquals(left, right) => {
+                left.infer_type(table)?;
+                right.infer_type(table)?;
+            }
+            Predicate::WithinRadius(expression, _, _)
+            | Predicate::IsNull(expression)
+            | Predicate::IsNotNull(expression) => {
+                expression.infer_type(table)?;
+            }
+            Predicate::And(predicates) | Predicate::Or(predicates) => {
+                for predicate in predicates {
+                    predicate.validate(table)?;
+                }
+            }
+            Predicate::Not(predicate) => predicate.validate(table)?,
+        }
+
+        Result::Ok(())
+    }
+
+    pub fn evaluate(&self, table: &Table, index: Index) -> Result<Truth, VirtualTableError> {
+        match self {
+            Predicate::Equals(left, right) => compare(table, index, left, right, |ordering| {
+                ordering == Ordering::Equal
+            }),
+            Predicate::NotEquals(left, right) => compare(table, index, left, right, |ordering| {
+                ordering != Ordering::Equal
+            }),
+            Predicate::LessThan(left, right) => compare(table, index, left, right, |ordering| {
+                ordering == Ordering::Less
+            }),
+            Predicate::LessThanOrEquals(left, right) => {
+                compare(table, index, left, right, |ordering| {
+                    ordering != Ordering::Greater
+                })
+            }
+            Predicate::GreaterThan(left, right) => compare(table, index, left, right, |ordering| {
+                ordering == Ordering::Greater
+            }),
+            Predicate::GreaterThanOrEquals(left, right) => {
+                compare(table, index, left, right, |ordering| {
+                    ordering != Ordering::Less
+                })
+            }
+            Predicate::WithinRadius(expression, center, meters) => {
+                match expression.evaluate(table, index)? {
+                    TableValue::Null => Result::Ok(Truth::Unknown),
+                    TableValue::Point(point) => {
+                        Result::Ok(Truth::from(point.distance_to(center) <= *meters as f64))
+                    }
+                    other => Result::Err(VirtualTableError::InvalidDataType(
+                        expression.to_string(),
+                        DataType::Point,
+                        other.data_type().unwrap_or(DataType::Point),
+                    )),
+                }
+            }
+            Predicate::IsNull(expression) => Result::Ok(Truth::from(
+                expression.evaluate(table, index)? == TableValue::Null,
+            )),
+            Predicate::IsNotNull(expression) => Result::Ok(Truth::from(
+                expression.evaluate(table, index)? != TableValue::Null,
+            )),
+            Predicate::And(predicates) => predicates
+                .iter()
+                .try_fold(Truth::True, |result, predicate| {
+                    Result::Ok(result.and(predicate.evaluate(table, index)?))
+                }),
+            Predicate::Or(predicates) => predicates
+                .iter()
+                .try_fold(Truth::False, |result, predicate| {
+                    Result::Ok(result.or(predicate.evaluate(table, index)?))
+                }),
+            Predicate::Not(predicate) => Result::Ok(predicate.evaluate(table, index)?.negate()),
+        }
+    }
+}
+
+/// Orders two values of the same data type, None means that at least one of them is NULL.
+/// The collation is only used for String values.
+pub fn compare_values(
+    left: &TableValue,
+    right: &TableValue,
+    collation: Collation,
+) -> Result<Option<Ordering>, VirtualTableError> {
+    let incomparable = || {
+        VirtualTableError::InvalidExpression(format!(
+            "Can't compare {} with {}.",
+            String::from(left),
+            String::from(right)
+        ))
+    };
+
+    let ordering = match (left, right) {
+        (TableValue::Null, _) | (_, TableValue::Null) => return Result::Ok(None),
+        // Integers of different types are compared by their value
+        (left, right) if left.as_i128().is_some() && right.as_i128().is_some() => {
+            left.as_i128().cmp(&right.as_i128())
+        }
+        (TableValue::Duration(left), TableValue::Duration(right)) => left.cmp(right),
+        (TableValue::Timestamp(left), TableValue::Timestamp(right)) => left.cmp(right),
+        // Amounts in different currencies can't be compared without exchange rates
+        (TableValue::Money(left), TableValue::Money(right))
+            if left.currency() == right.currency() =>
+        {
+            left.amount().cmp(&right.amount())
+        }
+        (TableValue::Money(left), TableValue::Money(right)) => {
+            return Result::Err(VirtualTableError::CurrencyMismatch(
+                left.currency(),
+                right.currency(),
+            ))
+        }
+        (TableValue::String(left), TableValue::String(right)) => collation.compare(left, right),
+        (TableValue::Uuid(left), TableValue::Uuid(right)) => left.cmp(right),
+        (TableValue::Custom(left), TableValue::Custom(right)) => {
+            left.compare(right).ok_or_else(incomparable)?
+        }
+        _ => return Result::Err(incomparable()),
+    };
+
+    Result::Ok(Some(ordering))
+}
+
+fn compare<F>(
+    table: &Table,
+    index: Index,
+    left: &Expression,
+    right: &Expression,
+    matches: F,
+) -> Result<Truth, VirtualTableError>
+where
+    F: Fn(Ordering) -> bool,
+{
+    // A column on either side decides the collation, just like in SQL
+    let collation = match table.collation_for(left) {
+        Collation::Binary => table.collation_for(right),
+        collation => collation,
+    };
+    let ordering = compare_values(
+        &left.evaluate(table, index)?,
+        &right.evaluate(table, index)?,
+        collation,
+    )?;
+
+    Result::Ok(ordering.map_or(Truth::Unknown, |ordering| Truth::from(matches(ordering))))
+}
+
+impl Table {
+    /// Finds all rows for which the predicate is true, in insertion order. Just like a WHERE
+    /// clause in SQL, rows for which the predicate is unknown are left out.
+    pub fn find_rows(
+        &self,
+        predicate: &Predicate,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let span = OperationSpan::start("find_rows", self);
+        self.statistics.record_query();
+        let result = self.collect_matching_rows(predicate, column_specification);
+        span.finish(&result, result.as_ref().map_or(0, |rows| rows.len()));
+
+        result
+    }
+
+    fn collect_matching_rows(
+        &self,
+        predicate: &Predicate,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        self.record_rows_scanned("find_rows", self.row_ids.len());
+        let mut rows = Vec::new();
+        for index in self.matching_indexes(predicate)? {
+            if let Some(key) = self.key_at(index) {
+                rows.extend(self.try_find_row(&key, column_specification.clone())?);
+            }
+        }
+
+        Result::Ok(rows)
+    }
+
+    pub(crate) fn matching_indexes(
+        &self,
+        predicate: &Predicate,
+    ) -> Result<Vec<Index>, VirtualTableError> {
+        let mut indexes = Vec::new();
+        for index in 0..self.row_ids.len() {
+            if predicate.evaluate(self, index)? == Truth::True {
+                indexes.push(index);
+            }
+        }
+
+        Result::Ok(indexes)
+    }
+}
@@ -0,0 +1,200 @@
+use crate::error::VirtualTableError;
+use crate::{Cell, Column, ColumnDefinition, DataType, IntoCell, Row, Table, TableValue};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// Number of most frequent values listed per column in a profile
+const TOP_VALUES: usize = 3;
+
+/// A bin of a histogram with the number of values in it. Bins include their start but not their
+/// end, only the last bin also includes its end.
+#[derive(Debug, PartialEq, Clone)]
+pub struct HistogramBin {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize,
+}
+
+impl Table {
+    /// Counts how often each value occurs in the column, the most frequent values come first.
+    /// Values with the same count keep the order they first occur in, NULL values are skipped.
+    pub fn value_counts(
+        &self,
+        column_identifier: &str,
+    ) -> Result<Vec<(TableValue, usize)>, VirtualTableError> {
+        let column = self.column(column_identifier)?;
+
+        let mut positions: HashMap<&TableValue, usize> = HashMap::new();
+        let mut counts: Vec<(TableValue, usize)> = Vec::new();
+        for value in column.values.iter() {
+            if *value == TableValue::Null {
+                continue;
+            }
+
+            match positions.get(value) {
+                Some(position) => counts[*position].1 += 1,
+                None => {
+                    positions.insert(value, counts.len());
+                    counts.push((value.clone(), 1));
+                }
+            }
+        }
+        counts.sort_by(|(_, left), (_, right)| right.cmp(left));
+
+        Result::Ok(counts)
+    }
+
+    /// Splits the range of an integer column into bins of equal width and counts the values in
+    /// each of them. NULL values are skipped, a column without values has no bins and one with a
+    /// single distinct value only one.
+    pub fn histogram(
+        &self,
+        column_identifier: &str,
+        bins: usize,
+    ) -> Result<Vec<HistogramBin>, VirtualTableError> {
+        let column = self.column(column_identifier)?;
+        if !column.data_type.is_integer() {
+            return Result::Err(VirtualTableError::InvalidDataType(
+                String::from(column_identifier),
+                DataType::Integer,
+                column.data_type,
+            ));
+        }
+        if bins == 0 {
+            return Result::Err(VirtualTableError::InvalidArgument(String::from(
+                "A histogram needs at least one bin.",
+            )));
+        }
+
+        let values = column
+            .values
+            .iter()
+            .filter_map(|value| value.as_i128().map(|value| value as f64))
+            .collect::<Vec<_>>();
+        let (min, max) = match values.iter().copied().reduce(f64::min) {
+            Some(min) => (min, values.iter().copied().fold(min, f64::max)),
+            None => return Result::Ok(Vec::new()),
+        };
+
+        // All values fall into a single bin if there is only one distinct value
+        if min == max {
+            return Result::Ok(vec![HistogramBin {
+                start: min,
+                end: max,
+                count: values.len(),
+            }]);
+        }
+
+        let width = (max - min) / bins as f64;
+        let mut histogram = (0..bins)
+            .map(|bin| HistogramBin {
+                start: min + width * bin as f64,
+                end: if bin + 1 == bins {
+                    max
+                } else {
+                    min + width * (bin + 1) as f64
+                },
+                count: 0,
+            })
+            .collect::<Vec<_>>();
+        for value in values {
+            let bin = (((value - min) / width) as usize).min(bins - 1);
+            histogram[bin].count += 1;
+        }
+
+        Result::Ok(histogram)
+    }
+
+    /// Describes every column with its data type, share of NULL values, number of distinct values,
+    /// smallest and largest value, most frequent values and, for String columns, the average
+    /// length. The report is a table itself, so it can be printed or queried like any other.
+    pub fn profile(&self) -> Table {
+        let mut report = Table::create(
+            format!("{}_profile", self.identifier),
+            vec![
+                ColumnDefinition::create("column_name", DataType::String, false),
+                ColumnDefinition::create("data_type", DataType::String, false),
+                ColumnDefinition::create("null_percentage", DataType::String, false),
+                ColumnDefinition::create("distinct_count", DataType::Integer, false),
+                ColumnDefinition::create("min", DataType::String, true),
+                ColumnDefinition::create("max", DataType::String, true),
+                ColumnDefinition::create("top_values", DataType::String, false),
+                ColumnDefinition::create("average_length", DataType::String, true),
+            ],
+        );
+
+        self.columns.iter().for_each(|(identifier, column)| {
+            let value_counts = self.value_counts(identifier).expect("The column exists.");
+            let null_percentage = match column.values.len() {
+                0 => 0.0,
+                len => column.values.null_count() as f64 * 100.0 / len as f64,
+            };
+            let top_values = value_counts
+                .iter()
+                .take(TOP_VALUES)
+                .map(|(value, count)| {
+                    format!("{} ({})", self.display_value(identifier, value), count)
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            // Values that can't be ordered, like points, have no min and max
+            let display = |value: Result<TableValue, VirtualTableError>| match value {
+                Ok(TableValue::Null) | Err(_) => string_cell(None),
+                Ok(value) => string_cell(Some(self.display_value(identifier, &value))),
+            };
+            let average_length = match column.data_type {
+                DataType::String => {
+                    let lengths = column.values.iter().filter_map(|value| match value {
+                        TableValue::String(value) => Some(value.chars().count()),
+                        _ => None,
+                    });
+                    let (count, total) =
+                        lengths.fold((0, 0), |(count, total), length| (count + 1, total + length));
+                    (count > 0).then(|| format!("{:.1}", total as f64 / count as f64))
+                }
+                _ => None,
+            };
+
+            let mut row = Row::create(&report, Uuid::new_v4());
+            row.set_cell(String::from("column_name"), identifier.as_str().into_cell());
+            row.set_cell(
+                String::from("data_type"),
+                column.data_type.to_string().into_cell(),
+            );
+            row.set_cell(
+                String::from("null_percentage"),
+                format!("{:.1}%", null_percentage).into_cell(),
+            );
+            row.set_cell(
+                String::from("distinct_count"),
+                (value_counts.len() as i64).into_cell(),
+            );
+            row.set_cell(String::from("min"), display(self.column_min(identifier)));
+            row.set_cell(String::from("max"), display(self.column_max(identifier)));
+            row.set_cell(String::from("top_values"), top_values.into_cell());
+            row.set_cell(String::from("average_length"), string_cell(average_length));
+
+            report
+                .create_row(row)
+                .expect("Profile rows always match the schema.");
+        });
+
+        report
+    }
+
+    fn column(&self, column_identifier: &str) -> Result<&Column, VirtualTableError> {
+        self.columns
+            .get(column_identifier)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(String::from(column_identifier)))
+    }
+}
+
+fn string_cell(value: Option<String>) -> Cell {
+    match value {
+        Some(value) => value.into_cell(),
+        None => Cell {
+            data_type: DataType::String,
+            inner: TableValue::Null,
+        },
+    }
+}
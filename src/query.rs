@@ -1,4 +1,12 @@
+use crate::expression::Expression;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ColumnSpecification {
     All,
     Some(Vec<String>),
+    // Pairs of column identifier and the alias it should have in the resulting row
+    Aliased(Vec<(String, String)>),
+    // Pairs of an expression and the identifier its result should have in the resulting row
+    Computed(Vec<(Expression, String)>),
 }
@@ -0,0 +1,286 @@
+use crate::format::Alignment;
+use crate::highlight::Highlight;
+use crate::{Row, Table, TableValue};
+use prettytable::format::Alignment as PAlignment;
+use prettytable::{Attr, Cell as PCell, Row as PRow, Table as PTable};
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum ReportFormat {
+    // Boxed tables like `Display` renders them, each below its caption
+    Text,
+    Markdown,
+    Html,
+}
+
+// A captioned table whose values were already formatted by the table they came from
+#[derive(Debug)]
+struct Section {
+    caption: String,
+    // The heading and alignment of every column
+    columns: Vec<(String, Alignment)>,
+    rows: Vec<Vec<Option<String>>>,
+    // The highlights of every cell, aligned with the rows
+    highlights: Vec<Vec<Vec<Highlight>>>,
+}
+
+/// Collects several tables or query results with a caption each and renders them into one
+/// document. Values are formatted when they're added, so masks and time zones of the source
+/// table apply.
+#[derive(Debug, Default)]
+pub struct Report {
+    sections: Vec<Section>,
+}
+
+impl Report {
+    pub fn create() -> Self {
+        Report::default()
+    }
+
+    /// Adds all rows of the table in insertion order.
+    pub fn with_table(mut self, caption: &str, table: &Table) -> Self {
+        let highlights = (0..table.row_ids.len())
+            .map(|index| {
+                table
+                    .columns
+                    .keys()
+                    .map(|identifier| table.highlights_at(index, identifier))
+                    .collect()
+            })
+            .collect();
+
+        self.sections.push(Section {
+            caption: String::from(caption),
+            columns: table
+                .columns
+                .keys()
+                .map(|identifier| column_of(table, identifier))
+                .collect(),
+            rows: table.text_rows(),
+            highlights,
+        });
+
+        self
+    }
+
+    /// Adds rows found in the table, e.g. by `Table::find_rows`. Only the columns the rows have
+    /// values for are shown.
+    pub fn with_rows(mut self, caption: &str, table: &Table, rows: &[Row]) -> Self {
+        let identifiers = table
+            .columns
+            .keys()
+            .filter(|identifier| {
+                rows.is_empty() || rows.iter().any(|row| row.get_cell(identifier).is_some())
+            })
+            .collect::<Vec<_>>();
+        // Rows that aren't in the table (anymore) can't be evaluated and stay plain
+        let highlights = rows
+            .iter()
+            .map(|row| {
+                identifiers
+                    .iter()
+                    .map(|identifier| match table.keys.get(&row.primary_key) {
+                        Some(index) => table.highlights_at(*index, identifier),
+                        None => Vec::new(),
+                    })
+                    .collect()
+            })
+            .collect();
+        let rows = rows
+            .iter()
+            .map(|row| {
+                identifiers
+                    .iter()
+                    .map(|identifier| match row.get_cell(identifier) {
+                        Some(TableValue::Null) | None => None,
+                        Some(value) => Some(table.display_value(identifier, value)),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        self.sections.push(Section {
+            caption: String::from(caption),
+            columns: identifiers
+                .into_iter()
+                .map(|identifier| column_of(table, identifier))
+                .collect(),
+            rows,
+            highlights,
+        });
+
+        self
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        let sections = self
+            .sections
+            .iter()
+            .map(|section| match format {
+                ReportFormat::Text => render_text(section),
+                ReportFormat::Markdown => render_markdown(section),
+                ReportFormat::Html => render_html(section),
+            })
+            .collect::<Vec<_>>();
+
+        // Sections are separated by an empty line, which also works for Markdown
+        sections.join("\n")
+    }
+}
+
+fn column_of(table: &Table, identifier: &str) -> (String, Alignment) {
+    (
+        String::from(table.heading_of(identifier)),
+        table.alignment_of(identifier),
+    )
+}
+
+fn render_text(section: &Section) -> String {
+    let mut display_table = PTable::new();
+    display_table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    display_table.set_titles(PRow::new(
+        section
+            .columns
+            .iter()
+            .map(|(heading, alignment)| {
+                PCell::new_align(heading, PAlignment::from(*alignment))
+                    .with_style(Attr::Bold)
+                    .with_style(Attr::ForegroundColor(prettytable::color::GREEN))
+            })
+            .collect(),
+    ));
+    section
+        .rows
+        .iter()
+        .zip(&section.highlights)
+        .for_each(|(fields, highlights)| {
+            display_table.add_row(PRow::new(
+                fields
+                    .iter()
+                    .zip(&section.columns)
+                    .zip(highlights)
+                    .map(|((field, (_, alignment)), highlights)| {
+                        let cell = PCell::new_align(
+                            field.as_deref().unwrap_or("*NULL*"),
+                            PAlignment::from(*alignment),
+                        );
+                        highlights
+                            .iter()
+                            .fold(cell, |cell, highlight| cell.with_style(highlight.attr()))
+                    })
+                    .collect(),
+            ));
+        });
+
+    format!("{}\n{}", section.caption, display_table)
+}
+
+fn render_markdown(section: &Section) -> String {
+    let line = |fields: Vec<String>| format!("| {} |\n", fields.join(" | "));
+
+    let mut lines = vec![format!("### {}\n\n", section.caption)];
+    lines.push(line(
+        section
+            .columns
+            .iter()
+            .map(|(heading, _)| escape_markdown(heading))
+            .collect(),
+    ));
+    lines.push(line(
+        section
+            .columns
+            .iter()
+            .map(|(_, alignment)| match alignment {
+                Alignment::Left => String::from(":---"),
+                Alignment::Center => String::from(":---:"),
+                Alignment::Right => String::from("---:"),
+            })
+            .collect(),
+    ));
+    lines.extend(section.rows.iter().map(|fields| {
+        line(
+            fields
+                .iter()
+                .map(|field| field.as_deref().map_or_else(String::new, escape_markdown))
+                .collect(),
+        )
+    }));
+
+    lines.concat()
+}
+
+fn render_html(section: &Section) -> String {
+    let cells = |tag: &str, fields: Vec<(String, Alignment, &[Highlight])>| {
+        let cells = fields
+            .into_iter()
+            .map(|(field, alignment, highlights)| {
+                let alignment = match alignment {
+                    Alignment::Left => "text-align: left",
+                    Alignment::Center => "text-align: center",
+                    Alignment::Right => "text-align: right",
+                };
+                let style = std::iter::once(alignment)
+                    .chain(highlights.iter().map(|highlight| highlight.css()))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                format!("<{tag} style=\"{}\">{}</{tag}>", style, field, tag = tag)
+            })
+            .collect::<String>();
+        format!("    <tr>{}</tr>\n", cells)
+    };
+
+    let mut lines = vec![
+        String::from("<table>\n"),
+        format!("  <caption>{}</caption>\n", escape_html(&section.caption)),
+        String::from("  <thead>\n"),
+        cells(
+            "th",
+            section
+                .columns
+                .iter()
+                .map(|(heading, alignment)| (escape_html(heading), *alignment, &[][..]))
+                .collect(),
+        ),
+        String::from("  </thead>\n"),
+        String::from("  <tbody>\n"),
+    ];
+    lines.extend(
+        section
+            .rows
+            .iter()
+            .zip(&section.highlights)
+            .map(|(fields, highlights)| {
+                cells(
+                    "td",
+                    fields
+                        .iter()
+                        .zip(&section.columns)
+                        .zip(highlights)
+                        .map(|((field, (_, alignment)), highlights)| {
+                            (
+                                field.as_deref().map_or_else(String::new, escape_html),
+                                *alignment,
+                                highlights.as_slice(),
+                            )
+                        })
+                        .collect(),
+                )
+            }),
+    );
+    lines.push(String::from("  </tbody>\n"));
+    lines.push(String::from("</table>\n"));
+
+    lines.concat()
+}
+
+fn escape_markdown(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace('\n', " ")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,54 @@
+use crate::query::ColumnSpecification;
+use crate::{Index, PrimaryKey, Row, Table, TableValue};
+
+/// A cheap handle to a row that stays valid as long as the row exists, even if other rows are removed.
+#[derive(Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Copy, Clone)]
+pub struct RowId(pub(crate) usize);
+
+impl Table {
+    pub fn row_id_for(&self, key: &PrimaryKey) -> Option<RowId> {
+        let index = *self.keys.get(key)?;
+        self.row_ids.get(index).copied()
+    }
+
+    pub fn pk_for(&self, row_id: RowId) -> Option<PrimaryKey> {
+        self.key_at(self.index_of(row_id)?)
+    }
+
+    pub fn find_row_by_id(
+        &self,
+        row_id: RowId,
+        column_specification: ColumnSpecification,
+    ) -> Option<Row> {
+        let key = self.pk_for(row_id)?;
+        self.find_row(&key, column_specification)
+    }
+
+    pub(crate) fn key_at(&self, index: Index) -> Option<PrimaryKey> {
+        match self.columns.get("ID")?.value_at(index)? {
+            TableValue::Uuid(key) => Some(*key),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn index_of(&self, row_id: RowId) -> Option<Index> {
+        *self.row_slots.get(row_id.0)?
+    }
+
+    pub(crate) fn assign_row_id(&mut self, index: Index) {
+        let row_id = RowId(self.row_slots.len());
+        self.row_slots.push(Some(index));
+        self.row_ids.insert(index, row_id);
+    }
+
+    pub(crate) fn release_row_id(&mut self, index: Index) {
+        let row_id = self.row_ids.remove(index);
+        self.row_slots[row_id.0] = None;
+
+        // Every row behind the removed one moved up by one
+        let row_slots = &mut self.row_slots;
+        self.row_ids[index..]
+            .iter()
+            .for_each(|row_id| row_slots[row_id.0] = row_slots[row_id.0].map(|index| index - 1));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::error::VirtualTableError;
+use crate::query::ColumnSpecification;
+use crate::{Index, Row, Table, TableValue};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+
+/// How `Table::sample_rows` picks rows.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Sampling {
+    // Every row has the same chance to be among the sampled rows
+    Reservoir,
+    // Every row is picked independently with the given probability between 0 and 1
+    Bernoulli(f64),
+}
+
+impl Table {
+    /// Samples at most n rows in a single pass over the table, the same seed always picks the
+    /// same rows of the same table. The rows are returned in insertion order. Bernoulli samples
+    /// with more than n rows are reduced to n rows with every one of them equally likely to stay.
+    pub fn sample_rows(
+        &self,
+        n: usize,
+        sampling: Sampling,
+        seed: u64,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let probability = match sampling {
+            Sampling::Reservoir => 1.0,
+            Sampling::Bernoulli(probability) if (0.0..=1.0).contains(&probability) => probability,
+            Sampling::Bernoulli(probability) => {
+                return Result::Err(VirtualTableError::InvalidArgument(format!(
+                    "The sampling probability {} isn't between 0 and 1.",
+                    probability
+                )))
+            }
+        };
+        self.record_rows_scanned("sample_rows", self.row_ids.len());
+
+        // Algorithm R, the k-th candidate replaces a sampled row with a chance of n / k
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut sample: Vec<Index> = Vec::with_capacity(n.min(self.row_ids.len()));
+        let mut candidates = 0;
+        for index in 0..self.row_ids.len() {
+            if probability < 1.0 && !rng.gen_bool(probability) {
+                continue;
+            }
+
+            candidates += 1;
+            if sample.len() < n {
+                sample.push(index);
+            } else {
+                let position = rng.gen_range(0..candidates);
+                if position < n {
+                    sample[position] = index;
+                }
+            }
+        }
+        sample.sort_unstable();
+
+        Result::Ok(self.rows_at(&sample))
+    }
+
+    /// All rows in a random order that only depends on the seed, e.g. to split a table into
+    /// reproducible training and test sets.
+    pub fn shuffled(&self, seed: u64) -> Vec<Row> {
+        let mut indexes = (0..self.row_ids.len()).collect::<Vec<_>>();
+        indexes.shuffle(&mut StdRng::seed_from_u64(seed));
+
+        self.rows_at(&indexes)
+    }
+
+    /// Splits the rows into two new tables with the same columns, "<identifier>_train" with about
+    /// the fraction of the rows and "<identifier>_test" with the others. Which rows end up where
+    /// only depends on the seed. Indexes and other settings of the table aren't copied.
+    pub fn split(&self, fraction: f64, seed: u64) -> Result<(Table, Table), VirtualTableError> {
+        let indexes = (0..self.row_ids.len()).collect::<Vec<_>>();
+        self.split_groups(fraction, vec![indexes], seed)
+    }
+
+    /// Like `Table::split`, but every value of the column, NULL included, is split by itself, so
+    /// both tables have about the same share of each value, e.g. of every class label.
+    pub fn split_stratified(
+        &self,
+        fraction: f64,
+        column_identifier: &str,
+        seed: u64,
+    ) -> Result<(Table, Table), VirtualTableError> {
+        let column = self
+            .columns
+            .get(column_identifier)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(String::from(column_identifier)))?;
+
+        let mut positions: HashMap<&TableValue, usize> = HashMap::new();
+        let mut groups: Vec<Vec<Index>> = Vec::new();
+        for (index, value) in column.values.iter().enumerate() {
+            let position = *positions.entry(value).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[position].push(index);
+        }
+
+        self.split_groups(fraction, groups, seed)
+    }
+
+    fn split_groups(
+        &self,
+        fraction: f64,
+        groups: Vec<Vec<Index>>,
+        seed: u64,
+    ) -> Result<(Table, Table), VirtualTableError> {
+        if !(0.0..=1.0).contains(&fraction) {
+            return Result::Err(VirtualTableError::InvalidArgument(format!(
+                "The fraction {} isn't between 0 and 1.",
+                fraction
+            )));
+        }
+        self.record_rows_scanned("split", self.row_ids.len());
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (mut first, mut second) = (Vec::new(), Vec::new());
+        for mut group in groups {
+            group.shuffle(&mut rng);
+            let split_at = (group.len() as f64 * fraction).round() as usize;
+            first.extend_from_slice(&group[..split_at]);
+            second.extend_from_slice(&group[split_at..]);
+        }
+
+        Result::Ok((
+            self.copy_rows(&format!("{}_train", self.identifier), first),
+            self.copy_rows(&format!("{}_test", self.identifier), second),
+        ))
+    }
+
+    // Copies the rows at the indexes into a new table with the same columns, in insertion order
+    pub(crate) fn copy_rows(&self, identifier: &str, mut indexes: Vec<Index>) -> Table {
+        indexes.sort_unstable();
+
+        let mut table = Table::create(String::from(identifier), self.column_definitions());
+        for row in self.rows_at(&indexes) {
+            table
+                .create_row(row)
+                .expect("Rows of a table always match its columns.");
+        }
+
+        table
+    }
+
+    fn rows_at(&self, indexes: &[Index]) -> Vec<Row> {
+        indexes
+            .iter()
+            .filter_map(|index| self.key_at(*index))
+            .filter_map(|key| self.find_row(&key, ColumnSpecification::All))
+            .collect()
+    }
+}
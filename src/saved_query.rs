@@ -0,0 +1,93 @@
+use crate::error::VirtualTableError;
+use crate::expression::Expression;
+use crate::instrumentation::OperationSpan;
+use crate::predicate::Predicate;
+use crate::query::ColumnSpecification;
+use crate::sorting::SortOrder;
+use crate::{Row, Table, TableValue};
+use std::collections::HashMap;
+
+/// A query that is defined once and run by name, see `Table::define_query`. Values that differ
+/// between runs are written as `Expression::Parameter`.
+#[derive(Debug, Clone)]
+pub struct SavedQuery {
+    predicate: Predicate,
+    column_specification: ColumnSpecification,
+    order: Vec<(Expression, SortOrder)>,
+}
+
+impl SavedQuery {
+    pub fn create(predicate: Predicate) -> Self {
+        SavedQuery {
+            predicate,
+            column_specification: ColumnSpecification::All,
+            order: Vec::new(),
+        }
+    }
+
+    pub fn with_columns(mut self, column_specification: ColumnSpecification) -> Self {
+        self.column_specification = column_specification;
+        self
+    }
+
+    pub fn ordered_by(mut self, order: Vec<(Expression, SortOrder)>) -> Self {
+        self.order = order;
+        self
+    }
+}
+
+impl Table {
+    /// Saves the query under the name, replacing a previous query with the same name. The query
+    /// is validated against the table right away, parameters are only checked when it runs.
+    pub fn define_query(&mut self, name: &str, query: SavedQuery) -> Result<(), VirtualTableError> {
+        query.predicate.validate(self)?;
+        self.saved_queries.insert(String::from(name), query);
+
+        Result::Ok(())
+    }
+
+    pub fn drop_query(&mut self, name: &str) -> bool {
+        self.saved_queries.remove(name).is_some()
+    }
+
+    /// Runs the saved query with the given values for its parameters.
+    pub fn run_query(
+        &self,
+        name: &str,
+        parameters: &HashMap<String, TableValue>,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let span = OperationSpan::start("run_query", self);
+        self.statistics.record_query();
+        let result = self.execute_saved_query(name, parameters);
+        span.finish(&result, result.as_ref().map_or(0, |rows| rows.len()));
+
+        result
+    }
+
+    fn execute_saved_query(
+        &self,
+        name: &str,
+        parameters: &HashMap<String, TableValue>,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let query = self
+            .saved_queries
+            .get(name)
+            .ok_or_else(|| VirtualTableError::UnknownQuery(String::from(name)))?;
+        let predicate = query.predicate.bind(parameters)?;
+        let order = query
+            .order
+            .iter()
+            .map(|(expression, sort_order)| Result::Ok((expression.bind(parameters)?, *sort_order)))
+            .collect::<Result<Vec<_>, VirtualTableError>>()?;
+
+        let indexes = self.sort_indexes(self.matching_indexes(&predicate)?, &order)?;
+        let mut rows = Vec::new();
+        for index in indexes {
+            if let Some(key) = self.key_at(index) {
+                rows.extend(self.try_find_row(&key, query.column_specification.clone())?);
+            }
+        }
+
+        Result::Ok(rows)
+    }
+}
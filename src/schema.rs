@@ -0,0 +1,86 @@
+use crate::coercion::coerce;
+use crate::error::VirtualTableError;
+use crate::{ColumnDefinition, DataType, IntoCell, Table};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+// Tried in this order, the first type all values of a field convert to wins
+const CANDIDATE_TYPES: [DataType; 8] = [
+    DataType::Integer,
+    DataType::UnsignedInteger,
+    DataType::BigInt,
+    DataType::Timestamp,
+    DataType::Duration,
+    DataType::Money,
+    DataType::Point,
+    DataType::Uuid,
+];
+
+/// Proposes column definitions for records read as text, e.g. from a CSV file. Every field gets
+/// the narrowest data type all its values convert to like with `Table::enable_coercion`, or String
+/// if there is none. Fields that are missing or empty in some records become nullable.
+///
+/// Columns are sorted by name, and a field named "ID" is left out since every table has its own ID
+/// column. The definitions are only a proposal, so they can be adjusted before creating the table.
+pub fn infer_from_records(records: &[HashMap<String, String>]) -> Vec<ColumnDefinition> {
+    let field_names = records
+        .iter()
+        .flat_map(|record| record.keys())
+        .filter(|name| *name != "ID")
+        .collect::<BTreeSet<_>>();
+
+    field_names
+        .into_iter()
+        .map(|name| {
+            let values = records
+                .iter()
+                .filter_map(|record| record.get(name))
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .collect::<Vec<_>>();
+            let is_nullable = values.len() < records.len();
+
+            let data_type = CANDIDATE_TYPES
+                .iter()
+                .copied()
+                .find(|data_type| {
+                    !values.is_empty()
+                        && values.iter().all(|value| {
+                            coerce(value.into_cell(), *data_type).data_type == *data_type
+                        })
+                })
+                .unwrap_or(DataType::String);
+
+            ColumnDefinition::create(name, data_type, is_nullable)
+        })
+        .collect()
+}
+
+impl Table {
+    /// Changes the order columns are displayed and exported in. The listed columns come first,
+    /// all others keep their order after them. This includes the ID column, so it can be moved
+    /// as well.
+    pub fn reorder_columns(&mut self, order: Vec<String>) -> Result<(), VirtualTableError> {
+        let mut listed = HashSet::new();
+        for identifier in &order {
+            if !self.columns.contains_key(identifier) {
+                return Result::Err(VirtualTableError::UnknownColumn(identifier.clone()));
+            }
+            if !listed.insert(identifier.as_str()) {
+                return Result::Err(VirtualTableError::DuplicateColumn(identifier.clone()));
+            }
+        }
+
+        let unlisted = self
+            .columns
+            .keys()
+            .filter(|identifier| !listed.contains(identifier.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        // Refreshing moves a column to the back, so the listed ones end up before the others
+        order.iter().chain(&unlisted).for_each(|identifier| {
+            self.columns.get_refresh(identifier);
+        });
+
+        Result::Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use crate::error::VirtualTableError;
+use crate::predicate::Predicate;
+use crate::query::ColumnSpecification;
+use crate::{PrimaryKey, Row, Table, TableValue};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+type PolicyCheck = dyn Fn(&PolicyContext, &Row) -> bool + Send + Sync;
+
+/// Describes who is accessing a table, policies decide based on it which rows are visible.
+#[derive(Debug, Clone)]
+pub struct PolicyContext {
+    principal: String,
+    attributes: HashMap<String, TableValue>,
+}
+
+impl PolicyContext {
+    pub fn create(principal: &str) -> Self {
+        PolicyContext {
+            principal: String::from(principal),
+            attributes: HashMap::new(),
+        }
+    }
+
+    pub fn with_attribute<T: Into<TableValue>>(mut self, name: &str, value: T) -> Self {
+        self.attributes.insert(String::from(name), value.into());
+        self
+    }
+
+    pub fn principal(&self) -> &str {
+        &self.principal
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&TableValue> {
+        self.attributes.get(name)
+    }
+}
+
+pub(crate) struct Policy {
+    check: Box<PolicyCheck>,
+}
+
+impl Debug for Policy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str("Policy")
+    }
+}
+
+impl Table {
+    /// Sets the policy that decides which rows a context may read and write. The policy is only
+    /// evaluated by `find_row_as`, `find_rows_as`, `rows_as`, `create_row_as`, `update_row_as`
+    /// and `delete_row_as`. All other methods, e.g. `find_rows`, `scan`, `aggregate`, joins and
+    /// index lookups, keep full access to all rows, so only the `*_as` methods may be exposed to
+    /// callers that are restricted by the policy.
+    pub fn set_policy<F>(&mut self, check: F)
+    where
+        F: Fn(&PolicyContext, &Row) -> bool + Send + Sync + 'static,
+    {
+        self.policy = Some(Policy {
+            check: Box::new(check),
+        });
+    }
+
+    pub fn clear_policy(&mut self) {
+        self.policy = None;
+    }
+
+    /// Like `Table::find_row`, but rows the context isn't permitted to read are treated as missing.
+    pub fn find_row_as(
+        &self,
+        context: &PolicyContext,
+        key: &PrimaryKey,
+        column_specification: ColumnSpecification,
+    ) -> Option<Row> {
+        if !self.is_permitted(context, &self.find_row(key, ColumnSpecification::All)?) {
+            return None;
+        }
+
+        self.find_row(key, column_specification)
+    }
+
+    /// Like `Table::find_rows`, but only returns the matching rows the context is permitted to read.
+    pub fn find_rows_as(
+        &self,
+        context: &PolicyContext,
+        predicate: &Predicate,
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let rows = self.find_rows(predicate, ColumnSpecification::All)?;
+
+        Result::Ok(
+            rows.into_iter()
+                .filter(|row| self.is_permitted(context, row))
+                .filter_map(|row| self.find_row(&row.primary_key, column_specification.clone()))
+                .collect(),
+        )
+    }
+
+    /// Fetches all rows the context is permitted to read, in insertion order.
+    pub fn rows_as(
+        &self,
+        context: &PolicyContext,
+        column_specification: ColumnSpecification,
+    ) -> Vec<Row> {
+        (0..self.row_ids.len())
+            .filter_map(|index| self.key_at(index))
+            .filter_map(|key| self.find_row_as(context, &key, column_specification.clone()))
+            .collect()
+    }
+
+    /// Like `Table::create_row`, but the new row has to be permitted for the context.
+    pub fn create_row_as(
+        &mut self,
+        context: &PolicyContext,
+        row: Row,
+    ) -> Result<(), Vec<VirtualTableError>> {
+        if !self.is_permitted(context, &row) {
+            return Result::Err(vec![VirtualTableError::PolicyViolation(row.primary_key)]);
+        }
+
+        self.create_row_by(row, Some(context.principal()))
+    }
+
+    /// Like `Table::update_row`, but the row has to be permitted for the context both before
+    /// and after the update, so rows can't be moved out of reach of the context.
+    pub fn update_row_as(
+        &mut self,
+        context: &PolicyContext,
+        update_row: Row,
+    ) -> Result<(), Vec<VirtualTableError>> {
+        let primary_key = update_row.primary_key;
+        let mut row = self
+            .find_row(&primary_key, ColumnSpecification::All)
+            .ok_or_else(|| vec![VirtualTableError::UnknownPrimaryKey(primary_key)])?;
+        if !self.is_permitted(context, &row) {
+            return Result::Err(vec![VirtualTableError::UnknownPrimaryKey(primary_key)]);
+        }
+
+        update_row
+            .cells
+            .iter()
+            .filter_map(|(identifier, cell)| Some((identifier, cell.clone()?)))
+            .for_each(|(identifier, cell)| row.set_cell(identifier.clone(), cell));
+        if !self.is_permitted(context, &row) {
+            return Result::Err(vec![VirtualTableError::PolicyViolation(primary_key)]);
+        }
+
+        self.update_row_by(update_row, Some(context.principal()))
+    }
+
+    /// Like `Table::delete_row`, but rows the context isn't permitted to write are treated as
+    /// missing.
+    pub fn delete_row_as(
+        &mut self,
+        context: &PolicyContext,
+        key: &PrimaryKey,
+    ) -> Result<Row, Vec<VirtualTableError>> {
+        match self.find_row_as(context, key, ColumnSpecification::All) {
+            Some(_) => self.delete_row(key),
+            None => Result::Err(vec![VirtualTableError::UnknownPrimaryKey(*key)]),
+        }
+    }
+
+    fn is_permitted(&self, context: &PolicyContext, row: &Row) -> bool {
+        match &self.policy {
+            Some(policy) => (policy.check)(context, row),
+            None => true,
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use crate::collation::Collation;
+use crate::error::VirtualTableError;
+use crate::expression::Expression;
+use crate::predicate::compare_values;
+use crate::query::ColumnSpecification;
+use crate::{Index, Row, Table, TableValue};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl Table {
+    /// Returns all rows sorted by the given expressions, like ORDER BY in SQL. String values are
+    /// compared with the collation of their column. NULL values are sorted after all other values
+    /// in ascending order and before them in descending order. The sort is stable, rows that
+    /// compare equal keep their insertion order.
+    pub fn order_by(
+        &self,
+        order: &[(Expression, SortOrder)],
+        column_specification: ColumnSpecification,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        self.sort_indexes((0..self.row_ids.len()).collect(), order)?
+            .into_iter()
+            .filter_map(|index| self.key_at(index))
+            .map(|key| {
+                self.try_find_row(&key, column_specification.clone())
+                    .map(|row| row.expect("Rows at valid indexes always exist."))
+            })
+            .collect()
+    }
+
+    /// The k rows with the smallest (Ascending) or largest (Descending) values of the column, in
+    /// that order. Unlike `Table::order_by`, only k rows are kept while scanning the table. NULL
+    /// values are skipped and rows with equal values keep their insertion order.
+    pub fn top_k(
+        &self,
+        column_identifier: &str,
+        k: usize,
+        order: SortOrder,
+    ) -> Result<Vec<Row>, VirtualTableError> {
+        let column = self
+            .columns
+            .get(column_identifier)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(String::from(column_identifier)))?;
+        self.statistics.record_query();
+        self.record_rows_scanned("top_k", column.values.len());
+
+        let collation = self.collation_of(column_identifier);
+        // The heap keeps the k best candidates with the worst one on top, so it can be replaced
+        let mut candidates = BinaryHeap::with_capacity(k.saturating_add(1));
+        for (index, value) in column.values.iter().enumerate() {
+            if k == 0 || *value == TableValue::Null {
+                continue;
+            }
+
+            let candidate = Candidate {
+                value,
+                index,
+                collation,
+                order,
+            };
+            if candidates.len() < k {
+                candidates.push(candidate);
+            } else if candidates.peek().is_some_and(|worst| candidate < *worst) {
+                candidates.pop();
+                candidates.push(candidate);
+            }
+        }
+
+        candidates
+            .into_sorted_vec()
+            .into_iter()
+            .filter_map(|candidate| self.key_at(candidate.index))
+            .map(|key| {
+                self.try_find_row(&key, ColumnSpecification::All)
+                    .map(|row| row.expect("Rows at valid indexes always exist."))
+            })
+            .collect()
+    }
+
+    // Sorts the row indexes by the expressions, rows that compare equal keep their order
+    pub(crate) fn sort_indexes(
+        &self,
+        indexes: Vec<Index>,
+        order: &[(Expression, SortOrder)],
+    ) -> Result<Vec<Index>, VirtualTableError> {
+        // Checking the types up front guarantees that all values of an expression are comparable
+        for (expression, _) in order {
+            expression.data_type(self)?;
+        }
+        let collations = order
+            .iter()
+            .map(|(expression, _)| self.collation_for(expression))
+            .collect::<Vec<_>>();
+
+        let mut sort_keys = Vec::new();
+        for index in indexes {
+            let values = order
+                .iter()
+                .map(|(expression, _)| expression.evaluate(self, index))
+                .collect::<Result<Vec<_>, _>>()?;
+            sort_keys.push((index, values));
+        }
+
+        sort_keys.sort_by(|(_, left), (_, right)| {
+            order
+                .iter()
+                .zip(&collations)
+                .enumerate()
+                .map(|(position, ((_, sort_order), collation))| {
+                    let ordering = match (&left[position], &right[position]) {
+                        (TableValue::Null, TableValue::Null) => Ordering::Equal,
+                        (TableValue::Null, _) => Ordering::Greater,
+                        (_, TableValue::Null) => Ordering::Less,
+                        (left, right) => compare_values(left, right, *collation)
+                            .ok()
+                            .flatten()
+                            .unwrap_or(Ordering::Equal),
+                    };
+
+                    match sort_order {
+                        SortOrder::Ascending => ordering,
+                        SortOrder::Descending => ordering.reverse(),
+                    }
+                })
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+
+        Result::Ok(sort_keys.into_iter().map(|(index, _)| index).collect())
+    }
+}
+
+// A row in `Table::top_k`, candidates that come first in the result are less than others
+struct Candidate<'a> {
+    value: &'a TableValue,
+    index: Index,
+    collation: Collation,
+    order: SortOrder,
+}
+
+impl Ord for Candidate<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // All values are from the same column, so they are always comparable
+        let ordering = compare_values(self.value, other.value, self.collation)
+            .ok()
+            .flatten()
+            .unwrap_or(Ordering::Equal);
+        let ordering = match self.order {
+            SortOrder::Ascending => ordering,
+            SortOrder::Descending => ordering.reverse(),
+        };
+
+        ordering.then(self.index.cmp(&other.index))
+    }
+}
+
+impl PartialOrd for Candidate<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Candidate<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate<'_> {}
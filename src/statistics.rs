@@ -0,0 +1,82 @@
+use crate::timestamp::Timestamp;
+use crate::Table;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counters of what happened to a table since it was created or its statistics were reset, see
+/// `Table::stats`.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct TableStats {
+    pub rows_inserted: u64,
+    pub rows_updated: u64,
+    pub rows_deleted: u64,
+    // Queries like `Table::find_rows` or `Table::scan`, single rows fetched by key aren't counted
+    pub queries_executed: u64,
+    // Reads that had to visit every row instead of consulting an index
+    pub scans: u64,
+    pub index_lookups: u64,
+    pub last_modified: Option<Timestamp>,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum Modification {
+    Insert,
+    Update,
+    Delete,
+}
+
+// Reads only borrow the table, so their counters are atomic
+#[derive(Debug, Default)]
+pub(crate) struct Statistics {
+    rows_inserted: u64,
+    rows_updated: u64,
+    rows_deleted: u64,
+    queries_executed: AtomicU64,
+    scans: AtomicU64,
+    index_lookups: AtomicU64,
+    last_modified: Option<Timestamp>,
+}
+
+impl Statistics {
+    pub(crate) fn record_query(&self) {
+        self.queries_executed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_scan(&self) {
+        self.scans.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_index_lookup(&self) {
+        self.index_lookups.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Table {
+    /// Counts writes and reads of the table, e.g. for capacity planning. Unlike `Table::set_metrics`
+    /// this needs no setup, but it only keeps totals.
+    pub fn stats(&self) -> TableStats {
+        let statistics = &self.statistics;
+        TableStats {
+            rows_inserted: statistics.rows_inserted,
+            rows_updated: statistics.rows_updated,
+            rows_deleted: statistics.rows_deleted,
+            queries_executed: statistics.queries_executed.load(Ordering::Relaxed),
+            scans: statistics.scans.load(Ordering::Relaxed),
+            index_lookups: statistics.index_lookups.load(Ordering::Relaxed),
+            last_modified: statistics.last_modified,
+        }
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.statistics = Statistics::default();
+    }
+
+    pub(crate) fn record_modification(&mut self, modification: Modification) {
+        let statistics = &mut self.statistics;
+        match modification {
+            Modification::Insert => statistics.rows_inserted += 1,
+            Modification::Update => statistics.rows_updated += 1,
+            Modification::Delete => statistics.rows_deleted += 1,
+        }
+        statistics.last_modified = Some(Timestamp::now());
+    }
+}
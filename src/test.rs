@@ -1,8 +1,54 @@
+use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use uuid::Uuid;
+use virtual_table::aggregate::{Aggregate, Precision};
+use virtual_table::anonymization::Anonymization;
+use virtual_table::audit;
+use virtual_table::bitmap_index::BitmapFilter;
+use virtual_table::cancellation::CancellationToken;
+use virtual_table::collation::Collation;
+use virtual_table::constraint::{Charset, Constraint};
+#[cfg(feature = "testing")]
+use virtual_table::consistency::InvariantViolation;
+use virtual_table::custom_type::{Custom, CustomType, CustomTypeId};
+use virtual_table::diff::{self, CellChange, TableDiff};
+use virtual_table::duplicates::KeepPolicy;
+use virtual_table::duration;
 use virtual_table::error::VirtualTableError;
+use virtual_table::etag;
+use virtual_table::events::TableEvent;
+use virtual_table::expression::Expression;
 use virtual_table::*;
+use virtual_table::fixtures::{Fixtures, Generator};
+use virtual_table::format::{Alignment, FormatStyle, Overflow};
+use virtual_table::functions::ScalarFunction;
+use virtual_table::geo::Point;
+use virtual_table::highlight::{Color, Highlight, HighlightRule};
+use virtual_table::id_format::IdFormat;
+use virtual_table::information_schema;
+use virtual_table::key_generator::{self, KeyGenerator};
+use virtual_table::key_value::KeyValueView;
+use virtual_table::linkage::{self, LinkRule};
+use virtual_table::masking::Mask;
+use virtual_table::metadata::ColumnMetadata;
+use virtual_table::metrics::Metrics;
+use virtual_table::money::{Currency, Money};
+use virtual_table::normalization::NormalizationForm;
+use virtual_table::predicate::{Predicate, Truth};
+use virtual_table::profiling::HistogramBin;
 use virtual_table::query::ColumnSpecification;
+use virtual_table::sampling::Sampling;
+use virtual_table::saved_query::SavedQuery;
+use virtual_table::schema;
+use virtual_table::report::{Report, ReportFormat};
+use virtual_table::security::PolicyContext;
+use virtual_table::sorting::SortOrder;
+use virtual_table::statistics::TableStats;
+use virtual_table::timestamp::{LocalDateTime, TimeZone, Timestamp};
+use virtual_table::transformer::WriteStage;
+use virtual_table::typed_table::{TableRecord, TypedTable};
 
 fn create_demo_table() -> Table {
     Table::create(
@@ -12,16 +58,22 @@ fn create_demo_table() -> Table {
                 identifier: String::from("first_name"),
                 data_type: DataType::String,
                 is_nullable: false,
+                constraints: Vec::new(),
+                metadata: ColumnMetadata::default(),
             },
             ColumnDefinition {
                 identifier: String::from("last_name"),
                 data_type: DataType::String,
                 is_nullable: false,
+                constraints: Vec::new(),
+                metadata: ColumnMetadata::default(),
             },
             ColumnDefinition {
                 identifier: String::from("age"),
                 data_type: DataType::Integer,
                 is_nullable: true,
+                constraints: Vec::new(),
+                metadata: ColumnMetadata::default(),
             },
         ],
     )
@@ -55,9 +107,9 @@ fn can_create_row() {
 
     let expected = "\
 +--------------------------------------+------------+-----------+-----+
-| ID                                   | first_name | last_name | age |
+|                  ID                  | first_name | last_name | age |
 +--------------------------------------+------------+-----------+-----+
-| 797724d9-491c-46ac-981c-566d6d65b199 | first      | last      | 69  |
+| 797724d9-491c-46ac-981c-566d6d65b199 | first      | last      |  69 |
 +--------------------------------------+------------+-----------+-----+
 ";
 
@@ -84,9 +136,9 @@ fn can_partially_update_row() {
 
     let expected = "\
 +--------------------------------------+--------------------+-----------+-----+
-| ID                                   | first_name         | last_name | age |
+|                  ID                  | first_name         | last_name | age |
 +--------------------------------------+--------------------+-----------+-----+
-| 797724d9-491c-46ac-981c-566d6d65b199 | changed first name | last      | 69  |
+| 797724d9-491c-46ac-981c-566d6d65b199 | changed first name | last      |  69 |
 +--------------------------------------+--------------------+-----------+-----+
 ";
 
@@ -164,3 +216,4625 @@ fn it_can_fetch_rows_with_selected_columns_via_primary_key() {
 
     assert_eq!(expected_row, table.find_row(&pk, ColumnSpecification::Some(vec![String::from("age")])).expect("Expected a value here."));
 }
+
+#[test]
+fn it_reports_no_invariant_violations_for_a_consistent_table() {
+    let mut table = create_demo_table();
+
+    for &(first_name, last_name) in &[("first", "last"), ("other", "person")] {
+        let mut row = Row::create(&table, Uuid::new_v4());
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), last_name.into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+
+    let report = table.check_invariants();
+    assert!(report.is_consistent(), "{}", report);
+}
+
+#[cfg(feature = "testing")]
+proptest::proptest! {
+    #[test]
+    fn arbitrary_tables_are_consistent(table in proptest::prelude::any::<Table>()) {
+        proptest::prop_assert!(table.check_invariants().is_consistent());
+    }
+}
+
+// A table the consistency tests break on purpose, see `virtual_table::testing`
+#[cfg(feature = "testing")]
+fn create_places_table(rows: u128) -> Table {
+    let mut table = Table::create(
+        String::from("places"),
+        vec![
+            ColumnDefinition::create("name", DataType::String, false),
+            ColumnDefinition::create("rating", DataType::Integer, true)
+                .with_constraint(Constraint::Max(5)),
+            ColumnDefinition::create("location", DataType::Point, true),
+        ],
+    );
+    for key in 0..rows {
+        let mut row = Row::create(&table, Uuid::from_u128(key));
+        row.set_cell(String::from("name"), format!("Place {}", key).into_cell());
+        row.set_cell(String::from("rating"), 4i64.into_cell());
+        let location = Point::create(52.0 + key as f64, 13.0).unwrap();
+        row.set_cell(String::from("location"), location.into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+
+    table
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn it_detects_row_ids_that_do_not_point_back() {
+    let mut table = create_places_table(3);
+    assert!(table.delete_row(&Uuid::from_u128(0)).is_ok());
+    assert!(table.check_invariants().is_consistent());
+
+    table.set_row_slot(0, Some(1));
+    table.set_row_slot(2, None);
+
+    assert_eq!(
+        &[
+            InvariantViolation::RowIdMismatch(1),
+            InvariantViolation::StaleRowSlot(0, 1),
+        ],
+        table.check_invariants().violations()
+    );
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn it_detects_stale_bitmap_indexes() {
+    let mut table = create_places_table(2);
+    assert!(table.create_bitmap_index("rating").is_ok());
+    assert!(table.check_invariants().is_consistent());
+
+    table.forget_indexed_row(1);
+
+    assert_eq!(
+        &[InvariantViolation::StaleBitmapIndex(String::from("rating"))],
+        table.check_invariants().violations()
+    );
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn it_detects_stale_bitmap_index_builds() {
+    let mut table = create_places_table(3);
+    assert!(table.start_bitmap_index_build("rating").is_ok());
+    assert_eq!(1, table.continue_bitmap_index_builds(2));
+    assert!(table.check_invariants().is_consistent());
+
+    // The last row isn't indexed by a batch yet, so only the first two are missed
+    table.forget_indexed_row(2);
+    assert!(table.check_invariants().is_consistent());
+    table.forget_indexed_row(1);
+    assert_eq!(
+        &[InvariantViolation::StaleBitmapIndexBuild(String::from(
+            "rating"
+        ))],
+        table.check_invariants().violations()
+    );
+
+    let mut table = create_places_table(3);
+    assert!(table.start_bitmap_index_build("rating").is_ok());
+    assert_eq!(1, table.continue_bitmap_index_builds(1));
+    table.set_value_unchecked("rating", 0, TableValue::Integer(3));
+    assert_eq!(
+        &[InvariantViolation::StaleBitmapIndexBuild(String::from(
+            "rating"
+        ))],
+        table.check_invariants().violations()
+    );
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn it_detects_stale_unique_indexes() {
+    let mut table = create_places_table(2);
+    assert!(table.create_unique_index("name").is_ok());
+    assert!(table.check_invariants().is_consistent());
+
+    table.forget_indexed_row(1);
+    assert_eq!(
+        &[InvariantViolation::StaleUniqueIndex(String::from("name"))],
+        table.check_invariants().violations()
+    );
+
+    let mut table = create_places_table(2);
+    assert!(table.create_unique_index("name").is_ok());
+    table.set_value_unchecked("name", 1, TableValue::from("Place 0"));
+    assert_eq!(
+        &[InvariantViolation::DuplicateUniqueValue(
+            String::from("name"),
+            TableValue::from("Place 0")
+        )],
+        table.check_invariants().violations()
+    );
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn it_detects_stale_spatial_indexes() {
+    let mut table = create_places_table(2);
+    assert!(table.create_spatial_index("location", 1_000.0).is_ok());
+    assert!(table.check_invariants().is_consistent());
+
+    table.forget_indexed_row(0);
+
+    assert_eq!(
+        &[InvariantViolation::StaleSpatialIndex(String::from(
+            "location"
+        ))],
+        table.check_invariants().violations()
+    );
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn it_detects_stale_ordered_keys() {
+    let mut table = create_places_table(2);
+    table.enable_ordered_keys();
+    assert!(table.check_invariants().is_consistent());
+
+    table.forget_ordered_key(&Uuid::from_u128(1));
+
+    assert_eq!(
+        &[InvariantViolation::StaleOrderedKeys],
+        table.check_invariants().violations()
+    );
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn it_detects_keys_missing_in_the_bloom_filter() {
+    let mut table = create_places_table(2);
+    table.enable_bloom_filter(10);
+    assert!(table.delete_row(&Uuid::from_u128(0)).is_ok());
+    assert!(table.check_invariants().is_consistent());
+
+    table.clear_bloom_filter();
+
+    let key = Uuid::from_u128(1);
+    assert_eq!(
+        &[InvariantViolation::MissingBloomFilterKey(key)],
+        table.check_invariants().violations()
+    );
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn it_detects_values_violating_column_constraints() {
+    let mut table = create_places_table(2);
+
+    table.set_value_unchecked("rating", 1, TableValue::Integer(6));
+
+    assert_eq!(
+        &[InvariantViolation::ConstraintViolation(
+            String::from("rating"),
+            1,
+            Constraint::Max(5)
+        )],
+        table.check_invariants().violations()
+    );
+}
+
+#[test]
+fn it_populates_tables_with_reproducible_fixtures() {
+    let populate = || {
+        let mut table = create_demo_table();
+        Fixtures::create()
+            .with_seed(42)
+            .with_generator("first_name", Generator::FirstName)
+            .with_generator("age", Generator::IntegerRange(18, 99))
+            .populate(&mut table, 10)
+            .expect("Fixtures should match the table.");
+
+        table
+    };
+
+    // Rows are not printed in a stable order, so we compare the sorted lines
+    let sorted_lines = |table: &Table| {
+        let mut lines = table
+            .to_string()
+            .lines()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines
+    };
+
+    let table = populate();
+    assert!(table.check_invariants().is_consistent());
+    assert_eq!(14, sorted_lines(&table).len());
+    assert_eq!(sorted_lines(&table), sorted_lines(&populate()));
+}
+
+#[test]
+fn it_rejects_generators_without_values_to_pick_from() {
+    let mut table = create_demo_table();
+    let center = Point::create(52.52, 13.405).unwrap();
+    let errors = Fixtures::create()
+        .with_generator("age", Generator::IntegerRange(99, 18))
+        .with_generator("first_name", Generator::PointNear(center, f64::NAN))
+        .with_generator("last_name", Generator::OneOf(Vec::new()))
+        .populate(&mut table, 10)
+        .unwrap_err();
+
+    assert_eq!(3, errors.len());
+    assert!(errors
+        .iter()
+        .all(|error| matches!(error, VirtualTableError::InvalidArgument(_))));
+    assert_eq!(
+        Ok(TableValue::Integer(0)),
+        table.aggregate(&Aggregate::Count, None)
+    );
+    assert!(table
+        .anonymize(
+            "last_name",
+            Anonymization::Fake(Generator::OneOf(Vec::new()))
+        )
+        .is_err());
+}
+
+#[test]
+fn it_lists_changed_cells_between_two_rows() {
+    let table = create_demo_table();
+    let pk = Uuid::new_v4();
+
+    let mut old_row = Row::create(&table, pk);
+    old_row.set_cell(String::from("first_name"), "first".into_cell());
+    old_row.set_cell(String::from("last_name"), "last".into_cell());
+
+    let mut new_row = old_row.clone();
+    new_row.set_cell(String::from("first_name"), "changed".into_cell());
+    new_row.set_cell(String::from("age"), 42.into_cell());
+
+    assert_eq!(
+        vec![
+            CellChange {
+                column_identifier: String::from("age"),
+                old: None,
+                new: Some(TableValue::Integer(42)),
+            },
+            CellChange {
+                column_identifier: String::from("first_name"),
+                old: Some(TableValue::from("first")),
+                new: Some(TableValue::from("changed")),
+            },
+        ],
+        old_row.diff(&new_row)
+    );
+    assert!(old_row.diff(&old_row).is_empty());
+}
+
+#[test]
+fn it_can_replay_event_sourced_tables_to_a_previous_state() {
+    let mut table = Table::create_event_sourced(
+        String::from("user"),
+        vec![ColumnDefinition {
+            identifier: String::from("first_name"),
+            data_type: DataType::String,
+            is_nullable: false,
+            constraints: Vec::new(),
+            metadata: ColumnMetadata::default(),
+        }],
+    );
+    let pk = Uuid::new_v4();
+
+    let mut row = Row::create(&table, pk);
+    row.set_cell(String::from("first_name"), "first".into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    let mut update_row = Row::create(&table, pk);
+    update_row.set_cell(String::from("first_name"), "changed".into_cell());
+    assert!(table.update_row(update_row).is_ok());
+
+    assert_eq!(2, table.events().len());
+
+    let mut expected_row = Row::create(&table, pk);
+    expected_row.set_cell(String::from("first_name"), "first".into_cell());
+    let replayed = table.replay_to(0).expect("Expected the first event to exist.");
+    assert_eq!(
+        Some(expected_row),
+        replayed.find_row(&pk, ColumnSpecification::All)
+    );
+    assert_eq!(1, replayed.events().len());
+
+    assert_eq!(
+        vec![VirtualTableError::UnknownEvent(2)],
+        table.replay_to(2).unwrap_err()
+    );
+}
+
+#[test]
+fn it_can_undo_and_redo_inserts_and_updates() {
+    let mut table = create_demo_table();
+    table.enable_history();
+    let pk = Uuid::new_v4();
+
+    let mut row = Row::create(&table, pk);
+    row.set_cell(String::from("first_name"), "first".into_cell());
+    row.set_cell(String::from("last_name"), "last".into_cell());
+    row.set_cell(String::from("age"), 69.into_cell());
+    assert!(table.create_row(row.clone()).is_ok());
+
+    let mut update_row = Row::create(&table, pk);
+    update_row.set_cell(String::from("age"), 70.into_cell());
+    assert!(table.update_row(update_row).is_ok());
+
+    let mut updated_row = row.clone();
+    updated_row.set_cell(String::from("age"), 70.into_cell());
+
+    assert_eq!(Ok(true), table.undo());
+    assert_eq!(Some(row.clone()), table.find_row(&pk, ColumnSpecification::All));
+
+    assert_eq!(Ok(true), table.undo());
+    assert_eq!(None, table.find_row(&pk, ColumnSpecification::All));
+    assert_eq!(Ok(false), table.undo());
+    assert!(table.check_invariants().is_consistent());
+
+    assert_eq!(Ok(true), table.redo());
+    assert_eq!(Ok(true), table.redo());
+    assert!(!table.can_redo());
+    assert_eq!(Some(updated_row), table.find_row(&pk, ColumnSpecification::All));
+    assert!(table.check_invariants().is_consistent());
+}
+
+#[test]
+fn it_keeps_the_row_untouched_when_an_update_fails() {
+    let mut table = create_demo_table();
+    let pk = Uuid::new_v4();
+
+    let mut row = Row::create(&table, pk);
+    row.set_cell(String::from("first_name"), "first".into_cell());
+    row.set_cell(String::from("last_name"), "last".into_cell());
+    row.set_cell(String::from("age"), 69.into_cell());
+    assert!(table.create_row(row.clone()).is_ok());
+
+    let mut update_row = Row::create(&table, pk);
+    update_row.set_cell(String::from("first_name"), "changed".into_cell());
+    update_row.set_cell(String::from("age"), "not a number".into_cell());
+    assert!(table.update_row(update_row).is_err());
+
+    assert_eq!(Some(row), table.find_row(&pk, ColumnSpecification::All));
+    assert!(table.check_invariants().is_consistent());
+}
+
+#[test]
+fn it_describes_tables_and_columns_as_tables() {
+    let table = create_demo_table();
+
+    let tables = information_schema::tables(&[&table]);
+    assert!(tables.to_string().contains("| user       |            4 |         0 |"));
+
+    let columns = information_schema::columns(&[&table]);
+    let output = columns.to_string();
+    assert_eq!(8, output.lines().count());
+    assert!(output.contains("| user       | age         |                4 | INTEGER   | YES         |"));
+}
+
+#[test]
+fn it_describes_constraints_and_indexes_as_tables() {
+    let mut table = Table::create(
+        String::from("account"),
+        vec![
+            ColumnDefinition::create("username", DataType::String, false)
+                .with_constraint(Constraint::MinLength(3))
+                .with_constraint(Constraint::MaxLength(8)),
+            ColumnDefinition::create("age", DataType::Integer, true)
+                .with_constraint(Constraint::Min(0)),
+            ColumnDefinition::create("location", DataType::Point, true),
+        ],
+    );
+    table.enable_ordered_keys();
+    assert!(table.create_bitmap_index("age").is_ok());
+    assert!(table
+        .create_partial_unique_index("username", Predicate::IsNotNull(Expression::column("age")))
+        .is_ok());
+    assert!(table.create_spatial_index("location", 1000.0).is_ok());
+
+    let constraints = information_schema::constraints(&[&table]);
+    assert_eq!(
+        Result::Ok(TableValue::Integer(3)),
+        constraints.aggregate(&Aggregate::Count, None)
+    );
+    let output = constraints.to_string();
+    assert!(output.contains("| account    | username    | MIN LENGTH 3 |"));
+    assert!(output.contains("| account    | age         | MIN 0        |"));
+
+    let indexes = information_schema::indexes(&[&table]);
+    assert_eq!(
+        Result::Ok(TableValue::Integer(4)),
+        indexes.aggregate(&Aggregate::Count, None)
+    );
+    let index_of = |column: &str| {
+        indexes
+            .find_rows(
+                &Predicate::Equals(
+                    Expression::column("column_name"),
+                    Expression::literal(column),
+                ),
+                ColumnSpecification::Some(vec![
+                    String::from("index_type"),
+                    String::from("is_partial"),
+                ]),
+            )
+            .expect("Expected the predicate to be valid.")
+            .iter()
+            .map(|row| {
+                ["index_type", "is_partial"]
+                    .iter()
+                    .map(|identifier| match row.get_cell(identifier) {
+                        Some(TableValue::String(value)) => value.clone(),
+                        _ => String::new(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec!["ORDERED NO"], index_of("ID"));
+    assert_eq!(vec!["UNIQUE YES"], index_of("username"));
+    assert_eq!(vec!["BITMAP NO"], index_of("age"));
+    assert_eq!(vec!["SPATIAL NO"], index_of("location"));
+}
+
+#[test]
+fn it_can_scan_rows_by_primary_key_range() {
+    let mut table = create_demo_table();
+    table.enable_ordered_keys();
+
+    for &key in &[3u128, 1, 5, 2, 4] {
+        let mut row = Row::create(&table, Uuid::from_u128(key));
+        row.set_cell(String::from("first_name"), "first".into_cell());
+        row.set_cell(String::from("last_name"), "last".into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+
+    let rows_of = |keys: &[u128]| {
+        keys.iter()
+            .map(|key| table.find_row(&Uuid::from_u128(*key), ColumnSpecification::All))
+            .collect::<Option<Vec<_>>>()
+            .expect("Expected all rows to exist.")
+    };
+
+    assert_eq!(
+        rows_of(&[2, 3]),
+        table
+            .range(Uuid::from_u128(2)..Uuid::from_u128(4))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(rows_of(&[1, 2]), table.first_rows(2));
+    assert_eq!(rows_of(&[4, 5]), table.last_rows(2));
+}
+
+#[test]
+fn it_keeps_row_ids_stable_when_other_rows_are_removed() {
+    let mut table = create_demo_table();
+    table.enable_history();
+
+    let first_pk = Uuid::new_v4();
+    let second_pk = Uuid::new_v4();
+    for &pk in &[first_pk, second_pk] {
+        let mut row = Row::create(&table, pk);
+        row.set_cell(String::from("first_name"), "first".into_cell());
+        row.set_cell(String::from("last_name"), "last".into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+
+    let first_id = table.row_id_for(&first_pk).expect("Expected a row id.");
+    let second_id = table.row_id_for(&second_pk).expect("Expected a row id.");
+    assert_ne!(first_id, second_id);
+    assert_eq!(Some(first_pk), table.pk_for(first_id));
+
+    // Undoing the last insert removes the second row
+    assert_eq!(Ok(true), table.undo());
+    assert_eq!(None, table.pk_for(second_id));
+    assert_eq!(Some(first_pk), table.pk_for(first_id));
+}
+
+#[test]
+fn it_can_fetch_rows_with_aliased_columns_via_primary_key() {
+    let mut table = create_demo_table();
+
+    let pk = Uuid::new_v4();
+    let mut row = Row::create(&table, pk);
+    row.set_cell(String::from("first_name"), "first".into_cell());
+    row.set_cell(String::from("last_name"), "last".into_cell());
+    row.set_cell(String::from("age"), 69.into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    let fetched_row = table
+        .find_row(
+            &pk,
+            ColumnSpecification::Aliased(vec![(String::from("age"), String::from("user_age"))]),
+        )
+        .expect("Expected a value here.");
+
+    // The aliased row only holds the requested cell, so compared to a full row only the ID is missing
+    let mut expected_row = Row::create(&table, pk);
+    expected_row.set_cell(String::from("user_age"), 69.into_cell());
+    assert_eq!(
+        vec![CellChange {
+            column_identifier: String::from("ID"),
+            old: None,
+            new: Some(TableValue::Uuid(pk)),
+        }],
+        fetched_row.diff(&expected_row)
+    );
+}
+
+#[test]
+fn it_can_fetch_rows_with_computed_columns_via_primary_key() {
+    let mut table = create_demo_table();
+
+    let pk = Uuid::new_v4();
+    let mut row = Row::create(&table, pk);
+    row.set_cell(String::from("first_name"), "first".into_cell());
+    row.set_cell(String::from("last_name"), "last".into_cell());
+    row.set_cell(String::from("age"), 69.into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    let fetched_row = table
+        .try_find_row(
+            &pk,
+            ColumnSpecification::Computed(vec![
+                (
+                    Expression::Concat(vec![
+                        Expression::column("first_name"),
+                        Expression::literal(" "),
+                        Expression::column("last_name"),
+                    ]),
+                    String::from("full_name"),
+                ),
+                (
+                    Expression::column("age").times(Expression::literal(2)),
+                    String::from("double_age"),
+                ),
+            ]),
+        )
+        .expect("Expected the projection to be valid.")
+        .expect("Expected a value here.");
+
+    let mut expected_row = Row::create(&table, pk);
+    expected_row.set_cell(String::from("full_name"), "first last".into_cell());
+    expected_row.set_cell(String::from("double_age"), 138.into_cell());
+    assert_eq!(
+        vec![CellChange {
+            column_identifier: String::from("ID"),
+            old: None,
+            new: Some(TableValue::Uuid(pk)),
+        }],
+        fetched_row.diff(&expected_row)
+    );
+
+    let invalid_projection = ColumnSpecification::Computed(vec![(
+        Expression::column("first_name").plus(Expression::literal(1)),
+        String::from("invalid"),
+    )]);
+    assert_eq!(
+        Err(VirtualTableError::InvalidDataType(
+            String::from("first_name"),
+            DataType::Integer,
+            DataType::String
+        )),
+        table.try_find_row(&pk, invalid_projection)
+    );
+}
+
+#[test]
+fn it_can_use_scalar_functions_in_projections() {
+    let mut table = create_demo_table();
+
+    let pk = Uuid::new_v4();
+    let mut row = Row::create(&table, pk);
+    row.set_cell(String::from("first_name"), "  First ".into_cell());
+    row.set_cell(String::from("last_name"), "Last".into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    let project = |function: ScalarFunction, arguments: Vec<Expression>| {
+        table.try_find_row(
+            &pk,
+            ColumnSpecification::Computed(vec![(
+                Expression::function(function, arguments),
+                String::from("result"),
+            )]),
+        )
+    };
+    let assert_projects_to = |expected: Cell, function: ScalarFunction, arguments: Vec<Expression>| {
+        let row = project(function, arguments)
+            .expect("Expected the projection to be valid.")
+            .expect("Expected a value here.");
+
+        // The projected row only holds the result, so compared to a full row only the ID is missing
+        let mut expected_row = Row::create(&table, pk);
+        expected_row.set_cell(String::from("result"), expected);
+        assert_eq!(1, row.diff(&expected_row).len());
+    };
+
+    assert_projects_to(
+        "FIRST".into_cell(),
+        ScalarFunction::Upper,
+        vec![Expression::function(
+            ScalarFunction::Trim,
+            vec![Expression::column("first_name")],
+        )],
+    );
+    assert_projects_to(
+        8.into_cell(),
+        ScalarFunction::Length,
+        vec![Expression::column("first_name")],
+    );
+    assert_projects_to(
+        "as".into_cell(),
+        ScalarFunction::Substr,
+        vec![
+            Expression::column("last_name"),
+            Expression::literal(2),
+            Expression::literal(2),
+        ],
+    );
+    assert_projects_to(
+        "Last".into_cell(),
+        ScalarFunction::Concat,
+        vec![
+            Expression::Literal(TableValue::Null),
+            Expression::column("last_name"),
+        ],
+    );
+    assert_projects_to(
+        0.into_cell(),
+        ScalarFunction::Coalesce,
+        vec![Expression::column("age"), Expression::literal(0)],
+    );
+    assert_eq!(
+        Err(VirtualTableError::InvalidDataType(
+            String::from("argument 1 of lower"),
+            DataType::String,
+            DataType::Integer
+        )),
+        project(ScalarFunction::Lower, vec![Expression::column("age")])
+    );
+}
+
+#[test]
+fn it_can_call_user_defined_functions_in_projections() {
+    let mut table = create_demo_table();
+    table.register_function("slugify", DataType::String, |arguments| match arguments {
+        [TableValue::String(value)] => Ok(TableValue::String(value.to_lowercase().replace(' ', "-"))),
+        _ => Err(String::from("Expected a single String argument.")),
+    });
+
+    let pk = Uuid::new_v4();
+    let mut row = Row::create(&table, pk);
+    row.set_cell(String::from("first_name"), "Hello World".into_cell());
+    row.set_cell(String::from("last_name"), "last".into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    let project = |expression: Expression| {
+        table.try_find_row(
+            &pk,
+            ColumnSpecification::Computed(vec![(expression, String::from("slug"))]),
+        )
+    };
+
+    let row = project(Expression::call("slugify", vec![Expression::column("first_name")]))
+        .expect("Expected the projection to be valid.")
+        .expect("Expected a value here.");
+    let mut expected_row = Row::create(&table, pk);
+    expected_row.set_cell(String::from("slug"), "hello-world".into_cell());
+    assert_eq!(1, row.diff(&expected_row).len());
+
+    assert_eq!(
+        Err(VirtualTableError::FunctionError(
+            String::from("slugify"),
+            String::from("Expected a single String argument.")
+        )),
+        project(Expression::call("slugify", vec![]))
+    );
+    assert_eq!(
+        Err(VirtualTableError::UnknownFunction(String::from("unknown"))),
+        project(Expression::call("unknown", vec![]))
+    );
+}
+
+#[derive(Debug, Clone)]
+struct EmailAddress(String);
+
+impl CustomType for EmailAddress {
+    const NAME: &'static str = "EMAIL";
+
+    fn parse(raw: &str) -> Result<Self, String> {
+        if !raw.contains('@') {
+            return Err(format!("{} is not an email address.", raw));
+        }
+
+        Ok(EmailAddress(raw.to_lowercase()))
+    }
+
+    fn format(&self) -> String {
+        self.0.clone()
+    }
+
+    fn compare(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+#[test]
+fn it_can_store_values_of_custom_types() {
+    let email_type = DataType::Custom(CustomTypeId::of::<EmailAddress>());
+    let mut table = Table::create(
+        String::from("user"),
+        vec![ColumnDefinition {
+            identifier: String::from("email"),
+            data_type: email_type,
+            is_nullable: false,
+            constraints: Vec::new(),
+            metadata: ColumnMetadata::default(),
+        }],
+    );
+
+    let pk = Uuid::new_v4();
+    let mut row = Row::create(&table, pk);
+    row.set_cell(
+        String::from("email"),
+        Custom(EmailAddress::parse("Foo@Bar.com").unwrap()).into_cell(),
+    );
+    assert!(table.create_row(row.clone()).is_ok());
+    assert_eq!(Some(row), table.find_row(&pk, ColumnSpecification::All));
+
+    let mut invalid_row = Row::create(&table, Uuid::new_v4());
+    invalid_row.set_cell(String::from("email"), "foo@bar.com".into_cell());
+    assert_eq!(
+        Err(vec![VirtualTableError::InvalidDataType(
+            String::from("email"),
+            email_type,
+            DataType::String
+        )]),
+        table.create_row(invalid_row)
+    );
+
+    assert!(CustomTypeId::of::<EmailAddress>().parse("invalid").is_err());
+    assert!(table.to_string().contains("foo@bar.com"));
+}
+
+#[test]
+fn it_can_filter_rows_via_bitmap_indexes() {
+    let mut table = create_demo_table();
+    let mut keys = Vec::new();
+    for &(first_name, age) in &[("Jane", 30), ("John", 30), ("Jane", 40)] {
+        let pk = Uuid::new_v4();
+        let mut row = Row::create(&table, pk);
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        row.set_cell(String::from("age"), (age as i64).into_cell());
+        assert!(table.create_row(row).is_ok());
+        keys.push(pk);
+    }
+
+    let filter = BitmapFilter::And(vec![
+        BitmapFilter::Equals(String::from("first_name"), TableValue::from("Jane")),
+        BitmapFilter::Equals(String::from("age"), TableValue::Integer(30)),
+    ]);
+    assert_eq!(
+        Err(VirtualTableError::MissingIndex(String::from("first_name"))),
+        table.find_rows_by_bitmap(&filter, ColumnSpecification::All)
+    );
+
+    assert!(table.create_bitmap_index("first_name").is_ok());
+    assert!(table.create_bitmap_index("age").is_ok());
+    let rows_for = |table: &Table, keys: &[Uuid]| {
+        keys.iter()
+            .filter_map(|key| table.find_row(key, ColumnSpecification::All))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        Ok(rows_for(&table, &keys[..1])),
+        table.find_rows_by_bitmap(&filter, ColumnSpecification::All)
+    );
+
+    // The indexes follow updates
+    let mut update = Row::create(&table, keys[1]);
+    update.set_cell(String::from("first_name"), "Jane".into_cell());
+    assert!(table.update_row(update).is_ok());
+    assert_eq!(
+        Ok(rows_for(&table, &keys[..2])),
+        table.find_rows_by_bitmap(&filter, ColumnSpecification::All)
+    );
+
+    let any_age = BitmapFilter::Or(vec![
+        BitmapFilter::Equals(String::from("age"), TableValue::Integer(30)),
+        BitmapFilter::Equals(String::from("age"), TableValue::Integer(40)),
+    ]);
+    assert_eq!(
+        Ok(3),
+        table
+            .find_rows_by_bitmap(&any_age, ColumnSpecification::All)
+            .map(|rows| rows.len())
+    );
+}
+
+#[test]
+fn it_answers_lookups_of_unknown_keys_via_the_bloom_filter() {
+    let mut table = create_demo_table();
+    let existing_pk = Uuid::new_v4();
+    let mut row = Row::create(&table, existing_pk);
+    row.set_cell(String::from("first_name"), "Jane".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    table.enable_bloom_filter(100);
+    assert!(table.has_bloom_filter());
+    assert!(table.contains_key(&existing_pk));
+
+    let new_pk = Uuid::new_v4();
+    let mut row = Row::create(&table, new_pk);
+    row.set_cell(String::from("first_name"), "John".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    assert!(table.create_row(row).is_ok());
+    assert!(table.contains_key(&new_pk));
+    assert!(table.find_row(&new_pk, ColumnSpecification::All).is_some());
+
+    let unknown_pk = Uuid::new_v4();
+    assert!(!table.contains_key(&unknown_pk));
+    assert_eq!(None, table.find_row(&unknown_pk, ColumnSpecification::All));
+}
+
+#[test]
+fn it_can_scan_for_null_values() {
+    let mut table = create_demo_table();
+    let mut keys = Vec::new();
+    for age in 0..100i64 {
+        let pk = Uuid::new_v4();
+        let mut row = Row::create(&table, pk);
+        row.set_cell(String::from("first_name"), "Jane".into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        // Cells that aren't set are stored as NULL
+        if age % 3 != 0 {
+            row.set_cell(String::from("age"), age.into_cell());
+        }
+        assert!(table.create_row(row).is_ok());
+        keys.push(pk);
+    }
+
+    assert_eq!(Ok(34), table.null_count("age"));
+    assert_eq!(Ok(0), table.null_count("first_name"));
+
+    // Values keep their position when a NULL value in front of them is replaced
+    let mut update = Row::create(&table, keys[0]);
+    update.set_cell(String::from("age"), 42i64.into_cell());
+    assert!(table.update_row(update).is_ok());
+    assert_eq!(Ok(33), table.null_count("age"));
+    let row = table
+        .find_row(&keys[1], ColumnSpecification::Some(vec![String::from("age")]))
+        .expect("Expected a row.");
+    let mut expected_row = Row::create(&table, keys[1]);
+    expected_row.set_cell(String::from("age"), 1i64.into_cell());
+    assert!(row.diff(&expected_row).is_empty());
+
+    let null_rows = table
+        .find_null_rows("age", ColumnSpecification::All)
+        .expect("Expected rows.");
+    assert_eq!(33, null_rows.len());
+    assert_eq!(
+        table.find_row(&keys[3], ColumnSpecification::All).as_ref(),
+        null_rows.first()
+    );
+    assert_eq!(
+        Ok(67),
+        table
+            .find_non_null_rows("age", ColumnSpecification::All)
+            .map(|rows| rows.len())
+    );
+    assert!(table.check_invariants().is_consistent());
+}
+
+#[test]
+fn it_keeps_values_behind_a_deleted_row() {
+    let mut table = create_demo_table();
+    let mut keys = Vec::new();
+    for age in 0..150i64 {
+        let mut row = Row::create(&table, Uuid::from_u128(age as u128));
+        row.set_cell(String::from("first_name"), "Jane".into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        if age % 3 != 0 {
+            row.set_cell(String::from("age"), age.into_cell());
+        }
+        assert!(table.create_row(row).is_ok());
+        keys.push(Uuid::from_u128(age as u128));
+    }
+
+    // The rows behind the deleted ones move across the words of the null bitmap
+    assert!(table.delete_row(&keys[1]).is_ok());
+    assert!(table.delete_row(&keys[63]).is_ok());
+    for (age, key) in keys
+        .iter()
+        .enumerate()
+        .filter(|(age, _)| ![1, 63].contains(age))
+    {
+        let row = table
+            .find_row(key, ColumnSpecification::Some(vec![String::from("age")]))
+            .expect("Expected a row.");
+        let expected = if age % 3 == 0 {
+            TableValue::Null
+        } else {
+            TableValue::Integer(age as i64)
+        };
+        assert_eq!(Some(&expected), row.get_cell("age"));
+    }
+    assert_eq!(Ok(49), table.null_count("age"));
+    assert!(table.check_invariants().is_consistent());
+}
+
+#[derive(Default)]
+struct RecordingMetrics {
+    finished: Mutex<Vec<(&'static str, usize)>>,
+    failed: Mutex<Vec<&'static str>>,
+    index_lookups: Mutex<Vec<(&'static str, bool)>>,
+}
+
+impl Metrics for RecordingMetrics {
+    fn operation_finished(&self, operation: &'static str, _duration: Duration, rows: usize) {
+        self.finished.lock().unwrap().push((operation, rows));
+    }
+
+    fn operation_failed(&self, operation: &'static str, _duration: Duration) {
+        self.failed.lock().unwrap().push(operation);
+    }
+
+    fn index_lookup(&self, index: &'static str, hit: bool) {
+        self.index_lookups.lock().unwrap().push((index, hit));
+    }
+}
+
+#[test]
+fn it_reports_operations_to_metrics() {
+    let metrics = Arc::new(RecordingMetrics::default());
+    let mut table = create_demo_table();
+    table.set_metrics(metrics.clone());
+    table.enable_bloom_filter(10);
+
+    let pk = Uuid::new_v4();
+    let mut row = Row::create(&table, pk);
+    row.set_cell(String::from("first_name"), "Jane".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    assert!(table.create_row(row.clone()).is_ok());
+    assert!(table.create_row(row).is_err());
+    assert!(table.find_row(&pk, ColumnSpecification::All).is_some());
+
+    assert_eq!(
+        vec![("create_row", 1), ("find_row", 1)],
+        *metrics.finished.lock().unwrap()
+    );
+    assert_eq!(vec!["create_row"], *metrics.failed.lock().unwrap());
+    // The first insert could be rejected by the bloom filter, the other lookups had to check the keys
+    assert_eq!(
+        vec![
+            ("bloom_filter", true),
+            ("bloom_filter", false),
+            ("bloom_filter", false)
+        ],
+        *metrics.index_lookups.lock().unwrap()
+    );
+}
+
+#[test]
+fn it_aborts_cancelled_scans_and_bulk_updates() {
+    let mut table = create_demo_table();
+    let mut keys = Vec::new();
+    for _ in 0..3 {
+        let pk = Uuid::new_v4();
+        let mut row = Row::create(&table, pk);
+        row.set_cell(String::from("first_name"), "Jane".into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        assert!(table.create_row(row).is_ok());
+        keys.push(pk);
+    }
+
+    let token = CancellationToken::create();
+    assert_eq!(
+        Ok(3),
+        table
+            .scan(ColumnSpecification::All, &token)
+            .map(|rows| rows.len())
+    );
+
+    let updates = keys
+        .iter()
+        .map(|pk| {
+            let mut row = Row::create(&table, *pk);
+            row.set_cell(String::from("last_name"), "Smith".into_cell());
+            row
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(Ok(3), table.update_rows(updates.clone(), &token));
+
+    token.clone().cancel();
+    assert!(token.is_cancelled());
+    assert_eq!(
+        Err(VirtualTableError::Cancelled),
+        table.scan(ColumnSpecification::All, &token)
+    );
+    assert_eq!(
+        Err(vec![VirtualTableError::Cancelled]),
+        table.update_rows(updates, &token)
+    );
+
+    let expired = CancellationToken::with_timeout(Duration::from_secs(0));
+    assert_eq!(
+        Err(VirtualTableError::Cancelled),
+        table.scan(ColumnSpecification::All, &expired)
+    );
+}
+
+#[test]
+fn it_only_shows_permitted_rows_to_a_policy_context() {
+    let mut table = create_demo_table();
+    table.set_policy(|context, row| row.get_cell("last_name") == context.attribute("tenant"));
+    let doe = PolicyContext::create("jane").with_attribute("tenant", "Doe");
+    let smith = PolicyContext::create("john").with_attribute("tenant", "Smith");
+
+    let pk = Uuid::new_v4();
+    let mut row = Row::create(&table, pk);
+    row.set_cell(String::from("first_name"), "Jane".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    assert_eq!(
+        Err(vec![VirtualTableError::PolicyViolation(pk)]),
+        table.create_row_as(&smith, row.clone())
+    );
+    assert!(table.create_row_as(&doe, row).is_ok());
+
+    assert_eq!(
+        Some(&TableValue::from("Jane")),
+        table
+            .find_row_as(&doe, &pk, ColumnSpecification::All)
+            .as_ref()
+            .and_then(|row| row.get_cell("first_name"))
+    );
+    assert_eq!(
+        None,
+        table.find_row_as(&smith, &pk, ColumnSpecification::All)
+    );
+    assert_eq!(1, table.rows_as(&doe, ColumnSpecification::All).len());
+    assert!(table.rows_as(&smith, ColumnSpecification::All).is_empty());
+
+    // Rows can't be moved to another tenant
+    let mut update = Row::create(&table, pk);
+    update.set_cell(String::from("last_name"), "Smith".into_cell());
+    assert_eq!(
+        Err(vec![VirtualTableError::UnknownPrimaryKey(pk)]),
+        table.update_row_as(&smith, update.clone())
+    );
+    assert_eq!(
+        Err(vec![VirtualTableError::PolicyViolation(pk)]),
+        table.update_row_as(&doe, update)
+    );
+
+    let janes = Predicate::Equals(
+        Expression::column("first_name"),
+        Expression::literal("Jane"),
+    );
+    assert_eq!(
+        Ok(1),
+        table
+            .find_rows_as(&doe, &janes, ColumnSpecification::All)
+            .map(|rows| rows.len())
+    );
+    assert_eq!(
+        Ok(Vec::new()),
+        table.find_rows_as(&smith, &janes, ColumnSpecification::All)
+    );
+    assert_eq!(
+        Err(vec![VirtualTableError::UnknownPrimaryKey(pk)]),
+        table.delete_row_as(&smith, &pk)
+    );
+    assert!(table.delete_row_as(&doe, &pk).is_ok());
+    assert!(table.rows_as(&doe, ColumnSpecification::All).is_empty());
+}
+
+#[test]
+fn it_masks_values_when_printing_the_table() {
+    let mut table = create_demo_table();
+    let pk = Uuid::new_v4();
+    let mut row = Row::create(&table, pk);
+    row.set_cell(String::from("first_name"), "Jane".into_cell());
+    row.set_cell(String::from("last_name"), "Doe-Smith".into_cell());
+    row.set_cell(String::from("age"), 42i64.into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    assert!(table.set_mask("first_name", Mask::Redact).is_ok());
+    assert!(table.set_mask("last_name", Mask::LastCharacters(5)).is_ok());
+    assert!(table.set_mask("age", Mask::Hash).is_ok());
+    assert_eq!(
+        Err(VirtualTableError::UnknownColumn(String::from("email"))),
+        table.set_mask("email", Mask::Redact)
+    );
+
+    let output = table.to_string();
+    assert!(output.contains("***"));
+    assert!(output.contains("****Smith"));
+    assert!(!output.contains("Jane"));
+    assert!(output.contains(&Mask::Hash.apply(&TableValue::Integer(42))));
+    // Hashes don't depend on the build, so masked output of different builds can be compared
+    assert_eq!(
+        "07ee7e07b4b19223",
+        Mask::Hash.apply(&TableValue::Integer(42))
+    );
+
+    // Programmatic reads aren't affected
+    let row = table.find_row(&pk, ColumnSpecification::All).unwrap();
+    assert_eq!(Some(&TableValue::from("Jane")), row.get_cell("first_name"));
+
+    assert!(table.clear_mask("first_name"));
+    assert!(table.to_string().contains("Jane"));
+}
+
+#[test]
+fn it_enforces_declarative_column_constraints() {
+    let mut table = Table::create(
+        String::from("account"),
+        vec![
+            ColumnDefinition::create("username", DataType::String, false)
+                .with_constraint(Constraint::MinLength(3))
+                .with_constraint(Constraint::MaxLength(8))
+                .with_constraint(Constraint::Charset(Charset::Alphanumeric)),
+            ColumnDefinition::create("age", DataType::Integer, true)
+                .with_constraint(Constraint::Min(0))
+                .with_constraint(Constraint::Max(150)),
+        ],
+    );
+
+    let pk = Uuid::new_v4();
+    let mut row = Row::create(&table, pk);
+    row.set_cell(String::from("username"), "jane".into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    for (username, constraint) in &[
+        ("jo", Constraint::MinLength(3)),
+        ("jane_doe!", Constraint::MaxLength(8)),
+        ("jane doe", Constraint::Charset(Charset::Alphanumeric)),
+    ] {
+        let mut row = Row::create(&table, Uuid::new_v4());
+        row.set_cell(String::from("username"), (*username).into_cell());
+        assert_eq!(
+            Err(vec![VirtualTableError::ConstraintViolation(
+                String::from("username"),
+                constraint.clone()
+            )]),
+            table.create_row(row)
+        );
+    }
+
+    let mut update = Row::create(&table, pk);
+    update.set_cell(String::from("age"), (-1i64).into_cell());
+    assert_eq!(
+        Err(vec![VirtualTableError::ConstraintViolation(
+            String::from("age"),
+            Constraint::Min(0)
+        )]),
+        table.update_row(update)
+    );
+
+    let columns = information_schema::columns(&[&table]).to_string();
+    assert!(columns.contains("MIN LENGTH 3, MAX LENGTH 8, CHARSET ALPHANUMERIC"));
+    assert!(columns.contains("MIN 0, MAX 150"));
+}
+
+#[test]
+fn it_coerces_compatible_values_when_enabled() {
+    let mut table = create_demo_table();
+    let pk = Uuid::new_v4();
+    let mut row = Row::create(&table, pk);
+    row.set_cell(String::from("first_name"), 42i64.into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    row.set_cell(String::from("age"), " 42 ".into_cell());
+    let errs = table.create_row(row.clone()).unwrap_err();
+    assert!(errs.contains(&VirtualTableError::InvalidDataType(
+        String::from("age"),
+        DataType::Integer,
+        DataType::String
+    )));
+
+    table.enable_coercion();
+    assert!(table.is_coercing_values());
+    assert!(table.create_row(row).is_ok());
+    let row = table.find_row(&pk, ColumnSpecification::All).unwrap();
+    assert_eq!(Some(&TableValue::from("42")), row.get_cell("first_name"));
+    assert_eq!(Some(&TableValue::Integer(42)), row.get_cell("age"));
+
+    // Values that can't be converted are still rejected
+    let mut update = Row::create(&table, pk);
+    update.set_cell(String::from("age"), "forty-two".into_cell());
+    assert_eq!(
+        Err(vec![VirtualTableError::InvalidDataType(
+            String::from("age"),
+            DataType::Integer,
+            DataType::String
+        )]),
+        table.update_row(update)
+    );
+}
+
+#[test]
+fn it_applies_three_valued_logic_to_predicates_and_aggregates() {
+    let mut table = create_demo_table();
+    let mut keys = Vec::new();
+    for age in &[Some(30i64), None, Some(40i64)] {
+        let pk = Uuid::new_v4();
+        let mut row = Row::create(&table, pk);
+        row.set_cell(String::from("first_name"), "Jane".into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        if let Some(age) = age {
+            row.set_cell(String::from("age"), (*age).into_cell());
+        }
+        assert!(table.create_row(row).is_ok());
+        keys.push(pk);
+    }
+
+    let rows_for = |indexes: &[usize]| {
+        indexes
+            .iter()
+            .filter_map(|index| table.find_row(&keys[*index], ColumnSpecification::All))
+            .collect::<Vec<_>>()
+    };
+    let older_than_35 =
+        Predicate::GreaterThan(Expression::column("age"), Expression::literal(35i64));
+    assert_eq!(
+        Ok(rows_for(&[2])),
+        table.find_rows(&older_than_35, ColumnSpecification::All)
+    );
+    // The row without an age is neither older nor younger
+    assert_eq!(
+        Ok(rows_for(&[0])),
+        table.find_rows(&older_than_35.clone().negate(), ColumnSpecification::All)
+    );
+    let is_null = Predicate::IsNull(Expression::column("age"));
+    assert_eq!(
+        Ok(rows_for(&[1])),
+        table.find_rows(&is_null, ColumnSpecification::All)
+    );
+    assert_eq!(
+        Ok(rows_for(&[1, 2])),
+        table.find_rows(
+            &Predicate::Or(vec![older_than_35, is_null.clone()]),
+            ColumnSpecification::All
+        )
+    );
+    assert_eq!(Truth::Unknown, Truth::Unknown.and(Truth::True));
+    assert_eq!(Truth::True, Truth::Unknown.or(Truth::True));
+
+    let age = Expression::column("age");
+    assert_eq!(Ok(TableValue::Integer(3)), table.aggregate(&Aggregate::Count, None));
+    assert_eq!(
+        Ok(TableValue::Integer(2)),
+        table.aggregate(&Aggregate::CountValues(age.clone()), None)
+    );
+    assert_eq!(
+        Ok(TableValue::Integer(70)),
+        table.aggregate(&Aggregate::Sum(age.clone()), None)
+    );
+    assert_eq!(
+        Ok(TableValue::Integer(30)),
+        table.aggregate(&Aggregate::Min(age.clone()), None)
+    );
+    assert_eq!(
+        Ok(TableValue::Integer(40)),
+        table.aggregate(&Aggregate::Max(age.clone()), None)
+    );
+    assert_eq!(
+        Ok(TableValue::Null),
+        table.aggregate(&Aggregate::Sum(age), Some(&is_null))
+    );
+}
+
+#[test]
+fn it_compares_and_sorts_strings_with_the_column_collation() {
+    let mut table = create_demo_table();
+    let mut keys = Vec::new();
+    for first_name in &["Bob", "alice", "carl"] {
+        let pk = Uuid::new_v4();
+        let mut row = Row::create(&table, pk);
+        row.set_cell(String::from("first_name"), (*first_name).into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        assert!(table.create_row(row).is_ok());
+        keys.push(pk);
+    }
+    let rows_for = |table: &Table, indexes: &[usize]| {
+        indexes
+            .iter()
+            .filter_map(|index| table.find_row(&keys[*index], ColumnSpecification::All))
+            .collect::<Vec<_>>()
+    };
+
+    let by_first_name = [(Expression::column("first_name"), SortOrder::Ascending)];
+    let is_alice = Predicate::Equals(Expression::column("first_name"), Expression::literal("ALICE"));
+    assert_eq!(
+        Ok(rows_for(&table, &[0, 1, 2])),
+        table.order_by(&by_first_name, ColumnSpecification::All)
+    );
+    assert_eq!(Ok(Vec::new()), table.find_rows(&is_alice, ColumnSpecification::All));
+
+    assert!(table
+        .set_collation("first_name", Collation::CaseInsensitive)
+        .is_ok());
+    assert_eq!(
+        Ok(rows_for(&table, &[1, 0, 2])),
+        table.order_by(&by_first_name, ColumnSpecification::All)
+    );
+    assert_eq!(
+        Ok(rows_for(&table, &[1])),
+        table.find_rows(&is_alice, ColumnSpecification::All)
+    );
+    assert_eq!(
+        Ok(TableValue::from("carl")),
+        table.aggregate(&Aggregate::Max(Expression::column("first_name")), None)
+    );
+
+    assert_eq!(
+        Err(VirtualTableError::InvalidDataType(
+            String::from("age"),
+            DataType::String,
+            DataType::Integer
+        )),
+        table.set_collation("age", Collation::CaseInsensitive)
+    );
+}
+
+#[test]
+fn it_normalizes_strings_when_enabled() {
+    let mut table = create_demo_table();
+    table.enable_normalization(NormalizationForm::Nfc);
+    assert_eq!(Some(NormalizationForm::Nfc), table.normalization());
+
+    let pk = Uuid::new_v4();
+    let mut row = Row::create(&table, pk);
+    // "e" followed by a combining acute accent
+    row.set_cell(String::from("first_name"), "Rene\u{301}".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    let row = table.find_row(&pk, ColumnSpecification::All).unwrap();
+    assert_eq!(Some(&TableValue::from("Ren\u{e9}")), row.get_cell("first_name"));
+    let is_rene = Predicate::Equals(Expression::column("first_name"), Expression::literal("René"));
+    assert_eq!(
+        Ok(1),
+        table
+            .find_rows(&is_rene, ColumnSpecification::All)
+            .map(|rows| rows.len())
+    );
+
+    assert_eq!("fi", NormalizationForm::Nfkc.normalize("\u{fb01}"));
+}
+
+#[test]
+fn it_enforces_unique_indexes_with_the_column_collation() {
+    let mut table = create_demo_table();
+    assert!(table
+        .set_collation("first_name", Collation::CaseInsensitive)
+        .is_ok());
+    assert!(table.create_unique_index("first_name").is_ok());
+    assert!(table.create_bitmap_index("first_name").is_ok());
+
+    let create = |table: &mut Table, pk: Uuid, first_name: &str| {
+        let mut row = Row::create(table, pk);
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        table.create_row(row)
+    };
+    let pk = Uuid::new_v4();
+    assert!(create(&mut table, pk, "Foo@Bar.com").is_ok());
+    assert_eq!(
+        Err(vec![VirtualTableError::DuplicateValue(
+            String::from("first_name"),
+            TableValue::from("foo@bar.com")
+        )]),
+        create(&mut table, Uuid::new_v4(), "foo@bar.com")
+    );
+
+    // Writing the same value to the row that already holds it is fine
+    let mut row = Row::create(&table, pk);
+    row.set_cell(String::from("first_name"), "FOO@bar.com".into_cell());
+    assert!(table.update_row(row).is_ok());
+
+    let filter = BitmapFilter::Equals(String::from("first_name"), TableValue::from("foo@BAR.com"));
+    assert_eq!(
+        Ok(vec![table.find_row(&pk, ColumnSpecification::All).unwrap()]),
+        table.find_rows_by_bitmap(&filter, ColumnSpecification::All)
+    );
+
+    // Going back to binary comparisons keeps the index and its values intact
+    assert!(table.set_collation("first_name", Collation::Binary).is_ok());
+    assert!(create(&mut table, Uuid::new_v4(), "foo@bar.com").is_ok());
+    assert_eq!(
+        Err(VirtualTableError::DuplicateValue(
+            String::from("first_name"),
+            TableValue::from("foo@bar.com")
+        )),
+        table.set_collation("first_name", Collation::CaseInsensitive)
+    );
+    assert_eq!(Collation::Binary, table.collation_of("first_name"));
+}
+
+#[test]
+fn it_generates_keys_with_the_configured_strategy() {
+    let mut table = Table::create_with_key_generator(
+        String::from("users"),
+        vec![ColumnDefinition::create("name", DataType::String, true)],
+        KeyGenerator::Sequence,
+    );
+    assert_eq!(KeyGenerator::Sequence, table.key_generator());
+    let first = table.generate_key();
+    assert_eq!(Uuid::from_u128(1), first);
+    assert_eq!(Uuid::from_u128(2), table.generate_key());
+    let row = Row::create(&table, first);
+    assert!(table.create_row(row).is_ok());
+
+    for key_generator in [KeyGenerator::UuidV7, KeyGenerator::Ulid] {
+        let mut table =
+            Table::create_with_key_generator(String::from("events"), Vec::new(), key_generator);
+        let first = table.generate_key();
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(first < table.generate_key());
+    }
+
+    let mut table = create_demo_table();
+    assert_eq!(KeyGenerator::UuidV4, table.key_generator());
+    assert_eq!(4, table.generate_key().get_version_num());
+    assert_eq!(
+        "00000000000000000000000001",
+        key_generator::to_ulid_string(&Uuid::from_u128(1))
+    );
+}
+
+#[test]
+fn it_stores_and_aggregates_wide_and_unsigned_integers() {
+    let mut table = Table::create(
+        String::from("counters"),
+        vec![
+            ColumnDefinition::create("total", DataType::BigInt, false),
+            ColumnDefinition::create("hits", DataType::UnsignedInteger, false),
+        ],
+    );
+    for (total, hits) in [(i128::from(i64::MAX) + 1, u64::MAX), (1, 1)] {
+        let mut row = Row::create(&table, Uuid::new_v4());
+        row.set_cell(String::from("total"), total.into_cell());
+        row.set_cell(String::from("hits"), hits.into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+
+    assert_eq!(
+        Ok(TableValue::BigInt(i128::from(i64::MAX) + 2)),
+        table.aggregate(&Aggregate::Sum(Expression::column("total")), None)
+    );
+    assert!(matches!(
+        table.aggregate(&Aggregate::Sum(Expression::column("hits")), None),
+        Err(VirtualTableError::ArithmeticError(_))
+    ));
+    assert_eq!(
+        Ok(TableValue::UnsignedInteger(u64::MAX)),
+        table.aggregate(&Aggregate::Max(Expression::column("hits")), None)
+    );
+
+    // Values of different integer types compare by value, but don't mix in arithmetic
+    let more_hits_than_total =
+        Predicate::GreaterThan(Expression::column("hits"), Expression::column("total"));
+    assert_eq!(
+        Ok(1),
+        table
+            .find_rows(&more_hits_than_total, ColumnSpecification::All)
+            .map(|rows| rows.len())
+    );
+    let below_zero =
+        Expression::column("hits").minus(Expression::Literal(TableValue::UnsignedInteger(2)));
+    assert!(matches!(
+        below_zero.data_type(&table),
+        Ok(DataType::UnsignedInteger)
+    ));
+    assert!(matches!(
+        below_zero.evaluate(&table, 1),
+        Err(VirtualTableError::ArithmeticError(_))
+    ));
+    let mixed = Expression::column("hits").plus(Expression::column("total"));
+    assert!(mixed.data_type(&table).is_err());
+
+    assert!(Cell::try_integer(-1, DataType::UnsignedInteger).is_err());
+    assert!(Cell::try_integer(u64::MAX, DataType::Integer).is_err());
+    assert_eq!(
+        Ok(u64::MAX.into_cell()),
+        Cell::try_integer(u64::MAX, DataType::UnsignedInteger)
+    );
+}
+
+#[test]
+fn it_formats_compares_and_adds_durations() {
+    let mut table = Table::create(
+        String::from("tasks"),
+        vec![
+            ColumnDefinition::create("estimate", DataType::Duration, false),
+            ColumnDefinition::create("overrun", DataType::Duration, false),
+        ],
+    );
+    table.enable_coercion();
+    let pk = Uuid::new_v4();
+    let mut row = Row::create(&table, pk);
+    row.set_cell(
+        String::from("estimate"),
+        Duration::from_secs(2 * 3600 + 15 * 60).into_cell(),
+    );
+    row.set_cell(String::from("overrun"), "1h 50m".into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    let row = table.find_row(&pk, ColumnSpecification::All).unwrap();
+    assert_eq!(
+        Some(&TableValue::Duration(Duration::from_secs(6600))),
+        row.get_cell("overrun")
+    );
+    assert_eq!("2h 15m", String::from(row.get_cell("estimate").unwrap()));
+
+    let total = Expression::column("estimate").plus(Expression::column("overrun"));
+    assert_eq!(Ok(DataType::Duration), total.data_type(&table));
+    assert_eq!(
+        Ok(TableValue::Duration(Duration::from_secs(4 * 3600 + 5 * 60))),
+        total.evaluate(&table, 0)
+    );
+    let negative = Expression::column("overrun").minus(Expression::column("estimate"));
+    assert!(matches!(
+        negative.evaluate(&table, 0),
+        Err(VirtualTableError::ArithmeticError(_))
+    ));
+    let squared = Expression::column("overrun").times(Expression::column("estimate"));
+    assert!(squared.data_type(&table).is_err());
+
+    let overran_estimate = Predicate::LessThan(
+        Expression::column("estimate"),
+        Expression::column("overrun"),
+    );
+    assert_eq!(Ok(Truth::False), overran_estimate.evaluate(&table, 0));
+
+    assert_eq!(
+        "1d 1s 500ms",
+        duration::format_duration(Duration::from_millis(86_401_500))
+    );
+    assert_eq!(
+        Some(Duration::from_millis(86_401_500)),
+        duration::parse_duration("1d 1s 500ms")
+    );
+    assert_eq!(None, duration::parse_duration("2 weeks"));
+}
+
+#[test]
+fn it_stores_timestamps_in_utc_and_displays_them_in_the_column_time_zone() {
+    let mut table = Table::create(
+        String::from("meetings"),
+        vec![
+            ColumnDefinition::create("starts_at", DataType::Timestamp, false),
+            ColumnDefinition::create("length", DataType::Duration, false),
+        ],
+    );
+    table.enable_coercion();
+    let pk = Uuid::new_v4();
+    let mut row = Row::create(&table, pk);
+    row.set_cell(
+        String::from("starts_at"),
+        "2024-02-29T23:30:00Z".into_cell(),
+    );
+    row.set_cell(String::from("length"), "1h 15m".into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    let starts_at = Timestamp::parse("2024-03-01T01:30:00+02:00").unwrap();
+    let row = table.find_row(&pk, ColumnSpecification::All).unwrap();
+    assert_eq!(
+        Some(&TableValue::Timestamp(starts_at)),
+        row.get_cell("starts_at")
+    );
+    assert_eq!(
+        Some(starts_at),
+        Timestamp::from_local(
+            LocalDateTime {
+                year: 2024,
+                month: 2,
+                day: 29,
+                hour: 23,
+                minute: 30,
+                second: 0,
+                microsecond: 0,
+            },
+            TimeZone::Utc,
+        )
+    );
+
+    let date = Expression::function(ScalarFunction::Date, vec![Expression::column("starts_at")]);
+    assert_eq!(Ok(TableValue::from("2024-02-29")), date.evaluate(&table, 0));
+    assert!(table.set_time_zone("starts_at", TimeZone::hours(2)).is_ok());
+    assert_eq!(Ok(TableValue::from("2024-03-01")), date.evaluate(&table, 0));
+    assert!(format!("{}", table).contains("2024-03-01T01:30:00+02:00"));
+    assert_eq!(
+        "2024-03-01T01:30:00+02:00",
+        starts_at.format(table.time_zone_of("starts_at"))
+    );
+
+    let ends_at = Expression::column("starts_at").plus(Expression::column("length"));
+    assert_eq!(Ok(DataType::Timestamp), ends_at.data_type(&table));
+    let ends_at = ends_at.evaluate(&table, 0).unwrap();
+    assert_eq!("2024-03-01T00:45:00Z", String::from(&ends_at));
+    let length = Expression::literal(ends_at).minus(Expression::column("starts_at"));
+    assert_eq!(Ok(DataType::Duration), length.data_type(&table));
+    assert_eq!(
+        Ok(TableValue::Duration(Duration::from_secs(75 * 60))),
+        length.evaluate(&table, 0)
+    );
+
+    assert_eq!(
+        Err(VirtualTableError::InvalidDataType(
+            String::from("length"),
+            DataType::Timestamp,
+            DataType::Duration
+        )),
+        table.set_time_zone("length", TimeZone::Utc)
+    );
+    assert_eq!(None, Timestamp::parse("2023-02-29T00:00:00Z"));
+    assert_eq!(
+        Some(Timestamp::from_unix_micros(-1)),
+        Timestamp::parse("1969-12-31T23:59:59.999999Z")
+    );
+}
+
+#[test]
+fn it_rejects_timestamps_it_cannot_represent() {
+    assert_eq!(None, Timestamp::parse("x€€x"));
+    assert_eq!(None, Timestamp::parse("2024-03-01T01:30:00+99:99"));
+    assert_eq!(None, Timestamp::parse("2024-03-01T01:30:00+02:60"));
+    assert_eq!(None, Timestamp::parse("2024-03-01T01:30:00+-2:00"));
+    assert_eq!(
+        None,
+        Timestamp::from_local(
+            LocalDateTime {
+                year: i64::MAX,
+                month: 1,
+                day: 1,
+                hour: 0,
+                minute: 0,
+                second: 0,
+                microsecond: 0,
+            },
+            TimeZone::Utc,
+        )
+    );
+
+    let latest = Timestamp::from_unix_micros(i64::MAX);
+    assert_eq!(
+        "294247-01-10T06:00:54.775807+02:00",
+        latest.format(TimeZone::hours(2))
+    );
+    assert_eq!(
+        Some(latest),
+        Timestamp::parse(&latest.format(TimeZone::Utc))
+    );
+
+    let mut table = Table::create(
+        String::from("meetings"),
+        vec![ColumnDefinition::create(
+            "starts_at",
+            DataType::Timestamp,
+            false,
+        )],
+    );
+    table.enable_coercion();
+    let mut row = Row::create(&table, Uuid::new_v4());
+    row.set_cell(String::from("starts_at"), "x€€x".into_cell());
+    assert!(table.create_row(row).is_err());
+}
+
+#[test]
+fn it_keeps_currencies_apart_when_aggregating_money() {
+    let mut table = Table::create(
+        String::from("orders"),
+        vec![ColumnDefinition::create("total", DataType::Money, false)],
+    );
+    table.enable_coercion();
+    let insert = |table: &mut Table, total: &str| {
+        let mut row = Row::create(table, Uuid::new_v4());
+        row.set_cell(String::from("total"), total.into_cell());
+        table.create_row(row)
+    };
+    assert!(insert(&mut table, "12.50 EUR").is_ok());
+    assert!(insert(&mut table, "0.75 EUR").is_ok());
+
+    let euro = Currency::create("EUR").unwrap();
+    let sum = Aggregate::Sum(Expression::column("total"));
+    assert_eq!(
+        Ok(TableValue::Money(Money::create(1325, euro))),
+        table.aggregate(&sum, None)
+    );
+    assert_eq!(
+        Ok(TableValue::Money(Money::create(75, euro))),
+        table.aggregate(&Aggregate::Min(Expression::column("total")), None)
+    );
+
+    assert!(insert(&mut table, "1500 JPY").is_ok());
+    let yen = Currency::create("JPY").unwrap();
+    assert_eq!(
+        Err(VirtualTableError::CurrencyMismatch(euro, yen)),
+        table.aggregate(&sum, None)
+    );
+    assert!(table
+        .aggregate(&Aggregate::Max(Expression::column("total")), None)
+        .is_err());
+
+    // The number of decimal places depends on the currency
+    assert_eq!("1500 JPY", Money::create(1500, yen).to_string());
+    assert_eq!("-0.05 EUR", Money::create(-5, euro).to_string());
+    assert_eq!(3, Currency::create("KWD").unwrap().decimal_places());
+    assert_eq!(None, Money::parse("12.5 JPY"));
+    assert_eq!(None, Currency::create("eur"));
+
+    // Signs are only allowed in front of the amount
+    assert_eq!(Some(Money::create(-1205, euro)), Money::parse("-12.05 EUR"));
+    for value in ["--5 EUR", "12.-5 EUR", "+5 EUR", "12. EUR", ".50 EUR"] {
+        assert_eq!(None, Money::parse(value));
+    }
+}
+
+#[test]
+fn it_finds_points_within_a_radius() {
+    let mut table = Table::create(
+        String::from("cafes"),
+        vec![
+            ColumnDefinition::create("name", DataType::String, false),
+            ColumnDefinition::create("location", DataType::Point, true),
+        ],
+    );
+    let berlin = Point::create(52.520_008, 13.404_954).unwrap();
+    let locations = [
+        ("Mitte", Point::create(52.524, 13.41)),
+        ("Potsdam", Point::create(52.390_5, 13.064_5)),
+        ("Fiji", Point::create(-17.7, 179.99)),
+        ("Across the antimeridian", Point::create(-17.7, -179.99)),
+    ];
+    let mut keys = Vec::new();
+    for (name, location) in locations.iter() {
+        let key = Uuid::new_v4();
+        let mut row = Row::create(&table, key);
+        row.set_cell(String::from("name"), name.into_cell());
+        row.set_cell(String::from("location"), location.unwrap().into_cell());
+        assert!(table.create_row(row).is_ok());
+        keys.push(key);
+    }
+    let mut row = Row::create(&table, Uuid::new_v4());
+    row.set_cell(String::from("name"), "Nowhere".into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    let rows_for = |table: &Table, indexes: &[usize]| {
+        indexes
+            .iter()
+            .map(|index| {
+                table
+                    .find_row(&keys[*index], ColumnSpecification::All)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>()
+    };
+    let near_berlin = Predicate::within_radius(Expression::column("location"), berlin, 5_000);
+    assert_eq!(
+        Ok(rows_for(&table, &[0])),
+        table.find_rows(&near_berlin, ColumnSpecification::All)
+    );
+
+    assert!(table.create_spatial_index("location", 10_000.0).is_ok());
+    let fiji = locations[2].1.unwrap();
+    for _ in 0..2 {
+        assert_eq!(
+            Ok(rows_for(&table, &[0, 1])),
+            table.find_rows_within_radius("location", &berlin, 30_000.0, ColumnSpecification::All)
+        );
+        assert_eq!(
+            Ok(rows_for(&table, &[2, 3])),
+            table.find_rows_within_radius("location", &fiji, 5_000.0, ColumnSpecification::All)
+        );
+        // Boxes with more cells than are populated, also around the pole and across the antimeridian
+        assert_eq!(
+            Ok(rows_for(&table, &[0, 1])),
+            table.find_rows_within_radius(
+                "location",
+                &berlin,
+                1_000_000.0,
+                ColumnSpecification::All
+            )
+        );
+        assert_eq!(
+            Ok(rows_for(&table, &[2, 3])),
+            table.find_rows_within_radius("location", &fiji, 500_000.0, ColumnSpecification::All)
+        );
+        let north_pole = Point::create(90.0, 0.0).unwrap();
+        assert_eq!(
+            Ok(Vec::new()),
+            table.find_rows_within_radius(
+                "location",
+                &north_pole,
+                1_000_000.0,
+                ColumnSpecification::All
+            )
+        );
+        // Without the index the same rows are found by scanning
+        table.drop_spatial_index("location");
+    }
+    assert!(!table.has_spatial_index("location"));
+
+    let potsdam = locations[1].1.unwrap();
+    assert!((berlin.distance_to(&potsdam) - 27_195.0).abs() < 1.0);
+    assert_eq!(Some(berlin), Point::parse("52.520008, 13.404954"));
+    assert_eq!(None, Point::create(91.0, 0.0));
+    assert!(table.create_spatial_index("name", 1_000.0).is_err());
+    assert!(matches!(
+        table.create_spatial_index("location", 0.0),
+        Err(VirtualTableError::InvalidArgument(_))
+    ));
+}
+
+#[test]
+fn it_exposes_the_key_and_cells_of_found_rows() {
+    let mut table = create_demo_table();
+    let pk = Uuid::new_v4();
+    let mut row = Row::create(&table, pk);
+    row.set_cell(String::from("first_name"), "Jane".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    assert_eq!(pk, row.primary_key());
+    // Cells that were never set aren't part of the row yet
+    assert_eq!(3, row.cells().count());
+    assert!(table.create_row(row).is_ok());
+
+    let row = table
+        .find_row(
+            &pk,
+            ColumnSpecification::Some(vec![String::from("first_name")]),
+        )
+        .unwrap();
+    assert_eq!(pk, row.primary_key());
+    assert_eq!(Some(&TableValue::from("Jane")), row.get_cell("first_name"));
+    let mut cells = row.cells().collect::<Vec<_>>();
+    cells.sort_by_key(|(identifier, _)| *identifier);
+    assert_eq!(
+        vec![
+            ("ID", &TableValue::Uuid(pk)),
+            ("first_name", &TableValue::from("Jane")),
+        ],
+        cells
+    );
+}
+
+#[test]
+fn it_validates_cells_when_they_are_set() {
+    let table = create_demo_table();
+    let mut row = Row::create(&table, Uuid::new_v4());
+
+    assert_eq!(
+        Ok(()),
+        row.try_set_cell(&table, String::from("first_name"), "Jane".into_cell())
+    );
+    assert_eq!(
+        Err(VirtualTableError::UnknownColumn(String::from("nickname"))),
+        row.try_set_cell(&table, String::from("nickname"), "JD".into_cell())
+    );
+    assert_eq!(
+        Err(VirtualTableError::InvalidDataType(
+            String::from("age"),
+            DataType::Integer,
+            DataType::String
+        )),
+        row.try_set_cell(&table, String::from("age"), "42".into_cell())
+    );
+    assert_eq!(Some(&TableValue::from("Jane")), row.get_cell("first_name"));
+    assert_eq!(None, row.get_cell("age"));
+}
+
+#[derive(Debug, PartialEq)]
+struct User {
+    id: Uuid,
+    name: String,
+    age: Option<i64>,
+}
+
+impl TableRecord for User {
+    fn columns() -> Vec<ColumnDefinition> {
+        vec![
+            ColumnDefinition::create("name", DataType::String, false),
+            ColumnDefinition::create("age", DataType::Integer, true),
+        ]
+    }
+
+    fn primary_key(&self) -> Uuid {
+        self.id
+    }
+
+    fn write_cells(&self, row: &mut Row) {
+        row.set_cell(String::from("name"), self.name.clone().into_cell());
+        if let Some(age) = self.age {
+            row.set_cell(String::from("age"), age.into_cell());
+        }
+    }
+
+    fn from_row(row: &Row) -> Option<Self> {
+        let name = match row.get_cell("name")? {
+            TableValue::String(name) => name.clone(),
+            _ => return None,
+        };
+        let age = match row.get_cell("age")? {
+            TableValue::Integer(age) => Some(*age),
+            _ => None,
+        };
+
+        Some(User {
+            id: row.primary_key(),
+            name,
+            age,
+        })
+    }
+}
+
+#[test]
+fn it_stores_records_in_a_typed_table() {
+    let mut users = TypedTable::<User>::create("users");
+    let jane = User {
+        id: Uuid::new_v4(),
+        name: String::from("Jane"),
+        age: Some(34),
+    };
+    let john = User {
+        id: Uuid::new_v4(),
+        name: String::from("John"),
+        age: None,
+    };
+    assert!(users.insert(&jane).is_ok());
+    assert!(users.insert(&john).is_ok());
+    assert_eq!(Some(&john), users.find(&john.id).as_ref());
+
+    let john = User {
+        age: Some(40),
+        ..john
+    };
+    assert!(users.update(&john).is_ok());
+    assert_eq!(
+        Ok(vec![john, jane]),
+        users.order_by(&[(Expression::column("age"), SortOrder::Descending)])
+    );
+
+    let minors = Predicate::LessThan(Expression::column("age"), Expression::literal(18i64));
+    assert_eq!(Ok(Vec::new()), users.filter(&minors));
+    assert_eq!(None, users.find(&Uuid::new_v4()));
+    assert_eq!(
+        Ok(TableValue::Integer(2)),
+        users.table().aggregate(&Aggregate::Count, None)
+    );
+}
+
+#[test]
+fn it_summarizes_single_columns() {
+    let mut table = create_demo_table();
+    for (first_name, age) in [("Jane", Some(30i64)), ("John", None), ("Max", Some(45))] {
+        let mut row = Row::create(&table, Uuid::new_v4());
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        if let Some(age) = age {
+            row.set_cell(String::from("age"), age.into_cell());
+        }
+        assert!(table.create_row(row).is_ok());
+    }
+
+    assert_eq!(Ok(TableValue::Integer(30)), table.column_min("age"));
+    assert_eq!(Ok(TableValue::Integer(45)), table.column_max("age"));
+    assert_eq!(Ok(TableValue::Integer(75)), table.column_sum("age"));
+    assert_eq!(Ok(Some(37.5)), table.column_mean("age"));
+    assert_eq!(Ok(TableValue::from("Max")), table.column_max("first_name"));
+
+    assert_eq!(
+        Err(VirtualTableError::InvalidDataType(
+            String::from("first_name"),
+            DataType::Integer,
+            DataType::String
+        )),
+        table.column_mean("first_name")
+    );
+    assert_eq!(
+        Err(VirtualTableError::UnknownColumn(String::from("height"))),
+        table.column_sum("height")
+    );
+    assert_eq!(Ok(None), create_demo_table().column_mean("age"));
+}
+
+#[test]
+fn it_counts_values_and_builds_histograms() {
+    let mut table = create_demo_table();
+    let people = [
+        ("Jane", Some(20i64)),
+        ("John", Some(25)),
+        ("Max", None),
+        ("John", Some(38)),
+        ("Anna", Some(40)),
+        ("John", Some(30)),
+        ("Anna", Some(21)),
+    ];
+    for (first_name, age) in people.iter() {
+        let mut row = Row::create(&table, Uuid::new_v4());
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        if let Some(age) = age {
+            row.set_cell(String::from("age"), age.into_cell());
+        }
+        assert!(table.create_row(row).is_ok());
+    }
+
+    assert_eq!(
+        Ok(vec![
+            (TableValue::from("John"), 3),
+            (TableValue::from("Anna"), 2),
+            (TableValue::from("Jane"), 1),
+            (TableValue::from("Max"), 1),
+        ]),
+        table.value_counts("first_name")
+    );
+    assert_eq!(
+        Ok(vec![
+            HistogramBin {
+                start: 20.0,
+                end: 30.0,
+                count: 3,
+            },
+            HistogramBin {
+                start: 30.0,
+                end: 40.0,
+                count: 3,
+            },
+        ]),
+        table.histogram("age", 2)
+    );
+    assert!(matches!(
+        table.histogram("age", 0),
+        Err(VirtualTableError::InvalidArgument(_))
+    ));
+    assert!(table.histogram("first_name", 2).is_err());
+    assert_eq!(Ok(Vec::new()), create_demo_table().histogram("age", 4));
+}
+
+#[test]
+fn it_profiles_every_column() {
+    let mut table = create_demo_table();
+    for (first_name, age) in [("Jane", Some(20i64)), ("John", None), ("Jane", Some(41))].iter() {
+        let mut row = Row::create(&table, Uuid::new_v4());
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        if let Some(age) = age {
+            row.set_cell(String::from("age"), age.into_cell());
+        }
+        assert!(table.create_row(row).is_ok());
+    }
+
+    let profile = table.profile();
+    let profile_of = |column: &str| {
+        let is_column = Predicate::Equals(
+            Expression::column("column_name"),
+            Expression::literal(column),
+        );
+        profile
+            .find_rows(&is_column, ColumnSpecification::All)
+            .unwrap()
+            .remove(0)
+    };
+
+    let first_name = profile_of("first_name");
+    assert_eq!(
+        Some(&TableValue::from("STRING")),
+        first_name.get_cell("data_type")
+    );
+    assert_eq!(
+        Some(&TableValue::Integer(2)),
+        first_name.get_cell("distinct_count")
+    );
+    assert_eq!(
+        Some(&TableValue::from("Jane (2), John (1)")),
+        first_name.get_cell("top_values")
+    );
+    assert_eq!(
+        Some(&TableValue::from("4.0")),
+        first_name.get_cell("average_length")
+    );
+
+    let age = profile_of("age");
+    assert_eq!(
+        Some(&TableValue::from("33.3%")),
+        age.get_cell("null_percentage")
+    );
+    assert_eq!(Some(&TableValue::from("20")), age.get_cell("min"));
+    assert_eq!(Some(&TableValue::from("41")), age.get_cell("max"));
+    assert_eq!(Some(&TableValue::Null), age.get_cell("average_length"));
+
+    assert!(format!("{}", profile).contains("null_percentage"));
+}
+
+#[test]
+fn it_renders_delimited_text() {
+    let mut table = create_demo_table();
+    let rows: Vec<(&str, &str, Option<i64>)> = vec![
+        ("John", "Doe, Jr.", Some(42i64)),
+        ("Jane", "\"Tab\"\tDoe", None),
+    ];
+    for (index, (first_name, last_name, age)) in rows.into_iter().enumerate() {
+        let mut row = Row::create(&table, Uuid::from_u128(index as u128 + 1));
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), last_name.into_cell());
+        if let Some(age) = age {
+            row.set_cell(String::from("age"), age.into_cell());
+        }
+        assert!(table.create_row(row).is_ok());
+    }
+
+    let csv = "\
+ID,first_name,last_name,age
+00000000-0000-0000-0000-000000000001,John,\"Doe, Jr.\",42
+00000000-0000-0000-0000-000000000002,Jane,\"\"\"Tab\"\"\tDoe\",
+";
+    assert_eq!(csv, table.to_string_with(FormatStyle::Csv));
+
+    let tsv = "\
+ID\tfirst_name\tlast_name\tage
+00000000-0000-0000-0000-000000000001\tJohn\tDoe, Jr.\t42
+00000000-0000-0000-0000-000000000002\tJane\t\"Tab\"\\tDoe\t
+";
+    assert_eq!(tsv, table.to_string_with(FormatStyle::Tsv));
+    assert_eq!(
+        table.to_string(),
+        table.to_string_with(FormatStyle::default())
+    );
+}
+
+#[test]
+fn it_renders_latex() {
+    let mut table = create_demo_table();
+    let mut row = Row::create(&table, Uuid::from_u128(1));
+    row.set_cell(String::from("first_name"), "John".into_cell());
+    row.set_cell(String::from("last_name"), "Doe & 100% Sons_".into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    let expected = "\
+\\begin{tabular}{cllr}
+\\hline
+ID & first\\_name & last\\_name & age \\\\
+\\hline
+00000000-0000-0000-0000-000000000001 & John & Doe \\& 100\\% Sons\\_ &  \\\\
+\\hline
+\\end{tabular}
+";
+    assert_eq!(expected, table.to_latex());
+
+    table.set_type_alignment(DataType::String, Alignment::Center);
+    assert_eq!(Alignment::Center, table.alignment_of("first_name"));
+    assert_eq!(Alignment::Right, table.alignment_of("age"));
+    assert!(table.to_latex().starts_with("\\begin{tabular}{cccr}"));
+}
+
+#[test]
+fn it_aligns_columns_by_data_type_unless_overridden() {
+    let mut table = create_demo_table();
+    let mut row = Row::create(&table, Uuid::new_v4());
+    row.set_cell(String::from("first_name"), "Jane".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    row.set_cell(String::from("age"), 7.into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    assert_eq!(Alignment::Center, table.alignment_of("ID"));
+    assert_eq!(Alignment::Left, table.alignment_of("first_name"));
+    assert_eq!(Alignment::Right, table.alignment_of("age"));
+    assert!(table
+        .to_string()
+        .contains("| Jane       | Doe       |   7 |"));
+
+    assert!(table.set_column_alignment("age", Alignment::Left).is_ok());
+    assert!(table
+        .to_string()
+        .contains("| Jane       | Doe       | 7   |"));
+    assert!(table
+        .set_column_alignment("unknown", Alignment::Left)
+        .is_err());
+
+    assert!(table.clear_column_alignment("age"));
+    assert_eq!(Alignment::Right, table.alignment_of("age"));
+}
+
+#[test]
+fn it_renders_reports_of_several_tables() {
+    let mut table = create_demo_table();
+    let mut row = Row::create(&table, Uuid::from_u128(1));
+    row.set_cell(String::from("first_name"), "Jane".into_cell());
+    row.set_cell(String::from("last_name"), "<Doe>".into_cell());
+    row.set_cell(String::from("age"), 41i64.into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    let adults = table
+        .find_rows(
+            &Predicate::GreaterThan(Expression::column("age"), Expression::literal(17i64)),
+            ColumnSpecification::Some(vec![String::from("first_name"), String::from("age")]),
+        )
+        .unwrap();
+    let report = Report::create()
+        .with_table("Users", &table)
+        .with_rows("Adults", &table, &adults);
+
+    let markdown = "\
+### Users
+
+| ID | first_name | last_name | age |
+| :---: | :--- | :--- | ---: |
+| 00000000-0000-0000-0000-000000000001 | Jane | <Doe> | 41 |
+
+### Adults
+
+| ID | first_name | age |
+| :---: | :--- | ---: |
+| 00000000-0000-0000-0000-000000000001 | Jane | 41 |
+";
+    assert_eq!(markdown, report.render(ReportFormat::Markdown));
+
+    let html = report.render(ReportFormat::Html);
+    assert_eq!(2, html.matches("<table>").count());
+    assert!(html.contains("<caption>Adults</caption>"));
+    assert!(html.contains("<td style=\"text-align: left\">&lt;Doe&gt;</td>"));
+
+    let text = report.render(ReportFormat::Text);
+    assert!(text.starts_with("Users\n"));
+    assert!(text.contains("\nAdults\n"));
+    assert!(text.contains("| Jane       |  41 |"));
+}
+
+#[test]
+fn it_displays_a_page_of_rows() {
+    let mut table = create_demo_table();
+    for age in 0..5i64 {
+        let mut row = Row::create(&table, Uuid::new_v4());
+        row.set_cell(
+            String::from("first_name"),
+            format!("User {}", age).as_str().into_cell(),
+        );
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        row.set_cell(String::from("age"), age.into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+
+    let page = table.display_page(2, 2);
+    assert!(page.contains("| User 2     | Doe       |   2 |"));
+    assert!(page.contains("| User 3     | Doe       |   3 |"));
+    assert!(!page.contains("User 4"));
+    assert!(page.ends_with("showing 3\u{2013}4 of 5 rows\n"));
+
+    assert!(table
+        .display_page(3, 2)
+        .ends_with("showing 5\u{2013}5 of 5 rows\n"));
+    assert!(table.display_page(4, 2).ends_with("showing 0 of 5 rows\n"));
+
+    let mut large_table = create_demo_table();
+    for _ in 0..1234 {
+        let mut row = Row::create(&large_table, Uuid::new_v4());
+        row.set_cell(String::from("first_name"), "Jane".into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        assert!(large_table.create_row(row).is_ok());
+    }
+    assert!(large_table
+        .display_page(21, 50)
+        .ends_with("showing 1,001\u{2013}1,050 of 1,234 rows\n"));
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn it_appends_arrow_record_batches() {
+    use arrow::array::{ArrayRef, Int32Array, StringArray};
+    use arrow::record_batch::RecordBatch;
+    use std::sync::Arc;
+    use virtual_table::arrow_import::ColumnMapping;
+
+    let mut table = create_demo_table();
+    let batch = RecordBatch::try_from_iter(vec![
+        (
+            "given_name",
+            Arc::new(StringArray::from(vec!["Jane", "John"])) as ArrayRef,
+        ),
+        (
+            "last_name",
+            Arc::new(StringArray::from(vec!["Doe", "Doe"])) as ArrayRef,
+        ),
+        (
+            "age",
+            Arc::new(Int32Array::from(vec![Some(41), None])) as ArrayRef,
+        ),
+        (
+            "source",
+            Arc::new(StringArray::from(vec!["crm", "crm"])) as ArrayRef,
+        ),
+    ])
+    .unwrap();
+    let mapping = ColumnMapping::create()
+        .rename("given_name", "first_name")
+        .ignore("source");
+    assert_eq!(Ok(2), table.append_record_batch(&batch, &mapping));
+
+    let jane = table
+        .find_rows(
+            &Predicate::Equals(
+                Expression::column("first_name"),
+                Expression::literal("Jane"),
+            ),
+            ColumnSpecification::All,
+        )
+        .unwrap();
+    assert_eq!(Some(&TableValue::Integer(41)), jane[0].get_cell("age"));
+
+    let invalid = RecordBatch::try_from_iter(vec![
+        (
+            "first_name",
+            Arc::new(StringArray::from(vec!["Max"])) as ArrayRef,
+        ),
+        (
+            "last_name",
+            Arc::new(StringArray::from(vec!["Doe"])) as ArrayRef,
+        ),
+        (
+            "age",
+            Arc::new(StringArray::from(vec!["unknown"])) as ArrayRef,
+        ),
+    ])
+    .unwrap();
+    let errors = table
+        .append_record_batch(&invalid, &ColumnMapping::create())
+        .unwrap_err();
+    assert!(matches!(errors[0], VirtualTableError::ImportError(ref column, _) if column == "age"));
+    assert_eq!(
+        2,
+        table
+            .find_rows(&Predicate::And(Vec::new()), ColumnSpecification::All)
+            .unwrap()
+            .len()
+    );
+}
+
+#[test]
+fn it_exposes_a_table_as_key_value_store() {
+    let table = Table::create(
+        String::from("settings"),
+        vec![
+            ColumnDefinition::create("key", DataType::String, false),
+            ColumnDefinition::create("value", DataType::Integer, false)
+                .with_constraint(Constraint::Max(100)),
+        ],
+    );
+    let mut store = KeyValueView::create(table, "key", "value").unwrap();
+
+    assert!(store.set("volume", 20i64.into_cell()).is_ok());
+    assert!(store.set("volume", 30i64.into_cell()).is_ok());
+    assert!(store.set("brightness", 200i64.into_cell()).is_err());
+    assert_eq!(Some(TableValue::Integer(30)), store.get("volume"));
+    assert_eq!(None, store.get("brightness"));
+    assert_eq!(
+        1,
+        store
+            .table()
+            .find_rows(&Predicate::And(Vec::new()), ColumnSpecification::All)
+            .unwrap()
+            .len()
+    );
+
+    assert!(store.set("session", 1i64.into_cell()).is_ok());
+    assert!(store.expire("session", Duration::from_secs(60)));
+    assert!(store.time_to_live("session").unwrap() <= Duration::from_secs(60));
+    // Too long to be represented, so the key doesn't expire at all
+    assert!(store.expire("session", Duration::MAX));
+    assert_eq!(None, store.time_to_live("session"));
+    assert_eq!(0, store.purge_expired());
+    assert!(store.expire("session", Duration::from_secs(0)));
+    assert_eq!(None, store.get("session"));
+    assert_eq!(1, store.len());
+    assert_eq!(1, store.purge_expired());
+
+    assert!(store.delete("volume"));
+    assert!(!store.delete("volume"));
+    assert!(store.is_empty());
+}
+
+#[test]
+fn it_undoes_deletes_of_a_key_value_store() {
+    let mut table = Table::create(
+        String::from("settings"),
+        vec![
+            ColumnDefinition::create("key", DataType::String, false),
+            ColumnDefinition::create("value", DataType::Integer, false),
+        ],
+    );
+    table.enable_history();
+    let mut store = KeyValueView::create(table, "key", "value").unwrap();
+    assert!(store.set("volume", 20i64.into_cell()).is_ok());
+    assert!(store.set("session", 1i64.into_cell()).is_ok());
+    assert!(store.expire("session", Duration::from_secs(0)));
+    assert_eq!(1, store.purge_expired());
+    assert!(store.delete("volume"));
+
+    let mut table = store.into_table();
+    assert_eq!(
+        Ok(TableValue::Integer(0)),
+        table.aggregate(&Aggregate::Count, None)
+    );
+    assert_eq!(Ok(true), table.undo());
+    assert_eq!(Ok(true), table.undo());
+    assert_eq!(
+        Ok(TableValue::Integer(2)),
+        table.aggregate(&Aggregate::Count, None)
+    );
+    let store = KeyValueView::create(table, "key", "value").unwrap();
+    assert_eq!(Some(TableValue::Integer(20)), store.get("volume"));
+    assert_eq!(Some(TableValue::Integer(1)), store.get("session"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn it_serializes_queries() {
+    let predicate = Predicate::And(vec![
+        Predicate::GreaterThan(
+            Expression::column("age").plus(Expression::literal(1i64)),
+            Expression::literal(18i64),
+        ),
+        Predicate::within_radius(
+            Expression::column("location"),
+            Point::create(52.52, 13.405).unwrap(),
+            1000,
+        ),
+        Predicate::Equals(
+            Expression::function(
+                ScalarFunction::Upper,
+                vec![Expression::column("first_name")],
+            ),
+            Expression::literal("JANE"),
+        ),
+    ]);
+
+    let json = serde_json::to_string(&predicate).unwrap();
+    assert_eq!(predicate, serde_json::from_str::<Predicate>(&json).unwrap());
+
+    let order: Vec<(Expression, SortOrder)> =
+        serde_json::from_str(r#"[[{"Column": "age"}, "Descending"]]"#).unwrap();
+    assert_eq!(
+        vec![(Expression::column("age"), SortOrder::Descending)],
+        order
+    );
+
+    let money = TableValue::Money(Money::create(1234, Currency::create("EUR").unwrap()));
+    let json = serde_json::to_string(&money).unwrap();
+    assert_eq!(r#"{"Money":{"amount":1234,"currency":"EUR"}}"#, json);
+    assert!(
+        serde_json::from_str::<TableValue>(r#"{"Money":{"amount":1,"currency":"eur"}}"#).is_err()
+    );
+
+    // Deserialized predicates are checked against the table before they're run
+    let table = create_demo_table();
+    let unknown: Predicate = serde_json::from_str(r#"{"IsNull": {"Column": "email"}}"#).unwrap();
+    assert_eq!(
+        Err(VirtualTableError::UnknownColumn(String::from("email"))),
+        unknown.validate(&table)
+    );
+    let valid: Predicate = serde_json::from_str(r#"{"IsNull": {"Column": "age"}}"#).unwrap();
+    assert_eq!(Ok(()), valid.validate(&table));
+}
+
+#[test]
+fn it_runs_saved_queries_with_parameters() {
+    let mut table = create_demo_table();
+    for &(first_name, age) in &[("Jane", 41i64), ("John", 17i64), ("Max", 25i64)] {
+        let mut row = Row::create(&table, Uuid::new_v4());
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        row.set_cell(String::from("age"), age.into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+
+    let adults = SavedQuery::create(Predicate::GreaterThanOrEquals(
+        Expression::column("age"),
+        Expression::parameter("min_age"),
+    ))
+    .with_columns(ColumnSpecification::Some(vec![String::from("first_name")]))
+    .ordered_by(vec![(Expression::column("age"), SortOrder::Descending)]);
+    assert!(table.define_query("adults", adults).is_ok());
+
+    let mut parameters = HashMap::new();
+    parameters.insert(String::from("min_age"), TableValue::Integer(18));
+    let names = table
+        .run_query("adults", &parameters)
+        .unwrap()
+        .iter()
+        .map(|row| row.get_cell("first_name").cloned())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            Some(TableValue::from("Jane")),
+            Some(TableValue::from("Max"))
+        ],
+        names
+    );
+
+    assert!(table.run_query("adults", &HashMap::new()).is_err());
+    assert_eq!(
+        Err(VirtualTableError::UnknownQuery(String::from("minors"))),
+        table.run_query("minors", &parameters)
+    );
+    let unknown_column = SavedQuery::create(Predicate::IsNull(Expression::column("email")));
+    assert!(table.define_query("no_email", unknown_column).is_err());
+    assert!(table.drop_query("adults"));
+}
+
+#[test]
+fn it_transforms_rows_before_writing_them() {
+    let mut table = create_demo_table();
+    table.enable_history();
+    table.add_row_transformer(WriteStage::BeforeInsert, |mut row| {
+        if let Some(TableValue::String(name)) = row.get_cell("first_name").cloned() {
+            row.set_cell(String::from("first_name"), name.trim().into_cell());
+        }
+        row
+    });
+    table.add_row_transformer(WriteStage::BeforeUpdate, |mut row| {
+        row.set_cell(String::from("age"), 99i64.into_cell());
+        row
+    });
+
+    let key = Uuid::from_u128(1);
+    let mut row = Row::create(&table, key);
+    row.set_cell(String::from("first_name"), "  Jane ".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    row.set_cell(String::from("age"), 41i64.into_cell());
+    assert!(table.create_row(row).is_ok());
+    let found = table.find_row(&key, ColumnSpecification::All).unwrap();
+    assert_eq!(
+        Some(&TableValue::from("Jane")),
+        found.get_cell("first_name")
+    );
+    assert_eq!(Some(&TableValue::Integer(41)), found.get_cell("age"));
+
+    let mut update = Row::create(&table, key);
+    update.set_cell(String::from("last_name"), "Roe".into_cell());
+    assert!(table.update_row(update).is_ok());
+    let found = table.find_row(&key, ColumnSpecification::All).unwrap();
+    assert_eq!(Some(&TableValue::from("Roe")), found.get_cell("last_name"));
+    assert_eq!(Some(&TableValue::Integer(99)), found.get_cell("age"));
+
+    // Undo restores the stored cells instead of transforming them again
+    assert_eq!(Ok(true), table.undo());
+    let found = table.find_row(&key, ColumnSpecification::All).unwrap();
+    assert_eq!(Some(&TableValue::Integer(41)), found.get_cell("age"));
+
+    table.clear_row_transformers();
+    let mut update = Row::create(&table, key);
+    update.set_cell(String::from("first_name"), " Jane".into_cell());
+    assert!(table.update_row(update).is_ok());
+    let found = table.find_row(&key, ColumnSpecification::All).unwrap();
+    assert_eq!(
+        Some(&TableValue::from(" Jane")),
+        found.get_cell("first_name")
+    );
+}
+
+#[test]
+fn it_maintains_audit_columns() {
+    let mut table = create_demo_table();
+    let existing = Uuid::from_u128(1);
+    let mut row = Row::create(&table, existing);
+    row.set_cell(String::from("first_name"), "John".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    assert!(table.enable_audit_columns(true).is_ok());
+    let found = table.find_row(&existing, ColumnSpecification::All).unwrap();
+    assert_eq!(Some(&TableValue::Null), found.get_cell(audit::CREATED_AT));
+
+    let key = Uuid::from_u128(2);
+    let mut row = Row::create(&table, key);
+    row.set_cell(String::from("first_name"), "Jane".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    row.set_cell(
+        String::from(audit::CREATED_AT),
+        Timestamp::from_unix_micros(0).into_cell(),
+    );
+    assert!(table
+        .create_row_as(&PolicyContext::create("jane"), row)
+        .is_ok());
+    let created = table.find_row(&key, ColumnSpecification::All).unwrap();
+    let created_at = created.get_cell(audit::CREATED_AT).cloned().unwrap();
+    assert_ne!(
+        TableValue::Timestamp(Timestamp::from_unix_micros(0)),
+        created_at
+    );
+    assert_eq!(Some(&created_at), created.get_cell(audit::UPDATED_AT));
+    assert_eq!(
+        Some(&TableValue::from("jane")),
+        created.get_cell(audit::UPDATED_BY)
+    );
+
+    let mut update = Row::create(&table, key);
+    update.set_cell(String::from("age"), 41i64.into_cell());
+    assert!(table.update_row(update).is_ok());
+    let updated = table.find_row(&key, ColumnSpecification::All).unwrap();
+    assert_eq!(Some(&created_at), updated.get_cell(audit::CREATED_AT));
+    assert_ne!(Some(&TableValue::Null), updated.get_cell(audit::UPDATED_AT));
+    assert_eq!(Some(&TableValue::Null), updated.get_cell(audit::UPDATED_BY));
+
+    let mut table = Table::create(
+        String::from("log"),
+        vec![ColumnDefinition::create(
+            audit::UPDATED_AT,
+            DataType::String,
+            true,
+        )],
+    );
+    assert_eq!(
+        Err(VirtualTableError::InvalidDataType(
+            String::from(audit::UPDATED_AT),
+            DataType::Timestamp,
+            DataType::String
+        )),
+        table.enable_audit_columns(false)
+    );
+}
+
+#[test]
+fn it_replays_events_recorded_before_audit_columns_were_enabled() {
+    let mut table = Table::create_event_sourced(
+        String::from("user"),
+        vec![ColumnDefinition::create(
+            "first_name",
+            DataType::String,
+            false,
+        )],
+    );
+    let mut row = Row::create(&table, Uuid::from_u128(1));
+    row.set_cell(String::from("first_name"), "John".into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    assert!(table.enable_audit_columns(false).is_ok());
+    let key = Uuid::from_u128(2);
+    let mut row = Row::create(&table, key);
+    row.set_cell(String::from("first_name"), "Jane".into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    // The first event has no cells for the audit columns, they are replayed as NULL
+    let replayed = table.replay_to(1).unwrap();
+    let first = replayed
+        .find_row(&Uuid::from_u128(1), ColumnSpecification::All)
+        .unwrap();
+    assert_eq!(Some(&TableValue::Null), first.get_cell(audit::CREATED_AT));
+    assert_eq!(
+        table.find_row(&key, ColumnSpecification::All),
+        replayed.find_row(&key, ColumnSpecification::All)
+    );
+    assert!(replayed.check_invariants().is_consistent());
+}
+
+#[test]
+fn it_reorders_columns() {
+    let mut table = create_demo_table();
+    let mut row = Row::create(&table, Uuid::from_u128(1));
+    row.set_cell(String::from("first_name"), "John".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    row.set_cell(String::from("age"), 42i64.into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    assert!(table
+        .reorder_columns(vec![String::from("age"), String::from("last_name")])
+        .is_ok());
+    let csv = "\
+age,last_name,ID,first_name
+42,Doe,00000000-0000-0000-0000-000000000001,John
+";
+    assert_eq!(csv, table.to_string_with(FormatStyle::Csv));
+
+    assert_eq!(
+        Err(VirtualTableError::UnknownColumn(String::from("email"))),
+        table.reorder_columns(vec![String::from("email")])
+    );
+    assert_eq!(
+        Err(VirtualTableError::DuplicateColumn(String::from("age"))),
+        table.reorder_columns(vec![String::from("age"), String::from("age")])
+    );
+    assert!(table
+        .to_string_with(FormatStyle::Csv)
+        .starts_with("age,last_name,ID,"));
+}
+
+#[test]
+fn it_keeps_column_metadata() {
+    let mut table = Table::create(
+        String::from("parcel"),
+        vec![
+            ColumnDefinition::create("weight", DataType::Integer, false).with_metadata(
+                ColumnMetadata::create()
+                    .with_description("Weight including the packaging")
+                    .with_label("Weight (g)")
+                    .with_unit("g")
+                    .with_tag("measurement"),
+            ),
+            ColumnDefinition::create("note", DataType::String, true),
+        ],
+    );
+    let mut row = Row::create(&table, Uuid::from_u128(1));
+    row.set_cell(String::from("weight"), 1200i64.into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    let metadata = table.column_metadata("weight").unwrap();
+    assert_eq!(Some("g"), metadata.unit.as_deref());
+    assert_eq!(
+        Some(&ColumnMetadata::default()),
+        table.column_metadata("note")
+    );
+    assert_eq!(None, table.column_metadata("size"));
+    assert_eq!(
+        vec![String::from("weight")],
+        table.columns_tagged("measurement")
+    );
+
+    // Labels replace identifiers in headings
+    assert!(table
+        .to_string_with(FormatStyle::Csv)
+        .starts_with("ID,Weight (g),note\n"));
+    assert!(table.to_string().contains("Weight (g)"));
+
+    let columns = information_schema::columns(&[&table]).to_string();
+    assert!(
+        columns.contains("| Weight including the packaging | Weight (g) | g    | measurement |")
+    );
+}
+
+#[test]
+fn it_enforces_partial_unique_indexes() {
+    let mut table = Table::create(
+        String::from("account"),
+        vec![
+            ColumnDefinition::create("email", DataType::String, false),
+            ColumnDefinition::create("deleted_at", DataType::Timestamp, true),
+        ],
+    );
+    let account = |table: &Table, key: u128, deleted: bool| {
+        let mut row = Row::create(table, Uuid::from_u128(key));
+        row.set_cell(String::from("email"), "jane@example.com".into_cell());
+        if deleted {
+            row.set_cell(
+                String::from("deleted_at"),
+                Timestamp::from_unix_micros(0).into_cell(),
+            );
+        }
+        row
+    };
+    assert!(table.create_row(account(&table, 1, true)).is_ok());
+    assert!(table.create_row(account(&table, 2, false)).is_ok());
+
+    let not_deleted = Predicate::IsNull(Expression::column("deleted_at"));
+    assert!(table
+        .create_partial_unique_index("email", not_deleted)
+        .is_ok());
+
+    // Deleted rows may share the value, rows that aren't deleted may not
+    assert!(table.create_row(account(&table, 3, true)).is_ok());
+    assert_eq!(
+        Err(vec![VirtualTableError::DuplicateValue(
+            String::from("email"),
+            TableValue::from("jane@example.com")
+        )]),
+        table.create_row(account(&table, 4, false))
+    );
+    assert!(!table.contains_key(&Uuid::from_u128(4)));
+
+    let mut restore = Row::create(&table, Uuid::from_u128(1));
+    restore.set_cell(String::from("deleted_at"), Cell::null(DataType::Timestamp));
+    assert!(table.update_row(restore.clone()).is_err());
+    let found = table
+        .find_row(&Uuid::from_u128(1), ColumnSpecification::All)
+        .unwrap();
+    assert_ne!(Some(&TableValue::Null), found.get_cell("deleted_at"));
+
+    let mut delete = Row::create(&table, Uuid::from_u128(2));
+    delete.set_cell(
+        String::from("deleted_at"),
+        Timestamp::from_unix_micros(0).into_cell(),
+    );
+    assert!(table.update_row(delete).is_ok());
+    assert!(table.update_row(restore).is_ok());
+    assert!(table.create_row(account(&table, 4, false)).is_err());
+}
+
+#[test]
+fn it_rebuilds_indexes() {
+    let mut table = create_demo_table();
+    for (key, first_name) in ["Jane", "John", "jane"].iter().enumerate() {
+        let mut row = Row::create(&table, Uuid::from_u128(key as u128));
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        if key == 2 {
+            row.set_cell(String::from("age"), 12i64.into_cell());
+        }
+        assert!(table.create_row(row).is_ok());
+    }
+    table.enable_ordered_keys();
+    table.enable_bloom_filter(10);
+    assert!(table.create_bitmap_index("last_name").is_ok());
+    let adults = Predicate::IsNull(Expression::column("age"));
+    assert!(table
+        .create_partial_unique_index("first_name", adults)
+        .is_ok());
+
+    // The collation applies to the rebuilt index, which is still restricted to its rows
+    assert!(table
+        .set_collation("first_name", Collation::CaseInsensitive)
+        .is_ok());
+    assert!(table.rebuild_indexes().is_ok());
+    assert!(table.check_invariants().is_consistent());
+
+    let does = table
+        .find_rows_by_bitmap(
+            &BitmapFilter::Equals(String::from("last_name"), TableValue::from("Doe")),
+            ColumnSpecification::All,
+        )
+        .unwrap();
+    assert_eq!(3, does.len());
+    assert_eq!(3, table.first_rows(5).len());
+    assert!(table.contains_key(&Uuid::from_u128(1)));
+
+    let mut row = Row::create(&table, Uuid::from_u128(3));
+    row.set_cell(String::from("first_name"), "JANE".into_cell());
+    row.set_cell(String::from("last_name"), "Roe".into_cell());
+    assert!(table.create_row(row).is_err());
+}
+
+#[test]
+fn it_builds_bitmap_indexes_in_batches() {
+    let mut table = create_demo_table();
+    for key in 0..10u128 {
+        let mut row = Row::create(&table, Uuid::from_u128(key));
+        row.set_cell(String::from("first_name"), "Jane".into_cell());
+        let last_name = if key % 2 == 0 { "Doe" } else { "Roe" };
+        row.set_cell(String::from("last_name"), last_name.into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+
+    assert!(table.start_bitmap_index_build("last_name").is_ok());
+    assert!(table.start_bitmap_index_build("email").is_err());
+    let does = BitmapFilter::Equals(String::from("last_name"), TableValue::from("Doe"));
+    assert_eq!(1, table.continue_bitmap_index_builds(4));
+    assert!(table.is_building_bitmap_index("last_name"));
+    assert!(!table.has_bitmap_index("last_name"));
+    assert!(table
+        .find_rows_by_bitmap(&does, ColumnSpecification::All)
+        .is_err());
+
+    // Rows written during the build end up in the index, no matter if their batch was done
+    let mut update = Row::create(&table, Uuid::from_u128(1));
+    update.set_cell(String::from("last_name"), "Doe".into_cell());
+    assert!(table.update_row(update).is_ok());
+    let mut update = Row::create(&table, Uuid::from_u128(9));
+    update.set_cell(String::from("last_name"), "Doe".into_cell());
+    assert!(table.update_row(update).is_ok());
+    let mut row = Row::create(&table, Uuid::from_u128(10));
+    row.set_cell(String::from("first_name"), "John".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    assert!(table.create_row(row).is_ok());
+
+    assert_eq!(1, table.continue_bitmap_index_builds(4));
+    assert_eq!(0, table.continue_bitmap_index_builds(4));
+    assert!(table.has_bitmap_index("last_name"));
+    assert!(!table.is_building_bitmap_index("last_name"));
+    assert_eq!(
+        8,
+        table
+            .find_rows_by_bitmap(&does, ColumnSpecification::All)
+            .unwrap()
+            .len()
+    );
+}
+
+#[test]
+fn it_counts_reads_and_writes() {
+    let mut table = create_demo_table();
+    assert_eq!(TableStats::default(), table.stats());
+
+    for key in 0..3u128 {
+        let mut row = Row::create(&table, Uuid::from_u128(key));
+        row.set_cell(String::from("first_name"), "Jane".into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+    let mut update = Row::create(&table, Uuid::from_u128(1));
+    update.set_cell(String::from("age"), 41i64.into_cell());
+    assert!(table.update_row(update.clone()).is_ok());
+    update.set_cell(String::from("age"), "41".into_cell());
+    assert!(table.update_row(update).is_err());
+
+    let adults = Predicate::IsNotNull(Expression::column("age"));
+    assert_eq!(
+        1,
+        table
+            .find_rows(&adults, ColumnSpecification::All)
+            .unwrap()
+            .len()
+    );
+    table.enable_ordered_keys();
+    assert_eq!(3, table.first_rows(5).len());
+
+    let stats = table.stats();
+    assert_eq!(3, stats.rows_inserted);
+    assert_eq!(1, stats.rows_updated);
+    assert_eq!(0, stats.rows_deleted);
+    assert_eq!(1, stats.queries_executed);
+    assert_eq!(1, stats.scans);
+    assert_eq!(1, stats.index_lookups);
+    assert!(stats.last_modified.is_some());
+
+    table.reset_stats();
+    assert_eq!(TableStats::default(), table.stats());
+}
+
+#[test]
+fn it_versions_every_mutation() {
+    let mut table = Table::create_event_sourced(
+        String::from("user"),
+        vec![ColumnDefinition::create("name", DataType::String, false)],
+    );
+    assert_eq!(0, table.current_version());
+
+    let key = Uuid::from_u128(1);
+    let mut row = Row::create(&table, key);
+    row.set_cell(String::from("name"), "Jane".into_cell());
+    assert!(table.create_row(row).is_ok());
+    let cached = table.current_version();
+    assert!(!table.has_changed_since(cached));
+
+    let mut update = Row::create(&table, key);
+    update.set_cell(String::from("name"), 42i64.into_cell());
+    assert!(table.update_row(update).is_err());
+    assert!(table.create_bitmap_index("name").is_ok());
+    assert!(!table.has_changed_since(cached));
+
+    let mut update = Row::create(&table, key);
+    update.set_cell(String::from("name"), "John".into_cell());
+    assert!(table.update_row(update.clone()).is_ok());
+    assert_eq!(2, table.current_version());
+    assert!(table.has_changed_since(cached));
+    assert_eq!(Some(2), table.event_version(1));
+    assert_eq!(
+        &[TableEvent::RowUpdated(update)],
+        table.events_since(cached)
+    );
+    assert!(table.events_since(2).is_empty());
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn it_quarantines_rejected_arrow_rows() {
+    use arrow::array::{ArrayRef, StringArray};
+    use arrow::record_batch::RecordBatch;
+    use std::sync::Arc;
+    use virtual_table::arrow_import::ColumnMapping;
+
+    let mut table = create_demo_table();
+    let batch = RecordBatch::try_from_iter(vec![
+        (
+            "first_name",
+            Arc::new(StringArray::from(vec![Some("Jane"), None, Some("Max")])) as ArrayRef,
+        ),
+        (
+            "last_name",
+            Arc::new(StringArray::from(vec!["Doe", "Doe", "Roe"])) as ArrayRef,
+        ),
+    ])
+    .unwrap();
+
+    let report = table
+        .append_record_batch_quarantined(&batch, &ColumnMapping::create())
+        .unwrap();
+    assert_eq!(2, report.appended);
+    assert!(!report.is_complete());
+    assert_eq!(1, report.quarantined[0].position);
+    assert_eq!(
+        vec![VirtualTableError::InvalidNullValue(String::from(
+            "first_name"
+        ))],
+        report.quarantined[0].errors
+    );
+    assert_eq!(
+        Some(&TableValue::from("Doe")),
+        report.quarantined[0].row.get_cell("last_name")
+    );
+    assert!(report
+        .to_string()
+        .starts_with("Appended 2 rows, quarantined 1 rows.\nRow 1: "));
+}
+
+#[test]
+fn it_ingests_rows_in_batches() {
+    let mut table = create_demo_table();
+    assert!(table.start_bitmap_index_build("last_name").is_ok());
+
+    let rows = (0..5u128)
+        .map(|key| {
+            let mut row = Row::create(&table, Uuid::from_u128(key));
+            row.set_cell(String::from("first_name"), "Jane".into_cell());
+            row.set_cell(String::from("last_name"), "Doe".into_cell());
+            row
+        })
+        .collect::<Vec<_>>();
+    let mut reported = Vec::new();
+    let progress = table
+        .ingest(rows, 2, |progress| reported.push(progress.rows_ingested))
+        .unwrap();
+
+    assert_eq!(5, progress.rows_ingested);
+    assert_eq!(3, progress.batches);
+    assert_eq!(0, progress.pending_index_builds);
+    assert_eq!(vec![2, 4, 5], reported);
+    assert!(table.has_bitmap_index("last_name"));
+}
+
+#[test]
+fn it_undoes_an_ingest_in_one_step() {
+    let mut table = create_demo_table();
+    table.enable_history();
+
+    let rows = (0..3u128)
+        .map(|key| {
+            let mut row = Row::create(&table, Uuid::from_u128(key));
+            row.set_cell(String::from("first_name"), "Jane".into_cell());
+            row.set_cell(String::from("last_name"), "Doe".into_cell());
+            row
+        })
+        .collect::<Vec<_>>();
+    assert!(table.ingest(rows, 2, |_| {}).is_ok());
+
+    assert_eq!(Ok(true), table.undo());
+    assert_eq!(
+        Ok(TableValue::Integer(0)),
+        table.aggregate(&Aggregate::Count, None)
+    );
+    assert!(!table.can_undo());
+    assert_eq!(Ok(true), table.redo());
+    assert_eq!(
+        Ok(TableValue::Integer(3)),
+        table.aggregate(&Aggregate::Count, None)
+    );
+    assert!(table.check_invariants().is_consistent());
+}
+
+#[test]
+fn it_infers_schemas_from_records() {
+    let record = |fields: &[(&str, &str)]| {
+        fields
+            .iter()
+            .map(|(name, value)| (String::from(*name), String::from(*value)))
+            .collect::<HashMap<_, _>>()
+    };
+    let records = vec![
+        record(&[
+            ("ID", "1"),
+            ("name", "Jane"),
+            ("age", "41"),
+            ("balance", "18446744073709551615"),
+            ("joined", "2024-02-29T12:00:00Z"),
+            ("session", "1h 30m"),
+        ]),
+        record(&[
+            ("name", "John"),
+            ("age", " "),
+            ("balance", "0"),
+            ("joined", "2024-03-01T08:30:00Z"),
+            ("session", "45m"),
+        ]),
+    ];
+
+    let definitions = schema::infer_from_records(&records)
+        .into_iter()
+        .map(|definition| {
+            (
+                definition.identifier,
+                definition.data_type,
+                definition.is_nullable,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            (String::from("age"), DataType::Integer, true),
+            (String::from("balance"), DataType::UnsignedInteger, false),
+            (String::from("joined"), DataType::Timestamp, false),
+            (String::from("name"), DataType::String, false),
+            (String::from("session"), DataType::Duration, false),
+        ],
+        definitions
+    );
+}
+
+#[test]
+fn it_computes_percentiles_and_standard_deviations() {
+    let mut table = Table::create(
+        String::from("measurements"),
+        vec![ColumnDefinition::create("value", DataType::Integer, true)],
+    );
+    for value in [
+        Some(2i64),
+        Some(4),
+        Some(4),
+        None,
+        Some(4),
+        Some(5),
+        Some(5),
+        Some(7),
+        Some(9),
+    ] {
+        let mut row = Row::create(&table, Uuid::new_v4());
+        let cell = match value {
+            Some(value) => value.into_cell(),
+            None => Cell::null(DataType::Integer),
+        };
+        row.set_cell(String::from("value"), cell);
+        assert!(table.create_row(row).is_ok());
+    }
+
+    assert_eq!(Ok(Some(4.5)), table.column_median("value"));
+    assert_eq!(Ok(Some(2.0)), table.column_percentile("value", 0.0));
+    assert_eq!(Ok(Some(5.5)), table.column_percentile("value", 75.0));
+    assert_eq!(
+        Ok(Some((32.0f64 / 7.0).sqrt())),
+        table.column_stddev("value")
+    );
+    assert!(matches!(
+        table.column_percentile("value", 101.0),
+        Err(VirtualTableError::ArithmeticError(_))
+    ));
+
+    let above_five = Predicate::GreaterThan(Expression::column("value"), Expression::literal(5));
+    let value = Expression::column("value");
+    assert_eq!(
+        Ok(None),
+        table.standard_deviation(
+            &value,
+            Some(&Predicate::GreaterThan(
+                value.clone(),
+                Expression::literal(8)
+            )),
+            Precision::Exact
+        )
+    );
+    assert_eq!(
+        Ok(Some(8.0)),
+        table.median(&value, Some(&above_five), Precision::Exact)
+    );
+
+    // Approximations of large tables only look at every other row here
+    let mut table = Table::create(
+        String::from("measurements"),
+        vec![ColumnDefinition::create("value", DataType::Integer, false)],
+    );
+    for value in 1..=12_000i64 {
+        let mut row = Row::create(&table, Uuid::from_u128(value as u128));
+        row.set_cell(String::from("value"), value.into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+    assert_eq!(
+        Ok(Some(6000.5)),
+        table.median(&value, None, Precision::Exact)
+    );
+    assert_eq!(
+        Ok(Some(6000.0)),
+        table.median(&value, None, Precision::Approximate)
+    );
+}
+
+#[test]
+fn it_finds_the_top_and_bottom_k_rows() {
+    let mut table = Table::create(
+        String::from("scores"),
+        vec![ColumnDefinition::create("score", DataType::Integer, true)],
+    );
+    let scores = [Some(7i64), None, Some(3), Some(9), Some(7), Some(1)];
+    for (position, score) in scores.iter().enumerate() {
+        let mut row = Row::create(&table, Uuid::from_u128(position as u128));
+        let cell = match score {
+            Some(score) => score.into_cell(),
+            None => Cell::null(DataType::Integer),
+        };
+        row.set_cell(String::from("score"), cell);
+        assert!(table.create_row(row).is_ok());
+    }
+    let keys_of = |rows: Result<Vec<Row>, VirtualTableError>| {
+        rows.map(|rows| {
+            rows.into_iter()
+                .map(|row| row.primary_key().as_u128())
+                .collect::<Vec<_>>()
+        })
+    };
+
+    assert_eq!(
+        Ok(vec![3, 0, 4]),
+        keys_of(table.top_k("score", 3, SortOrder::Descending))
+    );
+    assert_eq!(
+        Ok(vec![5, 2]),
+        keys_of(table.top_k("score", 2, SortOrder::Ascending))
+    );
+    assert_eq!(
+        Ok(vec![5, 2, 0, 4, 3]),
+        keys_of(table.top_k("score", 10, SortOrder::Ascending))
+    );
+    assert_eq!(
+        Ok(Vec::new()),
+        keys_of(table.top_k("score", 0, SortOrder::Ascending))
+    );
+    assert_eq!(
+        Err(VirtualTableError::UnknownColumn(String::from("rank"))),
+        keys_of(table.top_k("rank", 1, SortOrder::Ascending))
+    );
+}
+
+#[test]
+fn it_estimates_the_number_of_distinct_values() {
+    let mut table = Table::create(
+        String::from("visits"),
+        vec![ColumnDefinition::create("visitor", DataType::String, true)],
+    );
+    for visitor in [Some("Jane"), Some("John"), None, Some("Jane")] {
+        let mut row = Row::create(&table, Uuid::new_v4());
+        let cell = match visitor {
+            Some(visitor) => visitor.into_cell(),
+            None => Cell::null(DataType::String),
+        };
+        row.set_cell(String::from("visitor"), cell);
+        assert!(table.create_row(row).is_ok());
+    }
+    assert_eq!(Ok(2), table.approx_distinct("visitor"));
+
+    for position in 0..20_000 {
+        let mut row = Row::create(&table, Uuid::from_u128(position));
+        row.set_cell(
+            String::from("visitor"),
+            format!("visitor-{}", position % 5_000).into_cell(),
+        );
+        assert!(table.create_row(row).is_ok());
+    }
+    let estimate = table.approx_distinct("visitor").unwrap();
+    assert!((4_900..=5_100).contains(&estimate), "{}", estimate);
+
+    assert_eq!(
+        Err(VirtualTableError::UnknownColumn(String::from("page"))),
+        table.approx_distinct("page")
+    );
+}
+
+#[test]
+fn it_finds_rows_with_and_without_matches_in_another_table() {
+    let mut customers = Table::create(
+        String::from("customers"),
+        vec![ColumnDefinition::create("email", DataType::String, true)],
+    );
+    for (position, email) in [
+        Some("jane@example.com"),
+        Some("JOHN@example.com"),
+        None,
+        Some("max@example.com"),
+    ]
+    .iter()
+    .enumerate()
+    {
+        let mut row = Row::create(&customers, Uuid::from_u128(position as u128));
+        let cell = match email {
+            Some(email) => email.into_cell(),
+            None => Cell::null(DataType::String),
+        };
+        row.set_cell(String::from("email"), cell);
+        assert!(customers.create_row(row).is_ok());
+    }
+    let mut orders = Table::create(
+        String::from("orders"),
+        vec![ColumnDefinition::create(
+            "customer_email",
+            DataType::String,
+            true,
+        )],
+    );
+    for email in [
+        Some("jane@example.com"),
+        Some("john@example.com"),
+        Some("jane@example.com"),
+        None,
+    ] {
+        let mut row = Row::create(&orders, Uuid::new_v4());
+        let cell = match email {
+            Some(email) => email.into_cell(),
+            None => Cell::null(DataType::String),
+        };
+        row.set_cell(String::from("customer_email"), cell);
+        assert!(orders.create_row(row).is_ok());
+    }
+    let keys_of = |rows: Result<Vec<Row>, VirtualTableError>| {
+        rows.map(|rows| {
+            rows.into_iter()
+                .map(|row| row.primary_key().as_u128())
+                .collect::<Vec<_>>()
+        })
+    };
+
+    assert_eq!(
+        Ok(vec![0]),
+        keys_of(customers.semi_join("email", &orders, "customer_email", ColumnSpecification::All))
+    );
+    assert_eq!(
+        Ok(vec![1, 2, 3]),
+        keys_of(customers.anti_join("email", &orders, "customer_email", ColumnSpecification::All))
+    );
+
+    // Values of the other table are compared with the collation of the joined column
+    assert!(customers
+        .set_collation("email", Collation::CaseInsensitive)
+        .is_ok());
+    assert_eq!(
+        Ok(vec![0, 1]),
+        keys_of(customers.semi_join("email", &orders, "customer_email", ColumnSpecification::All))
+    );
+    assert_eq!(
+        Ok(vec![2, 3]),
+        keys_of(customers.anti_join("email", &orders, "customer_email", ColumnSpecification::All))
+    );
+
+    assert_eq!(
+        Err(VirtualTableError::UnknownColumn(String::from("id"))),
+        keys_of(customers.semi_join("email", &orders, "id", ColumnSpecification::All))
+    );
+}
+
+#[test]
+fn it_finds_the_descendants_of_a_row() {
+    let mut table = Table::create(
+        String::from("employees"),
+        vec![ColumnDefinition::create("manager_id", DataType::Uuid, true)],
+    );
+    // 0 manages 1 and 2, 1 manages 3, and 4 and 5 manage each other
+    let managers = [None, Some(0u128), Some(0), Some(1), Some(5), Some(4)];
+    for (position, manager) in managers.iter().enumerate() {
+        let mut row = Row::create(&table, Uuid::from_u128(position as u128));
+        let cell = match manager {
+            Some(manager) => Uuid::from_u128(*manager).into_cell(),
+            None => Cell::null(DataType::Uuid),
+        };
+        row.set_cell(String::from("manager_id"), cell);
+        assert!(table.create_row(row).is_ok());
+    }
+    let descendants = |position: u128| {
+        table
+            .descendants(
+                &Uuid::from_u128(position),
+                "manager_id",
+                ColumnSpecification::All,
+            )
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|row| row.primary_key().as_u128())
+                    .collect::<Vec<_>>()
+            })
+    };
+
+    assert_eq!(Ok(vec![1, 2, 3]), descendants(0));
+    assert_eq!(Ok(vec![3]), descendants(1));
+    assert_eq!(Ok(Vec::new()), descendants(3));
+    assert_eq!(Ok(vec![5]), descendants(4));
+    assert_eq!(
+        Err(VirtualTableError::UnknownPrimaryKey(Uuid::from_u128(9))),
+        descendants(9)
+    );
+}
+
+#[test]
+fn it_reads_tables_as_graphs() {
+    let mut table = Table::create(
+        String::from("flights"),
+        vec![
+            ColumnDefinition::create("origin", DataType::String, false),
+            ColumnDefinition::create("destination", DataType::String, true),
+        ],
+    );
+    let flights = [
+        ("BER", Some("FRA")),
+        ("BER", Some("MUC")),
+        ("FRA", Some("JFK")),
+        ("MUC", Some("FRA")),
+        ("BER", Some("FRA")),
+        ("LIS", Some("OPO")),
+        ("HAM", None),
+    ];
+    for (origin, destination) in flights {
+        let mut row = Row::create(&table, Uuid::new_v4());
+        row.set_cell(String::from("origin"), origin.into_cell());
+        let destination = match destination {
+            Some(destination) => destination.into_cell(),
+            None => Cell::null(DataType::String),
+        };
+        row.set_cell(String::from("destination"), destination);
+        assert!(table.create_row(row).is_ok());
+    }
+    let airports = |codes: &[&str]| {
+        codes
+            .iter()
+            .map(|code| TableValue::from(*code))
+            .collect::<Vec<_>>()
+    };
+
+    let graph = table.graph("origin", "destination").unwrap();
+    assert_eq!(
+        airports(&["BER", "FRA", "MUC", "JFK", "LIS", "OPO"]).as_slice(),
+        graph.nodes()
+    );
+    assert_eq!(
+        airports(&["FRA", "MUC"]),
+        graph.neighbors(&TableValue::from("BER"))
+    );
+    assert_eq!(
+        Vec::<TableValue>::new(),
+        graph.neighbors(&TableValue::from("HAM"))
+    );
+    assert_eq!(
+        airports(&["BER", "FRA", "MUC", "JFK"]),
+        graph.breadth_first(&TableValue::from("BER"))
+    );
+    assert_eq!(
+        airports(&["BER", "FRA", "JFK", "MUC"]),
+        graph.depth_first(&TableValue::from("BER"))
+    );
+    assert_eq!(
+        airports(&["MUC", "FRA", "JFK"]),
+        graph.depth_first(&TableValue::from("MUC"))
+    );
+    assert_eq!(
+        vec![
+            airports(&["BER", "FRA", "MUC", "JFK"]),
+            airports(&["LIS", "OPO"])
+        ],
+        graph.connected_components()
+    );
+
+    assert!(matches!(
+        table.graph("origin", "ID"),
+        Err(VirtualTableError::InvalidDataType(..))
+    ));
+}
+
+#[test]
+fn it_samples_rows_reproducibly() {
+    let mut table = Table::create(
+        String::from("events"),
+        vec![ColumnDefinition::create(
+            "sequence",
+            DataType::Integer,
+            false,
+        )],
+    );
+    for sequence in 0..1_000i64 {
+        let mut row = Row::create(&table, Uuid::from_u128(sequence as u128));
+        row.set_cell(String::from("sequence"), sequence.into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+    let keys_of = |rows: Vec<Row>| {
+        rows.into_iter()
+            .map(|row| row.primary_key().as_u128())
+            .collect::<Vec<_>>()
+    };
+
+    let sample = keys_of(table.sample_rows(10, Sampling::Reservoir, 7).unwrap());
+    assert_eq!(10, sample.len());
+    assert!(sample.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(
+        sample,
+        keys_of(table.sample_rows(10, Sampling::Reservoir, 7).unwrap())
+    );
+    assert_ne!(
+        sample,
+        keys_of(table.sample_rows(10, Sampling::Reservoir, 8).unwrap())
+    );
+    assert_eq!(
+        1_000,
+        table
+            .sample_rows(5_000, Sampling::Reservoir, 7)
+            .unwrap()
+            .len()
+    );
+
+    let sampled = table
+        .sample_rows(1_000, Sampling::Bernoulli(0.1), 7)
+        .unwrap()
+        .len();
+    assert!((60..=140).contains(&sampled), "{}", sampled);
+    assert_eq!(
+        20,
+        table
+            .sample_rows(20, Sampling::Bernoulli(0.1), 7)
+            .unwrap()
+            .len()
+    );
+    assert!(table
+        .sample_rows(20, Sampling::Bernoulli(0.0), 7)
+        .unwrap()
+        .is_empty());
+    assert!(matches!(
+        table.sample_rows(20, Sampling::Bernoulli(1.5), 7),
+        Err(VirtualTableError::InvalidArgument(_))
+    ));
+}
+
+#[test]
+fn it_shuffles_reproducibly_and_sorts_stably() {
+    let mut table = Table::create(
+        String::from("students"),
+        vec![ColumnDefinition::create("grade", DataType::Integer, false)],
+    );
+    for (position, grade) in [2i64, 1, 2, 1, 3, 2].iter().enumerate() {
+        let mut row = Row::create(&table, Uuid::from_u128(position as u128));
+        row.set_cell(String::from("grade"), grade.into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+    let keys_of = |rows: Vec<Row>| {
+        rows.into_iter()
+            .map(|row| row.primary_key().as_u128())
+            .collect::<Vec<_>>()
+    };
+
+    let by_grade = [(Expression::column("grade"), SortOrder::Ascending)];
+    assert_eq!(
+        vec![1, 3, 0, 2, 5, 4],
+        keys_of(table.order_by(&by_grade, ColumnSpecification::All).unwrap())
+    );
+    let by_grade = [(Expression::column("grade"), SortOrder::Descending)];
+    assert_eq!(
+        vec![4, 0, 2, 5, 1, 3],
+        keys_of(table.order_by(&by_grade, ColumnSpecification::All).unwrap())
+    );
+
+    let shuffled = keys_of(table.shuffled(42));
+    assert_eq!(shuffled, keys_of(table.shuffled(42)));
+    let mut sorted = shuffled.clone();
+    sorted.sort_unstable();
+    assert_eq!(vec![0, 1, 2, 3, 4, 5], sorted);
+    assert!((0..10).any(|seed| keys_of(table.shuffled(seed)) != shuffled));
+}
+
+#[test]
+fn it_splits_tables_into_training_and_test_sets() {
+    let mut table = Table::create(
+        String::from("emails"),
+        vec![ColumnDefinition::create("label", DataType::String, false)],
+    );
+    for position in 0..100u128 {
+        let label = if position % 4 == 0 { "spam" } else { "ham" };
+        let mut row = Row::create(&table, Uuid::from_u128(position));
+        row.set_cell(String::from("label"), label.into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+    let keys_of = |table: &Table| {
+        table
+            .order_by(&[], ColumnSpecification::All)
+            .unwrap()
+            .into_iter()
+            .map(|row| row.primary_key())
+            .collect::<Vec<_>>()
+    };
+    let spam_in = |table: &Table| {
+        table
+            .value_counts("label")
+            .unwrap()
+            .into_iter()
+            .find(|(value, _)| *value == TableValue::from("spam"))
+            .map_or(0, |(_, count)| count)
+    };
+
+    let (train, test) = table.split(0.8, 3).unwrap();
+    assert_eq!((80, 20), (keys_of(&train).len(), keys_of(&test).len()));
+    assert!(keys_of(&train).iter().all(|key| !test.contains_key(key)));
+    assert_eq!(keys_of(&train), keys_of(&table.split(0.8, 3).unwrap().0));
+
+    let (train, test) = table.split_stratified(0.8, "label", 3).unwrap();
+    assert_eq!((20, 5), (spam_in(&train), spam_in(&test)));
+    assert_eq!((80, 20), (keys_of(&train).len(), keys_of(&test).len()));
+
+    assert!(matches!(
+        table.split(1.2, 3),
+        Err(VirtualTableError::InvalidArgument(_))
+    ));
+    assert_eq!(
+        Err(VirtualTableError::UnknownColumn(String::from("sender"))),
+        table.split_stratified(0.8, "sender", 3).map(|_| ())
+    );
+}
+
+#[test]
+fn it_extracts_and_flattens_dimension_tables() {
+    let mut orders = Table::create(
+        String::from("orders"),
+        vec![
+            ColumnDefinition::create("item", DataType::String, false),
+            ColumnDefinition::create("city", DataType::String, false),
+            ColumnDefinition::create("zip", DataType::String, true),
+        ],
+    );
+    let rows = [
+        ("Lamp", "Berlin", Some("10115")),
+        ("Desk", "Hamburg", Some("20095")),
+        ("Chair", "Berlin", Some("10115")),
+        ("Shelf", "Berlin", None),
+    ];
+    for (position, (item, city, zip)) in rows.iter().enumerate() {
+        let mut row = Row::create(&orders, Uuid::from_u128(position as u128));
+        row.set_cell(String::from("item"), item.into_cell());
+        row.set_cell(String::from("city"), city.into_cell());
+        let zip = match zip {
+            Some(zip) => zip.into_cell(),
+            None => Cell::null(DataType::String),
+        };
+        row.set_cell(String::from("zip"), zip);
+        assert!(orders.create_row(row).is_ok());
+    }
+
+    let (normalized, addresses) = orders
+        .extract_dimension(&["city", "zip"], "addresses", "address_id")
+        .unwrap();
+    assert_eq!(
+        Ok(TableValue::Integer(3)),
+        addresses.aggregate(&Aggregate::Count, None)
+    );
+    let address_of = |position: u128| {
+        normalized
+            .find_row(&Uuid::from_u128(position), ColumnSpecification::All)
+            .and_then(|row| row.get_cell("address_id").cloned())
+            .unwrap()
+    };
+    assert_eq!(address_of(0), address_of(2));
+    assert_ne!(address_of(0), address_of(3));
+    let order = normalized
+        .find_row(&Uuid::from_u128(1), ColumnSpecification::All)
+        .unwrap();
+    assert_eq!(None, order.get_cell("city"));
+    assert_eq!(Some(&TableValue::from("Desk")), order.get_cell("item"));
+
+    let flattened = normalized.denormalize("address_id", &addresses).unwrap();
+    for position in 0..4 {
+        let key = Uuid::from_u128(position);
+        assert_eq!(
+            orders.find_row(&key, ColumnSpecification::All),
+            flattened.find_row(&key, ColumnSpecification::All)
+        );
+    }
+
+    assert_eq!(
+        Err(VirtualTableError::DuplicateColumn(String::from("item"))),
+        orders
+            .extract_dimension(&["city"], "cities", "item")
+            .map(|_| ())
+    );
+    assert_eq!(
+        Err(VirtualTableError::UnknownColumn(String::from("country"))),
+        orders
+            .extract_dimension(&["country"], "countries", "country_id")
+            .map(|_| ())
+    );
+}
+
+#[test]
+fn it_finds_and_removes_duplicates() {
+    let mut table = Table::create(
+        String::from("contacts"),
+        vec![
+            ColumnDefinition::create("name", DataType::String, false),
+            ColumnDefinition::create("phone", DataType::String, true),
+        ],
+    );
+    let contacts = [
+        ("Jane", Some("123")),
+        ("John", None),
+        ("JANE", Some("123")),
+        ("John", None),
+        ("Jane", Some("123")),
+        ("Max", Some("456")),
+    ];
+    for (position, (name, phone)) in contacts.iter().enumerate() {
+        let mut row = Row::create(&table, Uuid::from_u128(position as u128));
+        row.set_cell(String::from("name"), name.into_cell());
+        let phone = match phone {
+            Some(phone) => phone.into_cell(),
+            None => Cell::null(DataType::String),
+        };
+        row.set_cell(String::from("phone"), phone);
+        assert!(table.create_row(row).is_ok());
+    }
+    let keys = |positions: &[u128]| {
+        positions
+            .iter()
+            .map(|position| Uuid::from_u128(*position))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        Ok(vec![keys(&[0, 4]), keys(&[1, 3])]),
+        table.find_duplicates(&["name", "phone"])
+    );
+    assert_eq!(
+        Ok(vec![keys(&[0, 2, 4]), keys(&[1, 3])]),
+        table.find_duplicates(&["phone"])
+    );
+    assert!(table
+        .set_collation("name", Collation::CaseInsensitive)
+        .is_ok());
+    assert_eq!(
+        Ok(vec![keys(&[0, 2, 4]), keys(&[1, 3])]),
+        table.find_duplicates(&["name", "phone"])
+    );
+
+    assert_eq!(Ok(3), table.dedupe(&["name", "phone"], KeepPolicy::Last));
+    assert_eq!(Ok(Vec::new()), table.find_duplicates(&["name", "phone"]));
+    assert!(table.contains_key(&Uuid::from_u128(4)));
+    assert!(table.contains_key(&Uuid::from_u128(3)));
+    assert!(!table.contains_key(&Uuid::from_u128(0)));
+    assert_eq!(
+        Err(VirtualTableError::UnknownColumn(String::from("email"))),
+        table.dedupe(&["email"], KeepPolicy::First)
+    );
+}
+
+#[test]
+fn it_undoes_the_removal_of_duplicates() {
+    let mut table = Table::create(
+        String::from("contacts"),
+        vec![ColumnDefinition::create("name", DataType::String, false)],
+    );
+    table.enable_history();
+    for position in 0..2 {
+        let mut row = Row::create(&table, Uuid::from_u128(position));
+        row.set_cell(String::from("name"), "Jane".into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+    let duplicate = table
+        .find_row(&Uuid::from_u128(1), ColumnSpecification::All)
+        .unwrap();
+
+    assert_eq!(Ok(1), table.dedupe(&["name"], KeepPolicy::First));
+    assert_eq!(Ok(true), table.undo());
+    assert_eq!(
+        Some(duplicate),
+        table.find_row(&Uuid::from_u128(1), ColumnSpecification::All)
+    );
+    assert_eq!(Ok(true), table.undo());
+    assert_eq!(Ok(true), table.undo());
+    assert!(!table.can_undo());
+    assert!(table.check_invariants().is_consistent());
+}
+
+#[test]
+fn it_matches_strings_fuzzily() {
+    let mut table = create_demo_table();
+    for (position, (first_name, last_name)) in [
+        ("Jonathan", "Smith"),
+        ("Johnathan", "Smyth"),
+        ("Max", "Mustermann"),
+    ]
+    .iter()
+    .enumerate()
+    {
+        let mut row = Row::create(&table, Uuid::from_u128(position as u128));
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), last_name.into_cell());
+        row.set_cell(String::from("age"), Cell::null(DataType::Integer));
+        assert!(table.create_row(row).is_ok());
+    }
+    let keys_of = |rows: Result<Vec<Row>, VirtualTableError>| {
+        rows.map(|rows| {
+            rows.into_iter()
+                .map(|row| row.primary_key().as_u128())
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let distance = Expression::function(
+        ScalarFunction::Levenshtein,
+        vec![
+            Expression::column("last_name"),
+            Expression::literal("Smith"),
+        ],
+    );
+    assert_eq!(
+        Ok(vec![0, 1]),
+        keys_of(table.find_rows(
+            &Predicate::LessThanOrEquals(distance, Expression::literal(1)),
+            ColumnSpecification::All
+        ))
+    );
+    assert_eq!(
+        Ok(vec![0, 1]),
+        keys_of(table.fuzzy_find("first_name", "jonathan", 50))
+    );
+    assert_eq!(
+        Ok(vec![0]),
+        keys_of(table.fuzzy_find("first_name", "Jonathan", 100))
+    );
+    assert_eq!(
+        Ok(vec![2]),
+        keys_of(table.find_rows(
+            &Predicate::similar(Expression::column("last_name"), "Mustermann", 90),
+            ColumnSpecification::All
+        ))
+    );
+    assert_eq!(
+        Err(VirtualTableError::InvalidDataType(
+            String::from("age"),
+            DataType::String,
+            DataType::Integer
+        )),
+        table.fuzzy_find("age", "30", 50)
+    );
+}
+
+#[test]
+fn it_links_rows_of_two_tables() {
+    let create_people = |identifier: &str, people: &[(&str, &str)]| {
+        let mut table = Table::create(
+            String::from(identifier),
+            vec![
+                ColumnDefinition::create("name", DataType::String, false),
+                ColumnDefinition::create("city", DataType::String, false),
+            ],
+        );
+        for (position, (name, city)) in people.iter().enumerate() {
+            let mut row = Row::create(&table, Uuid::from_u128(position as u128));
+            row.set_cell(String::from("name"), name.into_cell());
+            row.set_cell(String::from("city"), city.into_cell());
+            assert!(table.create_row(row).is_ok());
+        }
+        table
+    };
+    let customers = create_people(
+        "customers",
+        &[("Jonathan Smith", "Berlin"), ("Max Mustermann", "Hamburg")],
+    );
+    let import = create_people(
+        "import",
+        &[
+            ("Johnathan Smith", "Berlin"),
+            ("Erika Mustermann", "Hamburg"),
+            ("Jonathan Smith", "Munich"),
+        ],
+    );
+    let rules = [
+        LinkRule::fuzzy("name", "name", 60).with_weight(2),
+        LinkRule::exact("city", "city"),
+    ];
+
+    let links = linkage::link_tables(&customers, &import, &rules, 2).unwrap();
+    let pairs = links
+        .order_by(&[], ColumnSpecification::All)
+        .unwrap()
+        .into_iter()
+        .map(|row| {
+            (
+                row.get_cell(linkage::LEFT_KEY).cloned(),
+                row.get_cell(linkage::RIGHT_KEY).cloned(),
+                row.get_cell(linkage::SCORE).cloned(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let pair = |left: u128, right: u128, score: u64| {
+        (
+            Some(TableValue::Uuid(Uuid::from_u128(left))),
+            Some(TableValue::Uuid(Uuid::from_u128(right))),
+            Some(TableValue::UnsignedInteger(score)),
+        )
+    };
+    assert_eq!(vec![pair(0, 0, 3), pair(0, 2, 2)], pairs);
+
+    assert!(matches!(
+        linkage::link_tables(&customers, &import, &[LinkRule::exact("name", "ID")], 1),
+        Err(VirtualTableError::InvalidDataType(..))
+    ));
+}
+
+#[test]
+fn it_anonymizes_columns() {
+    let mut table = create_demo_table();
+    let people = [("Jane", 30i64), ("John", 40), ("Jane", 50)];
+    for (position, (first_name, age)) in people.iter().enumerate() {
+        let mut row = Row::create(&table, Uuid::from_u128(position as u128));
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        row.set_cell(String::from("age"), age.into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+    let values_of = |table: &Table, column_identifier: &str| {
+        (0..3u128)
+            .filter_map(|position| {
+                table
+                    .find_row(&Uuid::from_u128(position), ColumnSpecification::All)?
+                    .get_cell(column_identifier)
+                    .cloned()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(Ok(()), table.anonymize("first_name", Anonymization::Hash));
+    let hashes = values_of(&table, "first_name");
+    assert_eq!(hashes[0], hashes[2]);
+    assert_ne!(hashes[0], hashes[1]);
+    assert!(!hashes.contains(&TableValue::from("Jane")));
+
+    assert_eq!(Ok(()), table.anonymize("age", Anonymization::Shuffle));
+    let mut ages = values_of(&table, "age");
+    ages.sort_by_key(|age| String::from(age));
+    assert_eq!(
+        vec![
+            TableValue::Integer(30),
+            TableValue::Integer(40),
+            TableValue::Integer(50)
+        ],
+        ages
+    );
+
+    let anonymous = Generator::Constant(TableValue::from("Anonymous"));
+    assert_eq!(
+        Ok(()),
+        table.anonymize("last_name", Anonymization::Fake(anonymous))
+    );
+    assert_eq!(
+        vec![TableValue::from("Anonymous"); 3],
+        values_of(&table, "last_name")
+    );
+
+    assert_eq!(
+        Err(vec![VirtualTableError::InvalidDataType(
+            String::from("age"),
+            DataType::String,
+            DataType::Integer
+        )]),
+        table.anonymize("age", Anonymization::Hash)
+    );
+}
+
+#[test]
+fn it_leaves_no_original_values_behind_when_anonymizing() {
+    let mut table = Table::create_event_sourced(
+        String::from("user"),
+        vec![
+            ColumnDefinition::create("first_name", DataType::String, false),
+            ColumnDefinition::create("last_name", DataType::String, false),
+        ],
+    );
+    table.enable_history();
+    for (position, first_name) in ["Jane", "John"].iter().enumerate() {
+        let mut row = Row::create(&table, Uuid::from_u128(position as u128));
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+    let mut row = Row::create(&table, Uuid::from_u128(0));
+    row.set_cell(String::from("first_name"), "Janet".into_cell());
+    assert!(table.update_row(row).is_ok());
+    assert!(table.delete_row(&Uuid::from_u128(1)).is_ok());
+
+    let copy = table
+        .anonymized_copy("first_name", Anonymization::Hash)
+        .unwrap();
+    assert!(!copy.to_string().contains("Janet"));
+    assert!(table.to_string().contains("Janet"));
+
+    assert_eq!(Ok(()), table.anonymize("first_name", Anonymization::Hash));
+    let reveals_original = |table: &Table| {
+        let text = format!("{:?}", table.events());
+        ["Jane", "John"].iter().any(|name| text.contains(name))
+    };
+    assert!(!reveals_original(&table));
+    let replayed = table.replay_to(table.events().len() - 1).unwrap();
+    assert_eq!(
+        table.find_row(&Uuid::from_u128(0), ColumnSpecification::All),
+        replayed.find_row(&Uuid::from_u128(0), ColumnSpecification::All)
+    );
+    // Undoing the delete brings the row back with a replaced value
+    assert_eq!(Ok(true), table.undo());
+    let restored = table
+        .find_row(&Uuid::from_u128(1), ColumnSpecification::All)
+        .unwrap();
+    assert_ne!(
+        Some(&TableValue::from("John")),
+        restored.get_cell("first_name")
+    );
+    while table.can_undo() {
+        assert_eq!(Ok(true), table.undo());
+        assert!(!table.to_string().contains("Jane"));
+        assert!(!table.to_string().contains("John"));
+    }
+}
+
+#[test]
+fn it_hashes_row_contents_stably() {
+    let mut table = create_demo_table();
+    let mut other = Table::create(
+        String::from("people"),
+        vec![
+            ColumnDefinition::create("age", DataType::Integer, true),
+            ColumnDefinition::create("last_name", DataType::String, false),
+            ColumnDefinition::create("first_name", DataType::String, false),
+        ],
+    );
+    for (target, position) in [(&mut table, 0u128), (&mut other, 1)] {
+        let mut row = Row::create(target, Uuid::from_u128(position));
+        row.set_cell(String::from("first_name"), "Jane".into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        row.set_cell(String::from("age"), Cell::null(DataType::Integer));
+        assert!(target.create_row(row).is_ok());
+    }
+
+    let hashes = table.row_hashes();
+    assert_eq!(
+        vec![(Uuid::from_u128(0), 2_889_847_008_010_944_135)],
+        hashes
+    );
+    assert_eq!(hashes[0].1, other.row_hashes()[0].1);
+
+    let mut row = Row::create(&table, Uuid::from_u128(0));
+    row.set_cell(String::from("age"), 30i64.into_cell());
+    assert!(table.update_row(row).is_ok());
+    assert_ne!(hashes, table.row_hashes());
+}
+
+#[test]
+fn it_finds_differing_key_ranges_with_merkle_trees() {
+    let create_copy = || {
+        let mut table = Table::create(
+            String::from("replica"),
+            vec![ColumnDefinition::create("value", DataType::Integer, false)],
+        );
+        for position in 0..100u128 {
+            let mut row = Row::create(&table, Uuid::from_u128(position << 120));
+            row.set_cell(String::from("value"), (position as i64).into_cell());
+            assert!(table.create_row(row).is_ok());
+        }
+        table
+    };
+    let primary = create_copy();
+    let mut replica = create_copy();
+
+    let tree = primary.merkle_tree(4);
+    assert_eq!(4, tree.depth());
+    assert_eq!(Some(tree.root()), tree.hash_at(0, 0));
+    assert_eq!(None, tree.hash_at(4, 16));
+    assert_eq!(tree, replica.merkle_tree(4));
+    assert!(tree.differing_ranges(&replica.merkle_tree(4)).is_empty());
+
+    let changed = Uuid::from_u128(42 << 120);
+    let mut row = Row::create(&replica, changed);
+    row.set_cell(String::from("value"), 0i64.into_cell());
+    assert!(replica.update_row(row).is_ok());
+
+    let ranges = tree.differing_ranges(&replica.merkle_tree(4));
+    assert_eq!(1, ranges.len());
+    assert!(ranges[0].contains(&changed));
+    assert_eq!(Uuid::from_u128(2 << 124), *ranges[0].start());
+    assert_eq!(1, tree.differing_ranges(&replica.merkle_tree(3)).len());
+}
+
+#[test]
+fn it_merges_replicas_with_last_writer_wins() {
+    let mut first = create_demo_table();
+    let mut second = create_demo_table();
+    first.enable_crdt(1);
+    second.enable_crdt(2);
+    let jane = Uuid::from_u128(1);
+    let mut row = Row::create(&first, jane);
+    row.set_cell(String::from("first_name"), "Jane".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    row.set_cell(String::from("age"), 30i64.into_cell());
+    assert!(first.create_row(row).is_ok());
+    assert_eq!(Ok(1), second.merge_crdt(&first));
+    assert_eq!(Ok(0), second.merge_crdt(&first));
+
+    // Both replicas change the row while they are apart, the age is changed on both
+    let mut row = Row::create(&first, jane);
+    row.set_cell(String::from("last_name"), "Smith".into_cell());
+    row.set_cell(String::from("age"), 31i64.into_cell());
+    assert!(first.update_row(row).is_ok());
+    std::thread::sleep(Duration::from_millis(2));
+    let mut row = Row::create(&second, jane);
+    row.set_cell(String::from("age"), 32i64.into_cell());
+    assert!(second.update_row(row).is_ok());
+    let john = Uuid::from_u128(2);
+    let mut row = Row::create(&second, john);
+    row.set_cell(String::from("first_name"), "John".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    row.set_cell(String::from("age"), Cell::null(DataType::Integer));
+    assert!(second.create_row(row).is_ok());
+    assert_eq!(
+        Some(2),
+        second.cell_clock(&jane, "age").map(|clock| clock.node_id)
+    );
+
+    assert_eq!(Ok(2), first.merge_crdt(&second));
+    assert_eq!(Ok(1), second.merge_crdt(&first));
+    for key in [jane, john] {
+        assert_eq!(
+            first.find_row(&key, ColumnSpecification::All),
+            second.find_row(&key, ColumnSpecification::All)
+        );
+    }
+    let merged = first.find_row(&jane, ColumnSpecification::All).unwrap();
+    assert_eq!(
+        Some(&TableValue::from("Smith")),
+        merged.get_cell("last_name")
+    );
+    assert_eq!(Some(&TableValue::Integer(32)), merged.get_cell("age"));
+}
+
+#[test]
+fn it_keeps_rows_deleted_on_one_replica_deleted() {
+    let mut first = create_demo_table();
+    let mut second = create_demo_table();
+    first.enable_crdt(1);
+    second.enable_crdt(2);
+    for key in 1..=2 {
+        let mut row = Row::create(&first, Uuid::from_u128(key));
+        row.set_cell(String::from("first_name"), "Jane".into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        assert!(first.create_row(row).is_ok());
+    }
+    assert_eq!(Ok(2), second.merge_crdt(&first));
+
+    assert!(first.delete_row(&Uuid::from_u128(1)).is_ok());
+    std::thread::sleep(Duration::from_millis(2));
+    assert!(second.delete_row(&Uuid::from_u128(2)).is_ok());
+
+    assert_eq!(Ok(1), first.merge_crdt(&second));
+    assert_eq!(Ok(1), second.merge_crdt(&first));
+    assert_eq!(Ok(0), first.merge_crdt(&second));
+    for table in [&first, &second] {
+        assert_eq!(
+            Ok(TableValue::Integer(0)),
+            table.aggregate(&Aggregate::Count, None)
+        );
+        assert!(table.check_invariants().is_consistent());
+    }
+
+    // A row written again after the delete comes back on the other replica
+    let mut row = Row::create(&second, Uuid::from_u128(1));
+    row.set_cell(String::from("first_name"), "John".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    assert!(second.create_row(row).is_ok());
+    assert_eq!(Ok(1), first.merge_crdt(&second));
+    assert_eq!(
+        second.find_row(&Uuid::from_u128(1), ColumnSpecification::All),
+        first.find_row(&Uuid::from_u128(1), ColumnSpecification::All)
+    );
+}
+
+#[test]
+fn it_leaves_the_table_untouched_if_a_merge_fails() {
+    let mut first = create_demo_table();
+    let mut second = create_demo_table();
+    first.enable_crdt(1);
+    second.enable_crdt(2);
+    for (key, first_name) in [(1, "Jane"), (2, "John")] {
+        let mut row = Row::create(&first, Uuid::from_u128(key));
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        assert!(first.create_row(row).is_ok());
+    }
+    assert_eq!(Ok(2), second.merge_crdt(&first));
+    assert!(first.create_unique_index("first_name").is_ok());
+    first.enable_history();
+
+    // The second replica deletes a row and creates two rows the unique index of the first rejects
+    assert!(second.delete_row(&Uuid::from_u128(1)).is_ok());
+    for key in 3..=4 {
+        let mut row = Row::create(&second, Uuid::from_u128(key));
+        row.set_cell(String::from("first_name"), "Max".into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        assert!(second.create_row(row).is_ok());
+    }
+    let clock = first.cell_clock(&Uuid::from_u128(1), "first_name");
+
+    assert!(matches!(
+        first.merge_crdt(&second).unwrap_err().as_slice(),
+        [VirtualTableError::DuplicateValue(_, _)]
+    ));
+    assert_eq!(
+        Ok(TableValue::Integer(2)),
+        first.aggregate(&Aggregate::Count, None)
+    );
+    assert_eq!(clock, first.cell_clock(&Uuid::from_u128(1), "first_name"));
+    assert!(first.check_invariants().is_consistent());
+    assert!(!first.can_undo());
+
+    // Once the conflict is resolved, the merge goes through completely
+    let mut row = Row::create(&second, Uuid::from_u128(4));
+    row.set_cell(String::from("first_name"), "Mia".into_cell());
+    assert!(second.update_row(row).is_ok());
+    assert_eq!(Ok(3), first.merge_crdt(&second));
+    assert_eq!(
+        Ok(TableValue::Integer(3)),
+        first.aggregate(&Aggregate::Count, None)
+    );
+    assert!(first.undo().is_ok());
+    assert_eq!(
+        Ok(TableValue::Integer(2)),
+        first.aggregate(&Aggregate::Count, None)
+    );
+}
+
+#[test]
+fn it_updates_rows_only_if_the_etag_matches() {
+    let mut table = create_demo_table();
+    let key = Uuid::from_u128(1);
+    let mut row = Row::create(&table, key);
+    row.set_cell(String::from("first_name"), "Jane".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    row.set_cell(String::from("age"), 30i64.into_cell());
+    assert!(table.create_row(row).is_ok());
+    let table_etag = table.etag();
+    let etag = table.row_etag(&key).unwrap();
+    assert_eq!(None, table.row_etag(&Uuid::from_u128(2)));
+
+    let mut row = Row::create(&table, key);
+    row.set_cell(String::from("age"), 31i64.into_cell());
+    assert_eq!(Ok(()), table.update_row_if_match(row, &etag));
+    assert_ne!(Some(etag.clone()), table.row_etag(&key));
+    assert_ne!(table_etag, table.etag());
+
+    // The second client still holds the ETag from before the update
+    let mut row = Row::create(&table, key);
+    row.set_cell(String::from("age"), 40i64.into_cell());
+    assert_eq!(
+        Err(vec![VirtualTableError::PreconditionFailed(key)]),
+        table.update_row_if_match(row.clone(), &etag)
+    );
+    assert_eq!(Ok(()), table.update_row_if_match(row, "*"));
+
+    let current = table.row_etag(&key).unwrap();
+    assert!(etag::matches(&format!("{}, {}", etag, current), &current));
+    assert!(!etag::matches(&table.etag(), &table.etag()));
+}
+
+#[test]
+fn it_renders_primary_keys_in_the_id_format() {
+    let mut table = create_demo_table();
+    let key = Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+    let mut row = Row::create(&table, key);
+    row.set_cell(String::from("first_name"), "John".into_cell());
+    row.set_cell(String::from("last_name"), "Doe".into_cell());
+    row.set_cell(String::from("age"), 42i64.into_cell());
+    assert!(table.create_row(row).is_ok());
+    assert_eq!(IdFormat::Full, table.id_format());
+
+    let formats = vec![
+        (IdFormat::Full, "67e55044-10b1-426f-9247-bb680e5fe0c8"),
+        (IdFormat::Short(8), "67e55044"),
+        (IdFormat::Base58, "Dq7QdGPZBdz9vwjm3jLQSB"),
+        (IdFormat::Base62, "3A30O3qtpTRBe7GtXy50u0"),
+    ];
+    for (id_format, rendered) in formats {
+        table.set_id_format(id_format);
+        assert_eq!(
+            format!("ID,first_name,last_name,age\n{},John,Doe,42\n", rendered),
+            table.to_string_with(FormatStyle::Csv)
+        );
+        assert!(table.to_string().contains(rendered));
+    }
+
+    assert_eq!("1", IdFormat::Base58.apply(&Uuid::from_u128(0)));
+    // Reads still return the actual key
+    assert_eq!(
+        Some(&TableValue::Uuid(key)),
+        table
+            .find_row(&key, ColumnSpecification::All)
+            .unwrap()
+            .get_cell("ID")
+    );
+}
+
+#[test]
+fn it_fits_the_display_into_the_width_budget() {
+    let mut table = create_demo_table();
+    let mut row = Row::create(&table, Uuid::from_u128(1));
+    row.set_cell(String::from("first_name"), "Jo".into_cell());
+    row.set_cell(
+        String::from("last_name"),
+        "Wolfeschlegelsteinhausenbergerdorff the Elder".into_cell(),
+    );
+    row.set_cell(String::from("age"), 42i64.into_cell());
+    assert!(table.create_row(row).is_ok());
+    let widest_line = |rendered: String| {
+        rendered
+            .lines()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap()
+    };
+    assert!(widest_line(table.to_string()) > 80);
+
+    table.set_width_budget(80, Overflow::Truncate);
+    let rendered = table.to_string();
+    assert_eq!(80, widest_line(rendered.clone()));
+    assert!(rendered.contains("| Jo "));
+    // The fixed columns leave 28 characters, first_name only needs 10 of them
+    assert!(rendered.contains("| Wolfeschlegelstei\u{2026} |"));
+
+    table.set_width_budget(80, Overflow::Wrap);
+    let rendered = table.to_string();
+    assert!(widest_line(rendered.clone()) <= 80);
+    assert!(rendered.contains("| Wolfeschlegelstein |"));
+    assert!(rendered.contains("| hausenbergerdorff  |"));
+    assert!(rendered.contains("| the Elder          |"));
+    assert!(widest_line(table.display_page(1, 10)) <= 80);
+
+    assert!(table.clear_width_budget());
+    assert!(widest_line(table.to_string()) > 80);
+    assert!(!table.clear_width_budget());
+
+    // Without any budget, the terminal width isn't used either
+    table.disable_width_budget();
+    assert!(widest_line(table.to_string()) > 80);
+    assert!(table.clear_width_budget());
+}
+
+#[test]
+fn it_highlights_values_in_html_reports() {
+    let mut table = create_demo_table();
+    let people: Vec<(&str, Option<i64>)> =
+        vec![("John", Some(104)), ("Jane", Some(30)), ("Max", None)];
+    for (index, (first_name, age)) in people.into_iter().enumerate() {
+        let mut row = Row::create(&table, Uuid::from_u128(index as u128 + 1));
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        match age {
+            Some(age) => row.set_cell(String::from("age"), age.into_cell()),
+            None => row.set_cell(String::from("age"), Cell::null(DataType::Integer)),
+        }
+        assert!(table.create_row(row).is_ok());
+    }
+
+    let over_100 = Predicate::GreaterThan(Expression::column("age"), Expression::literal(100i64));
+    assert!(table
+        .add_highlight_rule(HighlightRule::cell(
+            "age",
+            over_100,
+            Highlight::Color(Color::Red)
+        ))
+        .is_ok());
+    let unknown_age = Predicate::IsNull(Expression::column("age"));
+    assert!(table
+        .add_highlight_rule(HighlightRule::row(unknown_age, Highlight::Bold))
+        .is_ok());
+    assert_eq!(
+        Err(VirtualTableError::UnknownColumn(String::from("height"))),
+        table.add_highlight_rule(HighlightRule::cell(
+            "height",
+            Predicate::IsNull(Expression::column("age")),
+            Highlight::Bold
+        ))
+    );
+
+    let html = Report::create()
+        .with_table("People", &table)
+        .render(ReportFormat::Html);
+    assert!(html.contains("<td style=\"text-align: right; color: red\">104</td>"));
+    assert!(html.contains("<td style=\"text-align: left\">John</td>"));
+    assert!(html.contains("<td style=\"text-align: right\">30</td>"));
+    assert!(html.contains("<td style=\"text-align: left; font-weight: bold\">Max</td>"));
+    assert_eq!(4, html.matches("font-weight: bold").count());
+
+    let plain = table.to_string();
+    assert_eq!(2, table.clear_highlight_rules());
+    assert_eq!(plain, table.to_string());
+}
+
+#[test]
+fn it_elides_unpinned_columns_beyond_the_width_budget() {
+    let definitions = (1..=10)
+        .map(|number| ColumnDefinition {
+            identifier: format!("metric_{:02}", number),
+            data_type: DataType::Integer,
+            is_nullable: false,
+            constraints: Vec::new(),
+            metadata: ColumnMetadata::default(),
+        })
+        .collect();
+    let mut table = Table::create(String::from("metrics"), definitions);
+    let mut row = Row::create(&table, Uuid::from_u128(1));
+    for number in 1..=10 {
+        row.set_cell(format!("metric_{:02}", number), (number as i64).into_cell());
+    }
+    assert!(table.create_row(row).is_ok());
+    assert_eq!(
+        Err(VirtualTableError::UnknownColumn(String::from("metric_11"))),
+        table.pin_columns(vec![String::from("metric_11")])
+    );
+
+    // Pinning only has an effect with a width budget
+    assert!(table
+        .pin_columns(vec![String::from("ID"), String::from("metric_10")])
+        .is_ok());
+    assert!(table.to_string().contains("metric_05"));
+
+    table.set_width_budget(80, Overflow::Truncate);
+    let rendered = table.to_string();
+    let header = rendered.lines().nth(1).unwrap();
+    assert_eq!(
+        "|                  ID                  | metric_01 | metric_02 | metric_10 |",
+        header
+    );
+    assert!(rendered.ends_with("\u{2026} +7 more columns\n"));
+    assert!(table
+        .display_page(1, 10)
+        .ends_with("\u{2026} +7 more columns\nshowing 1\u{2013}1 of 1 rows\n"));
+
+    assert!(table.unpin_columns());
+    assert!(!table.to_string().contains("more columns"));
+}
+
+#[test]
+fn it_renders_rows_as_vertical_blocks() {
+    let mut table = create_demo_table();
+    let rows: Vec<(&str, &str, Option<i64>)> =
+        vec![("John", "Doe", Some(42i64)), ("Jane", "Doe\nSmith", None)];
+    for (index, (first_name, last_name, age)) in rows.into_iter().enumerate() {
+        let mut row = Row::create(&table, Uuid::from_u128(index as u128 + 1));
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), last_name.into_cell());
+        if let Some(age) = age {
+            row.set_cell(String::from("age"), age.into_cell());
+        }
+        assert!(table.create_row(row).is_ok());
+    }
+
+    let vertical = "\
+-[ RECORD 1 ]+-------------------------------------
+ID           | 00000000-0000-0000-0000-000000000001
+first_name   | John
+last_name    | Doe
+age          | 42
+-[ RECORD 2 ]+-------------------------------------
+ID           | 00000000-0000-0000-0000-000000000002
+first_name   | Jane
+last_name    | Doe
+             | Smith
+age          | *NULL*
+";
+    assert_eq!(vertical, table.to_string_with(FormatStyle::Vertical));
+    assert_eq!(
+        "",
+        create_demo_table().to_string_with(FormatStyle::Vertical)
+    );
+}
+
+#[test]
+fn it_renders_the_diff_of_two_table_states() {
+    let people: Vec<(u128, &str, i64)> = vec![(1, "John", 42), (2, "Jane", 30), (3, "Max", 20)];
+    let mut before = create_demo_table();
+    for (id, first_name, age) in people {
+        let mut row = Row::create(&before, Uuid::from_u128(id));
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        row.set_cell(String::from("age"), age.into_cell());
+        assert!(before.create_row(row).is_ok());
+    }
+    let people: Vec<(u128, &str, i64)> = vec![(1, "John", 43), (2, "Jane", 30), (4, "Erika", 25)];
+    let mut after = create_demo_table();
+    for (id, first_name, age) in people {
+        let mut row = Row::create(&after, Uuid::from_u128(id));
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        row.set_cell(String::from("age"), age.into_cell());
+        assert!(after.create_row(row).is_ok());
+    }
+
+    let table_diff = TableDiff::between(&before, &after);
+    assert_eq!(
+        vec![Uuid::from_u128(4)],
+        table_diff
+            .added
+            .iter()
+            .map(|row| row.primary_key())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(1, table_diff.removed.len());
+    assert_eq!(
+        vec![(
+            Uuid::from_u128(1),
+            vec![CellChange {
+                column_identifier: String::from("age"),
+                old: Some(TableValue::Integer(42)),
+                new: Some(TableValue::Integer(43)),
+            }]
+        )],
+        table_diff.changed
+    );
+
+    let rendered = diff::render_diff(&before, &after);
+    let lines = rendered.lines().collect::<Vec<_>>();
+    assert_eq!(8, lines.len());
+    assert!(lines[3].starts_with("| \u{2212} | 00000000-0000-0000-0000-000000000003 | Max "));
+    assert!(lines[4].starts_with("| ~ | 00000000-0000-0000-0000-000000000001 | John "));
+    assert!(lines[4].contains("| 42 \u{2192} 43 |"));
+    assert!(lines[5].starts_with("| + | 00000000-0000-0000-0000-000000000004 | Erika "));
+    assert_eq!("1 added, 1 removed, 1 changed", lines[7]);
+
+    assert_eq!("no differences\n", diff::render_diff(&after, &after));
+}
+
+#[test]
+fn it_deletes_rows_and_keeps_later_rows_consistent() {
+    let mut table = create_demo_table();
+    assert!(table.create_bitmap_index("last_name").is_ok());
+    assert!(table.create_unique_index("first_name").is_ok());
+    table.enable_history();
+    let people: Vec<(u128, &str, i64)> = vec![(1, "John", 42), (2, "Jane", 30), (3, "Max", 20)];
+    for (id, first_name, age) in people {
+        let mut row = Row::create(&table, Uuid::from_u128(id));
+        row.set_cell(String::from("first_name"), first_name.into_cell());
+        row.set_cell(String::from("last_name"), "Doe".into_cell());
+        row.set_cell(String::from("age"), age.into_cell());
+        assert!(table.create_row(row).is_ok());
+    }
+    let max = Uuid::from_u128(3);
+    let row_id = table.row_id_for(&max).unwrap();
+    let max_row = table.find_row(&max, ColumnSpecification::All).unwrap();
+    let version = table.current_version();
+
+    let jane = Uuid::from_u128(2);
+    let deleted = table.delete_row(&jane).unwrap();
+    assert_eq!(
+        Some(&TableValue::from("Jane")),
+        deleted.get_cell("first_name")
+    );
+    assert_eq!(Some(&TableValue::Integer(30)), deleted.get_cell("age"));
+    assert_eq!(
+        Err(vec![VirtualTableError::UnknownPrimaryKey(jane)]),
+        table.delete_row(&jane)
+    );
+    assert!(table.has_changed_since(version));
+
+    // The row behind the deleted one moved up, but still reads the same
+    assert_eq!(None, table.find_row(&jane, ColumnSpecification::All));
+    assert_eq!(
+        Some(max_row.clone()),
+        table.find_row(&max, ColumnSpecification::All)
+    );
+    assert_eq!(
+        Some(max_row),
+        table.find_row_by_id(row_id, ColumnSpecification::All)
+    );
+    assert_eq!(
+        Ok(TableValue::Integer(2)),
+        table.aggregate(&Aggregate::Count, None)
+    );
+    let does = table
+        .find_rows_by_bitmap(
+            &BitmapFilter::Equals(String::from("last_name"), TableValue::from("Doe")),
+            ColumnSpecification::All,
+        )
+        .unwrap();
+    assert_eq!(
+        vec![1, 3],
+        does.iter()
+            .map(|row| row.primary_key().as_u128())
+            .collect::<Vec<_>>()
+    );
+
+    // The unique value of the deleted row is free again, until the delete is undone
+    assert_eq!(Ok(true), table.undo());
+    assert_eq!(
+        Some(deleted),
+        table.find_row(&jane, ColumnSpecification::All)
+    );
+    assert_eq!(Ok(true), table.redo());
+    let mut row = Row::create(&table, Uuid::from_u128(4));
+    row.set_cell(String::from("first_name"), "Jane".into_cell());
+    row.set_cell(String::from("last_name"), "Roe".into_cell());
+    row.set_cell(String::from("age"), 25i64.into_cell());
+    assert!(table.create_row(row).is_ok());
+}
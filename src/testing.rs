@@ -0,0 +1,229 @@
+use crate::geo::Point;
+use crate::metadata::ColumnMetadata;
+use crate::money::{Currency, Money};
+use crate::timestamp::Timestamp;
+use crate::{
+    Cell, ColumnDefinition, DataType, Index, IntoCell, PrimaryKey, Row, Table, TableValue,
+};
+use proptest::prelude::*;
+use std::time::Duration;
+use uuid::Uuid;
+
+impl Arbitrary for DataType {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        prop_oneof![
+            Just(DataType::Integer),
+            Just(DataType::BigInt),
+            Just(DataType::UnsignedInteger),
+            Just(DataType::Duration),
+            Just(DataType::Timestamp),
+            Just(DataType::Money),
+            Just(DataType::Point),
+            Just(DataType::String),
+            Just(DataType::Uuid),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for TableValue {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        any::<DataType>()
+            .prop_flat_map(|data_type| value_of(data_type, true))
+            .boxed()
+    }
+}
+
+impl Arbitrary for ColumnDefinition {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (column_identifier(), any::<DataType>(), any::<bool>())
+            .prop_map(|(identifier, data_type, is_nullable)| ColumnDefinition {
+                identifier,
+                data_type,
+                is_nullable,
+                constraints: Vec::new(),
+                metadata: ColumnMetadata::default(),
+            })
+            .boxed()
+    }
+}
+
+/// Generates rows that are valid for the given column definitions.
+impl Arbitrary for Row {
+    type Parameters = Vec<ColumnDefinition>;
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(definitions: Self::Parameters) -> Self::Strategy {
+        let cells = definitions
+            .into_iter()
+            .map(|definition| {
+                let data_type = definition.data_type;
+                value_of(data_type, definition.is_nullable).prop_map(move |value| {
+                    (
+                        definition.identifier.clone(),
+                        Cell {
+                            data_type,
+                            inner: value,
+                        },
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+
+        (uuid(), cells)
+            .prop_map(|(primary_key, cells)| {
+                let mut row = Row {
+                    primary_key,
+                    cells: cells
+                        .into_iter()
+                        .map(|(identifier, cell)| (identifier, Some(cell)))
+                        .collect(),
+                };
+                row.cells
+                    .insert(String::from("ID"), Some(primary_key.into_cell()));
+
+                row
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for Table {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        column_definitions()
+            .prop_flat_map(|definitions| {
+                let rows = prop::collection::vec(any_with::<Row>(definitions.clone()), 0..16);
+                ("[a-z]{1,8}", Just(definitions), rows)
+            })
+            .prop_map(|(identifier, definitions, rows)| {
+                let mut table = Table::create(identifier, definitions);
+                rows.into_iter().for_each(|row| {
+                    // Rows with a duplicate primary key are simply skipped
+                    let _ = table.create_row(row);
+                });
+
+                table
+            })
+            .boxed()
+    }
+}
+
+/// Generates a list of column definitions with unique identifiers.
+pub fn column_definitions() -> impl Strategy<Value = Vec<ColumnDefinition>> {
+    prop::collection::btree_map(
+        column_identifier(),
+        (any::<DataType>(), any::<bool>()),
+        0..6,
+    )
+    .prop_map(|columns| {
+        columns
+            .into_iter()
+            .map(|(identifier, (data_type, is_nullable))| ColumnDefinition {
+                identifier,
+                data_type,
+                is_nullable,
+                constraints: Vec::new(),
+                metadata: ColumnMetadata::default(),
+            })
+            .collect()
+    })
+}
+
+/// Generates values that can be stored in a column of the given data type.
+pub fn value_of(data_type: DataType, is_nullable: bool) -> BoxedStrategy<TableValue> {
+    let values = match data_type {
+        DataType::Integer => any::<i64>().prop_map(TableValue::Integer).boxed(),
+        DataType::BigInt => any::<i128>().prop_map(TableValue::BigInt).boxed(),
+        DataType::UnsignedInteger => any::<u64>().prop_map(TableValue::UnsignedInteger).boxed(),
+        DataType::Duration => any::<Duration>().prop_map(TableValue::Duration).boxed(),
+        // Timestamps between the years 1 and 9999, which all have a four digit year
+        DataType::Timestamp => (-62_135_596_800_000_000i64..253_402_300_800_000_000)
+            .prop_map(|micros| TableValue::Timestamp(Timestamp::from_unix_micros(micros)))
+            .boxed(),
+        DataType::Point => (-90.0..=90.0, -180.0..=180.0)
+            .prop_filter_map("Points need valid coordinates", |(latitude, longitude)| {
+                Point::create(latitude, longitude).map(TableValue::Point)
+            })
+            .boxed(),
+        DataType::Money => (
+            any::<i64>(),
+            prop::sample::select(vec!["EUR", "USD", "JPY"]),
+        )
+            .prop_map(|(amount, code)| {
+                let currency = Currency::create(code).expect("The currency codes are valid.");
+                TableValue::Money(Money::create(amount, currency))
+            })
+            .boxed(),
+        DataType::String => "[a-zA-Z0-9 ]{0,16}".prop_map(TableValue::String).boxed(),
+        DataType::Uuid => uuid().prop_map(TableValue::Uuid).boxed(),
+        // There is no way to generate values for custom types we don't know
+        DataType::Custom(_) => Just(TableValue::Null).boxed(),
+    };
+
+    if is_nullable {
+        prop_oneof![1 => Just(TableValue::Null), 4 => values].boxed()
+    } else {
+        values
+    }
+}
+
+fn column_identifier() -> impl Strategy<Value = String> {
+    // Lowercase only, so we never collide with the generated "ID" column
+    "[a-z][a-z_]{0,11}"
+}
+
+fn uuid() -> impl Strategy<Value = Uuid> {
+    any::<u128>().prop_map(Uuid::from_u128)
+}
+
+// Break the bookkeeping of a table on purpose, so every check of `Table::check_invariants` can be
+// tested. None of these keep the table consistent.
+impl Table {
+    /// Points the slot of the row id at another index, or at none.
+    pub fn set_row_slot(&mut self, row_id: usize, index: Option<Index>) {
+        self.row_slots[row_id] = index;
+    }
+
+    /// Overwrites the value at the index without validating it or updating any index.
+    pub fn set_value_unchecked(
+        &mut self,
+        column_identifier: &str,
+        index: Index,
+        value: TableValue,
+    ) {
+        self.columns
+            .get_mut(column_identifier)
+            .expect("Only existing columns are corrupted.")
+            .values
+            .set(index, value);
+    }
+
+    /// Removes the row at the index from all secondary indexes, its values are kept.
+    pub fn forget_indexed_row(&mut self, index: Index) {
+        self.unindex_row(index);
+    }
+
+    pub fn forget_ordered_key(&mut self, key: &PrimaryKey) {
+        if let Some(ordered_keys) = self.ordered_keys.as_mut() {
+            ordered_keys.remove(key);
+        }
+    }
+
+    pub fn clear_bloom_filter(&mut self) {
+        if let Some(bloom_filter) = self.bloom_filter.as_mut() {
+            bloom_filter.clear();
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use crate::error::VirtualTableError;
+use crate::expression::Expression;
+use crate::{DataType, Table};
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MICROS_PER_SECOND: i64 = 1_000_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// An instant in UTC with microsecond precision. Time zones only matter for displaying
+/// timestamps and for date functions, see `Table::set_time_zone`.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timestamp(i64);
+
+/// Offset from UTC a timestamp is displayed in.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum TimeZone {
+    Utc,
+    // Minutes east of UTC
+    Offset(i32),
+}
+
+/// Date and time of day of a timestamp in a time zone.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct LocalDateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub microsecond: u32,
+}
+
+impl Timestamp {
+    pub fn from_unix_micros(micros: i64) -> Self {
+        Timestamp(micros)
+    }
+
+    pub fn unix_micros(&self) -> i64 {
+        self.0
+    }
+
+    pub fn now() -> Self {
+        let micros = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(duration) => i64::try_from(duration.as_micros()).unwrap_or(i64::MAX),
+            Err(error) => -i64::try_from(error.duration().as_micros()).unwrap_or(i64::MAX),
+        };
+
+        Timestamp(micros)
+    }
+
+    /// Creates the timestamp of a date and time of day in the time zone, None if it's not a valid date.
+    pub fn from_local(local: LocalDateTime, time_zone: TimeZone) -> Option<Self> {
+        if !(1..=12).contains(&local.month)
+            || local.day < 1
+            || local.day > days_in_month(local.year, local.month)
+            || local.hour > 23
+            || local.minute > 59
+            || local.second > 59
+            || local.microsecond >= 1_000_000
+        {
+            return None;
+        }
+
+        let seconds = days_from_civil(local.year, local.month, local.day)?
+            .checked_mul(SECONDS_PER_DAY)?
+            .checked_add(i64::from(
+                local.hour * 3600 + local.minute * 60 + local.second,
+            ))?
+            .checked_sub(time_zone.offset_seconds())?;
+
+        seconds
+            .checked_mul(MICROS_PER_SECOND)?
+            .checked_add(i64::from(local.microsecond))
+            .map(Timestamp)
+    }
+
+    pub fn to_local(&self, time_zone: TimeZone) -> LocalDateTime {
+        // The offset is added to whole seconds, so timestamps close to the limits don't overflow
+        let seconds = self.0.div_euclid(MICROS_PER_SECOND) + time_zone.offset_seconds();
+        let time_of_day = seconds.rem_euclid(SECONDS_PER_DAY);
+        let (year, month, day) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+
+        LocalDateTime {
+            year,
+            month,
+            day,
+            hour: (time_of_day / 3600) as u32,
+            minute: (time_of_day % 3600 / 60) as u32,
+            second: (time_of_day % 60) as u32,
+            microsecond: self.0.rem_euclid(MICROS_PER_SECOND) as u32,
+        }
+    }
+
+    /// Formats the timestamp as RFC 3339 in the time zone, e.g. "2024-03-01T14:30:00+02:00".
+    pub fn format(&self, time_zone: TimeZone) -> String {
+        let local = self.to_local(time_zone);
+        let fraction = match local.microsecond {
+            0 => String::new(),
+            microsecond => format!(".{:06}", microsecond),
+        };
+
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}{}",
+            local.year,
+            local.month,
+            local.day,
+            local.hour,
+            local.minute,
+            local.second,
+            fraction,
+            time_zone
+        )
+    }
+
+    /// Parses RFC 3339 timestamps like the ones `Timestamp::format` produces.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (date_time, time_zone) = if let Some(date_time) = value.strip_suffix('Z') {
+            (date_time, TimeZone::Utc)
+        } else {
+            // Values ending in a multibyte character have no offset to split off
+            let split = value.len().checked_sub(6)?;
+            let (date_time, offset) = (value.get(..split)?, value.get(split..)?);
+            (date_time, TimeZone::parse_offset(offset)?)
+        };
+
+        let (date, time) = date_time.split_at(date_time.find(&['T', ' '][..])?);
+        let mut date = date.splitn(3, '-').map(|part| part.parse::<u32>().ok());
+        let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+
+        let (time, fraction) = match time[1..].split_once('.') {
+            Some((time, fraction)) => (time, fraction),
+            None => (&time[1..], ""),
+        };
+        let mut time = time.splitn(3, ':').map(|part| part.parse::<u32>().ok());
+        let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+        let microsecond = match fraction.len() {
+            0 => 0,
+            1..=6 => fraction.parse::<u32>().ok()? * 10u32.pow(6 - fraction.len() as u32),
+            _ => return None,
+        };
+
+        Timestamp::from_local(
+            LocalDateTime {
+                year: i64::from(year),
+                month,
+                day,
+                hour,
+                minute,
+                second,
+                microsecond,
+            },
+            time_zone,
+        )
+    }
+
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> {
+        let micros = i64::try_from(duration.as_micros()).ok()?;
+        self.0.checked_add(micros).map(Timestamp)
+    }
+
+    pub fn checked_sub(&self, duration: Duration) -> Option<Self> {
+        let micros = i64::try_from(duration.as_micros()).ok()?;
+        self.0.checked_sub(micros).map(Timestamp)
+    }
+
+    /// The time that passed since the earlier timestamp, None if it's actually later.
+    pub fn duration_since(&self, earlier: Timestamp) -> Option<Duration> {
+        let micros = u64::try_from(self.0.checked_sub(earlier.0)?).ok()?;
+        Some(Duration::from_micros(micros))
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(&self.format(TimeZone::Utc))
+    }
+}
+
+impl TimeZone {
+    pub fn hours(hours: i32) -> Self {
+        TimeZone::Offset(hours * 60)
+    }
+
+    fn offset_seconds(&self) -> i64 {
+        match self {
+            TimeZone::Utc => 0,
+            TimeZone::Offset(minutes) => i64::from(*minutes) * 60,
+        }
+    }
+
+    // Parses offsets like "+02:00" or "-05:30"
+    fn parse_offset(offset: &str) -> Option<Self> {
+        let sign = match offset.get(..1)? {
+            "+" => 1,
+            "-" => -1,
+            _ => return None,
+        };
+        let (hours, minutes) = offset[1..].split_once(':')?;
+        let parse_digits = |digits: &str| {
+            Some(digits)
+                .filter(|digits| digits.bytes().all(|byte| byte.is_ascii_digit()))?
+                .parse::<i32>()
+                .ok()
+        };
+        let (hours, minutes) = (parse_digits(hours)?, parse_digits(minutes)?);
+        if hours > 23 || minutes > 59 {
+            return None;
+        }
+
+        Some(TimeZone::Offset(sign * (hours * 60 + minutes)))
+    }
+}
+
+impl Display for TimeZone {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            TimeZone::Utc | TimeZone::Offset(0) => f.write_str("Z"),
+            TimeZone::Offset(minutes) => f.write_str(&format!(
+                "{}{:02}:{:02}",
+                if *minutes < 0 { '-' } else { '+' },
+                minutes.abs() / 60,
+                minutes.abs() % 60
+            )),
+        }
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 for a date in the proleptic Gregorian calendar, None if that overflows
+fn days_from_civil(year: i64, month: u32, day: u32) -> Option<i64> {
+    let year = if month <= 2 {
+        year.checked_sub(1)?
+    } else {
+        year
+    };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era.checked_mul(146_097)?
+        .checked_add(day_of_era)?
+        .checked_sub(719_468)
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+impl Table {
+    /// Sets the time zone timestamps of the column are displayed in, which is also used by date
+    /// functions on the column. The stored instants don't change.
+    pub fn set_time_zone(
+        &mut self,
+        column_identifier: &str,
+        time_zone: TimeZone,
+    ) -> Result<(), VirtualTableError> {
+        let column = self
+            .columns
+            .get(column_identifier)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(String::from(column_identifier)))?;
+        if column.data_type != DataType::Timestamp {
+            return Result::Err(VirtualTableError::InvalidDataType(
+                String::from(column_identifier),
+                DataType::Timestamp,
+                column.data_type,
+            ));
+        }
+
+        self.time_zones
+            .insert(String::from(column_identifier), time_zone);
+
+        Result::Ok(())
+    }
+
+    pub fn time_zone_of(&self, column_identifier: &str) -> TimeZone {
+        self.time_zones
+            .get(column_identifier)
+            .copied()
+            .unwrap_or(TimeZone::Utc)
+    }
+
+    pub(crate) fn time_zone_for(&self, expression: &Expression) -> TimeZone {
+        match expression {
+            Expression::Column(identifier) => self.time_zone_of(identifier),
+            _ => TimeZone::Utc,
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use crate::{Row, Table};
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+type TransformerBody = dyn Fn(Row) -> Row + Send + Sync;
+
+/// The write a row transformer runs before.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum WriteStage {
+    BeforeInsert,
+    // The transformer gets the partial update row, only cells it contains are changed
+    BeforeUpdate,
+}
+
+pub struct RowTransformer {
+    stage: WriteStage,
+    body: Box<TransformerBody>,
+}
+
+impl Debug for RowTransformer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("RowTransformer")
+            .field("stage", &self.stage)
+            .finish()
+    }
+}
+
+impl Table {
+    /// Registers a function that rewrites rows before they are written, e.g. to normalize phone
+    /// numbers or to fill in a timestamp. Transformers of a stage run in the order they were
+    /// added, before coercion and validation, for every write through `Table::create_row` and
+    /// `Table::update_row`. Undo and redo write the rows as they were stored, without
+    /// transforming them again.
+    pub fn add_row_transformer<F>(&mut self, stage: WriteStage, transformer: F)
+    where
+        F: Fn(Row) -> Row + Send + Sync + 'static,
+    {
+        self.row_transformers.push(RowTransformer {
+            stage,
+            body: Box::new(transformer),
+        });
+    }
+
+    pub fn clear_row_transformers(&mut self) {
+        self.row_transformers.clear();
+    }
+
+    pub(crate) fn transform_row(&self, stage: WriteStage, row: Row) -> Row {
+        self.row_transformers
+            .iter()
+            .filter(|transformer| transformer.stage == stage)
+            .fold(row, |row, transformer| (transformer.body)(row))
+    }
+}
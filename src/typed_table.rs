@@ -0,0 +1,88 @@
+use crate::error::VirtualTableError;
+use crate::expression::Expression;
+use crate::predicate::Predicate;
+use crate::query::ColumnSpecification;
+use crate::sorting::SortOrder;
+use crate::{ColumnDefinition, PrimaryKey, Row, Table};
+use std::marker::PhantomData;
+
+/// A type whose values are stored as rows of a table, see `TypedTable`.
+pub trait TableRecord: Sized {
+    /// Columns of the table, without the "ID" column every table has.
+    fn columns() -> Vec<ColumnDefinition>;
+
+    fn primary_key(&self) -> PrimaryKey;
+
+    /// Sets the cells of a row that already has the primary key of the record.
+    fn write_cells(&self, row: &mut Row);
+
+    /// Reads the record back from a row with all columns, None if a value doesn't fit.
+    fn from_row(row: &Row) -> Option<Self>;
+}
+
+/// A table that only stores records of a single type. The conversion between records and rows
+/// lives in one place, the `TableRecord` implementation, instead of at every call site.
+#[derive(Debug)]
+pub struct TypedTable<T: TableRecord> {
+    table: Table,
+    record: PhantomData<T>,
+}
+
+impl<T: TableRecord> TypedTable<T> {
+    pub fn create(identifier: &str) -> Self {
+        TypedTable {
+            table: Table::create(String::from(identifier), T::columns()),
+            record: PhantomData,
+        }
+    }
+
+    pub fn insert(&mut self, record: &T) -> Result<(), Vec<VirtualTableError>> {
+        let row = self.row_of(record);
+        self.table.create_row(row)
+    }
+
+    pub fn update(&mut self, record: &T) -> Result<(), Vec<VirtualTableError>> {
+        let row = self.row_of(record);
+        self.table.update_row(row)
+    }
+
+    pub fn find(&self, key: &PrimaryKey) -> Option<T> {
+        self.table
+            .find_row(key, ColumnSpecification::All)
+            .and_then(|row| T::from_row(&row))
+    }
+
+    /// Finds all records for which the predicate is true, in insertion order.
+    pub fn filter(&self, predicate: &Predicate) -> Result<Vec<T>, VirtualTableError> {
+        self.table
+            .find_rows(predicate, ColumnSpecification::All)
+            .map(records_of)
+    }
+
+    pub fn order_by(&self, order: &[(Expression, SortOrder)]) -> Result<Vec<T>, VirtualTableError> {
+        self.table
+            .order_by(order, ColumnSpecification::All)
+            .map(records_of)
+    }
+
+    /// The untyped table underneath, for everything this wrapper doesn't cover.
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    pub fn into_table(self) -> Table {
+        self.table
+    }
+
+    fn row_of(&self, record: &T) -> Row {
+        let mut row = Row::create(&self.table, record.primary_key());
+        record.write_cells(&mut row);
+
+        row
+    }
+}
+
+// Rows that can't be read back as records are left out
+fn records_of<T: TableRecord>(rows: Vec<Row>) -> Vec<T> {
+    rows.iter().filter_map(T::from_row).collect()
+}
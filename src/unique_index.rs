@@ -0,0 +1,234 @@
+use crate::collation::collation_key;
+use crate::error::VirtualTableError;
+use crate::predicate::{Predicate, Truth};
+use crate::{Index, PrimaryKey, Row, Table, TableValue};
+use std::collections::HashMap;
+
+/// Maps every value of a column to the row holding it. Values are keyed by the collation of the
+/// column, so a case-insensitive column treats "Foo" and "foo" as the same value. NULL values
+/// aren't indexed, so a unique column may contain any number of them, just like in SQL.
+#[derive(Debug, Default)]
+pub(crate) struct UniqueIndex {
+    keys: HashMap<TableValue, PrimaryKey>,
+    // Partial indexes only cover the rows the predicate is true for
+    filter: Option<Predicate>,
+}
+
+impl UniqueIndex {
+    pub(crate) fn is_partial(&self) -> bool {
+        self.filter.is_some()
+    }
+}
+
+impl Table {
+    /// Ensures that no two rows have an equal value in the column from now on. Fails if the
+    /// column already contains duplicates.
+    pub fn create_unique_index(
+        &mut self,
+        column_identifier: &str,
+    ) -> Result<(), VirtualTableError> {
+        self.build_unique_index(column_identifier, None)
+    }
+
+    /// Like `Table::create_unique_index`, but values only need to be unique among the rows the
+    /// predicate is true for, e.g. among the rows that aren't soft-deleted. Rows that don't
+    /// match may share values with any other row.
+    pub fn create_partial_unique_index(
+        &mut self,
+        column_identifier: &str,
+        filter: Predicate,
+    ) -> Result<(), VirtualTableError> {
+        filter.validate(self)?;
+        self.build_unique_index(column_identifier, Some(filter))
+    }
+
+    pub fn drop_unique_index(&mut self, column_identifier: &str) -> bool {
+        self.unique_indexes.remove(column_identifier).is_some()
+    }
+
+    pub fn has_unique_index(&self, column_identifier: &str) -> bool {
+        self.unique_indexes.contains_key(column_identifier)
+    }
+
+    // Rebuilds the index of the column with its filter, the index is kept as it is on errors
+    pub(crate) fn rebuild_unique_index(
+        &mut self,
+        column_identifier: &str,
+    ) -> Result<(), VirtualTableError> {
+        let filter = match self.unique_indexes.get(column_identifier) {
+            Some(index) => index.filter.clone(),
+            None => return Result::Ok(()),
+        };
+
+        self.build_unique_index(column_identifier, filter)
+    }
+
+    // Indexes whose column now holds duplicates can't be rebuilt, they are dropped
+    pub(crate) fn rebuild_unique_indexes(&mut self) -> Vec<VirtualTableError> {
+        let filters = self
+            .unique_indexes
+            .drain()
+            .map(|(column_identifier, index)| (column_identifier, index.filter))
+            .collect::<Vec<_>>();
+
+        filters
+            .into_iter()
+            .filter_map(|(column_identifier, filter)| {
+                self.build_unique_index(&column_identifier, filter).err()
+            })
+            .collect()
+    }
+
+    fn build_unique_index(
+        &mut self,
+        column_identifier: &str,
+        filter: Option<Predicate>,
+    ) -> Result<(), VirtualTableError> {
+        let index = self.unique_index_of(column_identifier, filter)?;
+        self.unique_indexes
+            .insert(String::from(column_identifier), index);
+
+        Result::Ok(())
+    }
+
+    // Indexes the values the column holds right now, fails if they aren't unique
+    fn unique_index_of(
+        &self,
+        column_identifier: &str,
+        filter: Option<Predicate>,
+    ) -> Result<UniqueIndex, VirtualTableError> {
+        let column = self
+            .columns
+            .get(column_identifier)
+            .ok_or_else(|| VirtualTableError::UnknownColumn(String::from(column_identifier)))?;
+
+        let collation = self.collation_of(column_identifier);
+        let mut index = UniqueIndex {
+            keys: HashMap::new(),
+            filter,
+        };
+        for (position, value) in column.values.iter().enumerate() {
+            if *value == TableValue::Null || !self.is_covered_by(&index, position) {
+                continue;
+            }
+
+            let key = self
+                .key_at(position)
+                .expect("Rows at valid indexes always exist.");
+            if index
+                .keys
+                .insert(collation_key(collation, value), key)
+                .is_some()
+            {
+                return Result::Err(VirtualTableError::DuplicateValue(
+                    String::from(column_identifier),
+                    value.clone(),
+                ));
+            }
+        }
+
+        Result::Ok(index)
+    }
+
+    // Whether the index of the column holds exactly the values the column holds right now,
+    // fails if the column holds duplicates by now
+    pub(crate) fn is_unique_index_current(
+        &self,
+        column_identifier: &str,
+    ) -> Result<bool, VirtualTableError> {
+        let index = match self.unique_indexes.get(column_identifier) {
+            Some(index) => index,
+            None => return Result::Ok(true),
+        };
+
+        self.unique_index_of(column_identifier, index.filter.clone())
+            .map(|rebuilt| rebuilt.keys == index.keys)
+    }
+
+    // Checks the values of a row that is about to be written, cells that aren't set are skipped.
+    // Partial indexes need the whole row, so they are checked by `Table::check_partial_unique_values`.
+    pub(crate) fn check_unique_values(&self, row: &Row) -> Vec<VirtualTableError> {
+        self.unique_indexes
+            .iter()
+            .filter(|(_, index)| index.filter.is_none())
+            .filter_map(|(column_identifier, index)| {
+                let value = row.get_cell(column_identifier)?;
+                let key = collation_key(self.collation_of(column_identifier), value);
+                match index.keys.get(&key) {
+                    Some(existing) if *existing != row.primary_key => Some(
+                        VirtualTableError::DuplicateValue(column_identifier.clone(), value.clone()),
+                    ),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    // Checks the values of a row that was just written at the index, but isn't indexed yet
+    pub(crate) fn check_partial_unique_values(
+        &self,
+        index: Index,
+        primary_key: PrimaryKey,
+    ) -> Vec<VirtualTableError> {
+        self.unique_indexes
+            .iter()
+            .filter(|(_, unique_index)| unique_index.filter.is_some())
+            .filter(|(_, unique_index)| self.is_covered_by(unique_index, index))
+            .filter_map(|(column_identifier, unique_index)| {
+                let value = match self.columns.get(column_identifier)?.value_at(index)? {
+                    TableValue::Null => return None,
+                    value => value,
+                };
+                let key = collation_key(self.collation_of(column_identifier), value);
+                match unique_index.keys.get(&key) {
+                    Some(existing) if *existing != primary_key => Some(
+                        VirtualTableError::DuplicateValue(column_identifier.clone(), value.clone()),
+                    ),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    pub(crate) fn update_unique_indexes(&mut self, index: Index, is_insert: bool) {
+        let primary_key = match self.key_at(index) {
+            Some(primary_key) => primary_key,
+            None => return,
+        };
+
+        // The filters of partial indexes need the whole table, so the keys are collected first
+        let keys = self
+            .unique_indexes
+            .iter()
+            .filter(|(_, unique_index)| self.is_covered_by(unique_index, index))
+            .filter_map(|(column_identifier, _)| {
+                let value = match self.columns.get(column_identifier)?.value_at(index)? {
+                    TableValue::Null => return None,
+                    value => value,
+                };
+                let key = collation_key(self.collation_of(column_identifier), value);
+
+                Some((column_identifier.clone(), key))
+            })
+            .collect::<Vec<_>>();
+
+        keys.into_iter().for_each(|(column_identifier, key)| {
+            if let Some(unique_index) = self.unique_indexes.get_mut(&column_identifier) {
+                if is_insert {
+                    unique_index.keys.insert(key, primary_key);
+                } else {
+                    unique_index.keys.remove(&key);
+                }
+            }
+        });
+    }
+
+    // Whether the row at the index belongs into the index, a filter that fails to evaluate
+    // doesn't match just like in a query
+    fn is_covered_by(&self, unique_index: &UniqueIndex, index: Index) -> bool {
+        match &unique_index.filter {
+            Some(filter) => filter.evaluate(self, index) == Result::Ok(Truth::True),
+            None => true,
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use crate::events::TableEvent;
+use crate::Table;
+
+/// Counts the mutations of a table, every inserted, updated or deleted row increases it by one.
+pub type Version = u64;
+
+impl Table {
+    /// The version after the latest mutation, 0 for a table that was never written to. Changes
+    /// to the schema or to indexes don't count as mutations.
+    pub fn current_version(&self) -> Version {
+        self.version
+    }
+
+    /// Whether rows were written since the version was read, a cheap check for stale caches.
+    pub fn has_changed_since(&self, version: Version) -> bool {
+        self.version > version
+    }
+
+    /// The version the event with the given id produced.
+    pub fn event_version(&self, event_id: usize) -> Option<Version> {
+        self.event_versions.get(event_id).copied()
+    }
+
+    /// The events of all mutations after the version, so a cache can apply them instead of
+    /// reloading everything. Empty if the table isn't event-sourced.
+    pub fn events_since(&self, version: Version) -> &[TableEvent] {
+        let start = self
+            .event_versions
+            .partition_point(|event_version| *event_version <= version);
+
+        &self.events()[start..]
+    }
+
+    pub(crate) fn advance_version(&mut self) {
+        self.version += 1;
+    }
+}